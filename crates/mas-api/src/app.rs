@@ -41,6 +41,10 @@ pub fn create_router(state: AppState) -> Router {
         .route("/auth/login", post(handlers::auth::login))
         .route("/auth/refresh", post(handlers::auth::refresh));
 
+    // Inbound webhooks (authenticated by their signature, not a token)
+    let hook_routes = Router::new()
+        .route("/hooks/:system/:hook", post(handlers::hooks::receive_hook));
+
     // Authenticated auth routes
     let auth_protected = Router::new()
         .route("/auth/me", get(handlers::auth::get_me))
@@ -52,6 +56,7 @@ pub fn create_router(state: AppState) -> Router {
         .route("/systems", get(handlers::systems::list_systems))
         .route("/systems/:name", get(handlers::systems::get_system))
         .route("/systems/:name/config", get(handlers::systems::get_system_config))
+        .route("/systems/:name/triggers", get(handlers::systems::get_system_triggers))
         .route("/systems/:name", put(handlers::systems::update_system))
         .route("/systems/:name", delete(handlers::systems::delete_system))
         .route("/systems/:name/prompt", post(handlers::systems::send_prompt));
@@ -66,6 +71,10 @@ pub fn create_router(state: AppState) -> Router {
         .route("/sessions/:id/search", get(handlers::sessions::search_session))
         .route("/sessions/:id/prompt", post(handlers::sessions::send_session_prompt))
         .route("/sessions/:id/prompt/stream", post(handlers::sessions::send_session_prompt_stream))
+        .route("/sessions/:id/pending", get(handlers::sessions::list_pending))
+        .route("/sessions/:id/pending/:pending_id/answer", post(handlers::sessions::answer_pending))
+        .route("/sessions/:id/approvals", get(handlers::sessions::list_approvals))
+        .route("/sessions/:id/approvals/:approval_id", post(handlers::sessions::decide_approval))
         .route("/sessions/:id/build-index", post(handlers::sessions::build_session_index));
 
     // Organization routes (all authenticated)
@@ -82,11 +91,18 @@ pub fn create_router(state: AppState) -> Router {
         .route("/orgs/:id/members/:user_id", delete(handlers::orgs::remove_member))
         .route("/orgs/:id/systems", get(handlers::orgs::list_org_systems))
         .route("/orgs/:id/systems/:name", put(handlers::orgs::add_system_to_org))
-        .route("/orgs/:id/systems/:name", delete(handlers::orgs::remove_system_from_org));
+        .route("/orgs/:id/systems/:name", delete(handlers::orgs::remove_system_from_org))
+        .route("/orgs/:id/webhooks", get(handlers::orgs::list_webhooks))
+        .route("/orgs/:id/webhooks", post(handlers::orgs::create_webhook))
+        .route("/orgs/:id/webhooks/:webhook_id", get(handlers::orgs::get_webhook))
+        .route("/orgs/:id/webhooks/:webhook_id", put(handlers::orgs::update_webhook))
+        .route("/orgs/:id/webhooks/:webhook_id", delete(handlers::orgs::delete_webhook))
+        .route("/orgs/:id/webhooks/:webhook_id/deliveries", get(handlers::orgs::list_webhook_deliveries));
 
     // Combine all API routes under /api/v1
     let api_routes = Router::new()
         .merge(auth_routes)
+        .merge(hook_routes)
         .merge(auth_protected)
         .merge(system_routes)
         .merge(session_routes)
@@ -100,6 +116,7 @@ pub fn create_router(state: AppState) -> Router {
         .not_found_service(ServeFile::new(&index_path));
 
     Router::new()
+        .route("/metrics", get(handlers::metrics::get_metrics))
         .nest("/api/v1", api_routes)
         .layer(TraceLayer::new_for_http())
         .layer(
@@ -46,6 +46,10 @@ pub enum ApiError {
     /// Insufficient permissions
     #[error("Forbidden: {0}")]
     Forbidden(String),
+
+    /// The requested resource does not exist
+    #[error("Not found: {0}")]
+    NotFound(String),
 }
 
 /// JSON error response body
@@ -67,6 +71,7 @@ impl IntoResponse for ApiError {
             ApiError::BadRequest(_) => (StatusCode::BAD_REQUEST, "BAD_REQUEST"),
             ApiError::Unauthorized(_) => (StatusCode::UNAUTHORIZED, "UNAUTHORIZED"),
             ApiError::Forbidden(_) => (StatusCode::FORBIDDEN, "FORBIDDEN"),
+            ApiError::NotFound(_) => (StatusCode::NOT_FOUND, "NOT_FOUND"),
         };
 
         let body = ErrorResponse {
@@ -0,0 +1,159 @@
+//! Inbound webhook handlers
+//!
+//! Hooks are called by other services rather than by users, so they are not
+//! behind the JWT middleware; each call is authenticated by the HMAC
+//! signature of its body instead. Calls to hooks that do not exist are
+//! answered like calls with a bad signature, so hooks cannot be discovered.
+
+use std::sync::OnceLock;
+
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use mas_core::{Hook, HookError};
+use tracing::{info, warn};
+
+use crate::error::{ApiError, ApiResult};
+use crate::models::{HookCallback, HookResponse};
+use crate::scheduler::{deliver_prompt, record_session_owner, PromptSource, RunOutcome};
+use crate::state::AppState;
+use crate::webhooks::{EventKind, RetryPolicy, WebhookEvent};
+
+/// Answer to every call that fails authentication
+fn unauthorized() -> ApiError {
+    ApiError::Unauthorized("Invalid signature".to_string())
+}
+
+/// Client the results of asynchronous calls are POSTed to their callback with
+fn callback_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(RetryPolicy::default().request_timeout)
+            .build()
+            .expect("valid callback client")
+    })
+}
+
+/// POST /api/v1/hooks/{system}/{hook} - Turn a signed payload into a prompt
+///
+/// Answers with the result, or with 202 Accepted right away if the hook has
+/// a callback URL; the result is then POSTed there.
+pub async fn receive_hook(
+    State(state): State<AppState>,
+    Path((system_name, hook_name)): Path<(String, String)>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> ApiResult<(StatusCode, Json<HookResponse>)> {
+    let config = state
+        .get_system_hook(&system_name, &hook_name)
+        .await
+        .ok_or_else(unauthorized)?;
+    let hook = Hook::compile(&config).map_err(|e| ApiError::ConfigError(e.to_string()))?;
+
+    let signature = headers
+        .get(hook.signature_header())
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(unauthorized)?;
+    match hook.verify(&body, signature) {
+        Ok(true) => {}
+        Ok(false) => return Err(unauthorized()),
+        Err(e @ HookError::MissingSecret(_)) => return Err(ApiError::Internal(e.to_string())),
+        Err(e) => return Err(ApiError::ConfigError(e.to_string())),
+    }
+
+    let payload: serde_json::Value = serde_json::from_slice(&body)
+        .map_err(|e| ApiError::BadRequest(format!("Payload is not valid JSON: {}", e)))?;
+    let prompt = hook.render_prompt(&payload);
+
+    // Without a configured session every call starts a new one; create it
+    // here so an asynchronous call can report it right away
+    let session_id = match &config.session_id {
+        Some(id) => id.clone(),
+        None => {
+            let mut manager = state.session_manager().write().await;
+            let id = manager
+                .create_session(&system_name)
+                .await
+                .map_err(|e| ApiError::Internal(format!("Failed to create session: {}", e)))?
+                .id;
+            drop(manager);
+            record_session_owner(&state, &system_name, &id).await;
+            state
+                .webhooks()
+                .publish(WebhookEvent::new(EventKind::SessionCreated, &system_name).with_session(Some(&id)));
+            id
+        }
+    };
+    info!("Hook '{}' of '{}' called, posting to session {}", hook_name, system_name, session_id);
+
+    let Some(callback_url) = config.callback_url.clone() else {
+        let outcome = run_hook(&state, &system_name, &hook, &session_id, &prompt)
+            .await
+            .map_err(ApiError::Internal)?;
+        return Ok((
+            StatusCode::OK,
+            Json(HookResponse {
+                system: system_name,
+                hook: hook_name,
+                session_id: Some(session_id),
+                result: Some(outcome),
+            }),
+        ));
+    };
+
+    let response = HookResponse {
+        system: system_name.clone(),
+        hook: hook_name.clone(),
+        session_id: Some(session_id.clone()),
+        result: None,
+    };
+    tokio::spawn(async move {
+        let outcome = run_hook(&state, &system_name, &hook, &session_id, &prompt)
+            .await
+            .unwrap_or_else(|error| RunOutcome::Failed { error });
+        let callback = HookCallback {
+            system: system_name,
+            hook: hook_name,
+            session_id: Some(session_id),
+            outcome,
+        };
+        let sent = callback_client()
+            .post(&callback_url)
+            .json(&callback)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = sent {
+            warn!(
+                "Could not deliver the result of hook '{}' of '{}' to {}: {}",
+                callback.hook, callback.system, callback_url, e
+            );
+        }
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(response)))
+}
+
+async fn run_hook(
+    state: &AppState,
+    system_name: &str,
+    hook: &Hook,
+    session_id: &str,
+    prompt: &str,
+) -> Result<RunOutcome, String> {
+    let mut session_out = None;
+    deliver_prompt(
+        state,
+        system_name,
+        hook.config().target_agent.as_deref(),
+        Some(session_id),
+        prompt,
+        PromptSource::Hook(hook.name()),
+        &mut session_out,
+    )
+    .await
+}
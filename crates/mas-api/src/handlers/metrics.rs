@@ -0,0 +1,61 @@
+//! Prometheus metrics handler
+//!
+//! Served outside `/api/v1`, where scrapers expect it. Scrapers do not hold
+//! user accounts, so instead of a JWT they present the token configured with
+//! `MAS_METRICS_TOKEN` as a bearer token. Without a token the endpoint is not
+//! served at all, unless auth is disabled.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap},
+    response::IntoResponse,
+};
+use mas_core::metrics::{metrics, CONTENT_TYPE};
+
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+
+/// GET /metrics - Metrics in the Prometheus text format
+///
+/// Counters and histograms are recorded as the systems work; inbox depths
+/// and the number of stored sessions are read when scraped.
+pub async fn get_metrics(State(state): State<AppState>, headers: HeaderMap) -> ApiResult<impl IntoResponse> {
+    authorize(&state, &headers)?;
+
+    let metrics = metrics();
+    metrics.reset_inbox_depths();
+    for (name, system) in state.running_systems().await {
+        for (agent, depth) in system.inbox_depths().await {
+            metrics.add_inbox_depth(&name, &agent, depth);
+        }
+    }
+    let sessions = state.session_manager().read().await.list_sessions(None).len();
+    metrics.set_sessions(sessions);
+
+    Ok(([(header::CONTENT_TYPE, CONTENT_TYPE)], metrics.render()))
+}
+
+/// Check the request carries the metrics token
+fn authorize(state: &AppState, headers: &HeaderMap) -> ApiResult<()> {
+    if state.is_auth_disabled() {
+        return Ok(());
+    }
+    let Some(expected) = state.metrics_token() else {
+        return Err(ApiError::NotFound("Metrics".to_string()));
+    };
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(|| ApiError::Unauthorized("Missing metrics token".to_string()))?;
+    if tokens_match(presented.as_bytes(), expected.as_bytes()) {
+        Ok(())
+    } else {
+        Err(ApiError::Unauthorized("Invalid metrics token".to_string()))
+    }
+}
+
+/// Compare two tokens in time independent of where they differ
+fn tokens_match(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
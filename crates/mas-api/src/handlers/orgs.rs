@@ -1,16 +1,17 @@
 //! Organization management handlers
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
-use mas_auth::{repository, AuthenticatedUser, MemberInfo, OrgRole, OrgWithRole};
+use mas_auth::{repository, AuthenticatedUser, MemberInfo, OrgRole, OrgWithRole, Webhook, WebhookDelivery};
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
 use crate::error::{ApiError, ApiResult};
 use crate::state::AppState;
+use crate::webhooks::EventKind;
 
 // ─── Request/Response Types ─────────────────────────────
 
@@ -45,6 +46,43 @@ pub struct UpdateMemberRoleRequest {
     pub role: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    /// Only deliver events of this system (must belong to the organization)
+    pub system_name: Option<String>,
+    /// Event names to deliver; all events if empty
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Signing secret; generated if omitted
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateWebhookResponse {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    /// The signing secret, only returned on creation
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWebhookRequest {
+    pub url: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListDeliveriesQuery {
+    #[serde(default = "default_deliveries_limit")]
+    pub limit: u32,
+}
+
+fn default_deliveries_limit() -> u32 {
+    50
+}
+
 // ─── Organization CRUD ──────────────────────────────────
 
 /// POST /api/v1/orgs
@@ -463,3 +501,224 @@ pub async fn remove_system_from_org(
         "message": "System removed from organization"
     })))
 }
+
+// ─── Webhooks ───────────────────────────────────────────
+
+fn validate_webhook_url(url: &str) -> ApiResult<()> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(ApiError::BadRequest(format!(
+            "Webhook URL must start with http:// or https://: {}",
+            url
+        )));
+    }
+    Ok(())
+}
+
+fn validate_webhook_events(events: &[String]) -> ApiResult<()> {
+    if let Some(unknown) = events.iter().find(|e| EventKind::parse(e).is_none()) {
+        let known: Vec<&str> = EventKind::ALL.iter().map(|k| k.as_str()).collect();
+        return Err(ApiError::BadRequest(format!(
+            "Unknown event '{}'. Known events: {:?}",
+            unknown, known
+        )));
+    }
+    Ok(())
+}
+
+async fn find_org_webhook(state: &AppState, org_id: &str, webhook_id: &str) -> ApiResult<Webhook> {
+    repository::find_webhook(state.db(), org_id, webhook_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .ok_or_else(|| ApiError::NotFound(format!("Webhook {}", webhook_id)))
+}
+
+/// GET /api/v1/orgs/{id}/webhooks
+pub async fn list_webhooks(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(org_id): Path<String>,
+) -> ApiResult<Json<Vec<Webhook>>> {
+    let pool = state.db();
+
+    repository::get_membership(pool, &user.user_id, &org_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .ok_or_else(|| ApiError::Forbidden("You are not a member of this organization".to_string()))?;
+
+    let webhooks = repository::list_org_webhooks(pool, &org_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    Ok(Json(webhooks))
+}
+
+/// POST /api/v1/orgs/{id}/webhooks
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(org_id): Path<String>,
+    Json(request): Json<CreateWebhookRequest>,
+) -> ApiResult<(StatusCode, Json<CreateWebhookResponse>)> {
+    let pool = state.db();
+
+    let role = repository::get_membership(pool, &user.user_id, &org_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .ok_or_else(|| ApiError::Forbidden("You are not a member of this organization".to_string()))?;
+
+    if !role.can_modify_org() {
+        return Err(ApiError::Forbidden(
+            "Only owners and admins can add webhooks".to_string(),
+        ));
+    }
+
+    validate_webhook_url(&request.url)?;
+    validate_webhook_events(&request.events)?;
+
+    if let Some(ref system_name) = request.system_name {
+        let org_systems = repository::list_org_systems(pool, &org_id)
+            .await
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        if !org_systems.contains(system_name) {
+            return Err(ApiError::BadRequest(format!(
+                "System '{}' does not belong to this organization",
+                system_name
+            )));
+        }
+    }
+
+    let secret = match request.secret {
+        Some(secret) if !secret.trim().is_empty() => secret,
+        Some(_) => return Err(ApiError::BadRequest("Webhook secret must not be empty".to_string())),
+        None => format!("whsec_{}", uuid::Uuid::new_v4().simple()),
+    };
+
+    let webhook_id = uuid::Uuid::new_v4().to_string();
+    let webhook = repository::create_webhook(
+        pool,
+        &webhook_id,
+        &org_id,
+        request.system_name.as_deref(),
+        &request.url,
+        &secret,
+        &request.events,
+        Some(&user.user_id),
+    )
+    .await
+    .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    info!("Webhook {} added to org {} by {}", webhook_id, org_id, user.user_id);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateWebhookResponse { webhook, secret }),
+    ))
+}
+
+/// GET /api/v1/orgs/{id}/webhooks/{webhook_id}
+pub async fn get_webhook(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((org_id, webhook_id)): Path<(String, String)>,
+) -> ApiResult<Json<Webhook>> {
+    repository::get_membership(state.db(), &user.user_id, &org_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .ok_or_else(|| ApiError::Forbidden("You are not a member of this organization".to_string()))?;
+
+    Ok(Json(find_org_webhook(&state, &org_id, &webhook_id).await?))
+}
+
+/// PUT /api/v1/orgs/{id}/webhooks/{webhook_id}
+pub async fn update_webhook(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((org_id, webhook_id)): Path<(String, String)>,
+    Json(request): Json<UpdateWebhookRequest>,
+) -> ApiResult<Json<Webhook>> {
+    let pool = state.db();
+
+    let role = repository::get_membership(pool, &user.user_id, &org_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .ok_or_else(|| ApiError::Forbidden("You are not a member of this organization".to_string()))?;
+
+    if !role.can_modify_org() {
+        return Err(ApiError::Forbidden(
+            "Only owners and admins can change webhooks".to_string(),
+        ));
+    }
+
+    find_org_webhook(&state, &org_id, &webhook_id).await?;
+    if let Some(ref url) = request.url {
+        validate_webhook_url(url)?;
+    }
+    if let Some(ref events) = request.events {
+        validate_webhook_events(events)?;
+    }
+
+    repository::update_webhook(
+        pool,
+        &webhook_id,
+        request.url.as_deref(),
+        request.events.as_deref(),
+        request.active,
+    )
+    .await
+    .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    Ok(Json(find_org_webhook(&state, &org_id, &webhook_id).await?))
+}
+
+/// DELETE /api/v1/orgs/{id}/webhooks/{webhook_id}
+pub async fn delete_webhook(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((org_id, webhook_id)): Path<(String, String)>,
+) -> ApiResult<Json<serde_json::Value>> {
+    let pool = state.db();
+
+    let role = repository::get_membership(pool, &user.user_id, &org_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .ok_or_else(|| ApiError::Forbidden("You are not a member of this organization".to_string()))?;
+
+    if !role.can_modify_org() {
+        return Err(ApiError::Forbidden(
+            "Only owners and admins can remove webhooks".to_string(),
+        ));
+    }
+
+    find_org_webhook(&state, &org_id, &webhook_id).await?;
+    repository::delete_webhook(pool, &webhook_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    info!("Webhook {} removed from org {} by {}", webhook_id, org_id, user.user_id);
+
+    Ok(Json(serde_json::json!({
+        "message": "Webhook removed successfully"
+    })))
+}
+
+/// GET /api/v1/orgs/{id}/webhooks/{webhook_id}/deliveries
+pub async fn list_webhook_deliveries(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((org_id, webhook_id)): Path<(String, String)>,
+    Query(query): Query<ListDeliveriesQuery>,
+) -> ApiResult<Json<Vec<WebhookDelivery>>> {
+    let pool = state.db();
+
+    repository::get_membership(pool, &user.user_id, &org_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .ok_or_else(|| ApiError::Forbidden("You are not a member of this organization".to_string()))?;
+
+    find_org_webhook(&state, &org_id, &webhook_id).await?;
+    let deliveries = repository::list_webhook_deliveries(pool, &webhook_id, query.limit)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    Ok(Json(deliveries))
+}
@@ -1,6 +1,6 @@
 //! Session management handlers
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
 use std::sync::Arc;
 use std::time::Instant;
@@ -14,8 +14,8 @@ use axum::{
 use futures::stream::Stream;
 use mas_auth::AuthenticatedUser;
 use mas_core::{
-    agent_system::EchoHandler, AgentBuilder, SendResult, StoredMessage, TraceCollector,
-    TraceEventType,
+    agent_system::EchoHandler, metrics::metrics, AgentBuilder, AgentSystem, ApprovalDecision, GuardrailViolation,
+    Plan, SendResult, StoredMessage, TraceCollector, TraceEvent,
 };
 use serde::Deserialize;
 use tokio_stream::wrappers::ReceiverStream;
@@ -24,13 +24,15 @@ use uuid::Uuid;
 
 use crate::error::{ApiError, ApiResult};
 use crate::models::{
-    AgentTraceStep, CreateSessionRequest, CreateSessionResponse, DeleteSessionResponse,
-    ListSessionsResponse, MessageResponse, PromptResult, SearchHit, SessionDetailResponse,
+    AgentTraceStep, AnswerPendingRequest, AnswerPendingResponse, ApprovalDecisionResponse,
+    CreateSessionRequest, CreateSessionResponse, DeleteSessionResponse, ListSessionsResponse,
+    MessageResponse, PendingApprovalsResponse, PendingQuestionsResponse, PromptResult, SearchHit, SessionDetailResponse,
     SessionHistoryResponse, SessionPromptRequest, SessionPromptResponse, SessionSearchRequest,
     SessionSearchResponse, SessionSummary,
 };
 use crate::session::SessionError;
 use crate::state::AppState;
+use crate::webhooks::{publish_prompt_events, EventKind, WebhookEvent};
 
 /// Check if a user owns a session (skips check if auth is disabled)
 async fn require_session_ownership(
@@ -79,6 +81,73 @@ fn session_to_api_error(e: SessionError) -> ApiError {
     }
 }
 
+/// Look up the agent system a session runs on
+async fn session_system(state: &AppState, session_id: &str) -> ApiResult<Arc<AgentSystem>> {
+    let system_name = {
+        let manager = state.session_manager().read().await;
+        manager
+            .get_session_system(session_id)
+            .ok_or_else(|| ApiError::NotFound(format!("Session not found: {}", session_id)))?
+            .to_string()
+    };
+
+    state
+        .get_system(&system_name)
+        .await
+        .ok_or(ApiError::SystemNotFound(system_name))
+}
+
+/// Create the trace collector for a prompt, carrying the session (if any) and caller
+///
+/// Client metadata is applied first so it cannot override the keys the
+/// server sets for connection guards and agent memory (`authenticated`,
+/// `user_id`, `org_id`, `session_id`).
+pub(crate) async fn request_trace(
+    state: &AppState,
+    system_name: &str,
+    session_id: Option<&str>,
+    user: &AuthenticatedUser,
+    metadata: &HashMap<String, String>,
+) -> TraceCollector {
+    let mut trace = TraceCollector::new();
+    if let Some(session_id) = session_id {
+        trace = trace.with_session_id(session_id);
+    }
+    for (key, value) in metadata {
+        trace = trace.with_metadata(key, value);
+    }
+    // The org the caller reaches the system through, for org-scoped memories
+    let org_id = match state.try_db() {
+        Some(pool) => mas_auth::repository::find_user_system_org(pool, &user.user_id, system_name)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Could not look up the org of user {}: {}", user.user_id, e);
+                None
+            }),
+        None => None,
+    };
+    let trace = trace
+        .with_metadata("authenticated", "true")
+        .with_metadata("user_id", &user.user_id)
+        .with_metadata("org_id", org_id.unwrap_or_default());
+    match session_id {
+        Some(session_id) => trace.with_metadata("session_id", session_id),
+        None => trace,
+    }
+}
+
+/// Metadata stored with an agent's response in the session
+///
+/// Lists the guardrail violations of the request, if there were any.
+fn response_metadata(elapsed_ms: u64, trace_events: &[TraceEvent]) -> serde_json::Value {
+    let mut meta = serde_json::json!({ "elapsed_ms": elapsed_ms });
+    let violations = GuardrailViolation::from_events(trace_events);
+    if !violations.is_empty() {
+        meta["guardrails"] = serde_json::json!(violations);
+    }
+    meta
+}
+
 /// Query parameters for listing sessions
 #[derive(Debug, Deserialize)]
 pub struct ListSessionsQuery {
@@ -138,6 +207,10 @@ pub async fn create_session(
         }
     }
 
+    state.webhooks().publish(
+        WebhookEvent::new(EventKind::SessionCreated, &request.system_name).with_session(Some(&session_info.id)),
+    );
+
     info!(
         "Created session {} for system {}",
         session_info.id, request.system_name
@@ -339,22 +412,16 @@ pub async fn send_session_prompt(
 
     let target_agent = match request.target_agent {
         Some(ref agent_name) => {
-            if !metadata.agent_names.contains(agent_name) {
+            if !metadata.has_target(agent_name) {
                 return Err(ApiError::AgentNotFound(format!(
                     "Agent '{}' not found in system '{}'. Available agents: {:?}",
-                    agent_name, system_name, metadata.agent_names
+                    agent_name, system_name, metadata.target_names()
                 )));
             }
             agent_name.clone()
         }
         None => metadata
-            .agents
-            .iter()
-            .find(|a| a.entry_point)
-            .or_else(|| metadata.agents.iter().find(|a| a.name == "Coordinator"))
-            .or_else(|| metadata.agents.iter().find(|a| a.routing))
-            .or_else(|| metadata.agents.first())
-            .map(|a| a.name.clone())
+            .default_target()
             .ok_or_else(|| ApiError::Internal("No agents available in system".to_string()))?,
     };
 
@@ -397,8 +464,9 @@ pub async fn send_session_prompt(
         target_agent,
         &request.content[..request.content.len().min(50)]
     );
+    metrics().record_prompt(&system_name);
 
-    let trace_collector = TraceCollector::new();
+    let trace_collector = request_trace(&state, &system_name, Some(&session_id), &user, &request.metadata).await;
 
     let user_name = format!("_ApiUser_{}", Uuid::new_v4());
     let user = AgentBuilder::new(&user_name)
@@ -432,27 +500,35 @@ pub async fn send_session_prompt(
         to: target_agent.clone(),
         content: request.content.clone(),
         step_type: "request".to_string(),
+        system: None,
     });
 
     let trace_events = trace_collector.events().await;
+    publish_prompt_events(
+        &state,
+        &system_name,
+        Some(&session_id),
+        &target_agent,
+        &trace_events,
+        &result,
+        elapsed_ms,
+    )
+    .await;
+    let plan = Plan::latest(&trace_events, &target_agent);
+    let meta = response_metadata(elapsed_ms, &trace_events);
     for event in trace_events {
         trace.push(AgentTraceStep {
             from: event.from,
             to: event.to,
             content: event.content,
-            step_type: match event.event_type {
-                TraceEventType::Request => "request".to_string(),
-                TraceEventType::Response => "response".to_string(),
-                TraceEventType::Forward => "forward".to_string(),
-                TraceEventType::Synthesis => "synthesis".to_string(),
-            },
+            step_type: event.event_type.as_str().to_string(),
+            system: event.system,
         });
     }
 
     let prompt_result = match &result {
         SendResult::Response(msg) => {
             let mut manager = state.session_manager().write().await;
-            let meta = serde_json::json!({ "elapsed_ms": elapsed_ms });
             manager
                 .store_agent_response(&session_id, &msg.from, &msg.content, Some(meta))
                 .await
@@ -463,6 +539,7 @@ pub async fn send_session_prompt(
                 to: "User".to_string(),
                 content: msg.content.clone(),
                 step_type: "response".to_string(),
+                system: None,
             });
 
             PromptResult::Response {
@@ -476,6 +553,7 @@ pub async fn send_session_prompt(
                 to: "User".to_string(),
                 content: format!("Timeout: {}", err),
                 step_type: "response".to_string(),
+                system: None,
             });
             PromptResult::Timeout {
                 message: err.to_string(),
@@ -492,6 +570,7 @@ pub async fn send_session_prompt(
         elapsed_ms,
         context: context_messages,
         trace,
+        plan,
     }))
 }
 
@@ -526,22 +605,16 @@ pub async fn send_session_prompt_stream(
 
     let target_agent = match request.target_agent {
         Some(ref agent_name) => {
-            if !metadata.agent_names.contains(agent_name) {
+            if !metadata.has_target(agent_name) {
                 return Err(ApiError::AgentNotFound(format!(
                     "Agent '{}' not found in system '{}'. Available agents: {:?}",
-                    agent_name, system_name, metadata.agent_names
+                    agent_name, system_name, metadata.target_names()
                 )));
             }
             agent_name.clone()
         }
         None => metadata
-            .agents
-            .iter()
-            .find(|a| a.entry_point)
-            .or_else(|| metadata.agents.iter().find(|a| a.name == "Coordinator"))
-            .or_else(|| metadata.agents.iter().find(|a| a.routing))
-            .or_else(|| metadata.agents.first())
-            .map(|a| a.name.clone())
+            .default_target()
             .ok_or_else(|| ApiError::Internal("No agents available in system".to_string()))?,
     };
 
@@ -584,9 +657,12 @@ pub async fn send_session_prompt_stream(
         target_agent,
         &request.content[..request.content.len().min(50)]
     );
+    metrics().record_prompt(&system_name);
 
-    let trace_collector = TraceCollector::new();
+    let trace_collector = request_trace(&state, &system_name, Some(&session_id), &user, &request.metadata).await;
     let mut trace_rx = trace_collector.subscribe();
+    let mut pending_rx = system.human_inbox().subscribe();
+    let mut approval_rx = system.approval_queue().subscribe();
 
     let user_name = format!("_ApiUser_{}", Uuid::new_v4());
     let user = AgentBuilder::new(&user_name)
@@ -604,6 +680,7 @@ pub async fn send_session_prompt_stream(
     let task_content = request.content.clone();
     let task_target = target_agent.clone();
     let task_session_id = session_id.clone();
+    let task_system_name = system_name.clone();
 
     tokio::spawn(async move {
         let work_trace = trace_collector.clone();
@@ -619,6 +696,8 @@ pub async fn send_session_prompt_stream(
         });
 
         tokio::pin!(work);
+        let mut pending_open = true;
+        let mut approvals_open = true;
 
         loop {
             tokio::select! {
@@ -630,12 +709,8 @@ pub async fn send_session_prompt_stream(
                                 from: trace_event.from.clone(),
                                 to: trace_event.to.clone(),
                                 content: trace_event.content.clone(),
-                                step_type: match trace_event.event_type {
-                                    TraceEventType::Request => "request".to_string(),
-                                    TraceEventType::Response => "response".to_string(),
-                                    TraceEventType::Forward => "forward".to_string(),
-                                    TraceEventType::Synthesis => "synthesis".to_string(),
-                                },
+                                step_type: trace_event.event_type.as_str().to_string(),
+                                system: trace_event.system.clone(),
                             };
                             if let Ok(json) = serde_json::to_string(&step) {
                                 let event = Event::default().event("trace").data(json);
@@ -651,18 +726,52 @@ pub async fn send_session_prompt_stream(
                         Err(_) => break,
                     }
                 }
+                question = pending_rx.recv(), if pending_open => {
+                    match question {
+                        // Questions this request is waiting on, for the human to answer
+                        Ok(question) if question.session_id.as_deref() == Some(task_session_id.as_str()) => {
+                            if let Ok(json) = serde_json::to_string(&question) {
+                                let event = Event::default().event("pending").data(json);
+                                if tx.send(Ok(event)).await.is_err() {
+                                    return;
+                                }
+                            }
+                        }
+                        Ok(_) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                            tracing::warn!("SSE pending receiver lagged by {} events", n);
+                            continue;
+                        }
+                        Err(_) => pending_open = false,
+                    }
+                }
+                approval = approval_rx.recv(), if approvals_open => {
+                    match approval {
+                        // Calls this request is waiting on, for the session owner to decide
+                        Ok(approval) if approval.session_id.as_deref() == Some(task_session_id.as_str()) => {
+                            if let Ok(json) = serde_json::to_string(&approval) {
+                                let event = Event::default().event("approval").data(json);
+                                if tx.send(Ok(event)).await.is_err() {
+                                    return;
+                                }
+                            }
+                        }
+                        Ok(_) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                            tracing::warn!("SSE approval receiver lagged by {} events", n);
+                            continue;
+                        }
+                        Err(_) => approvals_open = false,
+                    }
+                }
                 result = &mut work => {
                     while let Ok(trace_event) = trace_rx.try_recv() {
                         let step = AgentTraceStep {
                             from: trace_event.from.clone(),
                             to: trace_event.to.clone(),
                             content: trace_event.content.clone(),
-                            step_type: match trace_event.event_type {
-                                TraceEventType::Request => "request".to_string(),
-                                TraceEventType::Response => "response".to_string(),
-                                TraceEventType::Forward => "forward".to_string(),
-                                TraceEventType::Synthesis => "synthesis".to_string(),
-                            },
+                            step_type: trace_event.event_type.as_str().to_string(),
+                            system: trace_event.system.clone(),
                         };
                         if let Ok(json) = serde_json::to_string(&step) {
                             let event = Event::default().event("trace").data(json);
@@ -679,27 +788,35 @@ pub async fn send_session_prompt_stream(
                                 to: task_target.clone(),
                                 content: task_content.clone(),
                                 step_type: "request".to_string(),
+                                system: None,
                             }];
 
                             let trace_events = trace_collector.events().await;
+                            publish_prompt_events(
+                                &state,
+                                &task_system_name,
+                                Some(&task_session_id),
+                                &task_target,
+                                &trace_events,
+                                &send_result,
+                                elapsed_ms,
+                            )
+                            .await;
+                            let plan = Plan::latest(&trace_events, &task_target);
                             for te in &trace_events {
                                 trace.push(AgentTraceStep {
                                     from: te.from.clone(),
                                     to: te.to.clone(),
                                     content: te.content.clone(),
-                                    step_type: match te.event_type {
-                                        TraceEventType::Request => "request".to_string(),
-                                        TraceEventType::Response => "response".to_string(),
-                                        TraceEventType::Forward => "forward".to_string(),
-                                        TraceEventType::Synthesis => "synthesis".to_string(),
-                                    },
+                                    step_type: te.event_type.as_str().to_string(),
+                                    system: te.system.clone(),
                                 });
                             }
 
                             let prompt_result = match &send_result {
                                 SendResult::Response(msg) => {
                                     let mut manager = state.session_manager().write().await;
-                                    let meta = serde_json::json!({ "elapsed_ms": elapsed_ms });
+                                    let meta = response_metadata(elapsed_ms, &trace_events);
                                     let _ = manager
                                         .store_agent_response(
                                             &task_session_id,
@@ -714,6 +831,7 @@ pub async fn send_session_prompt_stream(
                                         to: "User".to_string(),
                                         content: msg.content.clone(),
                                         step_type: "response".to_string(),
+                                        system: None,
                                     });
 
                                     PromptResult::Response {
@@ -727,6 +845,7 @@ pub async fn send_session_prompt_stream(
                                         to: "User".to_string(),
                                         content: format!("Timeout: {}", err),
                                         step_type: "response".to_string(),
+                                        system: None,
                                     });
                                     PromptResult::Timeout {
                                         message: err.to_string(),
@@ -743,6 +862,7 @@ pub async fn send_session_prompt_stream(
                                 elapsed_ms,
                                 context: context_messages,
                                 trace,
+                                plan,
                             };
 
                             if let Ok(json) = serde_json::to_string(&response) {
@@ -778,6 +898,132 @@ pub async fn send_session_prompt_stream(
     ))
 }
 
+/// GET /api/v1/sessions/{id}/pending - List questions waiting for a human answer
+pub async fn list_pending(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(session_id): Path<String>,
+) -> ApiResult<Json<PendingQuestionsResponse>> {
+    require_session_ownership(&state, &user, &session_id).await?;
+    let system = session_system(&state, &session_id).await?;
+
+    let pending = system.human_inbox().pending(Some(&session_id));
+    let total = pending.len();
+
+    Ok(Json(PendingQuestionsResponse {
+        session_id,
+        pending,
+        total,
+    }))
+}
+
+/// POST /api/v1/sessions/{id}/pending/{pending_id}/answer - Answer a pending question
+///
+/// The agent that asked resumes with the answer as the human agent's reply.
+pub async fn answer_pending(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((session_id, pending_id)): Path<(String, Uuid)>,
+    Json(request): Json<AnswerPendingRequest>,
+) -> ApiResult<Json<AnswerPendingResponse>> {
+    require_session_ownership(&state, &user, &session_id).await?;
+    let system = session_system(&state, &session_id).await?;
+    let inbox = system.human_inbox();
+
+    let not_found = || {
+        ApiError::NotFound(format!(
+            "No pending question '{}' in session '{}'",
+            pending_id, session_id
+        ))
+    };
+
+    // Only questions raised by this session can be answered through it
+    let question = inbox.get(pending_id).ok_or_else(not_found)?;
+    if question.session_id.as_deref() != Some(session_id.as_str()) {
+        return Err(not_found());
+    }
+    let question = inbox
+        .answer(pending_id, request.answer)
+        .map_err(|_| not_found())?;
+
+    info!(
+        "Answered pending question {} for '{}' in session {}",
+        pending_id, question.agent, session_id
+    );
+
+    Ok(Json(AnswerPendingResponse {
+        id: pending_id,
+        session_id,
+        agent: question.agent,
+        message: "Answer delivered".to_string(),
+    }))
+}
+
+/// GET /api/v1/sessions/{id}/approvals - List tool and database calls waiting for approval
+pub async fn list_approvals(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(session_id): Path<String>,
+) -> ApiResult<Json<PendingApprovalsResponse>> {
+    require_session_ownership(&state, &user, &session_id).await?;
+    let system = session_system(&state, &session_id).await?;
+
+    let approvals = system.approval_queue().pending(Some(&session_id));
+    let total = approvals.len();
+
+    Ok(Json(PendingApprovalsResponse {
+        session_id,
+        approvals,
+        total,
+    }))
+}
+
+/// POST /api/v1/sessions/{id}/approvals/{approval_id} - Approve, deny or edit a pending call
+///
+/// The body is `{"decision": "approve"}`, `{"decision": "deny", "reason": ...}`
+/// or `{"decision": "edit", "input": ...}` where `input` replaces the call's
+/// parameters (tools) or SQL (databases).
+pub async fn decide_approval(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((session_id, approval_id)): Path<(String, Uuid)>,
+    Json(decision): Json<ApprovalDecision>,
+) -> ApiResult<Json<ApprovalDecisionResponse>> {
+    require_session_ownership(&state, &user, &session_id).await?;
+    let system = session_system(&state, &session_id).await?;
+    let queue = system.approval_queue();
+
+    let not_found = || {
+        ApiError::NotFound(format!(
+            "No pending approval '{}' in session '{}'",
+            approval_id, session_id
+        ))
+    };
+
+    // Only calls made for this session can be decided through it
+    let approval = queue.get(approval_id).ok_or_else(not_found)?;
+    if approval.session_id.as_deref() != Some(session_id.as_str()) {
+        return Err(not_found());
+    }
+    let decision_name = decision.as_str().to_string();
+    let approval = queue
+        .decide(approval_id, decision)
+        .map_err(|_| not_found())?;
+
+    info!(
+        "Decided '{}' on call {} to '{}' in session {}",
+        decision_name, approval_id, approval.target, session_id
+    );
+
+    Ok(Json(ApprovalDecisionResponse {
+        id: approval_id,
+        session_id,
+        target: approval.target,
+        decision: decision_name,
+        message: "Decision delivered".to_string(),
+    }))
+}
+
 /// POST /api/v1/sessions/{id}/build-index - Build the search index for a session
 pub async fn build_session_index(
     State(state): State<AppState>,
@@ -11,20 +11,23 @@ use axum::{
 };
 use mas_auth::AuthenticatedUser;
 use mas_core::{
-    agent_system::EchoHandler, load_system_from_json, validate_config, AgentBuilder, SendResult,
+    agent_system::EchoHandler, load_named_system_from_json, metrics::metrics, validate_config, AgentBuilder, Plan,
+    SendResult,
 };
 use serde::Deserialize;
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
 use crate::error::{ApiError, ApiResult};
+use crate::handlers::sessions::request_trace;
 use crate::models::{
     AgentInfo, ConnectionInfo, DeleteSystemResponse, ListSystemsResponse, PromptResult,
     RegisterSystemRequest, RegisterSystemResponse, SendPromptRequest, SendPromptResponse,
-    SystemConfigResponse, SystemDetailResponse, SystemSummary, UpdateSystemRequest,
-    UpdateSystemResponse,
+    SystemConfigResponse, SystemDetailResponse, SystemSummary, SystemTriggersResponse, TriggerInfo,
+    UpdateSystemRequest, UpdateSystemResponse, WorkflowInfo, WorkflowStepInfo,
 };
 use crate::state::{extract_metadata, AppState, SystemEntry};
+use crate::webhooks::publish_prompt_events;
 
 /// Query parameters for listing systems
 #[derive(Debug, Deserialize)]
@@ -77,7 +80,7 @@ pub async fn create_system(
     // Extract metadata before we move the config
     let metadata = extract_metadata(&request.config);
 
-    // Write config to a temporary file for load_system_from_json
+    // Write config to a temporary file for load_named_system_from_json
     let temp_dir = std::env::temp_dir();
     let temp_file = temp_dir.join(format!("mas-api-{}.json", Uuid::new_v4()));
 
@@ -88,7 +91,7 @@ pub async fn create_system(
         .map_err(|e| ApiError::Internal(format!("Failed to write temp config: {}", e)))?;
 
     // Load the system
-    let system = load_system_from_json(&temp_file)
+    let system = load_named_system_from_json(&temp_file, &request.name)
         .await
         .map_err(|e| ApiError::ConfigError(e.to_string()))?;
 
@@ -253,15 +256,70 @@ pub async fn get_system(
         })
         .collect();
 
+    let workflows: Vec<WorkflowInfo> = metadata
+        .workflows
+        .iter()
+        .map(|workflow| WorkflowInfo {
+            name: workflow.name.clone(),
+            description: workflow.description.clone(),
+            entry_point: workflow.entry_point,
+            steps: workflow
+                .steps
+                .iter()
+                .map(|step| WorkflowStepInfo {
+                    id: step.id.clone(),
+                    target: step.target.clone(),
+                    depends_on: step.depends_on.clone(),
+                })
+                .collect(),
+        })
+        .collect();
+
     Ok(Json(SystemDetailResponse {
         name,
         agent_count: metadata.agent_count,
         agents,
+        workflows,
         global_timeout_secs: metadata.global_timeout_secs,
         created_at,
     }))
 }
 
+/// GET /api/v1/systems/{name}/triggers - List scheduled triggers with their run history
+pub async fn get_system_triggers(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(name): Path<String>,
+) -> ApiResult<Json<SystemTriggersResponse>> {
+    require_system_access(&state, &user, &name).await?;
+
+    let (config, _) = state
+        .get_system_config(&name)
+        .await
+        .ok_or_else(|| ApiError::SystemNotFound(name.clone()))?;
+
+    let mut status = state.trigger_scheduler().status(&name);
+    let triggers: Vec<TriggerInfo> = config
+        .triggers
+        .into_iter()
+        .map(|trigger| TriggerInfo {
+            status: status.remove(&trigger.name).unwrap_or_default(),
+            name: trigger.name,
+            cron: trigger.cron,
+            target_agent: trigger.target_agent,
+            prompt: trigger.prompt,
+            session_id: trigger.session_id,
+        })
+        .collect();
+    let total = triggers.len();
+
+    Ok(Json(SystemTriggersResponse {
+        system: name,
+        triggers,
+        total,
+    }))
+}
+
 /// GET /api/v1/systems/{name}/config - Get the full system configuration
 pub async fn get_system_config(
     State(state): State<AppState>,
@@ -343,7 +401,7 @@ pub async fn update_system(
     std::fs::write(&temp_file, &config_json)
         .map_err(|e| ApiError::Internal(format!("Failed to write temp config: {}", e)))?;
 
-    let system = load_system_from_json(&temp_file)
+    let system = load_named_system_from_json(&temp_file, &name)
         .await
         .map_err(|e| ApiError::ConfigError(e.to_string()))?;
 
@@ -401,22 +459,16 @@ pub async fn send_prompt(
 
     let target_agent = match request.target_agent {
         Some(ref agent_name) => {
-            if !metadata.agent_names.contains(agent_name) {
+            if !metadata.has_target(agent_name) {
                 return Err(ApiError::AgentNotFound(format!(
                     "Agent '{}' not found in system '{}'. Available agents: {:?}",
-                    agent_name, name, metadata.agent_names
+                    agent_name, name, metadata.target_names()
                 )));
             }
             agent_name.clone()
         }
         None => metadata
-            .agents
-            .iter()
-            .find(|a| a.entry_point)
-            .or_else(|| metadata.agents.iter().find(|a| a.name == "Coordinator"))
-            .or_else(|| metadata.agents.iter().find(|a| a.routing))
-            .or_else(|| metadata.agents.first())
-            .map(|a| a.name.clone())
+            .default_target()
             .ok_or_else(|| ApiError::Internal("No agents available in system".to_string()))?,
     };
 
@@ -426,20 +478,22 @@ pub async fn send_prompt(
         target_agent,
         &request.content[..request.content.len().min(50)]
     );
+    metrics().record_prompt(&name);
 
     let user_name = format!("_ApiUser_{}", Uuid::new_v4());
-    let user = AgentBuilder::new(&user_name)
+    let sender = AgentBuilder::new(&user_name)
         .blocking_connection(&target_agent)
         .build();
 
     system
-        .register_agent(user, Arc::new(EchoHandler))
+        .register_agent(sender, Arc::new(EchoHandler))
         .await
         .map_err(|e| ApiError::Internal(format!("Failed to create user agent: {}", e)))?;
 
     let message_id = Uuid::new_v4();
+    let trace = request_trace(&state, &name, None, &user, &request.metadata).await;
     let result = system
-        .send_message(&user_name, &target_agent, &request.content)
+        .send_message_with_trace(&user_name, &target_agent, &request.content, trace.clone())
         .await
         .map_err(|e| {
             error!("Error sending message: {}", e);
@@ -447,6 +501,8 @@ pub async fn send_prompt(
         })?;
 
     let elapsed_ms = start.elapsed().as_millis() as u64;
+    let trace_events = trace.events().await;
+    publish_prompt_events(&state, &name, None, &target_agent, &trace_events, &result, elapsed_ms).await;
 
     let prompt_result = match result {
         SendResult::Response(msg) => PromptResult::Response {
@@ -458,11 +514,13 @@ pub async fn send_prompt(
         },
         SendResult::Notified => PromptResult::Notified,
     };
+    let plan = Plan::latest(&trace_events, &target_agent);
 
     Ok(Json(SendPromptResponse {
         message_id,
         target_agent,
         result: prompt_result,
         elapsed_ms,
+        plan,
     }))
 }
@@ -7,8 +7,11 @@ pub mod app;
 pub mod error;
 pub mod handlers;
 pub mod models;
+mod nested;
+pub mod scheduler;
 pub mod session;
 pub mod state;
+pub mod webhooks;
 
 pub use app::create_router;
 pub use error::{ApiError, ApiResult};
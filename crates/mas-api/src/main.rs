@@ -8,7 +8,11 @@ use std::net::SocketAddr;
 use clap::Parser;
 use mas_api::{create_router, AppState};
 use mas_auth::{create_pool, run_migrations, JwtConfig};
-use tracing::{info, Level};
+#[cfg(feature = "otlp")]
+use mas_core::telemetry::{self, TelemetryConfig};
+use tracing::level_filters::LevelFilter;
+use tracing::{info, warn, Level};
+use tracing_subscriber::prelude::*;
 
 /// Multi-Agent System REST API Server
 ///
@@ -24,6 +28,12 @@ use tracing::{info, Level};
     POST   /api/v1/auth/login          Log in
     POST   /api/v1/auth/refresh        Refresh access token
 
+  Monitoring (bearer MAS_METRICS_TOKEN):
+    GET    /metrics                    Prometheus metrics
+
+  Hooks (signed with the hook's secret):
+    POST   /api/v1/hooks/{system}/{hook}  Turn a webhook payload into a prompt
+
   Auth (authenticated):
     GET    /api/v1/auth/me             Get current user profile
     PUT    /api/v1/auth/me             Update profile
@@ -35,6 +45,7 @@ use tracing::{info, Level};
     PUT    /api/v1/systems/{name}         Update a system
     DELETE /api/v1/systems/{name}         Remove a system
     POST   /api/v1/systems/{name}/prompt  Send a prompt (no session)
+    GET    /api/v1/systems/{name}/triggers  Scheduled triggers and their runs
 
   Sessions:
     POST   /api/v1/sessions               Create a new session
@@ -44,6 +55,10 @@ use tracing::{info, Level};
     GET    /api/v1/sessions/{id}/history  Get conversation history
     GET    /api/v1/sessions/{id}/search   Semantic search in session
     POST   /api/v1/sessions/{id}/prompt   Send a prompt (with memory)
+    GET    /api/v1/sessions/{id}/pending  List questions for human agents
+    POST   /api/v1/sessions/{id}/pending/{qid}/answer  Answer a question
+    GET    /api/v1/sessions/{id}/approvals  List calls waiting for approval
+    POST   /api/v1/sessions/{id}/approvals/{aid}  Approve, deny or edit a call
     POST   /api/v1/sessions/{id}/build-index  Build search index
 
   Organizations:
@@ -60,16 +75,28 @@ use tracing::{info, Level};
     GET    /api/v1/orgs/{id}/systems      List org's systems
     PUT    /api/v1/orgs/{id}/systems/{n}  Add system to org
     DELETE /api/v1/orgs/{id}/systems/{n}  Remove system from org
+    GET    /api/v1/orgs/{id}/webhooks     List webhooks
+    POST   /api/v1/orgs/{id}/webhooks     Add webhook (returns its secret)
+    GET    /api/v1/orgs/{id}/webhooks/{wid}  Get webhook
+    PUT    /api/v1/orgs/{id}/webhooks/{wid}  Update webhook
+    DELETE /api/v1/orgs/{id}/webhooks/{wid}  Remove webhook
+    GET    /api/v1/orgs/{id}/webhooks/{wid}/deliveries  Delivery log
 
 EXAMPLES:
     mas-api                     Start server on 0.0.0.0:8080
     mas-api --port 3000         Start server on port 3000
     mas-api --host 127.0.0.1    Bind to localhost only
+    mas-api --otlp-endpoint http://localhost:4318
+                                Export agent spans to an OTLP collector
+                                (needs a build with --features otlp)
 
 ENVIRONMENT VARIABLES:
     MAS_JWT_SECRET              JWT signing secret (required in production)
     MAS_DISABLE_AUTH=true       Disable authentication (dev mode)
     MAS_DB_PATH                 SQLite database path (default: data/mas.db)
+    MAS_METRICS_TOKEN           Bearer token for /metrics (not served without one)
+    OTEL_EXPORTER_OTLP_ENDPOINT OTLP/HTTP collector for spans (if --otlp-endpoint is not given)
+    OTEL_SERVICE_NAME           Service name of exported spans (default: mas-api)
 "#)]
 struct Args {
     /// Host address to bind to
@@ -83,6 +110,11 @@ struct Args {
     /// Enable debug logging
     #[arg(short, long)]
     debug: bool,
+
+    /// OTLP/HTTP collector to export agent spans to (e.g. http://localhost:4318,
+    /// needs the `otlp` feature)
+    #[arg(long)]
+    otlp_endpoint: Option<String>,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -99,9 +131,33 @@ async fn async_main() -> anyhow::Result<()> {
     let args = Args::parse();
 
     let log_level = if args.debug { Level::DEBUG } else { Level::INFO };
-    tracing_subscriber::fmt()
-        .with_max_level(log_level)
-        .init();
+
+    // Span export is off unless a collector is configured. The subscriber
+    // keeps a clone of the provider, so the remaining spans are only sent
+    // when the provider is shut down after the server stops.
+    let otlp_endpoint = args
+        .otlp_endpoint
+        .clone()
+        .or_else(|| std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok());
+    #[cfg(feature = "otlp")]
+    let tracer_provider = match &otlp_endpoint {
+        Some(endpoint) => {
+            let service_name = std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "mas-api".to_string());
+            let config = TelemetryConfig::new(endpoint).with_service_name(service_name);
+            Some(telemetry::otlp_provider(&config)?)
+        }
+        None => None,
+    };
+
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(LevelFilter::from_level(log_level)));
+    #[cfg(feature = "otlp")]
+    let subscriber = subscriber.with(
+        tracer_provider
+            .as_ref()
+            .map(|provider| telemetry::layer(provider).with_filter(LevelFilter::INFO)),
+    );
+    subscriber.init();
 
     let addr: SocketAddr = format!("{}:{}", args.host, args.port).parse()?;
 
@@ -127,16 +183,25 @@ async fn async_main() -> anyhow::Result<()> {
     let jwt_config = JwtConfig::new(jwt_secret);
 
     // Create application state
-    let state = AppState::new()
+    let mut state = AppState::new()
         .with_db(pool)
         .with_jwt_config(jwt_config)
         .with_auth_disabled(auth_disabled);
+    if let Ok(token) = std::env::var("MAS_METRICS_TOKEN") {
+        state = state.with_metrics_token(token);
+    }
 
     // Initialize application state (load existing sessions and systems)
     if let Err(e) = state.init().await {
         tracing::warn!("Failed to initialize state: {}", e);
     }
 
+    // Run configured triggers in the background
+    mas_api::scheduler::spawn(state.clone());
+
+    // Deliver events to outbound webhooks
+    mas_api::webhooks::spawn(state.clone());
+
     let app = create_router(state);
 
     info!("Starting Multi-Agent System API server on {}", addr);
@@ -145,11 +210,38 @@ async fn async_main() -> anyhow::Result<()> {
         info!("Authentication is DISABLED (MAS_DISABLE_AUTH=true)");
     }
     info!("Database: {}", db_path);
+    if let Some(endpoint) = &otlp_endpoint {
+        if cfg!(feature = "otlp") {
+            info!("Exporting spans to {}", endpoint);
+        } else {
+            warn!("Not exporting spans to {}: built without the otlp feature", endpoint);
+        }
+    }
     info!("Session data stored in data/sessions/");
     info!("System configs stored in data/systems/");
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    #[cfg(feature = "otlp")]
+    if let Some(provider) = tracer_provider {
+        // Shutting down waits for the exporter, which blocks
+        match tokio::task::spawn_blocking(move || provider.shutdown()).await? {
+            Ok(()) => info!("Flushed exported spans"),
+            Err(e) => warn!("Could not flush exported spans: {}", e),
+        }
+    }
 
     Ok(())
 }
+
+/// Resolves when the server is asked to stop (Ctrl+C)
+async fn shutdown_signal() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        warn!("Could not listen for Ctrl+C: {}", e);
+        std::future::pending::<()>().await;
+    }
+    info!("Shutting down");
+}
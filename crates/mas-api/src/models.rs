@@ -2,9 +2,13 @@
 
 use chrono::{DateTime, Utc};
 use mas_core::config_loader::SystemConfigJson;
+use mas_core::{PendingApproval, PendingQuestion, Plan};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
+use crate::scheduler::{RunOutcome, TriggerStatus};
+
 /// Request body for registering a new system
 #[derive(Debug, Deserialize)]
 pub struct RegisterSystemRequest {
@@ -48,6 +52,8 @@ pub struct SystemDetailResponse {
     pub name: String,
     pub agent_count: usize,
     pub agents: Vec<AgentInfo>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub workflows: Vec<WorkflowInfo>,
     pub global_timeout_secs: u64,
     pub created_at: DateTime<Utc>,
 }
@@ -62,6 +68,26 @@ pub struct AgentInfo {
     pub connections: Vec<ConnectionInfo>,
 }
 
+/// Information about a workflow within a system
+#[derive(Debug, Serialize)]
+pub struct WorkflowInfo {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub entry_point: bool,
+    pub steps: Vec<WorkflowStepInfo>,
+}
+
+/// Information about a step of a workflow
+#[derive(Debug, Serialize)]
+pub struct WorkflowStepInfo {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+}
+
 /// Information about a connection between agents
 #[derive(Debug, Serialize)]
 pub struct ConnectionInfo {
@@ -71,6 +97,52 @@ pub struct ConnectionInfo {
     pub timeout_secs: Option<u64>,
 }
 
+/// A scheduled trigger with its runs
+#[derive(Debug, Serialize)]
+pub struct TriggerInfo {
+    pub name: String,
+    pub cron: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_agent: Option<String>,
+    pub prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(flatten)]
+    pub status: TriggerStatus,
+}
+
+/// Response listing a system's triggers
+#[derive(Debug, Serialize)]
+pub struct SystemTriggersResponse {
+    pub system: String,
+    pub triggers: Vec<TriggerInfo>,
+    pub total: usize,
+}
+
+/// Response to an inbound hook call
+#[derive(Debug, Serialize)]
+pub struct HookResponse {
+    pub system: String,
+    pub hook: String,
+    /// Session the prompt was posted to, if it got that far
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// The answer; absent when it is delivered to the callback URL instead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<RunOutcome>,
+}
+
+/// Result of a hook run, POSTed to the hook's callback URL
+#[derive(Debug, Serialize)]
+pub struct HookCallback {
+    pub system: String,
+    pub hook: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(flatten)]
+    pub outcome: RunOutcome,
+}
+
 /// Full system config response (for editor reload)
 #[derive(Debug, Serialize)]
 pub struct SystemConfigResponse {
@@ -87,6 +159,10 @@ pub struct SendPromptRequest {
     /// Optional target agent (auto-selects Coordinator or first routing agent if not specified)
     #[serde(default)]
     pub target_agent: Option<String>,
+    /// Request metadata checked by connection guards (`authenticated` and
+    /// `user_id` are always set by the server)
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
 }
 
 /// Response after sending a prompt
@@ -100,6 +176,9 @@ pub struct SendPromptResponse {
     pub result: PromptResult,
     /// Time taken to process in milliseconds
     pub elapsed_ms: u64,
+    /// The final plan, if a planning agent handled the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<Plan>,
 }
 
 /// Result of processing a prompt
@@ -235,6 +314,10 @@ pub struct SessionPromptRequest {
     /// How many past messages to include as context
     #[serde(default = "default_context_limit")]
     pub context_limit: usize,
+    /// Request metadata checked by connection guards (`authenticated`,
+    /// `user_id` and `session_id` are always set by the server)
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
 }
 
 fn default_context_limit() -> usize {
@@ -250,8 +333,11 @@ pub struct AgentTraceStep {
     pub to: String,
     /// The message content
     pub content: String,
-    /// Type of message: "request", "response", "forward", "synthesis"
+    /// Type of step: "request", "response", "forward", "synthesis", "ballot", "vote", "plan", ...
     pub step_type: String,
+    /// Nested system the step happened in (see `system:` connections)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
 }
 
 /// Response after sending a prompt to a session
@@ -273,6 +359,9 @@ pub struct SessionPromptResponse {
     /// Trace of agent communications (for verbose mode)
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub trace: Vec<AgentTraceStep>,
+    /// The final plan, if a planning agent handled the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<Plan>,
 }
 
 /// Request for searching session history
@@ -303,3 +392,48 @@ pub struct SessionSearchResponse {
     pub query: String,
     pub hits: Vec<SearchHit>,
 }
+
+/// Questions in a session waiting for a human answer
+#[derive(Debug, Serialize)]
+pub struct PendingQuestionsResponse {
+    pub session_id: String,
+    pub pending: Vec<PendingQuestion>,
+    pub total: usize,
+}
+
+/// Request body for answering a pending question
+#[derive(Debug, Deserialize)]
+pub struct AnswerPendingRequest {
+    /// The reply handed back to the agent that asked
+    pub answer: String,
+}
+
+/// Response after answering a pending question
+#[derive(Debug, Serialize)]
+pub struct AnswerPendingResponse {
+    pub id: Uuid,
+    pub session_id: String,
+    /// The human agent that answered
+    pub agent: String,
+    pub message: String,
+}
+
+/// Tool and database calls in a session waiting for approval
+#[derive(Debug, Serialize)]
+pub struct PendingApprovalsResponse {
+    pub session_id: String,
+    pub approvals: Vec<PendingApproval>,
+    pub total: usize,
+}
+
+/// Response after deciding on a pending call
+#[derive(Debug, Serialize)]
+pub struct ApprovalDecisionResponse {
+    pub id: Uuid,
+    pub session_id: String,
+    /// The tool or database holding the call
+    pub target: String,
+    /// "approve", "deny" or "edit"
+    pub decision: String,
+    pub message: String,
+}
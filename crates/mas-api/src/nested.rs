@@ -0,0 +1,311 @@
+//! Nested systems
+//!
+//! An agent can connect to another registered system by naming it in its
+//! connections as `"system:<name>"`. When a system is registered, an agent is
+//! added under each such name that forwards what it receives to the other
+//! system's entry point, like a tool would call an API.
+//!
+//! Every call checks that the requesting user may access the other system.
+//! The calling system shows up as a sender registered for that call only. The
+//! nested system's trace events are recorded in the caller's trace, tagged
+//! with the nested system's name.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use mas_core::agent_system::EchoHandler;
+use mas_core::config_loader::SYSTEM_CONNECTION_PREFIX;
+use mas_core::{Agent, AgentBuilder, Message, MessageHandler, SendResult, TraceCollector};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::state::{AppState, SystemEntry};
+
+/// Most systems a request may pass through below the one it was sent to
+const MAX_NESTING: usize = 8;
+
+/// Register an agent for every `system:` connection of a system
+pub(crate) async fn connect_systems(state: &AppState, name: &str, entry: &SystemEntry) {
+    for target in entry.config.connected_systems() {
+        let agent = AgentBuilder::new(format!("{}{}", SYSTEM_CONNECTION_PREFIX, target)).build();
+        let handler = Arc::new(SystemConnectionHandler {
+            state: state.clone(),
+            caller: name.to_string(),
+            system: target.clone(),
+        });
+        match entry.system.register_agent(agent, handler).await {
+            Ok(()) => info!("System '{}' connected to system '{}'", name, target),
+            Err(e) => warn!("Could not connect system '{}' to system '{}': {}", name, target, e),
+        }
+    }
+}
+
+/// Forwards messages to the entry point of another registered system
+struct SystemConnectionHandler {
+    state: AppState,
+    /// The system this handler is registered in
+    caller: String,
+    /// The system messages are forwarded to
+    system: String,
+}
+
+impl SystemConnectionHandler {
+    async fn call(&self, message: &Message) -> Result<String, String> {
+        let trace = TraceCollector::current().unwrap_or_default();
+        // The system the request was sent to is not among the nested ones
+        let origin = trace.origin().unwrap_or(&self.caller).to_string();
+        let systems = trace.systems();
+        if self.system == self.caller || self.system == origin || systems.contains(&self.system) {
+            return Err(format!("system '{}' is already handling this request", self.system));
+        }
+        if systems.len() >= MAX_NESTING {
+            return Err(format!("more than {} nested systems", MAX_NESTING));
+        }
+
+        self.check_access(message).await?;
+
+        let system = self
+            .state
+            .get_system(&self.system)
+            .await
+            .ok_or_else(|| format!("system '{}' not found", self.system))?;
+        let target = self
+            .state
+            .get_system_metadata(&self.system)
+            .await
+            .and_then(|(metadata, _)| metadata.default_target())
+            .ok_or_else(|| format!("system '{}' has no entry point", self.system))?;
+
+        // The calling system shows up as the sender in the nested system
+        let id = Uuid::new_v4().simple().to_string();
+        let sender = format!("{}{}~{}", SYSTEM_CONNECTION_PREFIX, self.caller, &id[..8]);
+        let agent = AgentBuilder::new(&sender).blocking_connection(&target).build();
+        system
+            .register_agent(agent, Arc::new(EchoHandler))
+            .await
+            .map_err(|e| format!("could not register '{}' in system '{}': {}", sender, self.system, e))?;
+
+        let mut nested = trace.nested(&self.system).with_origin(origin);
+        if let Some(org_id) = self.org_of(message).await {
+            nested = nested.with_metadata("org_id", org_id);
+        }
+
+        nested.record_request(&sender, &target, &message.content).await;
+        let result = system.send_message_with_trace(&sender, &target, &message.content, nested.clone()).await;
+        system.unregister_agent(&sender).await;
+        match result {
+            Ok(SendResult::Response(response)) => {
+                nested.record_response(&target, &sender, &response.content).await;
+                Ok(response.content)
+            }
+            Ok(SendResult::Timeout(e)) => Err(e.to_string()),
+            Ok(SendResult::Notified) => Err(format!("'{}' did not answer", target)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Whether the requesting user may access the nested system
+    ///
+    /// Requests without a user (triggers, hooks) only reach other systems
+    /// when auth is disabled.
+    async fn check_access(&self, message: &Message) -> Result<(), String> {
+        if self.state.is_auth_disabled() {
+            return Ok(());
+        }
+        let user_id = message
+            .metadata
+            .get("user_id")
+            .filter(|id| !id.is_empty())
+            .ok_or_else(|| format!("access to system '{}' requires a user", self.system))?;
+        let pool = self
+            .state
+            .try_db()
+            .ok_or_else(|| "access check failed: no database".to_string())?;
+
+        match mas_auth::repository::user_has_system_access(pool, user_id, &self.system).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(format!("user does not have access to system '{}'", self.system)),
+            Err(e) => Err(format!("access check failed: {}", e)),
+        }
+    }
+
+    /// The org the requesting user reaches the nested system through
+    async fn org_of(&self, message: &Message) -> Option<String> {
+        let user_id = message.metadata.get("user_id").filter(|id| !id.is_empty())?;
+        let pool = self.state.try_db()?;
+        let org_id = mas_auth::repository::find_user_system_org(pool, user_id, &self.system)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Could not look up the org of user {}: {}", user_id, e);
+                None
+            });
+        Some(org_id.unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl MessageHandler for SystemConnectionHandler {
+    async fn handle(&self, message: &Message, _agent: &Agent) -> Option<String> {
+        match self.call(message).await {
+            Ok(content) => Some(content),
+            Err(e) => {
+                warn!("[{} -> {}] Nested system call failed: {}", self.caller, self.system, e);
+                Some(format!("System error: {}", e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::extract_metadata;
+    use mas_auth::repository;
+    use mas_core::config_loader::SystemConfigJson;
+    use mas_core::AgentSystem;
+
+    async fn register(state: &AppState, name: &str, config: &str, agents: Vec<mas_core::Agent>) -> Arc<AgentSystem> {
+        let config: SystemConfigJson = serde_json::from_str(config).unwrap();
+        let system = Arc::new(AgentSystem::with_default_config());
+        for agent in agents {
+            system.register_agent(agent, Arc::new(EchoHandler)).await.unwrap();
+        }
+        state
+            .register_system(name.to_string(), SystemEntry::new(system.clone(), extract_metadata(&config), config))
+            .await
+            .unwrap();
+        system
+    }
+
+    #[tokio::test]
+    async fn test_system_connection_checks_access_and_nests_trace() {
+        let pool = mas_auth::create_pool(":memory:").await.unwrap();
+        mas_auth::run_migrations(&pool).await.unwrap();
+        repository::create_user(&pool, "alice", "alice@example.com", "Alice", "x").await.unwrap();
+        repository::add_system_owner(&pool, "legal", "alice").await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let state = AppState::with_paths(dir.path().join("sessions"), dir.path().join("systems"))
+            .with_db(pool)
+            .with_auth_disabled(false);
+
+        let legal = register(
+            &state,
+            "legal",
+            r#"{ "system": {}, "llm_providers": {}, "agents": [ { "name": "Reviewer", "handler": { "provider": "default" } } ] }"#,
+            vec![AgentBuilder::new("Reviewer").build()],
+        )
+        .await;
+        let support = register(
+            &state,
+            "support",
+            r#"{
+                "system": {},
+                "llm_providers": {},
+                "agents": [
+                    { "name": "Intake", "handler": { "provider": "default" }, "connections": { "system:legal": { "type": "blocking" } } }
+                ]
+            }"#,
+            vec![AgentBuilder::new("Intake").blocking_connection("system:legal").build()],
+        )
+        .await;
+
+        let ask = |user_id: &str| {
+            let trace = TraceCollector::new().with_metadata("user_id", user_id);
+            let support = support.clone();
+            async move {
+                let result = support
+                    .send_message_with_trace("Intake", "system:legal", "Check clause 4", trace.clone())
+                    .await
+                    .unwrap();
+                (result.into_response().unwrap().content, trace.events().await)
+            }
+        };
+
+        let (content, events) = ask("alice").await;
+        assert_eq!(content, "Echo: Check clause 4");
+        let nested: Vec<_> = events.iter().filter(|e| e.system.as_deref() == Some("legal")).collect();
+        assert_eq!(nested.len(), 2);
+        assert!(nested[0].from.starts_with("system:support~"));
+        assert_eq!(nested[0].to, "Reviewer");
+        assert_eq!(nested[1].content, "Echo: Check clause 4");
+        assert!(legal.get_agent(&nested[0].from).await.is_none());
+
+        let (content, events) = ask("bob").await;
+        assert_eq!(content, "System error: user does not have access to system 'legal'");
+        assert!(events.iter().all(|e| e.system.is_none()));
+    }
+
+    /// Routing handler that passes every message on to `target`
+    struct Relay {
+        target: &'static str,
+    }
+
+    #[async_trait]
+    impl mas_core::RoutingHandler for Relay {
+        async fn handle(&self, message: &Message, _agent: &Agent) -> mas_core::HandlerDecision {
+            mas_core::HandlerDecision::forward_to(self.target, message.content.clone())
+        }
+
+        async fn synthesize(
+            &self,
+            _original_message: &Message,
+            outcomes: &[mas_core::ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<String> {
+            outcomes.first().and_then(|o| o.response()).map(String::from)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_system_connection_rejects_calls_back_into_the_origin() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = AppState::with_paths(dir.path().join("sessions"), dir.path().join("systems"))
+            .with_auth_disabled(true);
+
+        let legal_config: SystemConfigJson = serde_json::from_str(
+            r#"{
+                "system": {},
+                "llm_providers": {},
+                "agents": [
+                    { "name": "Reviewer", "handler": { "provider": "default" }, "connections": { "system:support": { "type": "blocking" } } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let legal = Arc::new(AgentSystem::with_default_config());
+        let reviewer = AgentBuilder::new("Reviewer").blocking_connection("system:support").build();
+        AgentSystem::register_routing_agent(legal.clone(), reviewer, Arc::new(Relay { target: "system:support" }))
+            .await
+            .unwrap();
+        state
+            .register_system(
+                "legal".to_string(),
+                SystemEntry::new(legal, extract_metadata(&legal_config), legal_config),
+            )
+            .await
+            .unwrap();
+        let support = register(
+            &state,
+            "support",
+            r#"{
+                "system": {},
+                "llm_providers": {},
+                "agents": [
+                    { "name": "Intake", "handler": { "provider": "default" }, "connections": { "system:legal": { "type": "blocking" } } }
+                ]
+            }"#,
+            vec![AgentBuilder::new("Intake").blocking_connection("system:legal").build()],
+        )
+        .await;
+
+        let result = support
+            .send_message_with_trace("Intake", "system:legal", "Check clause 4", TraceCollector::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            result.into_response().unwrap().content,
+            "System error: system 'support' is already handling this request"
+        );
+    }
+}
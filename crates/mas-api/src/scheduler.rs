@@ -0,0 +1,565 @@
+//! Scheduler for the triggers configured on registered systems
+//!
+//! Every tick the scheduler reads the triggers of all registered systems,
+//! works out which are due and sends their prompts, like a client calling the
+//! prompt endpoints would. Systems can be registered, updated and removed at
+//! any time; a trigger is first scheduled for its next occurrence after the
+//! scheduler sees it, and occurrences missed while the server was down are
+//! not caught up.
+//!
+//! Time comes from a [`Clock`], so tests can drive the scheduler with a
+//! [`MockClock`] and call [`TriggerScheduler::tick`] directly.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use mas_core::{agent_system::EchoHandler, metrics::metrics, AgentBuilder, SendResult, TraceCollector, Trigger};
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::state::AppState;
+use crate::webhooks::{publish_prompt_events, EventKind, WebhookEvent};
+
+/// How often the background task checks for due triggers
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Number of runs kept per trigger
+const HISTORY_LIMIT: usize = 20;
+
+/// Source of the current time for the scheduler
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system clock
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when told to, for tests
+pub struct MockClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl MockClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self { now: Mutex::new(now) }
+    }
+
+    /// Jump to a point in time
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, by: chrono::Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// Result of a trigger or hook run
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RunOutcome {
+    /// The target answered
+    Success { from: String, content: String },
+    /// The target did not answer in time
+    Timeout { message: String },
+    /// The run could not be carried out
+    Failed { error: String },
+}
+
+impl RunOutcome {
+    fn is_failure(&self) -> bool {
+        !matches!(self, RunOutcome::Success { .. })
+    }
+}
+
+/// One run of a trigger
+#[derive(Debug, Clone, Serialize)]
+pub struct TriggerRun {
+    /// The occurrence this run was for
+    pub scheduled_for: DateTime<Utc>,
+    pub started_at: DateTime<Utc>,
+    pub elapsed_ms: u64,
+    /// Session the prompt was posted to, if it got that far
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(flatten)]
+    pub outcome: RunOutcome,
+}
+
+/// Scheduling state and run history of a trigger
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TriggerStatus {
+    /// Next scheduled run (unknown until the scheduler has seen the trigger)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_run: Option<DateTime<Utc>>,
+    /// Whether a run is in progress
+    pub running: bool,
+    /// Runs that did not get a response, since the server started
+    pub failure_count: usize,
+    /// Recent runs, newest first
+    pub history: Vec<TriggerRun>,
+}
+
+#[derive(Default)]
+struct TriggerState {
+    cron: String,
+    next_run: Option<DateTime<Utc>>,
+    running: bool,
+    failure_count: usize,
+    history: VecDeque<TriggerRun>,
+}
+
+/// Runs the triggers of all registered systems
+pub struct TriggerScheduler {
+    clock: Arc<dyn Clock>,
+    /// State per (system, trigger)
+    states: Mutex<HashMap<(String, String), TriggerState>>,
+}
+
+impl Default for TriggerScheduler {
+    fn default() -> Self {
+        Self::new(Arc::new(SystemClock))
+    }
+}
+
+impl TriggerScheduler {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Run every trigger that is due, waiting for the runs to finish
+    ///
+    /// Returns the number of runs started. A trigger whose previous run is
+    /// still going skips the occurrence.
+    pub async fn tick(&self, state: &AppState) -> usize {
+        let now = self.clock.now();
+        let systems = state.system_triggers().await;
+
+        let mut due = Vec::new();
+        {
+            let mut states = self.states.lock().unwrap();
+
+            // Forget triggers that are no longer configured
+            states.retain(|(system, name), _| {
+                systems
+                    .iter()
+                    .any(|(s, triggers)| s == system && triggers.iter().any(|t| &t.name == name))
+            });
+
+            for (system_name, configs) in &systems {
+                for config in configs {
+                    let trigger = match Trigger::compile(config) {
+                        Ok(trigger) => trigger,
+                        Err(e) => {
+                            warn!("Skipping trigger '{}' of '{}': {}", config.name, system_name, e);
+                            continue;
+                        }
+                    };
+                    let entry = states
+                        .entry((system_name.clone(), config.name.clone()))
+                        .or_default();
+
+                    // New trigger or changed schedule: wait for the next occurrence
+                    if entry.next_run.is_none() || entry.cron != config.cron {
+                        entry.cron = config.cron.clone();
+                        entry.next_run = trigger.next_after(now);
+                        continue;
+                    }
+
+                    let Some(scheduled_for) = entry.next_run.filter(|at| *at <= now) else {
+                        continue;
+                    };
+                    entry.next_run = trigger.next_after(now);
+                    if entry.running {
+                        warn!(
+                            "Trigger '{}' of '{}' is still running, skipping the run for {}",
+                            config.name, system_name, scheduled_for
+                        );
+                        continue;
+                    }
+                    entry.running = true;
+                    due.push((system_name.clone(), trigger, scheduled_for));
+                }
+            }
+        }
+
+        let started = due.len();
+        join_all(due.into_iter().map(|(system_name, trigger, scheduled_for)| async move {
+            let run = self.run(state, &system_name, &trigger, scheduled_for).await;
+            self.record(&system_name, trigger.name(), run);
+        }))
+        .await;
+        started
+    }
+
+    /// Scheduling state of a system's triggers, by trigger name
+    pub fn status(&self, system_name: &str) -> HashMap<String, TriggerStatus> {
+        let states = self.states.lock().unwrap();
+        states
+            .iter()
+            .filter(|((system, _), _)| system == system_name)
+            .map(|((_, name), entry)| {
+                let status = TriggerStatus {
+                    next_run: entry.next_run,
+                    running: entry.running,
+                    failure_count: entry.failure_count,
+                    history: entry.history.iter().cloned().collect(),
+                };
+                (name.clone(), status)
+            })
+            .collect()
+    }
+
+    /// Send a trigger's prompt and wait for the answer
+    async fn run(
+        &self,
+        state: &AppState,
+        system_name: &str,
+        trigger: &Trigger,
+        scheduled_for: DateTime<Utc>,
+    ) -> TriggerRun {
+        let started_at = self.clock.now();
+        let start = Instant::now();
+        let mut session_id = None;
+        let config = trigger.config();
+        let outcome = match deliver_prompt(
+            state,
+            system_name,
+            config.target_agent.as_deref(),
+            config.session_id.as_deref(),
+            &trigger.render_prompt(scheduled_for),
+            PromptSource::Trigger(trigger.name()),
+            &mut session_id,
+        )
+        .await
+        {
+            Ok(outcome) => outcome,
+            Err(error) => RunOutcome::Failed { error },
+        };
+
+        match &outcome {
+            RunOutcome::Success { .. } => {
+                info!("Trigger '{}' of '{}' ran for {}", trigger.name(), system_name, scheduled_for)
+            }
+            RunOutcome::Timeout { message } => {
+                warn!("Trigger '{}' of '{}' timed out: {}", trigger.name(), system_name, message)
+            }
+            RunOutcome::Failed { error } => {
+                error!("Trigger '{}' of '{}' failed: {}", trigger.name(), system_name, error)
+            }
+        }
+
+        TriggerRun {
+            scheduled_for,
+            started_at,
+            elapsed_ms: start.elapsed().as_millis() as u64,
+            session_id,
+            outcome,
+        }
+    }
+
+    fn record(&self, system_name: &str, trigger_name: &str, run: TriggerRun) {
+        let mut states = self.states.lock().unwrap();
+        // The trigger may have been removed while it ran
+        let Some(entry) = states.get_mut(&(system_name.to_string(), trigger_name.to_string())) else {
+            return;
+        };
+        entry.running = false;
+        if run.outcome.is_failure() {
+            entry.failure_count += 1;
+        }
+        entry.history.push_front(run);
+        entry.history.truncate(HISTORY_LIMIT);
+    }
+}
+
+/// What sent a prompt into a system without a client asking for it
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum PromptSource<'a> {
+    Trigger(&'a str),
+    Hook(&'a str),
+}
+
+impl PromptSource<'_> {
+    /// Metadata key naming the source on the trace and the stored response
+    fn key(&self) -> &'static str {
+        match self {
+            PromptSource::Trigger(_) => "trigger",
+            PromptSource::Hook(_) => "hook",
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            PromptSource::Trigger(name) | PromptSource::Hook(name) => name,
+        }
+    }
+
+    fn sender_prefix(&self) -> &'static str {
+        match self {
+            PromptSource::Trigger(_) => "_Trigger_",
+            PromptSource::Hook(_) => "_Hook_",
+        }
+    }
+}
+
+/// Send a prompt to a system's agent and wait for the answer
+///
+/// Posts into `session_id` if given (it must belong to the system) or into a
+/// new session, storing the prompt and the answer like the prompt endpoints
+/// do. The session used is written to `session_out` as soon as it is known,
+/// so callers can report it even if the run fails afterwards.
+pub(crate) async fn deliver_prompt(
+    state: &AppState,
+    system_name: &str,
+    target_agent: Option<&str>,
+    session_id: Option<&str>,
+    prompt: &str,
+    source: PromptSource<'_>,
+    session_out: &mut Option<String>,
+) -> Result<RunOutcome, String> {
+    let system = state
+        .get_system(system_name)
+        .await
+        .ok_or_else(|| format!("System '{}' not found", system_name))?;
+    let (metadata, _) = state
+        .get_system_metadata(system_name)
+        .await
+        .ok_or_else(|| format!("System '{}' not found", system_name))?;
+    let target = match target_agent {
+        Some(target) => target.to_string(),
+        None => metadata
+            .default_target()
+            .ok_or_else(|| "No agents available in system".to_string())?,
+    };
+
+    // Post into the configured session, or start a new one
+    let session = match session_id {
+        Some(id) => {
+            let manager = state.session_manager().read().await;
+            if manager.get_session_system(id) != Some(system_name) {
+                return Err(format!("Session '{}' not found for system '{}'", id, system_name));
+            }
+            id.to_string()
+        }
+        None => {
+            let mut manager = state.session_manager().write().await;
+            let id = manager
+                .create_session(system_name)
+                .await
+                .map_err(|e| format!("Failed to create session: {}", e))?
+                .id;
+            drop(manager);
+            record_session_owner(state, system_name, &id).await;
+            state
+                .webhooks()
+                .publish(WebhookEvent::new(EventKind::SessionCreated, system_name).with_session(Some(&id)));
+            id
+        }
+    };
+    *session_out = Some(session.clone());
+
+    {
+        let mut manager = state.session_manager().write().await;
+        manager
+            .store_user_message(&session, &target, prompt)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    metrics().record_prompt(system_name);
+    let trace = TraceCollector::new()
+        .with_session_id(&session)
+        .with_metadata("session_id", &session)
+        .with_metadata(source.key(), source.name());
+
+    let sender = format!("{}{}", source.sender_prefix(), Uuid::new_v4());
+    system
+        .register_agent(
+            AgentBuilder::new(&sender).blocking_connection(&target).build(),
+            Arc::new(EchoHandler),
+        )
+        .await
+        .map_err(|e| format!("Failed to create {} agent: {}", source.key(), e))?;
+
+    let start = Instant::now();
+    let result = system.send_message_with_trace(&sender, &target, prompt, trace.clone()).await;
+    system.unregister_agent(&sender).await;
+    let result = result.map_err(|e| e.to_string())?;
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    publish_prompt_events(state, system_name, Some(&session), &target, &trace.events().await, &result, elapsed_ms)
+        .await;
+
+    Ok(match result {
+        SendResult::Response(msg) => {
+            let mut manager = state.session_manager().write().await;
+            let meta = serde_json::json!({ source.key(): source.name() });
+            manager
+                .store_agent_response(&session, &msg.from, &msg.content, Some(meta))
+                .await
+                .map_err(|e| e.to_string())?;
+            RunOutcome::Success {
+                from: msg.from,
+                content: msg.content,
+            }
+        }
+        SendResult::Timeout(err) => RunOutcome::Timeout {
+            message: err.to_string(),
+        },
+        SendResult::Notified => RunOutcome::Failed {
+            error: format!("'{}' did not respond", target),
+        },
+    })
+}
+
+/// Record the system's owner as the owner of a session started for it
+///
+/// Sessions started by triggers and hooks have no user behind them; giving
+/// them to the system's owner lets that user read them (skipped in dev mode).
+pub(crate) async fn record_session_owner(state: &AppState, system_name: &str, session_id: &str) {
+    let Some(db) = state.try_db().filter(|_| !state.is_auth_disabled()) else {
+        return;
+    };
+    let owner = match mas_auth::repository::find_system_owner(db, system_name).await {
+        Ok(Some(owner)) => owner,
+        Ok(None) => {
+            warn!("System '{}' has no owner to give session '{}' to", system_name, session_id);
+            return;
+        }
+        Err(e) => {
+            warn!("Failed to look up the owner of system '{}': {}", system_name, e);
+            return;
+        }
+    };
+    if let Err(e) = mas_auth::repository::create_session_record(db, session_id, &owner, system_name).await {
+        warn!("Failed to record session ownership for '{}': {}", session_id, e);
+    }
+}
+
+/// Run the state's trigger scheduler in the background
+pub fn spawn(state: AppState) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            // Runs can take a while; later ticks keep scheduling meanwhile
+            let state = state.clone();
+            tokio::spawn(async move {
+                state.trigger_scheduler().tick(&state).await;
+            });
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{extract_metadata, SystemEntry};
+    use chrono::TimeZone;
+    use mas_core::config_loader::SystemConfigJson;
+    use mas_core::AgentSystem;
+
+    async fn state_with_triggers(clock: Arc<MockClock>, dir: &std::path::Path) -> AppState {
+        let pool = mas_auth::create_pool(":memory:").await.unwrap();
+        mas_auth::run_migrations(&pool).await.unwrap();
+        mas_auth::repository::create_user(&pool, "u-1", "ops@example.com", "Ops", "hash").await.unwrap();
+        mas_auth::repository::add_system_owner(&pool, "ops", "u-1").await.unwrap();
+        let state = AppState::with_paths(dir.join("sessions"), dir.join("systems"))
+            .with_db(pool)
+            .with_auth_disabled(false)
+            .with_clock(clock);
+        state.init().await.unwrap();
+
+        let config: SystemConfigJson = serde_json::from_str(
+            r#"{
+                "system": {},
+                "llm_providers": {},
+                "agents": [ { "name": "Coordinator", "handler": { "provider": "default" } } ],
+                "triggers": [
+                    { "name": "digest", "cron": "0 7 * * *", "prompt": "Digest for ${date}" },
+                    { "name": "broken", "cron": "0 7 * * *", "prompt": "Hello", "session_id": "missing" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let system = Arc::new(AgentSystem::with_default_config());
+        system
+            .register_agent(AgentBuilder::new("Coordinator").build(), Arc::new(EchoHandler))
+            .await
+            .unwrap();
+        state
+            .register_system("ops".to_string(), SystemEntry::new(system, extract_metadata(&config), config))
+            .await
+            .unwrap();
+        state
+    }
+
+    #[tokio::test]
+    async fn test_triggers_run_when_due() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = Arc::new(MockClock::new(Utc.with_ymd_and_hms(2024, 5, 6, 6, 59, 0).unwrap()));
+        let state = state_with_triggers(clock.clone(), dir.path()).await;
+        let scheduler = state.trigger_scheduler();
+
+        // First sight only schedules
+        assert_eq!(scheduler.tick(&state).await, 0);
+        let seven = Utc.with_ymd_and_hms(2024, 5, 6, 7, 0, 0).unwrap();
+        assert_eq!(scheduler.status("ops")["digest"].next_run, Some(seven));
+
+        clock.advance(chrono::Duration::seconds(30));
+        assert_eq!(scheduler.tick(&state).await, 0);
+
+        clock.set(seven);
+        assert_eq!(scheduler.tick(&state).await, 2);
+        assert_eq!(scheduler.tick(&state).await, 0);
+
+        let status = scheduler.status("ops");
+        let digest = &status["digest"];
+        assert_eq!(digest.next_run, Some(seven + chrono::Duration::days(1)));
+        assert_eq!(digest.failure_count, 0);
+        let run = &digest.history[0];
+        assert!(matches!(
+            &run.outcome,
+            RunOutcome::Success { content, .. } if content == "Echo: Digest for 2024-05-06"
+        ));
+
+        // The run's prompt and answer are stored in a new session, which
+        // belongs to the system's owner
+        let session_id = run.session_id.clone().unwrap();
+        let manager = state.session_manager().read().await;
+        assert_eq!(manager.get_history(&session_id, None).unwrap().len(), 2);
+        assert!(mas_auth::repository::user_owns_session(state.db(), "u-1", &session_id).await.unwrap());
+
+        // The agents the runs were sent from are gone
+        let system = state.get_system("ops").await.unwrap();
+        let agents: Vec<String> = system.inbox_depths().await.into_iter().map(|(name, _)| name).collect();
+        assert_eq!(agents, vec!["Coordinator"]);
+
+        let broken = &status["broken"];
+        assert_eq!(broken.failure_count, 1);
+        assert!(matches!(broken.history[0].outcome, RunOutcome::Failed { .. }));
+    }
+}
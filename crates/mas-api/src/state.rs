@@ -3,7 +3,7 @@
 use chrono::{DateTime, Utc};
 use mas_auth::{AuthState, FromRef, JwtConfig};
 use mas_core::config_loader::SystemConfigJson;
-use mas_core::{load_system_from_json, AgentSystem};
+use mas_core::{load_named_system_from_json, AgentSystem, HookConfig, TriggerConfig};
 use sqlx::SqlitePool;
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -12,7 +12,9 @@ use thiserror::Error;
 use tokio::sync::RwLock;
 use tracing::{error, info, warn};
 
+use crate::scheduler::{Clock, TriggerScheduler};
 use crate::session::{create_session_manager, SharedSessionManager};
+use crate::webhooks::WebhookBus;
 
 /// Stored configuration metadata for a system
 #[derive(Debug, Clone)]
@@ -25,6 +27,43 @@ pub struct ConfigMetadata {
     pub global_timeout_secs: u64,
     /// Agent details for introspection
     pub agents: Vec<AgentMetadata>,
+    /// Workflows, which receive prompts like agents
+    pub workflows: Vec<WorkflowMetadata>,
+}
+
+impl ConfigMetadata {
+    /// Whether prompts can be sent to `name` (an agent or a workflow)
+    pub fn has_target(&self, name: &str) -> bool {
+        self.agent_names.iter().any(|n| n == name) || self.workflows.iter().any(|w| w.name == name)
+    }
+
+    /// Names prompts can be sent to
+    pub fn target_names(&self) -> Vec<&str> {
+        self.agent_names
+            .iter()
+            .map(|n| n.as_str())
+            .chain(self.workflows.iter().map(|w| w.name.as_str()))
+            .collect()
+    }
+
+    /// Target for prompts that do not name one: an entry point agent or
+    /// workflow, then "Coordinator", then the first routing agent, then the
+    /// first agent
+    pub fn default_target(&self) -> Option<String> {
+        self.agents
+            .iter()
+            .find(|a| a.entry_point)
+            .map(|a| a.name.clone())
+            .or_else(|| self.workflows.iter().find(|w| w.entry_point).map(|w| w.name.clone()))
+            .or_else(|| {
+                self.agents
+                    .iter()
+                    .find(|a| a.name == "Coordinator")
+                    .or_else(|| self.agents.iter().find(|a| a.routing))
+                    .or_else(|| self.agents.first())
+                    .map(|a| a.name.clone())
+            })
+    }
 }
 
 /// Stored metadata for an agent
@@ -37,6 +76,23 @@ pub struct AgentMetadata {
     pub entry_point: bool,
 }
 
+/// Stored metadata for a workflow
+#[derive(Debug, Clone)]
+pub struct WorkflowMetadata {
+    pub name: String,
+    pub description: Option<String>,
+    pub steps: Vec<WorkflowStepMetadata>,
+    pub entry_point: bool,
+}
+
+/// Stored metadata for a workflow step
+#[derive(Debug, Clone)]
+pub struct WorkflowStepMetadata {
+    pub id: String,
+    pub target: Option<String>,
+    pub depends_on: Vec<String>,
+}
+
 /// Stored metadata for a connection
 #[derive(Debug, Clone)]
 pub struct ConnectionMetadata {
@@ -75,11 +131,31 @@ pub fn extract_metadata(config: &SystemConfigJson) -> ConfigMetadata {
         })
         .collect();
 
+    let workflows: Vec<WorkflowMetadata> = config
+        .workflows
+        .iter()
+        .map(|workflow| WorkflowMetadata {
+            name: workflow.name.clone(),
+            description: workflow.description.clone(),
+            steps: workflow
+                .steps
+                .iter()
+                .map(|step| WorkflowStepMetadata {
+                    id: step.id.clone(),
+                    target: step.target.clone(),
+                    depends_on: step.depends_on.iter().map(|d| d.step().to_string()).collect(),
+                })
+                .collect(),
+            entry_point: workflow.entry_point,
+        })
+        .collect();
+
     ConfigMetadata {
         agent_count: config.agents.len(),
         agent_names: config.agents.iter().map(|a| a.name.clone()).collect(),
         global_timeout_secs: config.system.global_timeout_secs,
         agents,
+        workflows,
     }
 }
 
@@ -253,6 +329,12 @@ pub struct AppState {
     jwt_config: Arc<JwtConfig>,
     /// Whether auth is disabled (dev mode)
     auth_disabled: bool,
+    /// Bearer token scrapers present to read `/metrics`
+    metrics_token: Option<Arc<str>>,
+    /// Runs the triggers configured on the systems
+    trigger_scheduler: Arc<TriggerScheduler>,
+    /// Events delivered to outbound webhooks
+    webhooks: WebhookBus,
 }
 
 /// Implement FromRef so the AuthenticatedUser extractor can pull AuthState from AppState
@@ -293,6 +375,9 @@ impl AppState {
             db: None,
             jwt_config: Arc::new(JwtConfig::for_testing()),
             auth_disabled,
+            metrics_token: None,
+            trigger_scheduler: Arc::new(TriggerScheduler::default()),
+            webhooks: WebhookBus::new(),
         }
     }
 
@@ -319,6 +404,18 @@ impl AppState {
         self
     }
 
+    /// Set the bearer token that grants access to `/metrics`
+    pub fn with_metrics_token(mut self, token: impl Into<Arc<str>>) -> Self {
+        self.metrics_token = Some(token.into());
+        self
+    }
+
+    /// Schedule triggers by the given clock instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.trigger_scheduler = Arc::new(TriggerScheduler::new(clock));
+        self
+    }
+
     /// Get the database pool
     pub fn db(&self) -> &SqlitePool {
         self.db
@@ -326,6 +423,11 @@ impl AppState {
             .expect("Database pool not initialized. Call with_db() first.")
     }
 
+    /// Get the database pool, if one is set
+    pub fn try_db(&self) -> Option<&SqlitePool> {
+        self.db.as_ref()
+    }
+
     /// Get the JWT config
     pub fn jwt_config(&self) -> &JwtConfig {
         &self.jwt_config
@@ -336,11 +438,26 @@ impl AppState {
         self.auth_disabled
     }
 
+    /// The bearer token that grants access to `/metrics`, if one is set
+    pub fn metrics_token(&self) -> Option<&str> {
+        self.metrics_token.as_deref()
+    }
+
     /// Get the session manager
     pub fn session_manager(&self) -> &SharedSessionManager {
         &self.session_manager
     }
 
+    /// Get the trigger scheduler
+    pub fn trigger_scheduler(&self) -> &TriggerScheduler {
+        &self.trigger_scheduler
+    }
+
+    /// Get the bus outbound webhook events are published on
+    pub fn webhooks(&self) -> &WebhookBus {
+        &self.webhooks
+    }
+
     /// Get the system store
     pub fn system_store(&self) -> &SystemStore {
         &self.system_store
@@ -399,7 +516,7 @@ impl AppState {
         // Extract metadata before we use the config
         let metadata = extract_metadata(&config);
 
-        // Write config to a temporary file for load_system_from_json
+        // Write config to a temporary file for load_named_system_from_json
         let temp_dir = std::env::temp_dir();
         let temp_file = temp_dir.join(format!("mas-api-{}.json", uuid::Uuid::new_v4()));
 
@@ -410,7 +527,7 @@ impl AppState {
             .map_err(|e| format!("Failed to write temp config: {}", e))?;
 
         // Load the system
-        let system = load_system_from_json(&temp_file)
+        let system = load_named_system_from_json(&temp_file, &name)
             .await
             .map_err(|e| e.to_string())?;
 
@@ -424,6 +541,7 @@ impl AppState {
         if systems.contains_key(&name) {
             return Err(format!("System '{}' already exists", name));
         }
+        crate::nested::connect_systems(self, &name, &entry).await;
         systems.insert(name, entry);
 
         Ok(metadata)
@@ -437,6 +555,7 @@ impl AppState {
         if systems.contains_key(&name) {
             return Err(format!("System '{}' already exists", name));
         }
+        crate::nested::connect_systems(self, &name, &entry).await;
         systems.insert(name, entry);
         Ok(())
     }
@@ -447,6 +566,15 @@ impl AppState {
         systems.get(name).map(|e| e.system.clone())
     }
 
+    /// All registered systems by name
+    pub async fn running_systems(&self) -> Vec<(String, Arc<AgentSystem>)> {
+        let systems = self.systems.read().await;
+        systems
+            .iter()
+            .map(|(name, entry)| (name.clone(), entry.system.clone()))
+            .collect()
+    }
+
     /// Get system metadata by name
     pub async fn get_system_metadata(&self, name: &str) -> Option<(ConfigMetadata, DateTime<Utc>)> {
         let systems = self.systems.read().await;
@@ -478,6 +606,24 @@ impl AppState {
         systems.contains_key(name)
     }
 
+    /// Triggers configured on each system
+    pub async fn system_triggers(&self) -> Vec<(String, Vec<TriggerConfig>)> {
+        let systems = self.systems.read().await;
+        systems
+            .iter()
+            .filter(|(_, entry)| !entry.config.triggers.is_empty())
+            .map(|(name, entry)| (name.clone(), entry.config.triggers.clone()))
+            .collect()
+    }
+
+    /// Look up a hook configured on a system
+    pub async fn get_system_hook(&self, name: &str, hook: &str) -> Option<HookConfig> {
+        let systems = self.systems.read().await;
+        systems
+            .get(name)
+            .and_then(|entry| entry.config.hooks.iter().find(|h| h.name == hook).cloned())
+    }
+
     /// Get the full system config by name
     pub async fn get_system_config(&self, name: &str) -> Option<(SystemConfigJson, DateTime<Utc>)> {
         let systems = self.systems.read().await;
@@ -486,12 +632,12 @@ impl AppState {
             .map(|e| (e.config.clone(), e.created_at))
     }
 
-    /// Check if an agent exists in a system
+    /// Check if an agent (or workflow) exists in a system
     pub async fn agent_exists(&self, system_name: &str, agent_name: &str) -> bool {
         let systems = self.systems.read().await;
         systems
             .get(system_name)
-            .map(|e| e.metadata.agent_names.contains(&agent_name.to_string()))
+            .map(|e| e.metadata.has_target(agent_name))
             .unwrap_or(false)
     }
 }
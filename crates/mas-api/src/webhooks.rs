@@ -0,0 +1,432 @@
+//! Outbound webhooks
+//!
+//! Handlers and the trigger scheduler publish [`WebhookEvent`]s on the
+//! state's [`WebhookBus`]. A background task picks them up, looks up the
+//! webhooks subscribed to the event's system (stored in the auth database)
+//! and POSTs the event to each of them as signed JSON.
+//!
+//! Every delivery is logged before its first attempt and updated after each
+//! one. Network errors, `429` and `5xx` answers are retried with exponential
+//! backoff; other `4xx` answers fail the delivery right away.
+//!
+//! Requests carry these headers:
+//! - `X-Mas-Event`: the event name, e.g. `prompt.completed`
+//! - `X-Mas-Delivery`: the delivery id (the same across retries)
+//! - `X-Mas-Signature-256`: `sha256=<hex HMAC-SHA256 of the body>`, keyed
+//!   with the webhook's secret
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use mas_auth::{repository, DeliveryStatus, Webhook};
+use mas_core::{SendResult, TraceEvent, TraceEventType};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::state::AppState;
+
+/// Prefix of tool responses that report a failed call
+const TOOL_ERROR_PREFIX: &str = "Tool error:";
+
+/// Kinds of events webhooks can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum EventKind {
+    /// A session was created (by a client, a trigger or a hook)
+    #[serde(rename = "session.created")]
+    SessionCreated,
+    /// A prompt was answered
+    #[serde(rename = "prompt.completed")]
+    PromptCompleted,
+    /// A prompt was not answered in time
+    #[serde(rename = "agent.timeout")]
+    AgentTimeout,
+    /// A tool call made while answering a prompt failed
+    #[serde(rename = "tool.error")]
+    ToolError,
+}
+
+impl EventKind {
+    /// All event kinds
+    pub const ALL: [EventKind; 4] = [
+        EventKind::SessionCreated,
+        EventKind::PromptCompleted,
+        EventKind::AgentTimeout,
+        EventKind::ToolError,
+    ];
+
+    /// Event name, matching the serialized form
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::SessionCreated => "session.created",
+            EventKind::PromptCompleted => "prompt.completed",
+            EventKind::AgentTimeout => "agent.timeout",
+            EventKind::ToolError => "tool.error",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == s)
+    }
+}
+
+/// Something that happened in a system
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookEvent {
+    #[serde(rename = "event")]
+    pub kind: EventKind,
+    pub system: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+    /// Event-specific details
+    pub data: Value,
+}
+
+impl WebhookEvent {
+    pub fn new(kind: EventKind, system: impl Into<String>) -> Self {
+        Self {
+            kind,
+            system: system.into(),
+            session_id: None,
+            occurred_at: Utc::now(),
+            data: json!({}),
+        }
+    }
+
+    pub fn with_session(mut self, session_id: Option<&str>) -> Self {
+        self.session_id = session_id.map(|s| s.to_string());
+        self
+    }
+
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// The JSON body sent for this event in a delivery
+    fn payload(&self, delivery_id: &str) -> Value {
+        let mut payload = serde_json::to_value(self).unwrap_or_else(|_| json!({}));
+        payload["id"] = json!(delivery_id);
+        payload
+    }
+}
+
+/// Channel the API publishes its webhook events on
+///
+/// Separate from the system events of [`mas_core::EventBus`], which cover the
+/// agents of one system. Clones share the same channel. Publishing without subscribers is a no-op.
+#[derive(Clone)]
+pub struct WebhookBus {
+    tx: broadcast::Sender<WebhookEvent>,
+}
+
+impl Default for WebhookBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(256);
+        Self { tx }
+    }
+
+    pub fn publish(&self, event: WebhookEvent) {
+        debug!("Event {} in system '{}'", event.kind.as_str(), event.system);
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<WebhookEvent> {
+        self.tx.subscribe()
+    }
+}
+
+/// How often and how fast failed deliveries are retried
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubled for every further retry
+    pub initial_delay: Duration,
+    /// Timeout of a single attempt
+    pub request_timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: Duration::from_secs(2),
+            request_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Publish the events of an answered (or unanswered) prompt
+///
+/// Tool errors are found in the request's trace: responses of the system's
+/// tools that report a failed call.
+pub(crate) async fn publish_prompt_events(
+    state: &AppState,
+    system_name: &str,
+    session_id: Option<&str>,
+    target: &str,
+    trace_events: &[TraceEvent],
+    result: &SendResult,
+    elapsed_ms: u64,
+) {
+    let events = state.webhooks();
+
+    if let Some((config, _)) = state.get_system_config(system_name).await {
+        for event in trace_events {
+            let is_tool = config.tools.iter().any(|t| t.name == event.from);
+            if event.event_type == TraceEventType::Response && is_tool {
+                if let Some(message) = event.content.strip_prefix(TOOL_ERROR_PREFIX) {
+                    events.publish(
+                        WebhookEvent::new(EventKind::ToolError, system_name)
+                            .with_session(session_id)
+                            .with_data(json!({
+                                "tool": event.from,
+                                "caller": event.to,
+                                "error": message.trim(),
+                            })),
+                    );
+                }
+            }
+        }
+    }
+
+    let event = match result {
+        SendResult::Response(msg) => WebhookEvent::new(EventKind::PromptCompleted, system_name).with_data(json!({
+            "target": target,
+            "from": msg.from,
+            "content": msg.content,
+            "elapsed_ms": elapsed_ms,
+        })),
+        SendResult::Timeout(err) => WebhookEvent::new(EventKind::AgentTimeout, system_name).with_data(json!({
+            "target": target,
+            "message": err.to_string(),
+            "elapsed_ms": elapsed_ms,
+        })),
+        SendResult::Notified => return,
+    };
+    events.publish(event.with_session(session_id));
+}
+
+/// Deliver the state's events to their webhooks in the background
+pub fn spawn(state: AppState) -> JoinHandle<()> {
+    let mut rx = state.webhooks().subscribe();
+    tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        dispatch(&state, &event, RetryPolicy::default()).await;
+                    });
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Webhook dispatcher lagged, {} events were not delivered", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+/// Deliver an event to every webhook that wants it
+///
+/// Returns the number of webhooks it was delivered to.
+pub async fn dispatch(state: &AppState, event: &WebhookEvent, policy: RetryPolicy) -> usize {
+    let Some(pool) = state.try_db() else {
+        return 0;
+    };
+    let webhooks = match repository::list_system_webhooks(pool, &event.system).await {
+        Ok(webhooks) => webhooks,
+        Err(e) => {
+            error!("Failed to look up webhooks of '{}': {}", event.system, e);
+            return 0;
+        }
+    };
+
+    let client = reqwest::Client::new();
+    let deliveries = webhooks
+        .iter()
+        .filter(|webhook| webhook.wants(event.kind.as_str()))
+        .map(|webhook| deliver(pool, &client, webhook, event, policy));
+    futures::future::join_all(deliveries)
+        .await
+        .into_iter()
+        .filter(|status| *status == DeliveryStatus::Delivered)
+        .count()
+}
+
+/// Deliver an event to one webhook, retrying as the policy allows
+pub async fn deliver(
+    pool: &SqlitePool,
+    client: &reqwest::Client,
+    webhook: &Webhook,
+    event: &WebhookEvent,
+    policy: RetryPolicy,
+) -> DeliveryStatus {
+    let delivery_id = Uuid::new_v4().to_string();
+    let payload = event.payload(&delivery_id);
+    let body = payload.to_string();
+    let signature = mas_core::hook::sign(&webhook.secret, body.as_bytes());
+
+    if let Err(e) =
+        repository::create_webhook_delivery(pool, &delivery_id, &webhook.id, event.kind.as_str(), &payload).await
+    {
+        error!("Failed to log delivery to webhook {}: {}", webhook.id, e);
+    }
+
+    let mut delay = policy.initial_delay;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let sent = client
+            .post(&webhook.url)
+            .timeout(policy.request_timeout)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-Mas-Event", event.kind.as_str())
+            .header("X-Mas-Delivery", &delivery_id)
+            .header("X-Mas-Signature-256", &signature)
+            .body(body.clone())
+            .send()
+            .await;
+
+        let (response_status, error, retryable) = match sent {
+            Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None, false),
+            Ok(response) => {
+                let status = response.status();
+                let retryable = status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+                (Some(status.as_u16()), Some(format!("Endpoint answered {}", status)), retryable)
+            }
+            Err(e) => (None, Some(e.to_string()), true),
+        };
+
+        let status = match &error {
+            None => DeliveryStatus::Delivered,
+            Some(_) if retryable && attempt < policy.max_attempts => DeliveryStatus::Pending,
+            Some(_) => DeliveryStatus::Failed,
+        };
+        if let Err(e) = repository::update_webhook_delivery(
+            pool,
+            &delivery_id,
+            status,
+            attempt,
+            response_status,
+            error.as_deref(),
+        )
+        .await
+        {
+            error!("Failed to log delivery {} to webhook {}: {}", delivery_id, webhook.id, e);
+        }
+
+        match status {
+            DeliveryStatus::Delivered => {
+                info!("Delivered {} to webhook {}", event.kind.as_str(), webhook.id);
+                return status;
+            }
+            DeliveryStatus::Failed => {
+                warn!(
+                    "Giving up delivering {} to webhook {} after {} attempt(s): {}",
+                    event.kind.as_str(),
+                    webhook.id,
+                    attempt,
+                    error.unwrap_or_default()
+                );
+                return status;
+            }
+            DeliveryStatus::Pending => {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::HeaderMap, http::StatusCode, routing::post, Router};
+    use std::sync::{Arc, Mutex};
+
+    /// An endpoint that fails its first call, recording what it receives
+    async fn flaky_endpoint(received: Arc<Mutex<Vec<(HeaderMap, String)>>>) -> String {
+        let app = Router::new().route(
+            "/events",
+            post(move |headers: HeaderMap, body: String| {
+                let received = received.clone();
+                async move {
+                    let mut received = received.lock().unwrap();
+                    received.push((headers, body));
+                    if received.len() == 1 {
+                        StatusCode::SERVICE_UNAVAILABLE
+                    } else {
+                        StatusCode::OK
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/events", addr)
+    }
+
+    #[tokio::test]
+    async fn test_events_are_signed_retried_and_logged() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let url = flaky_endpoint(received.clone()).await;
+
+        let pool = mas_auth::create_pool(":memory:").await.unwrap();
+        mas_auth::run_migrations(&pool).await.unwrap();
+        repository::create_org(&pool, "org-1", "Ops", "ops", None).await.unwrap();
+        repository::add_system_org(&pool, "support", "org-1").await.unwrap();
+        let timeouts = vec!["agent.timeout".to_string()];
+        repository::create_webhook(&pool, "wh-1", "org-1", None, &url, "whsec", &timeouts, None)
+            .await
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let state = AppState::with_paths(dir.path().join("sessions"), dir.path().join("systems")).with_db(pool.clone());
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(10),
+            request_timeout: Duration::from_secs(5),
+        };
+
+        // Not subscribed to this kind
+        let created = WebhookEvent::new(EventKind::SessionCreated, "support");
+        assert_eq!(dispatch(&state, &created, policy).await, 0);
+
+        let timeout = WebhookEvent::new(EventKind::AgentTimeout, "support")
+            .with_session(Some("s-1"))
+            .with_data(json!({ "target": "Coordinator" }));
+        assert_eq!(dispatch(&state, &timeout, policy).await, 1);
+
+        let log = repository::list_webhook_deliveries(&pool, "wh-1", 10).await.unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].status, DeliveryStatus::Delivered);
+        assert_eq!(log[0].attempts, 2);
+        assert_eq!(log[0].response_status, Some(200));
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        let (headers, body) = &received[1];
+        assert_eq!(headers["X-Mas-Event"], "agent.timeout");
+        assert_eq!(headers["X-Mas-Signature-256"], mas_core::hook::sign("whsec", body.as_bytes()).as_str());
+        assert_eq!(headers["X-Mas-Delivery"], received[0].0["X-Mas-Delivery"]);
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["event"], "agent.timeout");
+        assert_eq!(body["session_id"], "s-1");
+        assert_eq!(body["data"]["target"], "Coordinator");
+    }
+}
@@ -0,0 +1,72 @@
+//! Calls `POST /api/v1/hooks/{system}/{hook}` with signed and unsigned payloads
+
+use std::sync::Arc;
+
+use mas_api::state::{extract_metadata, SystemEntry};
+use mas_api::{create_router, AppState};
+use mas_core::agent_system::EchoHandler;
+use mas_core::config_loader::SystemConfigJson;
+use mas_core::hook::sign;
+use mas_core::{AgentBuilder, AgentSystem};
+use serde_json::json;
+
+async fn serve(state: AppState) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, create_router(state)).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn test_hooks_accept_only_signed_payloads() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = AppState::with_paths(dir.path().join("sessions"), dir.path().join("systems"));
+    state.init().await.unwrap();
+
+    let config: SystemConfigJson = serde_json::from_value(json!({
+        "system": {},
+        "llm_providers": {},
+        "agents": [ { "name": "Echo", "handler": { "provider": "default" } } ],
+        "hooks": [ { "name": "alerts", "secret": "s3cret", "target_agent": "Echo", "prompt": "Alert: ${payload.alert}" } ]
+    }))
+    .unwrap();
+    let system = Arc::new(AgentSystem::with_default_config());
+    system.register_agent(AgentBuilder::new("Echo").build(), Arc::new(EchoHandler)).await.unwrap();
+    state
+        .register_system("ops".to_string(), SystemEntry::new(system, extract_metadata(&config), config))
+        .await
+        .unwrap();
+
+    let base = serve(state).await;
+    let client = reqwest::Client::new();
+    let body = r#"{"alert": "disk full"}"#;
+    let call = |hook: &str, signature: Option<String>| {
+        let mut request = client.post(format!("{}/api/v1/hooks/ops/{}", base, hook)).body(body);
+        if let Some(signature) = signature {
+            request = request.header("X-Hub-Signature-256", signature);
+        }
+        request.send()
+    };
+
+    // With and without the sha256= prefix
+    let signature = sign("s3cret", body.as_bytes());
+    for signature in [signature.clone(), signature.trim_start_matches("sha256=").to_string()] {
+        let response = call("alerts", Some(signature)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let answer: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(answer["result"]["content"], "Echo: Alert: disk full");
+    }
+
+    // A wrong or missing signature and an unknown hook get the same answer
+    let rejected = response_of(call("alerts", Some(sign("other", body.as_bytes())))).await;
+    assert_eq!(rejected.0, 401);
+    assert_eq!(response_of(call("alerts", None)).await, rejected);
+    assert_eq!(response_of(call("missing", Some(signature))).await, rejected);
+}
+
+async fn response_of(
+    response: impl std::future::Future<Output = reqwest::Result<reqwest::Response>>,
+) -> (u16, String) {
+    let response = response.await.unwrap();
+    (response.status().as_u16(), response.text().await.unwrap())
+}
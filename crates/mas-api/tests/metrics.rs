@@ -0,0 +1,104 @@
+//! Scrapes `GET /metrics` after sending a prompt through the API
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use mas_api::state::{extract_metadata, SystemEntry};
+use mas_api::{create_router, AppState};
+use mas_core::agent_system::{DelayedHandler, EchoHandler};
+use mas_core::config_loader::SystemConfigJson;
+use mas_core::{AgentBuilder, AgentSystem, SystemConfig};
+use serde_json::json;
+
+async fn serve(state: AppState) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, create_router(state)).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint_reports_prompts_timeouts_and_inboxes() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = AppState::with_paths(dir.path().join("sessions"), dir.path().join("systems"))
+        .with_auth_disabled(true);
+
+    let config: SystemConfigJson = serde_json::from_value(json!({
+        "system": {},
+        "llm_providers": {},
+        "agents": [
+            { "name": "Echo", "handler": { "provider": "default" } },
+            { "name": "Sleepy", "handler": { "provider": "default" } }
+        ]
+    }))
+    .unwrap();
+    let system = Arc::new(AgentSystem::new(
+        SystemConfig::new(Duration::from_millis(100)).with_name("metrics-demo"),
+    ));
+    system.register_agent(AgentBuilder::new("Echo").build(), Arc::new(EchoHandler)).await.unwrap();
+    system
+        .register_agent(
+            AgentBuilder::new("Sleepy").build(),
+            Arc::new(DelayedHandler::new(Duration::from_secs(1), "Too late")),
+        )
+        .await
+        .unwrap();
+    state
+        .register_system("metrics-demo".to_string(), SystemEntry::new(system, extract_metadata(&config), config))
+        .await
+        .unwrap();
+
+    let base = serve(state).await;
+    let client = reqwest::Client::new();
+    for target in ["Echo", "Sleepy"] {
+        let response = client
+            .post(format!("{}/api/v1/systems/metrics-demo/prompt", base))
+            .json(&json!({ "content": "Hello", "target_agent": target }))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+    }
+
+    let response = client.get(format!("{}/metrics", base)).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+    let text = response.text().await.unwrap();
+
+    assert!(text.contains(r#"mas_prompts_total{system="metrics-demo"} 2"#), "{}", text);
+    assert!(text.contains(r#"mas_timeouts_total{agent="Sleepy",system="metrics-demo"} 1"#), "{}", text);
+    assert!(text.contains(r#"mas_inbox_depth{agent="Echo",system="metrics-demo"} 0"#), "{}", text);
+    assert!(text.contains("mas_sessions 0"), "{}", text);
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint_requires_the_metrics_token() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = || AppState::with_paths(dir.path().join("sessions"), dir.path().join("systems"));
+    let client = reqwest::Client::new();
+
+    let unconfigured = serve(state().with_auth_disabled(false)).await;
+    let response = client.get(format!("{}/metrics", unconfigured)).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+
+    let state = state()
+        .with_auth_disabled(false)
+        .with_metrics_token("scrape-me");
+    let base = serve(state).await;
+    let response = client.get(format!("{}/metrics", base)).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    let response = client
+        .get(format!("{}/metrics", base))
+        .bearer_auth("guess")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+    let response = client
+        .get(format!("{}/metrics", base))
+        .bearer_auth("scrape-me")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+}
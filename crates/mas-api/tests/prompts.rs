@@ -0,0 +1,87 @@
+//! Sends prompts through `POST /api/v1/systems/{name}/prompt` to guarded connections
+
+use std::sync::Arc;
+
+use mas_api::state::{extract_metadata, SystemEntry};
+use mas_api::{create_router, AppState};
+use mas_core::agent_system::EchoHandler;
+use mas_core::config_loader::SystemConfigJson;
+use mas_core::{AgentBuilder, AgentSystem, Connection, Guard, GuardConfig, RuleConfig, RuleRouter};
+use serde_json::json;
+
+async fn serve(state: AppState) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, create_router(state)).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn test_prompt_metadata_reaches_connection_guards() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = AppState::with_paths(dir.path().join("sessions"), dir.path().join("systems"))
+        .with_auth_disabled(true);
+
+    let config: SystemConfigJson = serde_json::from_value(json!({
+        "system": {},
+        "llm_providers": {},
+        "agents": [
+            { "name": "Router", "handler": { "type": "rules" } },
+            { "name": "Records", "handler": { "provider": "default" } }
+        ]
+    }))
+    .unwrap();
+    let guard = Guard::compile(&GuardConfig {
+        metadata: [
+            ("authenticated".to_string(), "true".to_string()),
+            ("user_id".to_string(), "dev-user".to_string()),
+            ("tier".to_string(), "gold".to_string()),
+        ]
+        .into(),
+        ..Default::default()
+    })
+    .unwrap();
+    let router = RuleRouter::new(&[RuleConfig {
+        target: "Records".to_string(),
+        keywords: vec!["records".to_string()],
+        ..Default::default()
+    }])
+    .unwrap()
+    .with_no_match_response("Not allowed.");
+
+    let system = Arc::new(AgentSystem::with_default_config());
+    let agent = AgentBuilder::new("Router")
+        .connection("Records", Connection::blocking(None).with_guard(guard))
+        .build();
+    AgentSystem::register_routing_agent(system.clone(), agent, Arc::new(router)).await.unwrap();
+    system.register_agent(AgentBuilder::new("Records").build(), Arc::new(EchoHandler)).await.unwrap();
+    state
+        .register_system("clinic".to_string(), SystemEntry::new(system, extract_metadata(&config), config))
+        .await
+        .unwrap();
+
+    let base = serve(state).await;
+    let client = reqwest::Client::new();
+    let prompt = |metadata: serde_json::Value| {
+        client
+            .post(format!("{}/api/v1/systems/clinic/prompt", base))
+            .json(&json!({ "content": "Show my records", "target_agent": "Router", "metadata": metadata }))
+            .send()
+    };
+
+    // The server sets `authenticated` and `user_id`, the client adds the tier
+    let body: serde_json::Value = prompt(json!({ "tier": "gold" })).await.unwrap().json().await.unwrap();
+    assert_eq!(body["result"]["content"], "Echo: Show my records");
+
+    let body: serde_json::Value = prompt(json!({ "tier": "silver" })).await.unwrap().json().await.unwrap();
+    assert_eq!(body["result"]["content"], "Not allowed.");
+
+    // Clients cannot pose as another user
+    let body: serde_json::Value = prompt(json!({ "tier": "gold", "user_id": "someone-else" }))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["result"]["content"], "Echo: Show my records");
+}
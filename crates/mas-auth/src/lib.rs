@@ -6,6 +6,7 @@
 //! - Many-to-many user↔org membership with roles (owner/admin/member)
 //! - System↔org associations for access control
 //! - Session ownership tracking
+//! - Outbound webhook subscriptions and their delivery log
 //! - Axum middleware extractor for protected endpoints
 
 pub mod db;
@@ -50,6 +51,6 @@ pub use db::{create_pool, run_migrations};
 pub use jwt::{JwtConfig, TokenPair};
 pub use middleware::{AuthState, AuthenticatedUser, FromRef, OptionalUser};
 pub use models::{
-    AuthClaims, MemberInfo, OrgMembership, OrgRole, OrgWithRole, Organization, SessionRecord,
-    SystemOrg, User, UserInfo,
+    AuthClaims, DeliveryStatus, MemberInfo, OrgMembership, OrgRole, OrgWithRole, Organization,
+    SessionRecord, SystemOrg, User, UserInfo, Webhook, WebhookDelivery,
 };
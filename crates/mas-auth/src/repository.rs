@@ -442,6 +442,30 @@ pub async fn user_has_system_access(
     Ok(count > 0)
 }
 
+/// The organization through which a user reaches a system, if any
+///
+/// With several, the oldest membership wins so the answer is stable.
+pub async fn find_user_system_org(
+    pool: &SqlitePool,
+    user_id: &str,
+    system_name: &str,
+) -> Result<Option<String>, AuthError> {
+    let row = sqlx::query(
+        "SELECT so.org_id FROM system_orgs so
+         JOIN org_memberships m ON so.org_id = m.org_id
+         WHERE m.user_id = ? AND so.system_name = ?
+         ORDER BY m.created_at, so.org_id
+         LIMIT 1"
+    )
+    .bind(user_id)
+    .bind(system_name)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AuthError::Database(e.to_string()))?;
+
+    Ok(row.map(|r| r.get("org_id")))
+}
+
 /// List all system names a user has access to (via org memberships OR direct ownership)
 pub async fn list_user_systems(pool: &SqlitePool, user_id: &str) -> Result<Vec<String>, AuthError> {
     let rows = sqlx::query(
@@ -502,6 +526,15 @@ pub async fn remove_system_owner(
     Ok(())
 }
 
+/// The user who has owned a system the longest
+pub async fn find_system_owner(pool: &SqlitePool, system_name: &str) -> Result<Option<String>, AuthError> {
+    sqlx::query_scalar("SELECT user_id FROM system_owners WHERE system_name = ? ORDER BY created_at, rowid LIMIT 1")
+        .bind(system_name)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))
+}
+
 /// Remove all ownership records for a system
 pub async fn delete_system_owners(pool: &SqlitePool, system_name: &str) -> Result<(), AuthError> {
     sqlx::query("DELETE FROM system_owners WHERE system_name = ?")
@@ -647,3 +680,371 @@ pub async fn cleanup_expired_tokens(pool: &SqlitePool) -> Result<u64, AuthError>
         .map_err(|e| AuthError::Database(e.to_string()))?;
     Ok(result.rows_affected())
 }
+
+// ─── Webhooks ───────────────────────────────────────────
+
+const WEBHOOK_COLUMNS: &str =
+    "id, org_id, system_name, url, secret, events, active, created_by, created_at, updated_at";
+
+/// Create a webhook subscription
+#[allow(clippy::too_many_arguments)]
+pub async fn create_webhook(
+    pool: &SqlitePool,
+    id: &str,
+    org_id: &str,
+    system_name: Option<&str>,
+    url: &str,
+    secret: &str,
+    events: &[String],
+    created_by: Option<&str>,
+) -> Result<Webhook, AuthError> {
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let events_json = serde_json::to_string(events).map_err(|e| AuthError::Internal(e.to_string()))?;
+
+    sqlx::query(
+        "INSERT INTO webhooks (id, org_id, system_name, url, secret, events, active, created_by, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, 1, ?, ?, ?)"
+    )
+    .bind(id)
+    .bind(org_id)
+    .bind(system_name)
+    .bind(url)
+    .bind(secret)
+    .bind(&events_json)
+    .bind(created_by)
+    .bind(&now)
+    .bind(&now)
+    .execute(pool)
+    .await
+    .map_err(|e| AuthError::Database(e.to_string()))?;
+
+    Ok(Webhook {
+        id: id.to_string(),
+        org_id: org_id.to_string(),
+        system_name: system_name.map(|s| s.to_string()),
+        url: url.to_string(),
+        secret: secret.to_string(),
+        events: events.to_vec(),
+        active: true,
+        created_by: created_by.map(|s| s.to_string()),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    })
+}
+
+/// Find a webhook of an organization
+pub async fn find_webhook(pool: &SqlitePool, org_id: &str, id: &str) -> Result<Option<Webhook>, AuthError> {
+    let row = sqlx::query(&format!("SELECT {} FROM webhooks WHERE id = ? AND org_id = ?", WEBHOOK_COLUMNS))
+        .bind(id)
+        .bind(org_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+
+    Ok(row.map(|r| webhook_from_row(&r)))
+}
+
+/// List the webhooks of an organization
+pub async fn list_org_webhooks(pool: &SqlitePool, org_id: &str) -> Result<Vec<Webhook>, AuthError> {
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM webhooks WHERE org_id = ? ORDER BY created_at",
+        WEBHOOK_COLUMNS
+    ))
+    .bind(org_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AuthError::Database(e.to_string()))?;
+
+    Ok(rows.iter().map(webhook_from_row).collect())
+}
+
+/// Active webhooks receiving events of a system
+///
+/// These are the webhooks subscribed to the system itself, plus the
+/// org-wide webhooks of every organization the system belongs to. Either
+/// kind only applies while its organization still owns the system, so a
+/// system moved to (or recreated in) another organization stops reporting
+/// to the old one.
+pub async fn list_system_webhooks(pool: &SqlitePool, system_name: &str) -> Result<Vec<Webhook>, AuthError> {
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM webhooks
+         WHERE active = 1
+           AND (system_name = ? OR system_name IS NULL)
+           AND org_id IN (SELECT org_id FROM system_orgs WHERE system_name = ?)",
+        WEBHOOK_COLUMNS
+    ))
+    .bind(system_name)
+    .bind(system_name)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AuthError::Database(e.to_string()))?;
+
+    Ok(rows.iter().map(webhook_from_row).collect())
+}
+
+/// Update a webhook
+pub async fn update_webhook(
+    pool: &SqlitePool,
+    id: &str,
+    url: Option<&str>,
+    events: Option<&[String]>,
+    active: Option<bool>,
+) -> Result<(), AuthError> {
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+    if let Some(url) = url {
+        sqlx::query("UPDATE webhooks SET url = ?, updated_at = ? WHERE id = ?")
+            .bind(url)
+            .bind(&now)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| AuthError::Database(e.to_string()))?;
+    }
+
+    if let Some(events) = events {
+        let events_json = serde_json::to_string(events).map_err(|e| AuthError::Internal(e.to_string()))?;
+        sqlx::query("UPDATE webhooks SET events = ?, updated_at = ? WHERE id = ?")
+            .bind(&events_json)
+            .bind(&now)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| AuthError::Database(e.to_string()))?;
+    }
+
+    if let Some(active) = active {
+        sqlx::query("UPDATE webhooks SET active = ?, updated_at = ? WHERE id = ?")
+            .bind(active)
+            .bind(&now)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| AuthError::Database(e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Delete a webhook and its delivery log
+pub async fn delete_webhook(pool: &SqlitePool, id: &str) -> Result<(), AuthError> {
+    sqlx::query("DELETE FROM webhooks WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+    Ok(())
+}
+
+fn webhook_from_row(row: &SqliteRow) -> Webhook {
+    let events: String = row.get("events");
+    let active: i64 = row.get("active");
+    Webhook {
+        id: row.get("id"),
+        org_id: row.get("org_id"),
+        system_name: row.get("system_name"),
+        url: row.get("url"),
+        secret: row.get("secret"),
+        events: serde_json::from_str(&events).unwrap_or_default(),
+        active: active != 0,
+        created_by: row.get("created_by"),
+        created_at: parse_dt(row.get("created_at")),
+        updated_at: parse_dt(row.get("updated_at")),
+    }
+}
+
+// ─── Webhook Deliveries ─────────────────────────────────
+
+/// Record a delivery before its first attempt
+pub async fn create_webhook_delivery(
+    pool: &SqlitePool,
+    id: &str,
+    webhook_id: &str,
+    event: &str,
+    payload: &serde_json::Value,
+) -> Result<(), AuthError> {
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+    sqlx::query(
+        "INSERT INTO webhook_deliveries (id, webhook_id, event, payload, status, attempts, created_at, updated_at)
+         VALUES (?, ?, ?, ?, 'pending', 0, ?, ?)"
+    )
+    .bind(id)
+    .bind(webhook_id)
+    .bind(event)
+    .bind(payload.to_string())
+    .bind(&now)
+    .bind(&now)
+    .execute(pool)
+    .await
+    .map_err(|e| AuthError::Database(e.to_string()))?;
+    Ok(())
+}
+
+/// Record the outcome of a delivery attempt
+pub async fn update_webhook_delivery(
+    pool: &SqlitePool,
+    id: &str,
+    status: DeliveryStatus,
+    attempts: u32,
+    response_status: Option<u16>,
+    error: Option<&str>,
+) -> Result<(), AuthError> {
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+    sqlx::query(
+        "UPDATE webhook_deliveries SET status = ?, attempts = ?, response_status = ?, error = ?, updated_at = ?
+         WHERE id = ?"
+    )
+    .bind(status.as_str())
+    .bind(attempts)
+    .bind(response_status)
+    .bind(error)
+    .bind(&now)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| AuthError::Database(e.to_string()))?;
+    Ok(())
+}
+
+/// Recent deliveries of a webhook, newest first
+pub async fn list_webhook_deliveries(
+    pool: &SqlitePool,
+    webhook_id: &str,
+    limit: u32,
+) -> Result<Vec<WebhookDelivery>, AuthError> {
+    let rows = sqlx::query(
+        "SELECT id, webhook_id, event, payload, status, attempts, response_status, error, created_at, updated_at
+         FROM webhook_deliveries WHERE webhook_id = ?
+         ORDER BY created_at DESC, rowid DESC LIMIT ?"
+    )
+    .bind(webhook_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AuthError::Database(e.to_string()))?;
+
+    Ok(rows
+        .iter()
+        .map(|r| {
+            let payload: String = r.get("payload");
+            let status: String = r.get("status");
+            let attempts: i64 = r.get("attempts");
+            let response_status: Option<i64> = r.get("response_status");
+            WebhookDelivery {
+                id: r.get("id"),
+                webhook_id: r.get("webhook_id"),
+                event: r.get("event"),
+                payload: serde_json::from_str(&payload).unwrap_or(serde_json::Value::Null),
+                status: DeliveryStatus::parse(&status).unwrap_or(DeliveryStatus::Failed),
+                attempts: attempts as u32,
+                response_status: response_status.map(|s| s as u16),
+                error: r.get("error"),
+                created_at: parse_dt(r.get("created_at")),
+                updated_at: parse_dt(r.get("updated_at")),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{create_pool, run_migrations};
+
+    #[tokio::test]
+    async fn test_system_webhooks_include_org_wide_subscriptions() {
+        let pool = create_pool(":memory:").await.unwrap();
+        run_migrations(&pool).await.unwrap();
+
+        create_org(&pool, "org-1", "Ops", "ops", None).await.unwrap();
+        create_org(&pool, "org-2", "Legal", "legal", None).await.unwrap();
+        add_system_org(&pool, "support", "org-1").await.unwrap();
+
+        let all = vec![];
+        let timeouts = vec!["agent.timeout".to_string()];
+        create_webhook(&pool, "wh-org", "org-1", None, "https://a.example", "s", &all, None).await.unwrap();
+        create_webhook(&pool, "wh-sys", "org-1", Some("support"), "https://b.example", "s", &timeouts, None)
+            .await
+            .unwrap();
+        create_webhook(&pool, "wh-other", "org-1", Some("billing"), "https://c.example", "s", &all, None)
+            .await
+            .unwrap();
+        create_webhook(&pool, "wh-org2", "org-2", None, "https://d.example", "s", &all, None).await.unwrap();
+
+        let mut ids: Vec<String> = list_system_webhooks(&pool, "support")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|w| w.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["wh-org", "wh-sys"]);
+
+        update_webhook(&pool, "wh-org", None, None, Some(false)).await.unwrap();
+        let hooks = list_system_webhooks(&pool, "support").await.unwrap();
+        assert_eq!(hooks.len(), 1);
+        assert!(hooks[0].wants("agent.timeout"));
+        assert!(!hooks[0].wants("session.created"));
+
+        let payload = serde_json::json!({ "event": "agent.timeout" });
+        create_webhook_delivery(&pool, "d-1", "wh-sys", "agent.timeout", &payload).await.unwrap();
+        update_webhook_delivery(&pool, "d-1", DeliveryStatus::Delivered, 2, Some(200), None)
+            .await
+            .unwrap();
+        let deliveries = list_webhook_deliveries(&pool, "wh-sys", 10).await.unwrap();
+        assert_eq!(deliveries[0].status, DeliveryStatus::Delivered);
+        assert_eq!(deliveries[0].attempts, 2);
+        assert_eq!(deliveries[0].payload, payload);
+
+        // Deleting the webhook drops its log
+        delete_webhook(&pool, "wh-sys").await.unwrap();
+        assert!(list_webhook_deliveries(&pool, "wh-sys", 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_system_webhooks_stop_when_the_system_leaves_the_org() {
+        let pool = create_pool(":memory:").await.unwrap();
+        run_migrations(&pool).await.unwrap();
+
+        create_org(&pool, "org-1", "Ops", "ops", None).await.unwrap();
+        create_org(&pool, "org-2", "Legal", "legal", None).await.unwrap();
+        add_system_org(&pool, "support", "org-1").await.unwrap();
+
+        let all = vec![];
+        create_webhook(&pool, "wh-old", "org-1", Some("support"), "https://a.example", "s", &all, None)
+            .await
+            .unwrap();
+        create_webhook(&pool, "wh-new", "org-2", Some("support"), "https://b.example", "s", &all, None)
+            .await
+            .unwrap();
+        let ids = |hooks: Vec<Webhook>| hooks.into_iter().map(|w| w.id).collect::<Vec<_>>();
+        assert_eq!(ids(list_system_webhooks(&pool, "support").await.unwrap()), vec!["wh-old"]);
+
+        // The system moves to the other organization
+        remove_system_org(&pool, "support", "org-1").await.unwrap();
+        add_system_org(&pool, "support", "org-2").await.unwrap();
+        assert_eq!(ids(list_system_webhooks(&pool, "support").await.unwrap()), vec!["wh-new"]);
+    }
+
+    #[tokio::test]
+    async fn test_find_user_system_org() {
+        let pool = create_pool(":memory:").await.unwrap();
+        run_migrations(&pool).await.unwrap();
+
+        create_user(&pool, "u-1", "a@example.com", "A", "hash").await.unwrap();
+        create_org(&pool, "org-1", "Ops", "ops", None).await.unwrap();
+        create_org(&pool, "org-2", "Legal", "legal", None).await.unwrap();
+        add_membership(&pool, "u-1", "org-2", OrgRole::Member).await.unwrap();
+        add_system_org(&pool, "support", "org-1").await.unwrap();
+
+        assert_eq!(find_user_system_org(&pool, "u-1", "support").await.unwrap(), None);
+
+        add_system_org(&pool, "support", "org-2").await.unwrap();
+        assert_eq!(
+            find_user_system_org(&pool, "u-1", "support").await.unwrap().as_deref(),
+            Some("org-2")
+        );
+    }
+}
@@ -121,6 +121,21 @@ fn run_dry_run(path: &PathBuf) -> anyhow::Result<()> {
             info!("Configuration is valid!");
             info!("  Providers: {:?}", config.llm_providers.keys().collect::<Vec<_>>());
             info!("  Agents: {:?}", config.agents.iter().map(|a| &a.name).collect::<Vec<_>>());
+            if !config.workflows.is_empty() {
+                info!("  Workflows: {:?}", config.workflows.iter().map(|w| &w.name).collect::<Vec<_>>());
+            }
+            if !config.triggers.is_empty() {
+                info!(
+                    "  Triggers (run by mas-api): {:?}",
+                    config.triggers.iter().map(|t| format!("{} [{}]", t.name, t.cron)).collect::<Vec<_>>()
+                );
+            }
+            if !config.hooks.is_empty() {
+                info!(
+                    "  Hooks (served by mas-api): {:?}",
+                    config.hooks.iter().map(|h| h.name.as_str()).collect::<Vec<_>>()
+                );
+            }
             info!("  Global timeout: {}s", config.system.global_timeout_secs);
         }
         Err(e) => {
@@ -141,8 +156,10 @@ async fn run_prompt(path: &PathBuf, prompt: &str, target: Option<&str>) -> anyho
     // Determine target agent
     let target_agent = match target {
         Some(name) => {
-            // Verify the agent exists
-            if !config.agents.iter().any(|a| a.name == name) {
+            // Verify the agent (or workflow) exists
+            if !config.agents.iter().any(|a| a.name == name)
+                && !config.workflows.iter().any(|w| w.name == name)
+            {
                 eprintln!("Error: Agent '{}' not found in config", name);
                 eprintln!("Available agents: {:?}", config.agents.iter().map(|a| &a.name).collect::<Vec<_>>());
                 std::process::exit(1);
@@ -1,4 +1,5 @@
-use crate::connection::Connection;
+use crate::connection::{Connection, ConnectionType};
+use crate::message::Message;
 use std::collections::HashMap;
 
 /// An agent in the multi-agent system
@@ -41,6 +42,16 @@ impl Agent {
     pub fn connected_agents(&self) -> impl Iterator<Item = &String> {
         self.connections.keys()
     }
+
+    /// Blocking connections whose guards let the given message through
+    pub fn blocking_connections_for<'a>(
+        &'a self,
+        message: &'a Message,
+    ) -> impl Iterator<Item = (&'a String, &'a Connection)> {
+        self.connections.iter().filter(move |(_, conn)| {
+            conn.connection_type == ConnectionType::Blocking && conn.allows(message)
+        })
+    }
 }
 
 /// Builder for creating agents with a fluent API
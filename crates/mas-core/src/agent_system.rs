@@ -1,22 +1,41 @@
-use crate::agent::Agent;
+use crate::agent::{Agent, AgentBuilder};
+use crate::approval::ApprovalQueue;
+use crate::blackboard::Blackboard;
+use crate::circuit_breaker::CircuitBreaker;
 use crate::config::SystemConfig;
 use crate::connection::ConnectionType;
 use crate::conversation::ConversationStore;
-use crate::decision::{ConversationTurn, EvaluationDecision, ForwardTarget, HandlerDecision};
+use crate::debate;
+use crate::decision::{
+    ConversationTurn, EvaluationDecision, ForwardOutcome, ForwardStatus, ForwardTarget, HandlerDecision, SpawnSpec,
+};
 use crate::errors::{AgentError, Result};
+use crate::events::{EventBus, SystemEventKind};
+use crate::guardrail::{GuardrailStage, GuardrailVerdict};
 use crate::message::Message;
+use crate::metrics::metrics;
+use crate::plan::{self, Plan};
+use crate::replica::{InFlight, ReplicaDispatch, ReplicaPool};
 use crate::database::Database;
+use crate::human::{HumanHandler, HumanInbox};
+use crate::interceptor::{Interception, Interceptor};
 use crate::tool::Tool;
 use crate::tracer::{TraceCollector, TraceEvent};
+use crate::vote::{self, Ballot, VoteAggregation, VoteResult};
+use crate::workflow::{StepState, Workflow};
 
 use async_trait::async_trait;
 use futures::future::join_all;
+use futures::stream::{FuturesUnordered, StreamExt};
 use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{mpsc, oneshot, RwLock};
-use tokio::time::timeout;
-use tracing::{debug, error, info, warn};
+use tokio::time::{timeout, Instant};
+use tracing::{debug, error, field, info, info_span, instrument, warn, Instrument, Span};
+use uuid::Uuid;
 
 /// Result of sending a message to an agent
 #[derive(Debug)]
@@ -69,23 +88,28 @@ pub trait RoutingHandler: Send + Sync {
     /// - `HandlerDecision::Response` - respond directly
     /// - `HandlerDecision::Forward` - delegate to other agents
     /// - `HandlerDecision::ResponseAndForward` - acknowledge and delegate
+    /// - `HandlerDecision::Pipeline` - chain through stages in a fixed order
+    /// - `HandlerDecision::Vote` - collect ballots from targets and aggregate them
+    /// - `HandlerDecision::Debate` - let targets argue for several rounds, then judge
+    /// - `HandlerDecision::Plan` - carry out a plan step by step, re-planning on failures
+    /// - `HandlerDecision::Spawn` - hand tasks to short-lived sub-agents
     /// - `HandlerDecision::None` - no action
     async fn handle(&self, message: &Message, agent: &Agent) -> HandlerDecision;
 
     /// Synthesize multiple forwarded responses into a single response
     ///
-    /// Called when the handler forwarded to multiple agents and received
-    /// responses. The handler should combine these into a coherent response
-    /// to send back to the original sender.
+    /// Called when the handler forwarded to one or more agents. The handler
+    /// should combine the responses into a coherent answer for the original
+    /// sender, and may mention targets that failed to answer.
     ///
     /// # Arguments
     /// * `original_message` - The original incoming message
-    /// * `forwarded_responses` - Responses received from forwarded agents (agent_name, response)
+    /// * `outcomes` - One outcome per forwarded target (success, timeout, error, ...)
     /// * `agent` - The current agent
     async fn synthesize(
         &self,
         original_message: &Message,
-        forwarded_responses: &[(String, String)],
+        outcomes: &[ForwardOutcome],
         agent: &Agent,
     ) -> Option<String>;
 
@@ -112,21 +136,183 @@ pub trait RoutingHandler: Send + Sync {
     fn max_turns(&self) -> u16 {
         1
     }
+
+    /// Number of successful responses after which to stop waiting for the
+    /// remaining targets and synthesize. None (default) waits for all.
+    fn quorum(&self) -> Option<usize> {
+        None
+    }
+
+    /// Aggregate ballots collected for a `HandlerDecision::Vote`
+    ///
+    /// Default implementation: simple majority.
+    async fn tally(
+        &self,
+        _original_message: &Message,
+        ballots: &[Ballot],
+        _agent: &Agent,
+    ) -> Option<VoteResult> {
+        vote::aggregate(ballots, VoteAggregation::Majority)
+    }
+
+    /// Decide the final answer of a `HandlerDecision::Debate`
+    ///
+    /// `turns` holds every argument, with `turn_number` as the round.
+    /// Default implementation: synthesize the final round's arguments.
+    async fn judge_debate(
+        &self,
+        original_message: &Message,
+        turns: &[ConversationTurn],
+        agent: &Agent,
+    ) -> Option<String> {
+        let last_round = turns.iter().map(|t| t.turn_number).max()?;
+        let outcomes: Vec<ForwardOutcome> = turns
+            .iter()
+            .filter(|t| t.turn_number == last_round)
+            .map(|t| ForwardOutcome::success(&t.agent, &t.message_sent, &t.response))
+            .collect();
+        self.synthesize(original_message, &outcomes, agent).await
+    }
+
+    /// Maximum number of re-plans for a `HandlerDecision::Plan` (default 0)
+    fn max_replans(&self) -> u16 {
+        0
+    }
+
+    /// Plan the remaining work of a `HandlerDecision::Plan` after a step failed
+    ///
+    /// `outcomes` holds the completed steps followed by the failed one. Return
+    /// `None` (the default) or an empty plan to stop and synthesize what is known.
+    async fn replan(
+        &self,
+        _original_message: &Message,
+        _plan: &Plan,
+        _outcomes: &[ForwardOutcome],
+        _agent: &Agent,
+    ) -> Option<Plan> {
+        None
+    }
+
+    /// Handler for a sub-agent requested by a `HandlerDecision::Spawn`
+    ///
+    /// Return `None` (the default) if this handler cannot run sub-agents; the
+    /// sub-agent's task is then reported as failed.
+    fn spawn_handler(&self, _spec: &SpawnSpec) -> Option<Arc<dyn RoutingHandler>> {
+        None
+    }
+}
+
+/// What a forward-evaluate loop ended with
+enum ForwardRun {
+    /// The handler answered during evaluation
+    Answered(String),
+    /// Outcomes of every forward, to be synthesized
+    Outcomes(Vec<ForwardOutcome>),
+}
+
+/// What a receiver sends back on a request's response channel
+enum Reply {
+    /// The receiver's answer
+    Message(Message),
+    /// The receiver gave up waiting for an answer of its own (a human agent
+    /// nobody answered)
+    Timeout,
 }
 
 /// Internal message type for the agent's inbox
 struct InboxMessage {
     message: Message,
     /// Channel to send response back (None for notify messages)
-    response_tx: Option<oneshot::Sender<Message>>,
+    response_tx: Option<oneshot::Sender<Reply>>,
     /// Optional trace collector for recording agent communications
     trace: Option<TraceCollector>,
+    /// Absolute deadline inherited from the originating request.
+    /// Downstream sends never wait past this point.
+    deadline: Option<Instant>,
+    /// Span of the sender; the receiver's span is its child
+    span: Span,
+    /// Counts the message against the receiving replica's load until it is
+    /// dropped at the end of processing (None for tools and databases)
+    _in_flight: Option<InFlight>,
 }
 
-/// Handle to a running agent
+/// Clamp a per-hop timeout to the time remaining before the parent deadline.
+///
+/// Returns the timeout to wait for and the absolute deadline to pass downstream.
+fn clamp_to_deadline(hop_timeout: Duration, parent: Option<Instant>) -> (Duration, Instant) {
+    let now = Instant::now();
+    let own = now + hop_timeout;
+    let deadline = match parent {
+        Some(parent) if parent < own => parent,
+        _ => own,
+    };
+    (deadline.saturating_duration_since(now), deadline)
+}
+
+/// Start the message loop of `name` in its own task, with `events` as the
+/// current event bus
+///
+/// A loop that panics is gone for good; the panic is reported on the bus.
+fn spawn_loop<F>(events: &EventBus, name: &str, message_loop: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let task = tokio::spawn(events.clone().scope(Arc::<str>::from(name), message_loop));
+    let events = events.clone();
+    let name = name.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = task.await {
+            if e.is_panic() {
+                let payload = e.into_panic();
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".to_string());
+                error!("Message loop of {} panicked: {}", name, message);
+                events.emit(SystemEventKind::Panic { agent: name, message });
+            }
+        }
+    });
+}
+
+/// Handle one message in its own task, on the event bus of the current loop
+fn spawn_handling<F>(handling: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    match EventBus::current() {
+        Some((events, name)) => tokio::spawn(events.scope(name, handling)),
+        None => tokio::spawn(handling),
+    };
+}
+
+/// Span covering `agent`'s handling of one message, a child of the sender's span
+///
+/// Routing agents record their decision in `mas.routing.decision`.
+fn handle_span(agent: &str, inbox_msg: &InboxMessage) -> Span {
+    info_span!(
+        parent: &inbox_msg.span,
+        "mas.agent.handle",
+        mas.agent = agent,
+        mas.from = %inbox_msg.message.from,
+        mas.message_id = %inbox_msg.message.id,
+        mas.routing.decision = field::Empty,
+    )
+}
+
+/// Span covering the synthesis of `outcomes` into one answer
+fn synthesis_span(outcomes: &[ForwardOutcome]) -> Span {
+    info_span!("mas.synthesis", mas.outcomes = outcomes.len())
+}
+
+/// Handle to a running agent (one inbox per replica)
 struct RunningAgent {
     agent: Agent,
-    inbox_tx: mpsc::Sender<InboxMessage>,
+    replicas: ReplicaPool<mpsc::Sender<InboxMessage>>,
+    /// How long senders wait when their connection sets no timeout
+    /// (default: the global timeout)
+    timeout: Option<Duration>,
 }
 
 /// Handle to a running tool
@@ -141,6 +327,49 @@ struct RunningDatabase {
     inbox_tx: mpsc::Sender<InboxMessage>,
 }
 
+/// Sub-agent of a spawn decision, holding one slot of the spawn limit
+///
+/// Dropping the guard frees the slot and unregisters the sub-agent, so a
+/// spawn that is cancelled or panics leaks neither.
+struct SpawnedAgent {
+    system: Arc<AgentSystem>,
+    name: String,
+    registered: bool,
+}
+
+impl SpawnedAgent {
+    /// Unregister the sub-agent before freeing its slot
+    async fn release(mut self) {
+        self.system.unregister_agent(&self.name).await;
+        self.registered = false;
+    }
+}
+
+impl Drop for SpawnedAgent {
+    fn drop(&mut self) {
+        self.system.spawned.fetch_sub(1, Ordering::SeqCst);
+        if !self.registered {
+            return;
+        }
+        // Dropped without `release`: unregister in the background
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let system = self.system.clone();
+            let name = std::mem::take(&mut self.name);
+            runtime.spawn(async move {
+                system.unregister_agent(&name).await;
+            });
+        }
+    }
+}
+
+/// Where the `before_send` interceptors left a message
+enum Outgoing {
+    /// Deliver the (possibly modified) message
+    Deliver(Message),
+    /// An interceptor answered the message itself
+    Answered(Message),
+}
+
 /// Type of handler registered for an agent
 #[allow(dead_code)]
 enum HandlerType {
@@ -163,17 +392,32 @@ pub struct AgentSystem {
     databases: RwLock<HashMap<String, RunningDatabase>>,
     conversations: Arc<RwLock<ConversationStore>>,
     handlers: RwLock<HashMap<String, HandlerType>>,
+    circuit_breaker: Option<CircuitBreaker>,
+    human_inbox: HumanInbox,
+    approvals: ApprovalQueue,
+    /// Sub-agents spawned by routing agents that are still registered
+    spawned: AtomicUsize,
+    /// Interceptors on every delivered message, in registration order
+    interceptors: RwLock<Vec<Arc<dyn Interceptor>>>,
+    events: EventBus,
 }
 
 impl AgentSystem {
     pub fn new(config: SystemConfig) -> Self {
+        let events = EventBus::default().with_system(config.name.as_str());
         Self {
+            circuit_breaker: config.circuit_breaker.map(CircuitBreaker::new),
             config,
             agents: RwLock::new(HashMap::new()),
             tools: RwLock::new(HashMap::new()),
             databases: RwLock::new(HashMap::new()),
             conversations: Arc::new(RwLock::new(ConversationStore::new())),
             handlers: RwLock::new(HashMap::new()),
+            human_inbox: HumanInbox::new(),
+            approvals: ApprovalQueue::new(),
+            spawned: AtomicUsize::new(0),
+            interceptors: RwLock::new(Vec::new()),
+            events,
         }
     }
 
@@ -181,6 +425,27 @@ impl AgentSystem {
         Self::new(SystemConfig::default())
     }
 
+    /// The system's name (empty when unnamed)
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// The system's lifecycle event bus
+    ///
+    /// Subscribe to it to follow registrations, deliveries, LLM and tool calls,
+    /// timeouts, errors and panics across all requests.
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Register an interceptor on message delivery
+    ///
+    /// Interceptors run in the order they were registered, on every hop.
+    pub async fn register_interceptor(&self, interceptor: Arc<dyn Interceptor>) {
+        info!("Registered interceptor: {}", interceptor.name());
+        self.interceptors.write().await.push(interceptor);
+    }
+
     /// Get a shared reference to the conversation store
     pub fn conversation_store(&self) -> Arc<RwLock<ConversationStore>> {
         self.conversations.clone()
@@ -191,35 +456,100 @@ impl AgentSystem {
         &self,
         agent: Agent,
         handler: Arc<dyn MessageHandler>,
+    ) -> Result<()> {
+        self.register_agent_replicas(agent, vec![handler], ReplicaDispatch::default())
+            .await
+    }
+
+    /// Register an agent backed by several replicas
+    ///
+    /// Each handler gets its own message loop; messages sent to the agent's
+    /// name are spread over them according to `dispatch`.
+    pub async fn register_agent_replicas(
+        &self,
+        agent: Agent,
+        handlers: Vec<Arc<dyn MessageHandler>>,
+        dispatch: ReplicaDispatch,
     ) -> Result<()> {
         let name = agent.name.clone();
-        let (inbox_tx, inbox_rx) = mpsc::channel::<InboxMessage>(100);
+        let first = handlers.first().cloned().ok_or_else(|| {
+            AgentError::ConfigError(format!("Agent '{}' needs at least one replica", name))
+        })?;
 
         // Store the handler
+        {
+            let mut handlers = self.handlers.write().await;
+            handlers.insert(name.clone(), HandlerType::Simple(first));
+        }
+
+        // Spawn one message processing loop per replica
+        let mut inboxes = Vec::with_capacity(handlers.len());
+        for handler in handlers {
+            let (inbox_tx, inbox_rx) = mpsc::channel::<InboxMessage>(100);
+            let agent_clone = agent.clone();
+            spawn_loop(&self.events, &name, Self::simple_agent_loop(agent_clone, inbox_rx, handler));
+            inboxes.push(inbox_tx);
+        }
+
+        // Store the running agent
+        {
+            let mut agents = self.agents.write().await;
+            agents.insert(
+                name.clone(),
+                RunningAgent {
+                    agent,
+                    replicas: ReplicaPool::new(inboxes, dispatch),
+                    timeout: None,
+                },
+            );
+        }
+
+        self.events.emit(SystemEventKind::AgentRegistered { agent: name });
+        Ok(())
+    }
+
+    /// Questions waiting for answers from human agents
+    pub fn human_inbox(&self) -> &HumanInbox {
+        &self.human_inbox
+    }
+
+    /// Tool and database calls waiting for approval
+    pub fn approval_queue(&self) -> &ApprovalQueue {
+        &self.approvals
+    }
+
+    /// Register a human agent
+    ///
+    /// Messages sent to the agent are published in the system's human inbox and
+    /// answered through it. Each message is handled in its own task, so one
+    /// unanswered question does not hold up the others.
+    pub async fn register_human_agent(&self, agent: Agent, answer_timeout: Duration) -> Result<()> {
+        let name = agent.name.clone();
+        let handler = Arc::new(HumanHandler::new(self.human_inbox.clone(), answer_timeout));
+
         {
             let mut handlers = self.handlers.write().await;
             handlers.insert(name.clone(), HandlerType::Simple(handler.clone()));
         }
 
-        // Spawn the agent's message processing loop
+        let (inbox_tx, inbox_rx) = mpsc::channel::<InboxMessage>(100);
         let agent_clone = agent.clone();
-        let handler_clone = handler;
-        tokio::spawn(async move {
-            Self::simple_agent_loop(agent_clone, inbox_rx, handler_clone).await;
-        });
+        spawn_loop(&self.events, &name, Self::human_loop(agent_clone, inbox_rx, handler));
 
-        // Store the running agent
         {
             let mut agents = self.agents.write().await;
             agents.insert(
-                name,
+                name.clone(),
                 RunningAgent {
                     agent,
-                    inbox_tx,
+                    replicas: ReplicaPool::new(vec![inbox_tx], ReplicaDispatch::default()),
+                    timeout: None,
                 },
             );
         }
 
+        info!("Registered human agent: {}", name);
+        self.events.emit(SystemEventKind::AgentRegistered { agent: name });
         Ok(())
     }
 
@@ -231,39 +561,70 @@ impl AgentSystem {
         system: Arc<Self>,
         agent: Agent,
         handler: Arc<dyn RoutingHandler>,
+    ) -> Result<()> {
+        Self::register_routing_agent_replicas(system, agent, vec![handler], ReplicaDispatch::default())
+            .await
+    }
+
+    /// Register a routing agent backed by several replicas
+    ///
+    /// See [`AgentSystem::register_agent_replicas`].
+    pub async fn register_routing_agent_replicas(
+        system: Arc<Self>,
+        agent: Agent,
+        handlers: Vec<Arc<dyn RoutingHandler>>,
+        dispatch: ReplicaDispatch,
     ) -> Result<()> {
         let name = agent.name.clone();
-        let (inbox_tx, inbox_rx) = mpsc::channel::<InboxMessage>(100);
+        let first = handlers.first().cloned().ok_or_else(|| {
+            AgentError::ConfigError(format!("Agent '{}' needs at least one replica", name))
+        })?;
 
         // Store the handler
         {
             let mut handlers = system.handlers.write().await;
-            handlers.insert(name.clone(), HandlerType::Routing(handler.clone()));
+            handlers.insert(name.clone(), HandlerType::Routing(first));
         }
 
-        // Spawn the agent's message processing loop with routing support
-        let agent_clone = agent.clone();
-        let handler_clone = handler;
-        let system_clone = system.clone();
-        tokio::spawn(async move {
-            Self::routing_agent_loop(system_clone, agent_clone, inbox_rx, handler_clone).await;
-        });
+        // Spawn one message processing loop with routing support per replica
+        let mut inboxes = Vec::with_capacity(handlers.len());
+        for handler in handlers {
+            let (inbox_tx, inbox_rx) = mpsc::channel::<InboxMessage>(100);
+            Self::spawn_routing_loop(system.clone(), agent.clone(), inbox_rx, handler);
+            inboxes.push(inbox_tx);
+        }
 
         // Store the running agent
         {
             let mut agents = system.agents.write().await;
             agents.insert(
-                name,
+                name.clone(),
                 RunningAgent {
                     agent,
-                    inbox_tx,
+                    replicas: ReplicaPool::new(inboxes, dispatch),
+                    timeout: None,
                 },
             );
         }
 
+        system.events.emit(SystemEventKind::AgentRegistered { agent: name });
         Ok(())
     }
 
+    /// Unregister an agent
+    ///
+    /// Its message loops stop once the messages already in their inboxes are
+    /// handled. Returns whether an agent of that name was registered.
+    pub async fn unregister_agent(&self, name: &str) -> bool {
+        self.handlers.write().await.remove(name);
+        let removed = self.agents.write().await.remove(name).is_some();
+        if removed {
+            info!("Unregistered agent: {}", name);
+            self.events.emit(SystemEventKind::AgentUnregistered { agent: name.to_string() });
+        }
+        removed
+    }
+
     /// Register a tool with its handler
     ///
     /// Tools are HTTP-based endpoints that agents can forward messages to.
@@ -277,11 +638,7 @@ impl AgentSystem {
         let (inbox_tx, inbox_rx) = mpsc::channel::<InboxMessage>(100);
 
         // Spawn the tool's message processing loop
-        let tool_clone = tool.clone();
-        let handler_clone = handler;
-        tokio::spawn(async move {
-            Self::tool_loop(tool_clone, inbox_rx, handler_clone).await;
-        });
+        spawn_loop(&self.events, &name, Self::tool_loop(tool.clone(), inbox_rx, handler));
 
         // Store the running tool
         {
@@ -312,11 +669,7 @@ impl AgentSystem {
         let (inbox_tx, inbox_rx) = mpsc::channel::<InboxMessage>(100);
 
         // Spawn the database's message processing loop
-        let db_clone = database.clone();
-        let handler_clone = handler;
-        tokio::spawn(async move {
-            Self::database_loop(db_clone, inbox_rx, handler_clone).await;
-        });
+        spawn_loop(&self.events, &name, Self::database_loop(database.clone(), inbox_rx, handler));
 
         // Store the running database
         {
@@ -334,6 +687,45 @@ impl AgentSystem {
         Ok(())
     }
 
+    /// Register a workflow under its own name
+    ///
+    /// The workflow is addressed like an agent: each message it receives runs
+    /// the whole DAG and is answered with the workflow's output.
+    pub async fn register_workflow(system: Arc<Self>, workflow: Workflow) -> Result<()> {
+        let name = workflow.name().to_string();
+        let agent = workflow
+            .targets()
+            .into_iter()
+            .fold(AgentBuilder::new(&name), |builder, target| match workflow.step_timeout(target) {
+                Some(timeout) => builder.blocking_connection_with_timeout(target, timeout),
+                None => builder.blocking_connection(target),
+            })
+            .build();
+        let timeout = workflow.timeout();
+
+        // Spawn the workflow's message processing loop
+        let (inbox_tx, inbox_rx) = mpsc::channel::<InboxMessage>(100);
+        let workflow = Arc::new(workflow);
+        spawn_loop(&system.events, &name, Self::workflow_loop(system.clone(), workflow, inbox_rx));
+
+        // Store the running workflow alongside the agents
+        {
+            let mut agents = system.agents.write().await;
+            agents.insert(
+                name.clone(),
+                RunningAgent {
+                    agent,
+                    replicas: ReplicaPool::new(vec![inbox_tx], ReplicaDispatch::default()),
+                    timeout,
+                },
+            );
+        }
+
+        info!("Registered workflow: {}", name);
+        system.events.emit(SystemEventKind::AgentRegistered { agent: name });
+        Ok(())
+    }
+
     /// Get info about all registered tools and databases (for LLM routing prompts)
     pub async fn get_tool_infos(&self) -> Vec<ToolInfo> {
         let tools = self.tools.read().await;
@@ -365,30 +757,73 @@ impl AgentSystem {
         handler: Arc<dyn MessageHandler>,
     ) {
         while let Some(inbox_msg) = inbox.recv().await {
-            let response_content = handler.handle(&inbox_msg.message, &agent).await;
+            let version = inbox_msg.message.blackboard.version();
+            let response_content =
+                TraceCollector::scope(inbox_msg.trace.clone(), handler.handle(&inbox_msg.message, &agent))
+                    .instrument(handle_span(&agent.name, &inbox_msg))
+                    .await;
+            Self::record_blackboard_change(&agent.name, &inbox_msg, version).await;
 
             // If there's a response channel and we have content, send the response
             if let (Some(tx), Some(content)) = (inbox_msg.response_tx, response_content) {
                 let response = inbox_msg.message.reply(content);
-                let _ = tx.send(response);
+                let _ = tx.send(Reply::Message(response));
             }
         }
     }
 
+    /// Message processing loop for a human agent
+    ///
+    /// Every message is handled in its own task, so one open question does not
+    /// hold up the others. Questions are withdrawn at the sender's deadline at
+    /// the latest. A question nobody answered in time is replied to with a
+    /// timeout, which the sender sees as such.
+    async fn human_loop(agent: Agent, mut inbox: mpsc::Receiver<InboxMessage>, handler: Arc<HumanHandler>) {
+        let agent = Arc::new(agent);
+        while let Some(inbox_msg) = inbox.recv().await {
+            let agent = agent.clone();
+            let handler = handler.clone();
+            spawn_handling(async move {
+                let response_content = handler
+                    .ask(&inbox_msg.message, &agent, inbox_msg.deadline)
+                    .instrument(handle_span(&agent.name, &inbox_msg))
+                    .await;
+                if let Some(tx) = inbox_msg.response_tx {
+                    let reply = match response_content {
+                        Some(content) => Reply::Message(inbox_msg.message.reply(content)),
+                        None => Reply::Timeout,
+                    };
+                    let _ = tx.send(reply);
+                }
+            });
+        }
+    }
+
+    /// Record a snapshot of the blackboard if the handler changed it
+    async fn record_blackboard_change(agent_name: &str, inbox_msg: &InboxMessage, version_before: u64) {
+        let blackboard = &inbox_msg.message.blackboard;
+        if let (Some(t), true) = (&inbox_msg.trace, blackboard.version() != version_before) {
+            let snapshot = blackboard.snapshot().to_string();
+            t.record(TraceEvent::blackboard(agent_name, agent_name, snapshot)).await;
+        }
+    }
+
     /// Tool processing loop
     ///
-    /// Similar to simple_agent_loop but for tools. Uses a dummy Agent for the handler.
+    /// Similar to human_loop but for tools: every call runs in its
+    /// own task, so a call held for approval does not hold up the others.
+    /// Uses a dummy Agent for the handler.
     async fn tool_loop(
         tool: Arc<Tool>,
         mut inbox: mpsc::Receiver<InboxMessage>,
         handler: Arc<dyn MessageHandler>,
     ) {
         // Create a minimal dummy agent for the handler interface
-        let dummy_agent = Agent {
+        let dummy_agent = Arc::new(Agent {
             name: tool.name().to_string(),
             system_prompt: tool.description().to_string(),
             connections: HashMap::new(),
-        };
+        });
 
         while let Some(inbox_msg) = inbox.recv().await {
             debug!(
@@ -398,13 +833,62 @@ impl AgentSystem {
                 &inbox_msg.message.content[..inbox_msg.message.content.len().min(100)]
             );
 
-            let response_content = handler.handle(&inbox_msg.message, &dummy_agent).await;
+            let span = info_span!(
+                parent: &inbox_msg.span,
+                "mas.tool.call",
+                mas.tool = tool.name(),
+                mas.caller = %inbox_msg.message.from,
+            );
+            spawn_handling(Self::answer_tool_call(handler.clone(), inbox_msg, dummy_agent.clone()).instrument(span));
+        }
+    }
 
-            // If there's a response channel and we have content, send the response
-            if let (Some(tx), Some(content)) = (inbox_msg.response_tx, response_content) {
-                let response = inbox_msg.message.reply(content);
-                let _ = tx.send(response);
-            }
+    /// Run a tool or database call and send its response back, if any
+    async fn answer_tool_call(handler: Arc<dyn MessageHandler>, inbox_msg: InboxMessage, agent: Arc<Agent>) {
+        let response_content = Self::handle_tool_call(&handler, &inbox_msg, &agent).await;
+
+        // If there's a response channel and we have content, send the response
+        if let (Some(tx), Some(content)) = (inbox_msg.response_tx, response_content) {
+            let response = inbox_msg.message.reply(content);
+            let _ = tx.send(Reply::Message(response));
+        }
+    }
+
+    /// Run a tool or database handler, reporting the call on the event bus
+    async fn handle_tool_call(
+        handler: &Arc<dyn MessageHandler>,
+        inbox_msg: &InboxMessage,
+        agent: &Agent,
+    ) -> Option<String> {
+        let caller = &inbox_msg.message.from;
+        EventBus::emit_current(|tool| SystemEventKind::ToolCallStarted {
+            tool: tool.to_string(),
+            caller: caller.clone(),
+        });
+        let started = Instant::now();
+        let response_content = Self::handle_until_deadline(handler, inbox_msg, agent).await;
+        EventBus::emit_current(|tool| SystemEventKind::ToolCallFinished {
+            tool: tool.to_string(),
+            caller: caller.clone(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            answered: response_content.is_some(),
+        });
+        response_content
+    }
+
+    /// Run a tool or database handler, giving up once the caller's deadline passes
+    ///
+    /// Calls held for approval must not execute after the caller stopped
+    /// waiting, so the handler is dropped at the deadline.
+    async fn handle_until_deadline(
+        handler: &Arc<dyn MessageHandler>,
+        inbox_msg: &InboxMessage,
+        agent: &Agent,
+    ) -> Option<String> {
+        let handling = handler.handle(&inbox_msg.message, agent);
+        match inbox_msg.deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, handling).await.ok().flatten(),
+            None => handling.await,
         }
     }
 
@@ -417,11 +901,11 @@ impl AgentSystem {
         handler: Arc<dyn MessageHandler>,
     ) {
         // Create a minimal dummy agent for the handler interface
-        let dummy_agent = Agent {
+        let dummy_agent = Arc::new(Agent {
             name: database.name().to_string(),
             system_prompt: database.description().to_string(),
             connections: HashMap::new(),
-        };
+        });
 
         while let Some(inbox_msg) = inbox.recv().await {
             debug!(
@@ -431,15 +915,32 @@ impl AgentSystem {
                 &inbox_msg.message.content[..inbox_msg.message.content.len().min(100)]
             );
 
-            let response_content = handler.handle(&inbox_msg.message, &dummy_agent).await;
-
-            if let (Some(tx), Some(content)) = (inbox_msg.response_tx, response_content) {
-                let response = inbox_msg.message.reply(content);
-                let _ = tx.send(response);
-            }
+            let span = info_span!(
+                parent: &inbox_msg.span,
+                "mas.db.query",
+                mas.database = database.name(),
+                mas.caller = %inbox_msg.message.from,
+            );
+            spawn_handling(Self::answer_tool_call(handler.clone(), inbox_msg, dummy_agent.clone()).instrument(span));
         }
     }
 
+    /// Start a routing agent loop in its own task
+    ///
+    /// Kept out of the async registration functions: routing loops register
+    /// spawned sub-agents, and the compiler cannot tell that the resulting
+    /// recursive future is `Send` if the task is started inside one.
+    fn spawn_routing_loop(
+        system: Arc<Self>,
+        agent: Agent,
+        inbox: mpsc::Receiver<InboxMessage>,
+        handler: Arc<dyn RoutingHandler>,
+    ) {
+        let events = system.events.clone();
+        let name = agent.name.clone();
+        spawn_loop(&events, &name, Self::routing_agent_loop(system, agent, inbox, handler));
+    }
+
     /// Routing agent loop for RoutingHandler (with dynamic routing)
     async fn routing_agent_loop(
         system: Arc<Self>,
@@ -448,73 +949,157 @@ impl AgentSystem {
         handler: Arc<dyn RoutingHandler>,
     ) {
         while let Some(inbox_msg) = inbox.recv().await {
-            debug!(
-                "[{}] Received message from {}: {}",
-                agent.name, inbox_msg.message.from, inbox_msg.message.content
-            );
+            let span = handle_span(&agent.name, &inbox_msg);
+            Self::handle_routed(&system, &handler, &agent, inbox_msg).instrument(span).await;
+        }
+    }
 
-            // Extract trace collector if present
-            let trace = inbox_msg.trace.clone();
+    /// Handle one message of a routing agent: notify, decide, carry out the
+    /// decision and reply
+    async fn handle_routed(
+        system: &Arc<Self>,
+        handler: &Arc<dyn RoutingHandler>,
+        agent: &Agent,
+        inbox_msg: InboxMessage,
+    ) {
+        debug!(
+            "[{}] Received message from {}: {}",
+            agent.name, inbox_msg.message.from, inbox_msg.message.content
+        );
+
+        // Extract trace collector and deadline if present
+        let trace = inbox_msg.trace.clone();
+        let deadline = inbox_msg.deadline;
+
+        // Step 1: Auto-send to all Notify connections (fire-and-forget)
+        // whose guards allow the message. The trace goes along so the
+        // request metadata reaches the notified agents.
+        let notify_targets: Vec<String> = agent
+            .connections
+            .iter()
+            .filter(|(_, conn)| {
+                conn.connection_type == ConnectionType::Notify && conn.allows(&inbox_msg.message)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
 
-            // Step 1: Auto-send to all Notify connections (fire-and-forget)
-            let notify_targets: Vec<String> = agent
-                .connections
-                .iter()
-                .filter(|(_, conn)| conn.connection_type == ConnectionType::Notify)
-                .map(|(name, _)| name.clone())
-                .collect();
+        for target in notify_targets {
+            debug!("[{}] Auto-notifying: {}", agent.name, target);
+            if let Err(e) = system
+                .send_message_internal_traced(
+                    &agent.name,
+                    &target,
+                    &inbox_msg.message.content,
+                    &inbox_msg.message.blackboard,
+                    trace.clone(),
+                    None,
+                )
+                .await
+            {
+                warn!(
+                    "[{}] Failed to notify {}: {}",
+                    agent.name, target, e
+                );
+            }
+        }
 
-            for target in notify_targets {
-                debug!("[{}] Auto-notifying: {}", agent.name, target);
-                if let Err(e) = system
-                    .send_message_internal(&agent.name, &target, &inbox_msg.message.content)
-                    .await
-                {
-                    warn!(
-                        "[{}] Failed to notify {}: {}",
-                        agent.name, target, e
-                    );
-                }
+        // Step 2: Process with handler to get routing decision
+        let version = inbox_msg.message.blackboard.version();
+        let decision = TraceCollector::scope(trace.clone(), handler.handle(&inbox_msg.message, agent)).await;
+        Self::record_blackboard_change(&agent.name, &inbox_msg, version).await;
+        debug!("[{}] Handler decision: {:?}", agent.name, decision);
+        Span::current().record("mas.routing.decision", decision.kind());
+
+        // Step 3: Process the decision
+        let final_response = match decision {
+            HandlerDecision::Response { content } => {
+                // Direct response - just send it back
+                Some(content)
             }
 
-            // Step 2: Process with handler to get routing decision
-            let decision = handler.handle(&inbox_msg.message, &agent).await;
-            debug!("[{}] Handler decision: {:?}", agent.name, decision);
+            HandlerDecision::Forward { targets } => {
+                Self::multi_turn_forward(
+                    system, handler, agent, &inbox_msg.message, targets, trace.clone(), deadline,
+                ).await
+            }
 
-            // Step 3: Process the decision
-            let final_response = match decision {
-                HandlerDecision::Response { content } => {
-                    // Direct response - just send it back
-                    Some(content)
-                }
+            HandlerDecision::ResponseAndForward { content, targets } => {
+                let forwarded = Self::multi_turn_forward(
+                    system, handler, agent, &inbox_msg.message, targets, trace.clone(), deadline,
+                ).await;
 
-                HandlerDecision::Forward { targets } => {
-                    Self::multi_turn_forward(
-                        &system, &handler, &agent, &inbox_msg.message, targets, trace.clone(),
-                    ).await
+                match forwarded {
+                    Some(synthesized) => Some(format!("{}\n\n{}", content, synthesized)),
+                    None => Some(content),
                 }
+            }
 
-                HandlerDecision::ResponseAndForward { content, targets } => {
-                    let forwarded = Self::multi_turn_forward(
-                        &system, &handler, &agent, &inbox_msg.message, targets, trace.clone(),
-                    ).await;
+            HandlerDecision::Pipeline { stages } => {
+                Some(
+                    system
+                        .run_pipeline(agent, &inbox_msg.message, &stages, trace.clone(), deadline)
+                        .await,
+                )
+            }
 
-                    match forwarded {
-                        Some(synthesized) => Some(format!("{}\n\n{}", content, synthesized)),
-                        None => Some(content),
-                    }
-                }
+            HandlerDecision::Vote { targets } => {
+                Self::run_vote(
+                    system, handler, agent, &inbox_msg.message, targets, trace.clone(), deadline,
+                ).await
+            }
 
-                HandlerDecision::None => {
-                    // No action
-                    None
-                }
-            };
+            HandlerDecision::Debate { participants, rounds } => {
+                Self::run_debate(
+                    system, handler, agent, &inbox_msg.message, participants, rounds, trace.clone(), deadline,
+                ).await
+            }
 
-            // Step 4: Send final response if we have one and there's a channel
-            if let (Some(tx), Some(content)) = (inbox_msg.response_tx, final_response) {
-                let response = inbox_msg.message.reply(content);
-                let _ = tx.send(response);
+            HandlerDecision::Plan { plan } => {
+                Self::run_plan(
+                    system, handler, agent, &inbox_msg.message, plan, trace.clone(), deadline,
+                ).await
+            }
+
+            HandlerDecision::Spawn { agents } => {
+                Self::run_spawn(
+                    system, handler, agent, &inbox_msg.message, agents, trace.clone(), deadline,
+                ).await
+            }
+
+            HandlerDecision::None => {
+                // No action
+                None
+            }
+        };
+
+        // Step 4: Send final response if we have one and there's a channel
+        if let (Some(tx), Some(content)) = (inbox_msg.response_tx, final_response) {
+            let response = inbox_msg.message.reply(content);
+            let _ = tx.send(Reply::Message(response));
+        }
+    }
+
+    /// Message processing loop for a workflow
+    async fn workflow_loop(
+        system: Arc<Self>,
+        workflow: Arc<Workflow>,
+        mut inbox: mpsc::Receiver<InboxMessage>,
+    ) {
+        while let Some(inbox_msg) = inbox.recv().await {
+            debug!(
+                "[{}] Workflow received message from {}: {}",
+                workflow.name(),
+                inbox_msg.message.from,
+                inbox_msg.message.content
+            );
+
+            let output = system
+                .run_workflow(&workflow, &inbox_msg.message, inbox_msg.trace.clone(), inbox_msg.deadline)
+                .instrument(handle_span(workflow.name(), &inbox_msg))
+                .await;
+
+            if let Some(tx) = inbox_msg.response_tx {
+                let _ = tx.send(Reply::Message(inbox_msg.message.reply(output)));
             }
         }
     }
@@ -524,6 +1109,10 @@ impl AgentSystem {
     /// Forwards to targets, then optionally evaluates whether follow-up questions
     /// are needed. Loops until the handler is satisfied or `max_turns` is reached.
     /// When `max_turns` is 1 (default), this behaves identically to the old single-turn flow.
+    ///
+    /// If the incoming request carries a deadline, forwards are cut off early enough
+    /// to leave the synthesis reserve, so synthesis runs over partial results instead
+    /// of the whole request timing out.
     async fn multi_turn_forward(
         system: &Arc<Self>,
         handler: &Arc<dyn RoutingHandler>,
@@ -531,14 +1120,67 @@ impl AgentSystem {
         original_message: &Message,
         initial_targets: Vec<ForwardTarget>,
         trace: Option<TraceCollector>,
+        deadline: Option<Instant>,
     ) -> Option<String> {
-        let max_turns = handler.max_turns();
-        let mut all_turns: Vec<ConversationTurn> = Vec::new();
-        let mut current_targets = initial_targets;
-        // 0 means unlimited; use u32::MAX as practical limit
-        let effective_max: u32 = if max_turns == 0 { u32::MAX } else { max_turns as u32 };
+        let all_outcomes = match Self::forward_turns(
+            system, handler, agent, original_message, initial_targets, trace.clone(), deadline,
+        )
+        .await
+        {
+            ForwardRun::Answered(response) => {
+                if let Some(ref t) = trace {
+                    t.record(TraceEvent::synthesis(&agent.name, &original_message.from, &response)).await;
+                }
+                return Some(response);
+            }
+            ForwardRun::Outcomes(outcomes) => outcomes,
+        };
+
+        if all_outcomes.is_empty() {
+            return None;
+        }
+
+        // Final synthesis from all accumulated outcomes (including failures)
+        let synthesized = TraceCollector::scope(
+            trace.clone(),
+            handler.synthesize(original_message, &all_outcomes, agent),
+        )
+        .instrument(synthesis_span(&all_outcomes))
+        .await;
+
+        if let (Some(ref t), Some(ref content)) = (&trace, &synthesized) {
+            t.record(TraceEvent::synthesis(&agent.name, &original_message.from, content)).await;
+        }
+
+        synthesized
+    }
+
+    /// Forward to targets and evaluate follow-ups until the handler is satisfied
+    /// or `max_turns` is reached, without synthesizing.
+    async fn forward_turns(
+        system: &Arc<Self>,
+        handler: &Arc<dyn RoutingHandler>,
+        agent: &Agent,
+        original_message: &Message,
+        initial_targets: Vec<ForwardTarget>,
+        trace: Option<TraceCollector>,
+        deadline: Option<Instant>,
+    ) -> ForwardRun {
+        let forward_deadline = system.forward_deadline(deadline);
+        let max_turns = handler.max_turns();
+        let quorum = handler.quorum();
+        let mut all_turns: Vec<ConversationTurn> = Vec::new();
+        let mut all_outcomes: Vec<ForwardOutcome> = Vec::new();
+        let mut current_targets = initial_targets;
+        // 0 means unlimited; use u32::MAX as practical limit
+        let effective_max: u32 = if max_turns == 0 { u32::MAX } else { max_turns as u32 };
 
         for turn in 0..effective_max {
+            if forward_deadline.is_some_and(|d| Instant::now() >= d) {
+                warn!("[{}] Deadline reached before turn {}, synthesizing partial results", agent.name, turn);
+                break;
+            }
+
             // Fill in empty messages with the original user message
             for target in &mut current_targets {
                 if target.message.is_empty() {
@@ -554,27 +1196,34 @@ impl AgentSystem {
             }
 
             // Forward to targets
-            let forwarded_responses = system
-                .forward_to_agents_with_trace(&agent.name, &current_targets, trace.clone())
+            let outcomes = system
+                .forward_to_agents_with_trace(
+                    &agent.name,
+                    &current_targets,
+                    &original_message.blackboard,
+                    trace.clone(),
+                    forward_deadline,
+                    quorum,
+                )
                 .await;
 
-            if forwarded_responses.is_empty() {
-                warn!("[{}] No responses from forwarded agents on turn {}", agent.name, turn);
-                break;
+            // Record turns for the targets that answered
+            let turns_before = all_turns.len();
+            for outcome in &outcomes {
+                if let Some(response) = outcome.response() {
+                    all_turns.push(ConversationTurn {
+                        agent: outcome.agent.clone(),
+                        message_sent: outcome.message_sent.clone(),
+                        response: response.to_string(),
+                        turn_number: turn as u16,
+                    });
+                }
             }
+            all_outcomes.extend(outcomes);
 
-            // Record turns
-            for (agent_name, response) in &forwarded_responses {
-                let msg_sent = current_targets.iter()
-                    .find(|t| t.agent == *agent_name)
-                    .map(|t| t.message.clone())
-                    .unwrap_or_default();
-                all_turns.push(ConversationTurn {
-                    agent: agent_name.clone(),
-                    message_sent: msg_sent,
-                    response: response.clone(),
-                    turn_number: turn as u16,
-                });
+            if all_turns.len() == turns_before {
+                warn!("[{}] No responses from forwarded agents on turn {}", agent.name, turn);
+                break;
             }
 
             // If max_turns is 1, skip evaluation (preserves single-turn behavior exactly)
@@ -594,10 +1243,7 @@ impl AgentSystem {
                 EvaluationDecision::Satisfied { response } => {
                     if !response.is_empty() {
                         // LLM already produced a final answer during evaluation
-                        if let Some(ref t) = trace {
-                            t.record(TraceEvent::synthesis(&agent.name, &original_message.from, &response)).await;
-                        }
-                        return Some(response);
+                        return ForwardRun::Answered(response);
                     }
                     // Empty response means "satisfied, please synthesize normally"
                     break;
@@ -615,19 +1261,112 @@ impl AgentSystem {
             }
         }
 
-        if all_turns.is_empty() {
-            return None;
-        }
+        ForwardRun::Outcomes(all_outcomes)
+    }
 
-        // Final synthesis from all accumulated responses
-        let all_responses: Vec<(String, String)> = all_turns
-            .iter()
-            .map(|t| (t.agent.clone(), t.response.clone()))
-            .collect();
+    /// Carry out a plan step by step, then synthesize the completed steps.
+    ///
+    /// Each step is forwarded to its agent through the forward-evaluate loop,
+    /// with the results of earlier steps as context. When a step fails, the
+    /// handler may re-plan up to `max_replans` times; a new plan starts from
+    /// its first step and keeps the results completed so far. Every plan and
+    /// every settled step is recorded in the trace.
+    async fn run_plan(
+        system: &Arc<Self>,
+        handler: &Arc<dyn RoutingHandler>,
+        agent: &Agent,
+        original_message: &Message,
+        mut plan: Plan,
+        trace: Option<TraceCollector>,
+        deadline: Option<Instant>,
+    ) -> Option<String> {
+        let forward_deadline = system.forward_deadline(deadline);
+        let mut completed: Vec<ForwardOutcome> = Vec::new();
+        let mut failed: Option<ForwardOutcome> = None;
+        let mut replans = 0;
+        Self::record_plan(&trace, agent, original_message, &plan).await;
+
+        let mut index = 0;
+        while index < plan.steps.len() {
+            if forward_deadline.is_some_and(|d| Instant::now() >= d) {
+                warn!("[{}] Deadline reached before plan step {}, synthesizing partial results", agent.name, index + 1);
+                break;
+            }
+
+            let step = &plan.steps[index];
+            let message = plan::step_message(&original_message.content, step, &completed);
+            let target = ForwardTarget::new(&step.agent, message.clone());
+            let outcome = match Self::forward_turns(
+                system, handler, agent, original_message, vec![target], trace.clone(), deadline,
+            )
+            .await
+            {
+                ForwardRun::Answered(response) => ForwardOutcome::success(&step.agent, &message, response),
+                ForwardRun::Outcomes(outcomes) => match outcomes.iter().rposition(|o| o.is_success()) {
+                    Some(last) => outcomes.into_iter().nth(last).expect("position is in range"),
+                    None => outcomes.into_iter().next().unwrap_or_else(|| {
+                        ForwardOutcome::new(
+                            &step.agent,
+                            &message,
+                            ForwardStatus::Error { message: "deadline reached".to_string() },
+                        )
+                    }),
+                },
+            };
+            Self::record_plan_step(&trace, agent, &plan, index, &outcome).await;
+
+            if outcome.is_success() {
+                completed.push(outcome);
+                index += 1;
+                continue;
+            }
+
+            warn!(
+                "[{}] Plan step {} ({}) failed: {}",
+                agent.name,
+                index + 1,
+                outcome.agent,
+                outcome.failure_reason().unwrap_or_default()
+            );
+            if replans >= handler.max_replans() {
+                failed = Some(outcome);
+                break;
+            }
+            replans += 1;
 
-        let synthesized = handler
-            .synthesize(original_message, &all_responses, agent)
+            let mut progress = completed.clone();
+            progress.push(outcome.clone());
+            let new_plan = TraceCollector::scope(
+                trace.clone(),
+                handler.replan(original_message, &plan, &progress, agent),
+            )
             .await;
+            match new_plan.filter(|p| !p.steps.is_empty()) {
+                Some(mut new_plan) => {
+                    new_plan.revision = plan.revision + 1;
+                    info!("[{}] Re-planned ({} steps)", agent.name, new_plan.steps.len());
+                    plan = new_plan;
+                    Self::record_plan(&trace, agent, original_message, &plan).await;
+                    index = 0;
+                }
+                None => {
+                    failed = Some(outcome);
+                    break;
+                }
+            }
+        }
+
+        completed.extend(failed);
+        if completed.is_empty() {
+            return None;
+        }
+
+        let synthesized = TraceCollector::scope(
+            trace.clone(),
+            handler.synthesize(original_message, &completed, agent),
+        )
+        .instrument(synthesis_span(&completed))
+        .await;
 
         if let (Some(ref t), Some(ref content)) = (&trace, &synthesized) {
             t.record(TraceEvent::synthesis(&agent.name, &original_message.from, content)).await;
@@ -636,219 +1375,702 @@ impl AgentSystem {
         synthesized
     }
 
-    /// Forward messages to multiple agents in parallel and collect responses
-    async fn forward_to_agents(
-        &self,
-        from: &str,
-        targets: &[ForwardTarget],
-    ) -> Vec<(String, String)> {
-        self.forward_to_agents_with_trace(from, targets, None).await
+    /// Record a plan in the trace
+    async fn record_plan(trace: &Option<TraceCollector>, agent: &Agent, original_message: &Message, plan: &Plan) {
+        if let Some(t) = trace {
+            let content = serde_json::to_string(plan).unwrap_or_default();
+            t.record(TraceEvent::plan(&agent.name, &original_message.from, content)).await;
+        }
     }
 
-    /// Forward messages to multiple agents in parallel with optional tracing
-    async fn forward_to_agents_with_trace(
-        &self,
-        from: &str,
-        targets: &[ForwardTarget],
+    /// Record a settled plan step in the trace
+    async fn record_plan_step(
+        trace: &Option<TraceCollector>,
+        agent: &Agent,
+        plan: &Plan,
+        index: usize,
+        outcome: &ForwardOutcome,
+    ) {
+        let Some(t) = trace else {
+            return;
+        };
+        let mut content = serde_json::json!({
+            "plan_revision": plan.revision,
+            "step": index + 1,
+            "status": if outcome.is_success() { "completed" } else { "failed" },
+        });
+        if let Some(reason) = outcome.failure_reason() {
+            content["reason"] = reason.into();
+        }
+        t.record(TraceEvent::step(&agent.name, &outcome.agent, content.to_string())).await;
+    }
+
+    /// Hand tasks to short-lived sub-agents, then synthesize their answers.
+    ///
+    /// Each sub-agent is registered under a unique name derived from the
+    /// spawning agent and the spec, with only those of the spawning agent's
+    /// connections the spec lists. All tasks run in parallel; afterwards, or
+    /// when the spawn is cancelled, the sub-agents are unregistered. Specs beyond the system's spawn limit are
+    /// not started and reported as failed.
+    async fn run_spawn(
+        system: &Arc<Self>,
+        handler: &Arc<dyn RoutingHandler>,
+        agent: &Agent,
+        original_message: &Message,
+        specs: Vec<SpawnSpec>,
         trace: Option<TraceCollector>,
-    ) -> Vec<(String, String)> {
-        let futures: Vec<_> = targets
-            .iter()
-            .map(|target| {
-                let from = from.to_string();
-                let agent_name = target.agent.clone();
-                let message = target.message.clone();
-                let trace = trace.clone();
-                async move {
-                    info!("[{}] Forwarding to {}: {}", from, agent_name, message);
-                    match self
-                        .send_message_internal_traced(&from, &agent_name, &message, trace.clone())
-                        .await
-                    {
-                        Ok(SendResult::Response(msg)) => {
-                            info!("[{}] Got response from {}", from, agent_name);
-                            // Record response event in trace
-                            if let Some(ref t) = trace {
-                                t.record(TraceEvent::response(&agent_name, &from, &msg.content)).await;
-                            }
-                            Some((agent_name, msg.content))
-                        }
-                        Ok(SendResult::Timeout(e)) => {
-                            warn!("[{}] Timeout waiting for {}: {}", from, agent_name, e);
-                            None
-                        }
-                        Ok(SendResult::Notified) => {
-                            debug!("[{}] {} notified (no response expected)", from, agent_name);
-                            None
-                        }
-                        Err(e) => {
-                            error!("[{}] Failed to forward to {}: {}", from, agent_name, e);
-                            None
-                        }
+        deadline: Option<Instant>,
+    ) -> Option<String> {
+        let mut outcomes: Vec<Option<ForwardOutcome>> = vec![None; specs.len()];
+        let mut targets: Vec<ForwardTarget> = Vec::new();
+        let mut started: Vec<usize> = Vec::new();
+        let mut spawned: Vec<SpawnedAgent> = Vec::new();
+
+        for (index, spec) in specs.iter().enumerate() {
+            let message = if spec.message.is_empty() {
+                original_message.content.clone()
+            } else {
+                spec.message.clone()
+            };
+            match system.spawn_agent(handler, agent, spec).await {
+                Ok(sub_agent) => {
+                    if let Some(ref t) = trace {
+                        let content = serde_json::to_string(spec).unwrap_or_default();
+                        t.record(TraceEvent::spawn(&agent.name, &sub_agent.name, content)).await;
                     }
+                    targets.push(ForwardTarget::new(sub_agent.name.clone(), message));
+                    started.push(index);
+                    spawned.push(sub_agent);
                 }
-            })
-            .collect();
+                Err(reason) => {
+                    warn!("[{}] Could not spawn sub-agent '{}': {}", agent.name, spec.name, reason);
+                    outcomes[index] =
+                        Some(ForwardOutcome::new(&spec.name, &message, ForwardStatus::Error { message: reason }));
+                }
+            }
+        }
 
-        join_all(futures)
-            .await
-            .into_iter()
-            .flatten()
-            .collect()
-    }
+        if let Some(ref t) = trace {
+            for target in &targets {
+                t.record(TraceEvent::forward(&agent.name, &target.agent, &target.message)).await;
+            }
+        }
 
-    /// Internal send method that doesn't require an explicit connection
-    /// Used for forwarding where the routing handler decides the target
-    /// Can send to both agents and tools
-    async fn send_message_internal(
-        &self,
-        from: &str,
-        to: &str,
-        content: &str,
-    ) -> Result<SendResult> {
-        self.send_message_internal_traced(from, to, content, None).await
-    }
+        let forwarded = system
+            .forward_to_agents_with_trace(
+                &agent.name,
+                &targets,
+                &original_message.blackboard,
+                trace.clone(),
+                system.forward_deadline(deadline),
+                None,
+            )
+            .await;
+        for (index, outcome) in started.into_iter().zip(forwarded) {
+            outcomes[index] = Some(outcome);
+        }
 
-    /// Internal send with optional trace propagation
-    ///
-    /// When a trace is provided, it is attached to the InboxMessage so that
-    /// sub-agents (routing agents receiving a forwarded message) can record
-    /// their own trace events (e.g., forwarding to tools/databases).
-    async fn send_message_internal_traced(
-        &self,
-        from: &str,
-        to: &str,
-        content: &str,
-        trace: Option<TraceCollector>,
-    ) -> Result<SendResult> {
-        let agents = self.agents.read().await;
-        let tools = self.tools.read().await;
-        let databases = self.databases.read().await;
+        for sub_agent in spawned {
+            sub_agent.release().await;
+        }
 
-        // Get sender agent
-        let sender = agents
-            .get(from)
-            .ok_or_else(|| AgentError::AgentNotFound(from.to_string()))?;
+        let outcomes: Vec<ForwardOutcome> = outcomes.into_iter().flatten().collect();
+        if outcomes.is_empty() {
+            return None;
+        }
 
-        // Check if there's an explicit connection
-        let connection = sender.agent.get_connection(to);
+        let synthesized = TraceCollector::scope(
+            trace.clone(),
+            handler.synthesize(original_message, &outcomes, agent),
+        )
+        .instrument(synthesis_span(&outcomes))
+        .await;
 
-        // Create the message
-        let message = Message::new(from, to, content);
-        let message_id = message.id;
+        if let (Some(ref t), Some(ref content)) = (&trace, &synthesized) {
+            t.record(TraceEvent::synthesis(&agent.name, &original_message.from, content)).await;
+        }
 
-        // Store in conversation history
+        synthesized
+    }
+
+    /// Register a sub-agent for `spec`, counting it against the spawn limit
+    ///
+    /// Returns the registered sub-agent, or why it could not be registered.
+    async fn spawn_agent(
+        self: &Arc<Self>,
+        handler: &Arc<dyn RoutingHandler>,
+        agent: &Agent,
+        spec: &SpawnSpec,
+    ) -> std::result::Result<SpawnedAgent, String> {
+        let limit = self.config.spawn_limit;
+        if self
+            .spawned
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| (n < limit).then_some(n + 1))
+            .is_err()
         {
-            let mut conversations = self.conversations.write().await;
-            conversations.add_message(message.clone());
+            return Err(format!("spawn limit of {} sub-agents reached", limit));
         }
-
-        // Determine connection type (default to blocking for forwards)
-        let (is_notify, effective_timeout) = match connection {
-            Some(conn) => (
-                conn.connection_type == ConnectionType::Notify,
-                conn.effective_timeout(self.config.global_timeout),
-            ),
-            None => (false, self.config.global_timeout),
+        let id = Uuid::new_v4().simple().to_string();
+        let mut sub_agent = SpawnedAgent {
+            system: self.clone(),
+            name: format!("{}~{}~{}", agent.name, spec.name, &id[..8]),
+            registered: false,
         };
 
-        // Get receiver inbox - check agents first, then tools, then databases
-        let receiver_inbox = if let Some(agent) = agents.get(to) {
-            agent.inbox_tx.clone()
-        } else if let Some(tool) = tools.get(to) {
-            tool.inbox_tx.clone()
-        } else if let Some(db) = databases.get(to) {
-            db.inbox_tx.clone()
-        } else {
-            return Err(AgentError::AgentNotFound(to.to_string()));
+        let Some(sub_handler) = handler.spawn_handler(spec) else {
+            return Err(format!("'{}' cannot spawn sub-agents", agent.name));
         };
 
-        if is_notify {
-            // Fire and forget
-            let inbox_msg = InboxMessage {
-                message,
-                response_tx: None,
-                trace,
-            };
-            receiver_inbox
-                .send(inbox_msg)
-                .await
-                .map_err(|_| AgentError::ChannelError("Failed to send to inbox".into()))?;
-            Ok(SendResult::Notified)
-        } else {
-            // Blocking with response
-            let (response_tx, response_rx) = oneshot::channel();
-            let inbox_msg = InboxMessage {
-                message,
-                response_tx: Some(response_tx),
-                trace,
+        let mut builder = AgentBuilder::new(&sub_agent.name).system_prompt(&spec.system_prompt);
+        for connection in &spec.connections {
+            match agent.get_connection(connection) {
+                Some(conn) => builder = builder.connection(connection, conn.clone()),
+                None => warn!(
+                    "[{}] Sub-agent '{}' may not use '{}', which is not a connection",
+                    agent.name, spec.name, connection
+                ),
+            }
+        }
+
+        // Marked before registering, so a spawn cancelled mid-registration still cleans up
+        sub_agent.registered = true;
+        if let Err(e) = Self::register_routing_agent(self.clone(), builder.build(), sub_handler).await {
+            sub_agent.registered = false;
+            return Err(e.to_string());
+        }
+        info!("[{}] Spawned sub-agent {}", agent.name, sub_agent.name);
+        Ok(sub_agent)
+    }
+
+    /// Collect ballots from all targets and answer with the aggregated vote.
+    ///
+    /// Each ballot and the final result are recorded in the trace. If no target
+    /// returns a ballot, the handler's synthesis reports the failures instead.
+    async fn run_vote(
+        system: &Arc<Self>,
+        handler: &Arc<dyn RoutingHandler>,
+        agent: &Agent,
+        original_message: &Message,
+        mut targets: Vec<ForwardTarget>,
+        trace: Option<TraceCollector>,
+        deadline: Option<Instant>,
+    ) -> Option<String> {
+        for target in &mut targets {
+            let question = if target.message.is_empty() {
+                &original_message.content
+            } else {
+                &target.message
             };
+            target.message = vote::ballot_request(question);
+        }
 
-            receiver_inbox
-                .send(inbox_msg)
-                .await
-                .map_err(|_| AgentError::ChannelError("Failed to send to inbox".into()))?;
+        if let Some(ref t) = trace {
+            for target in &targets {
+                t.record(TraceEvent::forward(&agent.name, &target.agent, &target.message)).await;
+            }
+        }
 
-            match timeout(effective_timeout, response_rx).await {
-                Ok(Ok(response)) => {
-                    let mut conversations = self.conversations.write().await;
-                    conversations.add_message(response.clone());
-                    Ok(SendResult::Response(response))
+        let forward_deadline = system.forward_deadline(deadline);
+        let outcomes = system
+            .forward_to_agents_with_trace(
+                &agent.name,
+                &targets,
+                &original_message.blackboard,
+                trace.clone(),
+                forward_deadline,
+                None,
+            )
+            .await;
+
+        let ballots: Vec<Ballot> = outcomes
+            .iter()
+            .filter_map(|o| o.response().map(|r| vote::parse_ballot(&o.agent, r)))
+            .collect();
+
+        if let Some(ref t) = trace {
+            for ballot in &ballots {
+                let content = serde_json::to_string(ballot).unwrap_or_default();
+                t.record(TraceEvent::ballot(&ballot.agent, &agent.name, content)).await;
+            }
+        }
+
+        match TraceCollector::scope(trace.clone(), handler.tally(original_message, &ballots, agent)).await {
+            Some(result) => {
+                info!(
+                    "[{}] Vote result ({:?}): {} (support {:?})",
+                    agent.name, result.method, result.answer, result.support
+                );
+                if let Some(ref t) = trace {
+                    let content = serde_json::to_string(&result).unwrap_or_default();
+                    t.record(TraceEvent::vote(&agent.name, &original_message.from, content)).await;
                 }
-                Ok(Err(_)) => Ok(SendResult::Timeout(AgentError::Timeout {
-                    agent: to.to_string(),
-                    message_id,
-                    waited: effective_timeout,
-                })),
-                Err(_) => Ok(SendResult::Timeout(AgentError::Timeout {
-                    agent: to.to_string(),
-                    message_id,
-                    waited: effective_timeout,
-                })),
+                Some(result.answer)
+            }
+            None => {
+                warn!("[{}] No ballots received, falling back to synthesis", agent.name);
+                TraceCollector::scope(trace.clone(), handler.synthesize(original_message, &outcomes, agent))
+                    .instrument(synthesis_span(&outcomes))
+                    .await
             }
         }
     }
 
-    /// Send a message from one agent to another
-    /// Respects connection types and timeouts
-    pub async fn send_message(
-        &self,
-        from: &str,
-        to: &str,
-        content: &str,
-    ) -> Result<SendResult> {
-        let agents = self.agents.read().await;
-        let tools = self.tools.read().await;
-        let databases = self.databases.read().await;
+    /// Run a debate between `participants` for up to `rounds` rounds, then judge it.
+    ///
+    /// Within a round, participants argue in parallel; from the second round on,
+    /// each sees all earlier arguments. Participants that fail to answer simply
+    /// miss the round. The judge's verdict is recorded as a synthesis event.
+    #[allow(clippy::too_many_arguments)]
+    async fn run_debate(
+        system: &Arc<Self>,
+        handler: &Arc<dyn RoutingHandler>,
+        agent: &Agent,
+        original_message: &Message,
+        participants: Vec<String>,
+        rounds: u16,
+        trace: Option<TraceCollector>,
+        deadline: Option<Instant>,
+    ) -> Option<String> {
+        let forward_deadline = system.forward_deadline(deadline);
+        let mut turns: Vec<ConversationTurn> = Vec::new();
 
-        // Get sender agent to check connection
-        let sender = agents
-            .get(from)
-            .ok_or_else(|| AgentError::AgentNotFound(from.to_string()))?;
+        for round in 0..rounds {
+            if forward_deadline.is_some_and(|d| Instant::now() >= d) {
+                warn!("[{}] Deadline reached before debate round {}, judging early", agent.name, round + 1);
+                break;
+            }
 
-        // Require explicit connection for public API
-        let connection = sender
-            .agent
-            .get_connection(to)
-            .ok_or_else(|| AgentError::NoConnection {
-                from: from.to_string(),
-                to: to.to_string(),
-            })?;
+            let targets: Vec<ForwardTarget> = participants
+                .iter()
+                .map(|p| {
+                    let prompt = debate::debate_prompt(
+                        &original_message.content, p, &participants, round, rounds, &turns,
+                    );
+                    ForwardTarget::new(p, prompt)
+                })
+                .collect();
 
-        // Get receiver inbox - check agents, tools, and databases
-        let receiver_inbox = if let Some(agent) = agents.get(to) {
-            agent.inbox_tx.clone()
+            if let Some(ref t) = trace {
+                for target in &targets {
+                    t.record(TraceEvent::forward(&agent.name, &target.agent, &target.message)).await;
+                }
+            }
+
+            let outcomes = system
+                .forward_to_agents_with_trace(
+                    &agent.name,
+                    &targets,
+                    &original_message.blackboard,
+                    trace.clone(),
+                    forward_deadline,
+                    None,
+                )
+                .await;
+
+            let before = turns.len();
+            for outcome in &outcomes {
+                if let Some(response) = outcome.response() {
+                    turns.push(ConversationTurn {
+                        agent: outcome.agent.clone(),
+                        message_sent: outcome.message_sent.clone(),
+                        response: response.to_string(),
+                        turn_number: round,
+                    });
+                }
+            }
+
+            if turns.len() == before {
+                warn!("[{}] No arguments in debate round {}, stopping", agent.name, round + 1);
+                if turns.is_empty() {
+                    return TraceCollector::scope(trace.clone(), handler.synthesize(original_message, &outcomes, agent))
+                        .instrument(synthesis_span(&outcomes))
+                        .await;
+                }
+                break;
+            }
+        }
+
+        let verdict = TraceCollector::scope(trace.clone(), handler.judge_debate(original_message, &turns, agent)).await;
+        if let (Some(ref t), Some(ref content)) = (&trace, &verdict) {
+            t.record(TraceEvent::synthesis(&agent.name, &original_message.from, content)).await;
+        }
+        verdict
+    }
+
+    /// Run a fixed pipeline: each stage receives the previous stage's output.
+    ///
+    /// The last stage's output is returned as-is. If a stage fails, the pipeline
+    /// stops and the failure is reported instead.
+    async fn run_pipeline(
+        &self,
+        agent: &Agent,
+        original_message: &Message,
+        stages: &[String],
+        trace: Option<TraceCollector>,
+        deadline: Option<Instant>,
+    ) -> String {
+        let mut content = original_message.content.clone();
+
+        for stage in stages {
+            if let Some(ref t) = trace {
+                t.record(TraceEvent::forward(&agent.name, stage, &content)).await;
+            }
+
+            let target = ForwardTarget::new(stage, content.clone());
+            let outcome = ForwardOutcome::new(
+                stage,
+                &content,
+                self.forward_to_target(&agent.name, &target, &original_message.blackboard, trace.clone(), deadline)
+                    .await,
+            );
+
+            match outcome.response() {
+                Some(response) => content = response.to_string(),
+                None => {
+                    let reason = outcome.failure_reason().unwrap_or_default();
+                    warn!("[{}] Pipeline stopped at {}: {}", agent.name, stage, reason);
+                    return format!("Pipeline stopped at {}: {}", stage, reason);
+                }
+            }
+        }
+
+        content
+    }
+
+    /// Run a workflow for one request.
+    ///
+    /// Steps start as soon as their dependencies have settled, so independent
+    /// branches run in parallel. Every step, including skipped ones, is recorded
+    /// in the trace. A failing step stops the workflow and the failure is
+    /// reported instead, unless the step has `continue_on_error`.
+    async fn run_workflow(
+        &self,
+        workflow: &Workflow,
+        request: &Message,
+        trace: Option<TraceCollector>,
+        deadline: Option<Instant>,
+    ) -> String {
+        let steps = workflow.steps();
+        let mut states = workflow.initial_states();
+        let mut running = FuturesUnordered::new();
+
+        loop {
+            // Start or skip every ready step. Skipping settles a step, which can
+            // make further steps ready, so repeat until nothing is ready.
+            loop {
+                let ready = workflow.ready_steps(&states);
+                if ready.is_empty() {
+                    break;
+                }
+
+                for index in ready {
+                    let step = &steps[index];
+                    if !workflow.should_run(index, &states, request) {
+                        debug!("[{}] Skipping step {}", workflow.name(), step.id);
+                        states[index] = StepState::Skipped;
+                        Self::record_workflow_step(&trace, workflow, index, &states[index], Duration::ZERO).await;
+                        continue;
+                    }
+
+                    let input = workflow.render_input(index, &states, request);
+                    let Some(target) = step.target.as_deref() else {
+                        states[index] = StepState::Completed(input);
+                        Self::record_workflow_step(&trace, workflow, index, &states[index], Duration::ZERO).await;
+                        continue;
+                    };
+
+                    states[index] = StepState::Running;
+                    if let Some(ref t) = trace {
+                        t.record(TraceEvent::forward(workflow.name(), target, &input)).await;
+                    }
+
+                    let trace = trace.clone();
+                    running.push(async move {
+                        let started = Instant::now();
+                        let forward = ForwardTarget::new(target, input.clone());
+                        let status = self
+                            .forward_to_target(workflow.name(), &forward, &request.blackboard, trace, deadline)
+                            .await;
+                        (index, ForwardOutcome::new(target, input, status), started.elapsed())
+                    });
+                }
+            }
+
+            let Some((index, outcome, elapsed)) = running.next().await else {
+                break;
+            };
+
+            let step = &steps[index];
+            states[index] = match outcome.response() {
+                Some(response) => StepState::Completed(response.to_string()),
+                None => StepState::Failed(outcome.failure_reason().unwrap_or_default()),
+            };
+            Self::record_workflow_step(&trace, workflow, index, &states[index], elapsed).await;
+
+            if let StepState::Failed(reason) = &states[index] {
+                if !step.continue_on_error {
+                    warn!("[{}] Workflow stopped at step {}: {}", workflow.name(), step.id, reason);
+                    return format!("Workflow stopped at step {}: {}", step.id, reason);
+                }
+            }
+        }
+
+        workflow.render_output(&states, request)
+    }
+
+    /// Record a settled workflow step in the trace
+    async fn record_workflow_step(
+        trace: &Option<TraceCollector>,
+        workflow: &Workflow,
+        index: usize,
+        state: &StepState,
+        elapsed: Duration,
+    ) {
+        let Some(t) = trace else {
+            return;
+        };
+
+        let step = &workflow.steps()[index];
+        let mut status = serde_json::json!({
+            "workflow": workflow.name(),
+            "step": step.id,
+            "status": state.status(),
+            "elapsed_ms": elapsed.as_millis() as u64,
+        });
+        if let StepState::Failed(reason) = state {
+            status["reason"] = reason.clone().into();
+        }
+        t.record(TraceEvent::step(workflow.name(), step.label(), status.to_string())).await;
+    }
+
+    /// How long senders wait for `to` when their connection sets no timeout
+    fn default_timeout(&self, agents: &HashMap<String, RunningAgent>, to: &str) -> Duration {
+        agents
+            .get(to)
+            .and_then(|agent| agent.timeout)
+            .unwrap_or(self.config.global_timeout)
+    }
+
+    /// Deadline for forwarded requests, leaving the synthesis reserve before `deadline`
+    fn forward_deadline(&self, deadline: Option<Instant>) -> Option<Instant> {
+        deadline.map(|d| {
+            let remaining = d.saturating_duration_since(Instant::now());
+            d - self.config.synthesis_reserve.min(remaining / 5)
+        })
+    }
+
+    /// Forward messages to multiple agents in parallel and collect responses
+    async fn forward_to_agents(
+        &self,
+        from: &str,
+        targets: &[ForwardTarget],
+    ) -> Vec<ForwardOutcome> {
+        self.forward_to_agents_with_trace(from, targets, &Blackboard::new(), None, None, None).await
+    }
+
+    /// Forward messages to multiple agents in parallel with optional tracing
+    ///
+    /// Returns one outcome per target, in target order. With a `quorum`, stops
+    /// waiting once that many targets have answered; the rest are reported as
+    /// `ForwardStatus::Cancelled`.
+    async fn forward_to_agents_with_trace(
+        &self,
+        from: &str,
+        targets: &[ForwardTarget],
+        blackboard: &Blackboard,
+        trace: Option<TraceCollector>,
+        deadline: Option<Instant>,
+        quorum: Option<usize>,
+    ) -> Vec<ForwardOutcome> {
+        let mut pending: FuturesUnordered<_> = targets
+            .iter()
+            .enumerate()
+            .map(|(index, target)| {
+                let from = from.to_string();
+                let trace = trace.clone();
+                async move {
+                    let status = self.forward_to_target(&from, target, blackboard, trace, deadline).await;
+                    (index, ForwardOutcome::new(&target.agent, &target.message, status))
+                }
+            })
+            .collect();
+
+        let mut finished: Vec<Option<ForwardOutcome>> = vec![None; targets.len()];
+        let mut successes = 0;
+        while let Some((index, outcome)) = pending.next().await {
+            if outcome.is_success() {
+                successes += 1;
+            }
+            finished[index] = Some(outcome);
+            if quorum.is_some_and(|q| successes >= q) {
+                info!("[{}] Quorum of {} reached, not waiting for remaining targets", from, successes);
+                break;
+            }
+        }
+        drop(pending);
+
+        finished
+            .into_iter()
+            .zip(targets)
+            .map(|(outcome, target)| {
+                outcome.unwrap_or_else(|| {
+                    ForwardOutcome::new(&target.agent, &target.message, ForwardStatus::Cancelled)
+                })
+            })
+            .collect()
+    }
+
+    /// Forward a single message, consulting and updating the circuit breaker
+    async fn forward_to_target(
+        &self,
+        from: &str,
+        target: &ForwardTarget,
+        blackboard: &Blackboard,
+        trace: Option<TraceCollector>,
+        deadline: Option<Instant>,
+    ) -> ForwardStatus {
+        let agent_name = &target.agent;
+        if let Some(breaker) = &self.circuit_breaker {
+            if breaker.is_open(agent_name) {
+                warn!("[{}] Circuit open for {}, skipping forward", from, agent_name);
+                return ForwardStatus::CircuitOpen;
+            }
+        }
+
+        info!("[{}] Forwarding to {}: {}", from, agent_name, target.message);
+        let status = match self
+            .send_message_internal_traced(from, agent_name, &target.message, blackboard, trace.clone(), deadline)
+            .await
+        {
+            Ok(SendResult::Response(msg)) => {
+                info!("[{}] Got response from {}", from, agent_name);
+                // Record response event in trace
+                if let Some(ref t) = trace {
+                    t.record(TraceEvent::response(agent_name, from, &msg.content)).await;
+                }
+                ForwardStatus::Success { response: msg.content }
+            }
+            Ok(SendResult::Timeout(e)) => {
+                warn!("[{}] Timeout waiting for {}: {}", from, agent_name, e);
+                let waited_ms = match e {
+                    AgentError::Timeout { waited, .. } => waited.as_millis() as u64,
+                    _ => 0,
+                };
+                ForwardStatus::Timeout { waited_ms }
+            }
+            Ok(SendResult::Notified) => {
+                debug!("[{}] {} notified (no response expected)", from, agent_name);
+                ForwardStatus::Notified
+            }
+            Err(AgentError::GuardRejected { .. }) => {
+                warn!("[{}] Guard on connection to {} rejected the message", from, agent_name);
+                ForwardStatus::Blocked
+            }
+            Err(AgentError::Intercepted { interceptor, .. }) => {
+                warn!("[{}] Interceptor {} rejected the forward to {}", from, interceptor, agent_name);
+                ForwardStatus::Blocked
+            }
+            Err(e) => {
+                error!("[{}] Failed to forward to {}: {}", from, agent_name, e);
+                self.events.emit(SystemEventKind::Error {
+                    agent: from.to_string(),
+                    message: e.to_string(),
+                });
+                ForwardStatus::Error { message: e.to_string() }
+            }
+        };
+
+        if let Some(breaker) = &self.circuit_breaker {
+            match status {
+                ForwardStatus::Success { .. } => breaker.record_success(agent_name),
+                ForwardStatus::Timeout { .. } | ForwardStatus::Error { .. } => {
+                    breaker.record_failure(agent_name)
+                }
+                _ => {}
+            }
+        }
+
+        status
+    }
+
+    /// Internal send with optional trace propagation
+    ///
+    /// When a trace is provided, it is attached to the InboxMessage so that
+    /// sub-agents (routing agents receiving a forwarded message) can record
+    /// their own trace events (e.g., forwarding to tools/databases).
+    ///
+    /// The connection timeout is clamped to the parent `deadline`, and the
+    /// resulting deadline travels with the message to further hops.
+    async fn send_message_internal_traced(
+        &self,
+        from: &str,
+        to: &str,
+        content: &str,
+        blackboard: &Blackboard,
+        trace: Option<TraceCollector>,
+        deadline: Option<Instant>,
+    ) -> Result<SendResult> {
+        let agents = self.agents.read().await;
+        let tools = self.tools.read().await;
+        let databases = self.databases.read().await;
+
+        // Get sender agent
+        let sender = agents
+            .get(from)
+            .ok_or_else(|| AgentError::AgentNotFound(from.to_string()))?;
+
+        // Check if there's an explicit connection
+        let connection = sender.agent.get_connection(to);
+
+        // Create the message, sharing the blackboard of the message it came from
+        let message = Message::new(from, to, content)
+            .with_metadata(trace.as_ref().map(|t| t.metadata().clone()).unwrap_or_default())
+            .with_blackboard(blackboard.clone());
+        let message_id = message.id;
+
+        // Determine connection type (default to blocking for forwards)
+        let default_timeout = self.default_timeout(&agents, to);
+        let (is_notify, effective_timeout) = match connection {
+            Some(conn) => (
+                conn.connection_type == ConnectionType::Notify,
+                conn.effective_timeout(default_timeout),
+            ),
+            None => (false, default_timeout),
+        };
+        let (effective_timeout, deadline) = clamp_to_deadline(effective_timeout, deadline);
+
+        // Messages the connection's guard rejects are never delivered
+        if connection.is_some_and(|conn| !conn.allows(&message)) {
+            return Err(AgentError::GuardRejected {
+                from: from.to_string(),
+                to: to.to_string(),
+            });
+        }
+
+        // Get receiver inbox - check agents first, then tools, then databases
+        let (receiver_inbox, in_flight) = if let Some(agent) = agents.get(to) {
+            let (inbox, in_flight) = agent.replicas.pick(trace.as_ref().and_then(|t| t.session_id()));
+            (inbox, Some(in_flight))
         } else if let Some(tool) = tools.get(to) {
-            tool.inbox_tx.clone()
+            (tool.inbox_tx.clone(), None)
         } else if let Some(db) = databases.get(to) {
-            db.inbox_tx.clone()
+            (db.inbox_tx.clone(), None)
         } else {
             return Err(AgentError::AgentNotFound(to.to_string()));
         };
 
-        // Create the message
-        let message = Message::new(from, to, content);
-        let message_id = message.id;
+        // Don't hold the registries while intercepting or waiting: interceptors
+        // and receivers may register agents of their own (spawned sub-agents)
+        drop(databases);
+        drop(tools);
+        drop(agents);
+
+        let message = match self.intercept_request(message).await? {
+            Outgoing::Deliver(message) => message,
+            Outgoing::Answered(_) if is_notify => return Ok(SendResult::Notified),
+            Outgoing::Answered(reply) => return Ok(SendResult::Response(reply)),
+        };
+        let request = message.clone();
 
         // Store in conversation history
         {
@@ -856,64 +2078,62 @@ impl AgentSystem {
             conversations.add_message(message.clone());
         }
 
-        // Handle based on connection type
-        match connection.connection_type {
-            ConnectionType::Notify => {
-                let inbox_msg = InboxMessage {
-                    message,
-                    response_tx: None,
-                    trace: None,
-                };
-                receiver_inbox
-                    .send(inbox_msg)
-                    .await
-                    .map_err(|_| AgentError::ChannelError("Failed to send to inbox".into()))?;
-                Ok(SendResult::Notified)
-            }
-            ConnectionType::Blocking => {
-                let (response_tx, response_rx) = oneshot::channel();
-                let inbox_msg = InboxMessage {
-                    message,
-                    response_tx: Some(response_tx),
-                    trace: None,
-                };
+        if is_notify {
+            // Fire and forget
+            let inbox_msg = InboxMessage {
+                message,
+                response_tx: None,
+                trace,
+                deadline: None,
+                span: Span::current(),
+                _in_flight: in_flight,
+            };
+            self.deliver(&receiver_inbox, inbox_msg).await?;
+            Ok(SendResult::Notified)
+        } else {
+            // Blocking with response
+            let (response_tx, response_rx) = oneshot::channel();
+            let inbox_msg = InboxMessage {
+                message,
+                response_tx: Some(response_tx),
+                trace,
+                deadline: Some(deadline),
+                span: Span::current(),
+                _in_flight: in_flight,
+            };
 
-                receiver_inbox
-                    .send(inbox_msg)
-                    .await
-                    .map_err(|_| AgentError::ChannelError("Failed to send to inbox".into()))?;
+            self.deliver(&receiver_inbox, inbox_msg).await?;
 
-                let effective_timeout = connection.effective_timeout(self.config.global_timeout);
-                match timeout(effective_timeout, response_rx).await {
-                    Ok(Ok(response)) => {
-                        let mut conversations = self.conversations.write().await;
-                        conversations.add_message(response.clone());
-                        Ok(SendResult::Response(response))
-                    }
-                    Ok(Err(_)) => Ok(SendResult::Timeout(AgentError::Timeout {
-                        agent: to.to_string(),
-                        message_id,
-                        waited: effective_timeout,
-                    })),
-                    Err(_) => Ok(SendResult::Timeout(AgentError::Timeout {
-                        agent: to.to_string(),
-                        message_id,
-                        waited: effective_timeout,
-                    })),
+            let sent_at = Instant::now();
+
+            match timeout(effective_timeout, response_rx).await {
+                Ok(Ok(Reply::Message(response))) => {
+                    self.received(&response, sent_at);
+                    let response = self.intercept_response(&request, response).await?;
+                    let mut conversations = self.conversations.write().await;
+                    conversations.add_message(response.clone());
+                    Ok(SendResult::Response(response))
                 }
+                Ok(Ok(Reply::Timeout)) => Ok(self.timed_out(from, to, message_id, sent_at.elapsed())),
+                // A dropped response channel means the handler gave up (or panicked)
+                Ok(Err(_)) | Err(_) => Ok(self.timed_out(from, to, message_id, effective_timeout)),
             }
         }
     }
 
-    /// Send a message with tracing enabled
-    /// The trace collector will record all agent-to-agent communications
-    pub async fn send_message_with_trace(
+    /// Send a message from one agent to another
+    /// Respects connection types and timeouts
+    #[instrument(name = "mas.request", skip_all, fields(mas.from = from, mas.to = to))]
+    pub async fn send_message(
         &self,
         from: &str,
         to: &str,
         content: &str,
-        trace: TraceCollector,
     ) -> Result<SendResult> {
+        let checked = self.check_guardrails(GuardrailStage::Input, content, from, to, None).await;
+        let blocked = checked.as_ref().is_some_and(|verdict| verdict.blocked);
+        let content = checked.as_ref().map_or(content, |verdict| verdict.text.as_str());
+
         let agents = self.agents.read().await;
         let tools = self.tools.read().await;
         let databases = self.databases.read().await;
@@ -927,26 +2147,57 @@ impl AgentSystem {
         let connection = sender
             .agent
             .get_connection(to)
+            .cloned()
             .ok_or_else(|| AgentError::NoConnection {
                 from: from.to_string(),
                 to: to.to_string(),
             })?;
 
         // Get receiver inbox - check agents, tools, and databases
-        let receiver_inbox = if let Some(agent) = agents.get(to) {
-            agent.inbox_tx.clone()
+        let (receiver_inbox, in_flight) = if let Some(agent) = agents.get(to) {
+            let (inbox, in_flight) = agent.replicas.pick(None);
+            (inbox, Some(in_flight))
         } else if let Some(tool) = tools.get(to) {
-            tool.inbox_tx.clone()
+            (tool.inbox_tx.clone(), None)
         } else if let Some(db) = databases.get(to) {
-            db.inbox_tx.clone()
+            (db.inbox_tx.clone(), None)
         } else {
             return Err(AgentError::AgentNotFound(to.to_string()));
         };
 
+        let default_timeout = self.default_timeout(&agents, to);
+
+        // Don't hold the registries while waiting for the response
+        drop(databases);
+        drop(tools);
+        drop(agents);
+
         // Create the message
         let message = Message::new(from, to, content);
         let message_id = message.id;
 
+        // Messages the connection's guard rejects are never delivered
+        if !connection.allows(&message) {
+            return Err(AgentError::GuardRejected {
+                from: from.to_string(),
+                to: to.to_string(),
+            });
+        }
+
+        // Requests the guardrails block are answered with the blocked response
+        if blocked {
+            return Ok(SendResult::Response(message.reply(content)));
+        }
+
+        let message = match self.intercept_request(message).await? {
+            Outgoing::Deliver(message) => message,
+            Outgoing::Answered(_) if connection.connection_type == ConnectionType::Notify => {
+                return Ok(SendResult::Notified)
+            }
+            Outgoing::Answered(reply) => return Ok(SendResult::Response(self.guard_response(reply, None).await)),
+        };
+        let request = message.clone();
+
         // Store in conversation history
         {
             let mut conversations = self.conversations.write().await;
@@ -959,60 +2210,193 @@ impl AgentSystem {
                 let inbox_msg = InboxMessage {
                     message,
                     response_tx: None,
-                    trace: Some(trace),
+                    trace: None,
+                    deadline: None,
+                    span: Span::current(),
+                    _in_flight: in_flight,
                 };
-                receiver_inbox
-                    .send(inbox_msg)
-                    .await
-                    .map_err(|_| AgentError::ChannelError("Failed to send to inbox".into()))?;
+                self.deliver(&receiver_inbox, inbox_msg).await?;
                 Ok(SendResult::Notified)
             }
             ConnectionType::Blocking => {
+                let effective_timeout = connection.effective_timeout(default_timeout);
                 let (response_tx, response_rx) = oneshot::channel();
                 let inbox_msg = InboxMessage {
                     message,
                     response_tx: Some(response_tx),
-                    trace: Some(trace),
+                    trace: None,
+                    deadline: Some(Instant::now() + effective_timeout),
+                    span: Span::current(),
+                    _in_flight: in_flight,
                 };
 
-                receiver_inbox
-                    .send(inbox_msg)
-                    .await
-                    .map_err(|_| AgentError::ChannelError("Failed to send to inbox".into()))?;
+                self.deliver(&receiver_inbox, inbox_msg).await?;
+
+                let sent_at = Instant::now();
 
-                let effective_timeout = connection.effective_timeout(self.config.global_timeout);
                 match timeout(effective_timeout, response_rx).await {
-                    Ok(Ok(response)) => {
+                    Ok(Ok(Reply::Message(response))) => {
+                        self.received(&response, sent_at);
+                        let response = self.intercept_response(&request, response).await?;
+                        let response = self.guard_response(response, None).await;
                         let mut conversations = self.conversations.write().await;
                         conversations.add_message(response.clone());
                         Ok(SendResult::Response(response))
                     }
-                    Ok(Err(_)) => Ok(SendResult::Timeout(AgentError::Timeout {
-                        agent: to.to_string(),
-                        message_id,
-                        waited: effective_timeout,
-                    })),
-                    Err(_) => Ok(SendResult::Timeout(AgentError::Timeout {
-                        agent: to.to_string(),
-                        message_id,
-                        waited: effective_timeout,
-                    })),
+                    Ok(Ok(Reply::Timeout)) => Ok(self.timed_out(from, to, message_id, sent_at.elapsed())),
+                    // A dropped response channel means the handler gave up (or panicked)
+                    Ok(Err(_)) | Err(_) => Ok(self.timed_out(from, to, message_id, effective_timeout)),
                 }
             }
         }
     }
 
-    /// Send messages to multiple agents in parallel
-    /// Returns results in the same order as the recipients
-    pub async fn send_to_multiple(
+    /// Send a message with tracing enabled
+    /// The trace collector will record all agent-to-agent communications
+    #[instrument(name = "mas.request", skip_all, fields(mas.from = from, mas.to = to))]
+    pub async fn send_message_with_trace(
         &self,
         from: &str,
-        recipients: &[&str],
+        to: &str,
         content: &str,
-    ) -> Vec<(String, Result<SendResult>)> {
-        let futures: Vec<_> = recipients
-            .iter()
-            .map(|&to| {
+        trace: TraceCollector,
+    ) -> Result<SendResult> {
+        let checked = self.check_guardrails(GuardrailStage::Input, content, from, to, Some(&trace)).await;
+        let blocked = checked.as_ref().is_some_and(|verdict| verdict.blocked);
+        let content = checked.as_ref().map_or(content, |verdict| verdict.text.as_str());
+
+        let agents = self.agents.read().await;
+        let tools = self.tools.read().await;
+        let databases = self.databases.read().await;
+
+        // Get sender agent to check connection
+        let sender = agents
+            .get(from)
+            .ok_or_else(|| AgentError::AgentNotFound(from.to_string()))?;
+
+        // Require explicit connection for public API
+        let connection = sender
+            .agent
+            .get_connection(to)
+            .cloned()
+            .ok_or_else(|| AgentError::NoConnection {
+                from: from.to_string(),
+                to: to.to_string(),
+            })?;
+
+        // Get receiver inbox - check agents, tools, and databases
+        let (receiver_inbox, in_flight) = if let Some(agent) = agents.get(to) {
+            let (inbox, in_flight) = agent.replicas.pick(trace.session_id());
+            (inbox, Some(in_flight))
+        } else if let Some(tool) = tools.get(to) {
+            (tool.inbox_tx.clone(), None)
+        } else if let Some(db) = databases.get(to) {
+            (db.inbox_tx.clone(), None)
+        } else {
+            return Err(AgentError::AgentNotFound(to.to_string()));
+        };
+
+        let default_timeout = self.default_timeout(&agents, to);
+
+        // Don't hold the registries while waiting for the response
+        drop(databases);
+        drop(tools);
+        drop(agents);
+
+        // Create the message
+        let message = Message::new(from, to, content)
+            .with_metadata(trace.metadata().clone())
+            .with_blackboard(trace.blackboard().clone());
+        let message_id = message.id;
+
+        // Messages the connection's guard rejects are never delivered
+        if !connection.allows(&message) {
+            return Err(AgentError::GuardRejected {
+                from: from.to_string(),
+                to: to.to_string(),
+            });
+        }
+
+        // Requests the guardrails block are answered with the blocked response
+        if blocked {
+            return Ok(SendResult::Response(message.reply(content)));
+        }
+
+        let message = match self.intercept_request(message).await? {
+            Outgoing::Deliver(message) => message,
+            Outgoing::Answered(_) if connection.connection_type == ConnectionType::Notify => {
+                return Ok(SendResult::Notified)
+            }
+            Outgoing::Answered(reply) => {
+                return Ok(SendResult::Response(self.guard_response(reply, Some(&trace)).await))
+            }
+        };
+        let request = message.clone();
+
+        // Store in conversation history
+        {
+            let mut conversations = self.conversations.write().await;
+            conversations.add_message(message.clone());
+        }
+
+        // Handle based on connection type
+        match connection.connection_type {
+            ConnectionType::Notify => {
+                let inbox_msg = InboxMessage {
+                    message,
+                    response_tx: None,
+                    trace: Some(trace),
+                    deadline: None,
+                    span: Span::current(),
+                    _in_flight: in_flight,
+                };
+                self.deliver(&receiver_inbox, inbox_msg).await?;
+                Ok(SendResult::Notified)
+            }
+            ConnectionType::Blocking => {
+                let effective_timeout = connection.effective_timeout(default_timeout);
+                let (response_tx, response_rx) = oneshot::channel();
+                let inbox_msg = InboxMessage {
+                    message,
+                    response_tx: Some(response_tx),
+                    trace: Some(trace.clone()),
+                    deadline: Some(Instant::now() + effective_timeout),
+                    span: Span::current(),
+                    _in_flight: in_flight,
+                };
+
+                self.deliver(&receiver_inbox, inbox_msg).await?;
+
+                let sent_at = Instant::now();
+
+                match timeout(effective_timeout, response_rx).await {
+                    Ok(Ok(Reply::Message(response))) => {
+                        self.received(&response, sent_at);
+                        let response = self.intercept_response(&request, response).await?;
+                        let response = self.guard_response(response, Some(&trace)).await;
+                        let mut conversations = self.conversations.write().await;
+                        conversations.add_message(response.clone());
+                        Ok(SendResult::Response(response))
+                    }
+                    Ok(Ok(Reply::Timeout)) => Ok(self.timed_out(from, to, message_id, sent_at.elapsed())),
+                    // A dropped response channel means the handler gave up (or panicked)
+                    Ok(Err(_)) | Err(_) => Ok(self.timed_out(from, to, message_id, effective_timeout)),
+                }
+            }
+        }
+    }
+
+    /// Send messages to multiple agents in parallel
+    /// Returns results in the same order as the recipients
+    pub async fn send_to_multiple(
+        &self,
+        from: &str,
+        recipients: &[&str],
+        content: &str,
+    ) -> Vec<(String, Result<SendResult>)> {
+        let futures: Vec<_> = recipients
+            .iter()
+            .map(|&to| {
                 let from = from.to_string();
                 let to = to.to_string();
                 let content = content.to_string();
@@ -1055,6 +2439,192 @@ impl AgentSystem {
             .map(|c| c.messages().to_vec())
     }
 
+    /// Put a message into a receiver's inbox
+    async fn deliver(&self, inbox: &mpsc::Sender<InboxMessage>, inbox_msg: InboxMessage) -> Result<()> {
+        let (message_id, from, to) = (
+            inbox_msg.message.id,
+            inbox_msg.message.from.clone(),
+            inbox_msg.message.to.clone(),
+        );
+        inbox
+            .send(inbox_msg)
+            .await
+            .map_err(|_| AgentError::ChannelError("Failed to send to inbox".into()))?;
+        self.events.emit(SystemEventKind::MessageSent { message_id, from, to });
+        Ok(())
+    }
+
+    /// Report a response that came back in time
+    fn received(&self, response: &Message, sent_at: Instant) {
+        self.events.emit_with(|| SystemEventKind::MessageReceived {
+            message_id: response.in_reply_to.unwrap_or(response.id),
+            from: response.from.clone(),
+            to: response.to.clone(),
+            elapsed_ms: sent_at.elapsed().as_millis() as u64,
+        });
+    }
+
+    /// The result for a message that was not answered in time
+    fn timed_out(&self, from: &str, to: &str, message_id: Uuid, waited: Duration) -> SendResult {
+        metrics().record_timeout(self.name(), to);
+        self.events.emit_with(|| SystemEventKind::Timeout {
+            message_id,
+            from: from.to_string(),
+            to: to.to_string(),
+            waited_ms: waited.as_millis() as u64,
+        });
+        SendResult::Timeout(AgentError::Timeout {
+            agent: to.to_string(),
+            message_id,
+            waited,
+        })
+    }
+
+    /// Run a message through the interceptors' `before_send` hooks
+    async fn intercept_request(&self, mut message: Message) -> Result<Outgoing> {
+        let interceptors = self.interceptors.read().await.clone();
+        for interceptor in &interceptors {
+            match interceptor.before_send(message.clone()).await {
+                Interception::Continue(next) => {
+                    message = Message {
+                        from: message.from,
+                        to: message.to,
+                        ..next
+                    };
+                }
+                Interception::Respond(content) => {
+                    debug!(
+                        "Interceptor {} answered the message from {} to {}",
+                        interceptor.name(),
+                        message.from,
+                        message.to
+                    );
+                    return Ok(Outgoing::Answered(message.reply(content)));
+                }
+                Interception::Reject(reason) => {
+                    warn!(
+                        "Interceptor {} rejected the message from {} to {}: {}",
+                        interceptor.name(),
+                        message.from,
+                        message.to,
+                        reason
+                    );
+                    return Err(AgentError::Intercepted {
+                        interceptor: interceptor.name().to_string(),
+                        from: message.from,
+                        to: message.to,
+                        reason,
+                    });
+                }
+            }
+        }
+        Ok(Outgoing::Deliver(message))
+    }
+
+    /// Run a response through the interceptors' `after_receive` hooks
+    async fn intercept_response(&self, request: &Message, mut response: Message) -> Result<Message> {
+        let interceptors = self.interceptors.read().await.clone();
+        for interceptor in &interceptors {
+            match interceptor.after_receive(request, response.clone()).await {
+                Interception::Continue(next) => {
+                    response = Message {
+                        from: response.from,
+                        to: response.to,
+                        ..next
+                    };
+                }
+                Interception::Respond(content) => {
+                    debug!(
+                        "Interceptor {} replaced the response from {} to {}",
+                        interceptor.name(),
+                        response.from,
+                        response.to
+                    );
+                    response.content = content;
+                    break;
+                }
+                Interception::Reject(reason) => {
+                    warn!(
+                        "Interceptor {} rejected the response from {} to {}: {}",
+                        interceptor.name(),
+                        response.from,
+                        response.to,
+                        reason
+                    );
+                    return Err(AgentError::Intercepted {
+                        interceptor: interceptor.name().to_string(),
+                        from: response.from,
+                        to: response.to,
+                        reason,
+                    });
+                }
+            }
+        }
+        Ok(response)
+    }
+
+    /// Run the system's guardrails over a request or response, recording
+    /// violations in the trace (`None` without guardrails for that side)
+    async fn check_guardrails(
+        &self,
+        stage: GuardrailStage,
+        text: &str,
+        from: &str,
+        to: &str,
+        trace: Option<&TraceCollector>,
+    ) -> Option<GuardrailVerdict> {
+        let guardrails = self.config.guardrails.as_ref().filter(|g| g.checks(stage))?;
+        let verdict = guardrails.check(stage, text).await;
+        if verdict.blocked {
+            warn!("Guardrails blocked a message from {} to {}", from, to);
+        }
+        if let Some(trace) = trace {
+            verdict.record(trace, from, to).await;
+        }
+        Some(verdict)
+    }
+
+    /// Run the system's output guardrails over a response
+    async fn guard_response(&self, mut response: Message, trace: Option<&TraceCollector>) -> Message {
+        if let Some(verdict) = self
+            .check_guardrails(GuardrailStage::Output, &response.content, &response.from, &response.to, trace)
+            .await
+        {
+            response.content = verdict.text;
+        }
+        response
+    }
+
+    /// Number of messages waiting in each agent's, tool's and database's inbox
+    pub async fn inbox_depths(&self) -> Vec<(String, usize)> {
+        fn queued(inbox: &mpsc::Sender<InboxMessage>) -> usize {
+            inbox.max_capacity() - inbox.capacity()
+        }
+
+        let mut depths: Vec<(String, usize)> = {
+            let agents = self.agents.read().await;
+            agents
+                .iter()
+                .map(|(name, agent)| (name.clone(), agent.replicas.inboxes().map(queued).sum()))
+                .collect()
+        };
+        depths.extend(
+            self.tools
+                .read()
+                .await
+                .iter()
+                .map(|(name, tool)| (name.clone(), queued(&tool.inbox_tx))),
+        );
+        depths.extend(
+            self.databases
+                .read()
+                .await
+                .iter()
+                .map(|(name, db)| (name.clone(), queued(&db.inbox_tx))),
+        );
+        depths
+    }
+
     /// Get an agent by name (returns a clone)
     pub async fn get_agent(&self, name: &str) -> Option<Agent> {
         let agents = self.agents.read().await;
@@ -1127,6 +2697,7 @@ where
 mod tests {
     use super::*;
     use crate::agent::AgentBuilder;
+    use crate::circuit_breaker::CircuitBreakerConfig;
 
     #[tokio::test]
     async fn test_blocking_send_receive() {
@@ -1291,4 +2862,1546 @@ mod tests {
 
         assert!(matches!(result, Err(AgentError::NoConnection { .. })));
     }
+
+    /// Routing handler that forwards to fixed targets and joins the responses
+    struct FanOutHandler {
+        targets: Vec<&'static str>,
+        quorum: Option<usize>,
+    }
+
+    #[async_trait]
+    impl RoutingHandler for FanOutHandler {
+        async fn handle(&self, message: &Message, _agent: &Agent) -> HandlerDecision {
+            HandlerDecision::forward_to_many(
+                self.targets
+                    .iter()
+                    .map(|t| ForwardTarget::new(*t, message.content.clone()))
+                    .collect(),
+            )
+        }
+
+        async fn synthesize(
+            &self,
+            _original_message: &Message,
+            outcomes: &[ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<String> {
+            let parts: Vec<String> = outcomes
+                .iter()
+                .map(|o| match &o.status {
+                    ForwardStatus::Success { response } => format!("{}={}", o.agent, response),
+                    ForwardStatus::Timeout { .. } => format!("{}=timeout", o.agent),
+                    ForwardStatus::Error { .. } => format!("{}=error", o.agent),
+                    ForwardStatus::CircuitOpen => format!("{}=circuit_open", o.agent),
+                    ForwardStatus::Blocked => format!("{}=blocked", o.agent),
+                    ForwardStatus::Cancelled => format!("{}=cancelled", o.agent),
+                    ForwardStatus::Notified => format!("{}=notified", o.agent),
+                })
+                .collect();
+            Some(parts.join(","))
+        }
+
+        fn quorum(&self) -> Option<usize> {
+            self.quorum
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deadline_clamps_downstream_timeouts() {
+        let system = Arc::new(AgentSystem::new(
+            SystemConfig::with_timeout_secs(30).with_synthesis_reserve(Duration::from_millis(200)),
+        ));
+
+        let user = AgentBuilder::new("User")
+            .blocking_connection_with_timeout("Coordinator", Duration::from_secs(1))
+            .build();
+        // The worker connection allows far longer than the user is willing to wait
+        let coordinator = AgentBuilder::new("Coordinator")
+            .blocking_connection("Fast")
+            .blocking_connection_with_timeout("Slow", Duration::from_secs(60))
+            .build();
+
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system
+            .register_agent(AgentBuilder::new("Fast").build(), Arc::new(EchoHandler))
+            .await
+            .unwrap();
+        system
+            .register_agent(
+                AgentBuilder::new("Slow").build(),
+                Arc::new(DelayedHandler::new(Duration::from_secs(10), "Too late")),
+            )
+            .await
+            .unwrap();
+        AgentSystem::register_routing_agent(
+            system.clone(),
+            coordinator,
+            Arc::new(FanOutHandler { targets: vec!["Fast", "Slow"], quorum: None }),
+        )
+        .await
+        .unwrap();
+
+        let started = std::time::Instant::now();
+        let result = system.send_message("User", "Coordinator", "Hi").await.unwrap();
+
+        // Synthesis ran over the partial results before the user's deadline
+        match result {
+            SendResult::Response(msg) => assert_eq!(msg.content, "Fast=Echo: Hi,Slow=timeout"),
+            other => panic!("Expected partial response, got {:?}", other),
+        }
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    /// Register Coordinator (FanOutHandler over Fast/Slow/Missing) plus its workers
+    async fn fan_out_system(config: SystemConfig, quorum: Option<usize>) -> Arc<AgentSystem> {
+        let system = Arc::new(AgentSystem::new(config));
+        let user = AgentBuilder::new("User").blocking_connection("Coordinator").build();
+        let coordinator = AgentBuilder::new("Coordinator")
+            .blocking_connection("Fast")
+            .blocking_connection_with_timeout("Slow", Duration::from_millis(300))
+            .build();
+
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system
+            .register_agent(AgentBuilder::new("Fast").build(), Arc::new(EchoHandler))
+            .await
+            .unwrap();
+        system
+            .register_agent(
+                AgentBuilder::new("Slow").build(),
+                Arc::new(DelayedHandler::new(Duration::from_secs(10), "Too late")),
+            )
+            .await
+            .unwrap();
+        AgentSystem::register_routing_agent(
+            system.clone(),
+            coordinator,
+            Arc::new(FanOutHandler { targets: vec!["Fast", "Slow", "Missing"], quorum }),
+        )
+        .await
+        .unwrap();
+        system
+    }
+
+    #[tokio::test]
+    async fn test_synthesis_receives_structured_outcomes() {
+        let system = fan_out_system(SystemConfig::default(), None).await;
+
+        let result = system.send_message("User", "Coordinator", "Hi").await.unwrap();
+        let content = result.into_response().unwrap().content;
+        assert_eq!(content, "Fast=Echo: Hi,Slow=timeout,Missing=error");
+    }
+
+    #[tokio::test]
+    async fn test_quorum_returns_before_slow_targets() {
+        let system = fan_out_system(SystemConfig::default(), Some(1)).await;
+
+        let started = std::time::Instant::now();
+        let result = system.send_message("User", "Coordinator", "Hi").await.unwrap();
+        let content = result.into_response().unwrap().content;
+
+        assert!(content.starts_with("Fast=Echo: Hi,Slow=cancelled"));
+        assert!(started.elapsed() < Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_skips_failing_target() {
+        let config = SystemConfig::default().with_circuit_breaker(CircuitBreakerConfig {
+            failure_threshold: 1,
+            cooldown: Duration::from_secs(60),
+        });
+        let system = fan_out_system(config, None).await;
+
+        let first = system.send_message("User", "Coordinator", "Hi").await.unwrap();
+        assert_eq!(first.into_response().unwrap().content, "Fast=Echo: Hi,Slow=timeout,Missing=error");
+
+        let second = system.send_message("User", "Coordinator", "Hi").await.unwrap();
+        assert_eq!(
+            second.into_response().unwrap().content,
+            "Fast=Echo: Hi,Slow=circuit_open,Missing=circuit_open"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_ignores_notified_targets() {
+        let config = SystemConfig::default().with_circuit_breaker(CircuitBreakerConfig {
+            failure_threshold: 1,
+            cooldown: Duration::from_secs(60),
+        });
+        let system = Arc::new(AgentSystem::new(config));
+        let user = AgentBuilder::new("User").blocking_connection("Coordinator").build();
+        let coordinator = AgentBuilder::new("Coordinator").notify_connection("Log").build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system
+            .register_agent(AgentBuilder::new("Log").build(), Arc::new(EchoHandler))
+            .await
+            .unwrap();
+        AgentSystem::register_routing_agent(
+            system.clone(),
+            coordinator,
+            Arc::new(FanOutHandler { targets: vec!["Log"], quorum: None }),
+        )
+        .await
+        .unwrap();
+
+        for _ in 0..2 {
+            let result = system.send_message("User", "Coordinator", "Hi").await.unwrap();
+            assert_eq!(result.into_response().unwrap().content, "Log=notified");
+        }
+    }
+
+    /// Routing handler that records a severity on the blackboard and hands over to the nurse
+    struct TriageHandler;
+
+    #[async_trait]
+    impl RoutingHandler for TriageHandler {
+        async fn handle(&self, message: &Message, _agent: &Agent) -> HandlerDecision {
+            message.blackboard.set("patient.severity", serde_json::json!("high"));
+            HandlerDecision::forward_to("Nurse", message.content.clone())
+        }
+
+        async fn synthesize(
+            &self,
+            _original_message: &Message,
+            outcomes: &[ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<String> {
+            outcomes.first().and_then(|o| o.response()).map(str::to_string)
+        }
+    }
+
+    /// Answers with the severity found on the blackboard
+    struct SeverityReader;
+
+    #[async_trait]
+    impl MessageHandler for SeverityReader {
+        async fn handle(&self, message: &Message, _agent: &Agent) -> Option<String> {
+            let severity = message.blackboard.get_text("patient.severity").unwrap_or_default();
+            Some(format!("Severity: {}", severity))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blackboard_is_shared_across_hops_and_traced() {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let user = AgentBuilder::new("User").blocking_connection("Triage").build();
+        let triage = AgentBuilder::new("Triage").blocking_connection("Nurse").build();
+
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system
+            .register_agent(AgentBuilder::new("Nurse").build(), Arc::new(SeverityReader))
+            .await
+            .unwrap();
+        AgentSystem::register_routing_agent(system.clone(), triage, Arc::new(TriageHandler))
+            .await
+            .unwrap();
+
+        // Untraced requests share a blackboard too
+        let result = system.send_message("User", "Triage", "chest pain").await.unwrap();
+        assert_eq!(result.into_response().unwrap().content, "Severity: high");
+
+        let trace = TraceCollector::new();
+        let result = system
+            .send_message_with_trace("User", "Triage", "chest pain", trace.clone())
+            .await
+            .unwrap();
+        assert_eq!(result.into_response().unwrap().content, "Severity: high");
+        assert_eq!(trace.blackboard().get_text("patient.severity").as_deref(), Some("high"));
+
+        let snapshots: Vec<_> = trace
+            .events()
+            .await
+            .into_iter()
+            .filter(|e| e.event_type == crate::tracer::TraceEventType::Blackboard)
+            .map(|e| (e.from, e.content))
+            .collect();
+        assert_eq!(
+            snapshots,
+            vec![("Triage".to_string(), r#"{"patient":{"severity":"high"}}"#.to_string())]
+        );
+    }
+
+    /// Routing handler that always runs a fixed pipeline
+    struct PipelineHandler {
+        stages: Vec<String>,
+    }
+
+    #[async_trait]
+    impl RoutingHandler for PipelineHandler {
+        async fn handle(&self, _message: &Message, _agent: &Agent) -> HandlerDecision {
+            HandlerDecision::pipeline(self.stages.clone())
+        }
+
+        async fn synthesize(
+            &self,
+            _original_message: &Message,
+            _outcomes: &[ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<String> {
+            None
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_chains_stage_outputs() {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let user = AgentBuilder::new("User").blocking_connection("Lead").build();
+        let lead = AgentBuilder::new("Lead")
+            .blocking_connection("First")
+            .blocking_connection("Second")
+            .build();
+
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        for name in ["First", "Second"] {
+            system
+                .register_agent(AgentBuilder::new(name).build(), Arc::new(EchoHandler))
+                .await
+                .unwrap();
+        }
+        AgentSystem::register_routing_agent(
+            system.clone(),
+            lead,
+            Arc::new(PipelineHandler {
+                stages: vec!["First".to_string(), "Second".to_string()],
+            }),
+        )
+        .await
+        .unwrap();
+
+        let trace = TraceCollector::new();
+        let result = system
+            .send_message_with_trace("User", "Lead", "doc", trace.clone())
+            .await
+            .unwrap();
+
+        // Second stage echoed the first stage's output
+        assert_eq!(result.into_response().unwrap().content, "Echo: Echo: doc");
+        let forwards: Vec<_> = trace
+            .events()
+            .await
+            .into_iter()
+            .filter(|e| e.event_type == crate::tracer::TraceEventType::Forward)
+            .map(|e| (e.to, e.content))
+            .collect();
+        assert_eq!(
+            forwards,
+            vec![
+                ("First".to_string(), "doc".to_string()),
+                ("Second".to_string(), "Echo: doc".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_stops_at_failed_stage() {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let user = AgentBuilder::new("User").blocking_connection("Lead").build();
+        let lead = AgentBuilder::new("Lead").blocking_connection("First").build();
+
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system
+            .register_agent(AgentBuilder::new("First").build(), Arc::new(EchoHandler))
+            .await
+            .unwrap();
+        AgentSystem::register_routing_agent(
+            system.clone(),
+            lead,
+            Arc::new(PipelineHandler {
+                stages: vec!["First".to_string(), "Missing".to_string()],
+            }),
+        )
+        .await
+        .unwrap();
+
+        let result = system.send_message("User", "Lead", "doc").await.unwrap();
+        assert!(result.into_response().unwrap().content.starts_with("Pipeline stopped at Missing"));
+    }
+
+    /// Routing handler that always asks its targets to vote
+    struct VoteHandler {
+        targets: Vec<&'static str>,
+    }
+
+    #[async_trait]
+    impl RoutingHandler for VoteHandler {
+        async fn handle(&self, _message: &Message, _agent: &Agent) -> HandlerDecision {
+            HandlerDecision::vote(self.targets.iter().map(|t| ForwardTarget::new(*t, "")).collect())
+        }
+
+        async fn synthesize(
+            &self,
+            _original_message: &Message,
+            _outcomes: &[ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<String> {
+            Some("no ballots".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_vote_records_ballots_and_result() {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let user = AgentBuilder::new("User").blocking_connection("Panel").build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+
+        let voters = [
+            ("V1", r#"{"answer": "Yes", "confidence": 0.6}"#),
+            ("V2", r#"{"answer": "no", "confidence": 0.9}"#),
+            ("V3", r#"{"answer": "yes", "confidence": 0.7}"#),
+        ];
+        let mut panel = AgentBuilder::new("Panel");
+        for (name, ballot) in voters {
+            panel = panel.blocking_connection(name);
+            system
+                .register_agent(
+                    AgentBuilder::new(name).build(),
+                    Arc::new(DelayedHandler::new(Duration::ZERO, ballot)),
+                )
+                .await
+                .unwrap();
+        }
+        AgentSystem::register_routing_agent(
+            system.clone(),
+            panel.build(),
+            Arc::new(VoteHandler { targets: vec!["V1", "V2", "V3"] }),
+        )
+        .await
+        .unwrap();
+
+        let trace = TraceCollector::new();
+        let result = system
+            .send_message_with_trace("User", "Panel", "Ship it?", trace.clone())
+            .await
+            .unwrap();
+        assert_eq!(result.into_response().unwrap().content, "Yes");
+
+        let events = trace.events().await;
+        let ballots = events
+            .iter()
+            .filter(|e| e.event_type == crate::tracer::TraceEventType::Ballot)
+            .count();
+        assert_eq!(ballots, 3);
+
+        let vote = events
+            .iter()
+            .find(|e| e.event_type == crate::tracer::TraceEventType::Vote)
+            .unwrap();
+        let result: VoteResult = serde_json::from_str(&vote.content).unwrap();
+        assert_eq!(result.method, VoteAggregation::Majority);
+        assert_eq!(result.tally[0], ("Yes".to_string(), 2.0));
+    }
+
+    /// Routing handler that runs a debate and returns the transcript as the verdict
+    /// Routing handler that answers with whether a trace is in scope
+    struct TraceProbe(HandlerDecision);
+
+    impl TraceProbe {
+        fn traced() -> String {
+            format!("traced: {}", TraceCollector::current().is_some())
+        }
+    }
+
+    #[async_trait]
+    impl RoutingHandler for TraceProbe {
+        async fn handle(&self, _message: &Message, _agent: &Agent) -> HandlerDecision {
+            self.0.clone()
+        }
+
+        async fn synthesize(
+            &self,
+            _original_message: &Message,
+            _outcomes: &[ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<String> {
+            Some(Self::traced())
+        }
+
+        async fn tally(&self, _original_message: &Message, ballots: &[Ballot], _agent: &Agent) -> Option<VoteResult> {
+            vote::aggregate(ballots, VoteAggregation::Majority).map(|result| VoteResult {
+                answer: Self::traced(),
+                ..result
+            })
+        }
+
+        async fn judge_debate(
+            &self,
+            _original_message: &Message,
+            _turns: &[ConversationTurn],
+            _agent: &Agent,
+        ) -> Option<String> {
+            Some(Self::traced())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_vote_tally_and_fallback_run_in_the_trace_scope() {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let user = AgentBuilder::new("User")
+            .blocking_connection("Tally")
+            .blocking_connection("Fallback")
+            .build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system
+            .register_agent(AgentBuilder::new("Voter").build(), Arc::new(EchoHandler))
+            .await
+            .unwrap();
+
+        // Tally gets a ballot from Voter, Fallback none from Missing
+        for (name, voter) in [("Tally", "Voter"), ("Fallback", "Missing")] {
+            let probe = AgentBuilder::new(name).blocking_connection(voter).build();
+            let decision = HandlerDecision::vote(vec![ForwardTarget::new(voter, "")]);
+            AgentSystem::register_routing_agent(system.clone(), probe, Arc::new(TraceProbe(decision)))
+                .await
+                .unwrap();
+
+            let result = system
+                .send_message_with_trace("User", name, "Ship it?", TraceCollector::new())
+                .await
+                .unwrap();
+            assert_eq!(result.into_response().unwrap().content, "traced: true", "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_debate_judging_and_fallback_run_in_the_trace_scope() {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let user = AgentBuilder::new("User")
+            .blocking_connection("Judge")
+            .blocking_connection("Fallback")
+            .build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system
+            .register_agent(AgentBuilder::new("Pro").build(), Arc::new(EchoHandler))
+            .await
+            .unwrap();
+
+        // Judge hears Pro's argument, Fallback none from Missing
+        for (name, participant) in [("Judge", "Pro"), ("Fallback", "Missing")] {
+            let probe = AgentBuilder::new(name).blocking_connection(participant).build();
+            let decision = HandlerDecision::debate(vec![participant.to_string()], 1);
+            AgentSystem::register_routing_agent(system.clone(), probe, Arc::new(TraceProbe(decision)))
+                .await
+                .unwrap();
+
+            let result = system
+                .send_message_with_trace("User", name, "Tabs or spaces?", TraceCollector::new())
+                .await
+                .unwrap();
+            assert_eq!(result.into_response().unwrap().content, "traced: true", "{}", name);
+        }
+    }
+
+    struct DebateHandler {
+        participants: Vec<String>,
+        rounds: u16,
+    }
+
+    #[async_trait]
+    impl RoutingHandler for DebateHandler {
+        async fn handle(&self, _message: &Message, _agent: &Agent) -> HandlerDecision {
+            HandlerDecision::debate(self.participants.clone(), self.rounds)
+        }
+
+        async fn synthesize(
+            &self,
+            _original_message: &Message,
+            _outcomes: &[ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<String> {
+            None
+        }
+
+        async fn judge_debate(
+            &self,
+            _original_message: &Message,
+            turns: &[ConversationTurn],
+            _agent: &Agent,
+        ) -> Option<String> {
+            Some(format!("{} arguments", turns.len()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_debate_participants_see_previous_rounds() {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let user = AgentBuilder::new("User").blocking_connection("Judge").build();
+        let judge = AgentBuilder::new("Judge")
+            .blocking_connection("Pro")
+            .blocking_connection("Contra")
+            .build();
+
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        for name in ["Pro", "Contra"] {
+            system
+                .register_agent(AgentBuilder::new(name).build(), Arc::new(EchoHandler))
+                .await
+                .unwrap();
+        }
+        AgentSystem::register_routing_agent(
+            system.clone(),
+            judge,
+            Arc::new(DebateHandler {
+                participants: vec!["Pro".to_string(), "Contra".to_string()],
+                rounds: 2,
+            }),
+        )
+        .await
+        .unwrap();
+
+        let trace = TraceCollector::new();
+        let result = system
+            .send_message_with_trace("User", "Judge", "Tabs or spaces?", trace.clone())
+            .await
+            .unwrap();
+        assert_eq!(result.into_response().unwrap().content, "4 arguments");
+
+        // In round 2, Pro's prompt quotes Contra's round-1 argument
+        let second_round_to_pro = trace
+            .events()
+            .await
+            .into_iter()
+            .filter(|e| e.event_type == crate::tracer::TraceEventType::Forward && e.to == "Pro")
+            .nth(1)
+            .unwrap();
+        assert!(second_round_to_pro.content.contains("(round 2 of 2)"));
+        assert!(second_round_to_pro.content.contains("[Round 1] Contra: Echo: Question: Tabs or spaces?"));
+    }
+
+    /// Routing handler that plans a step on a missing agent, then routes around it
+    struct PlanHandler;
+
+    #[async_trait]
+    impl RoutingHandler for PlanHandler {
+        async fn handle(&self, _message: &Message, _agent: &Agent) -> HandlerDecision {
+            HandlerDecision::plan(vec![
+                crate::plan::PlanStep::new("Search", "Find flights"),
+                crate::plan::PlanStep::new("Broken", "Book a flight"),
+            ])
+        }
+
+        async fn synthesize(
+            &self,
+            _original_message: &Message,
+            outcomes: &[ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<String> {
+            Some(
+                outcomes
+                    .iter()
+                    .map(|o| format!("{}={}", o.agent, o.response().unwrap_or("failed")))
+                    .collect::<Vec<_>>()
+                    .join(" | "),
+            )
+        }
+
+        fn max_replans(&self) -> u16 {
+            1
+        }
+
+        async fn replan(
+            &self,
+            _original_message: &Message,
+            plan: &Plan,
+            outcomes: &[ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<Plan> {
+            assert_eq!(plan.revision, 0);
+            assert!(outcomes[0].is_success());
+            assert_eq!(outcomes[1].agent, "Broken");
+            Some(Plan::new(vec![crate::plan::PlanStep::new("Booker", "Book a flight")]))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_replans_around_failed_step() {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let user = AgentBuilder::new("User").blocking_connection("Planner").build();
+        let planner = AgentBuilder::new("Planner")
+            .blocking_connection("Search")
+            .blocking_connection("Broken")
+            .blocking_connection("Booker")
+            .build();
+
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        for name in ["Search", "Booker"] {
+            system
+                .register_agent(AgentBuilder::new(name).build(), Arc::new(EchoHandler))
+                .await
+                .unwrap();
+        }
+        AgentSystem::register_routing_agent(system.clone(), planner, Arc::new(PlanHandler))
+            .await
+            .unwrap();
+
+        let trace = TraceCollector::new();
+        let result = system
+            .send_message_with_trace("User", "Planner", "Fly to Rome", trace.clone())
+            .await
+            .unwrap();
+        let content = result.into_response().unwrap().content;
+        assert!(content.starts_with("Search=Echo: Find flights | Booker=Echo: Book a flight"), "{}", content);
+        // The booking step sees the search result
+        assert!(content.contains("[Search]: Echo: Find flights"));
+
+        let events = trace.events().await;
+        let plans: Vec<Plan> = events
+            .iter()
+            .filter(|e| e.event_type == crate::tracer::TraceEventType::Plan)
+            .map(|e| serde_json::from_str(&e.content).unwrap())
+            .collect();
+        assert_eq!(plans.len(), 2);
+        assert_eq!(plans[1].revision, 1);
+        assert_eq!(Plan::latest(&events, "Planner"), Some(plans[1].clone()));
+
+        let steps: Vec<serde_json::Value> = events
+            .iter()
+            .filter(|e| e.event_type == crate::tracer::TraceEventType::Step)
+            .map(|e| serde_json::from_str(&e.content).unwrap())
+            .collect();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[1]["status"], "failed");
+        assert_eq!(steps[2]["plan_revision"], 1);
+        assert_eq!(steps[2]["status"], "completed");
+    }
+
+    /// Routing handler that hands each document to its own sub-agent
+    struct SpawnHandler;
+
+    #[async_trait]
+    impl RoutingHandler for SpawnHandler {
+        async fn handle(&self, _message: &Message, _agent: &Agent) -> HandlerDecision {
+            HandlerDecision::spawn(vec![
+                SpawnSpec::new("Summarizer", "Summarize", "Doc 1")
+                    .with_connections(vec!["Search".to_string(), "Missing".to_string()]),
+                SpawnSpec::new("Summarizer", "Summarize", "Doc 2"),
+                SpawnSpec::new("Summarizer", "Summarize", "Doc 3"),
+            ])
+        }
+
+        async fn synthesize(
+            &self,
+            _original_message: &Message,
+            outcomes: &[ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<String> {
+            Some(
+                outcomes
+                    .iter()
+                    .map(|o| o.response().map_or_else(|| o.failure_reason().unwrap_or_default(), String::from))
+                    .collect::<Vec<_>>()
+                    .join(" | "),
+            )
+        }
+
+        fn spawn_handler(&self, _spec: &SpawnSpec) -> Option<Arc<dyn RoutingHandler>> {
+            Some(Arc::new(SubAgentHandler))
+        }
+    }
+
+    /// Sub-agent that answers with its prompt, task and connections
+    struct SubAgentHandler;
+
+    #[async_trait]
+    impl RoutingHandler for SubAgentHandler {
+        async fn handle(&self, message: &Message, agent: &Agent) -> HandlerDecision {
+            let connections: Vec<&str> = agent.connections.keys().map(String::as_str).collect();
+            HandlerDecision::response(format!("{} {} {:?}", agent.system_prompt, message.content, connections))
+        }
+
+        async fn synthesize(
+            &self,
+            _original_message: &Message,
+            _outcomes: &[ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<String> {
+            None
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawned_sub_agents_answer_and_are_unregistered() {
+        let system = Arc::new(AgentSystem::new(SystemConfig::default().with_spawn_limit(2)));
+        let user = AgentBuilder::new("User").blocking_connection("Coordinator").build();
+        let coordinator = AgentBuilder::new("Coordinator").blocking_connection("Search").build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        AgentSystem::register_routing_agent(system.clone(), coordinator, Arc::new(SpawnHandler))
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            let trace = TraceCollector::new();
+            let result = system
+                .send_message_with_trace("User", "Coordinator", "Summarize the documents", trace.clone())
+                .await
+                .unwrap();
+            assert_eq!(
+                result.into_response().unwrap().content,
+                "Summarize Doc 1 [\"Search\"] | Summarize Doc 2 [] | failed: spawn limit of 2 sub-agents reached"
+            );
+
+            let spawned: Vec<_> = trace
+                .events()
+                .await
+                .into_iter()
+                .filter(|e| e.event_type == crate::tracer::TraceEventType::Spawn)
+                .collect();
+            assert_eq!(spawned.len(), 2);
+            assert!(spawned[0].to.starts_with("Coordinator~Summarizer~"));
+            for event in &spawned {
+                assert!(system.get_agent(&event.to).await.is_none());
+            }
+            assert_eq!(system.spawned.load(Ordering::SeqCst), 0);
+        }
+    }
+
+    /// Sub-agent that never answers in time
+    struct StalledSubAgentHandler;
+
+    #[async_trait]
+    impl RoutingHandler for StalledSubAgentHandler {
+        async fn handle(&self, _message: &Message, _agent: &Agent) -> HandlerDecision {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            HandlerDecision::response("too late")
+        }
+
+        async fn synthesize(
+            &self,
+            _original_message: &Message,
+            _outcomes: &[ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<String> {
+            None
+        }
+    }
+
+    /// Routing handler whose sub-agents stall
+    struct StalledSpawnHandler;
+
+    #[async_trait]
+    impl RoutingHandler for StalledSpawnHandler {
+        async fn handle(&self, _message: &Message, _agent: &Agent) -> HandlerDecision {
+            HandlerDecision::spawn(vec![SpawnSpec::new("Summarizer", "Summarize", "Doc 1")])
+        }
+
+        async fn synthesize(
+            &self,
+            _original_message: &Message,
+            _outcomes: &[ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<String> {
+            None
+        }
+
+        fn spawn_handler(&self, _spec: &SpawnSpec) -> Option<Arc<dyn RoutingHandler>> {
+            Some(Arc::new(StalledSubAgentHandler))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_spawn_unregisters_sub_agents() {
+        let system = Arc::new(AgentSystem::new(SystemConfig::default().with_spawn_limit(2)));
+        let coordinator = AgentBuilder::new("Coordinator").build();
+        system.register_agent(coordinator.clone(), Arc::new(EchoHandler)).await.unwrap();
+        let handler: Arc<dyn RoutingHandler> = Arc::new(StalledSpawnHandler);
+        let message = Message::new("User", "Coordinator", "Summarize the documents");
+        let specs = vec![
+            SpawnSpec::new("Summarizer", "Summarize", "Doc 1"),
+            SpawnSpec::new("Summarizer", "Summarize", "Doc 2"),
+        ];
+
+        let spawn = AgentSystem::run_spawn(&system, &handler, &coordinator, &message, specs, None, None);
+        assert!(tokio::time::timeout(Duration::from_millis(100), spawn).await.is_err());
+
+        assert_eq!(system.spawned.load(Ordering::SeqCst), 0);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let agents: Vec<String> = system.agents.read().await.keys().cloned().collect();
+        assert_eq!(agents, vec!["Coordinator".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_system_guardrails_block_requests_and_redact_responses() {
+        let config: crate::guardrail::GuardrailConfig = serde_json::from_value(serde_json::json!({
+            "input": { "deny": ["(?i)drop table"] },
+            "output": { "pii": ["email"] },
+            "blocked_response": "Not allowed."
+        }))
+        .unwrap();
+        let guardrails = crate::guardrail::Guardrails::compile(&config, |_| None).unwrap();
+        let system = AgentSystem::new(SystemConfig::default().with_guardrails(Arc::new(guardrails)));
+        let user = AgentBuilder::new("User").blocking_connection("Echo").build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system.register_agent(AgentBuilder::new("Echo").build(), Arc::new(EchoHandler)).await.unwrap();
+
+        let trace = TraceCollector::new();
+        let result = system
+            .send_message_with_trace("User", "Echo", "Write to bob@example.com", trace.clone())
+            .await
+            .unwrap();
+        assert_eq!(result.into_response().unwrap().content, "Echo: Write to [EMAIL]");
+
+        let result = system.send_message("User", "Echo", "DROP TABLE users").await.unwrap();
+        assert_eq!(result.into_response().unwrap().content, "Not allowed.");
+
+        let violations = crate::guardrail::GuardrailViolation::from_events(&trace.events().await);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].stage, GuardrailStage::Output);
+        assert_eq!(violations[0].detail, "1 email");
+    }
+
+    /// Interceptor that logs every hook call and applies a few fixed rules
+    struct Recorder {
+        name: &'static str,
+        log: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Interceptor for Recorder {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn before_send(&self, mut message: Message) -> Interception {
+            self.log.lock().unwrap().push(format!("{} before {}->{}", self.name, message.from, message.to));
+            match (self.name, message.to.as_str(), message.content.as_str()) {
+                ("audit", "Billing", _) => Interception::Reject("billing is closed".to_string()),
+                (_, _, "ping") => Interception::Respond("pong".to_string()),
+                ("redact", _, _) => {
+                    message.content = message.content.replace("secret", "***");
+                    Interception::Continue(message)
+                }
+                _ => Interception::Continue(message),
+            }
+        }
+
+        async fn after_receive(&self, request: &Message, mut response: Message) -> Interception {
+            self.log.lock().unwrap().push(format!("{} after {}", self.name, request.to));
+            response.content = format!("{} [{}]", response.content, self.name);
+            Interception::Continue(response)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interceptors_modify_short_circuit_and_reject_in_order() {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let log = Arc::new(std::sync::Mutex::new(Vec::new()));
+        for name in ["redact", "audit"] {
+            system
+                .register_interceptor(Arc::new(Recorder { name, log: log.clone() }))
+                .await;
+        }
+        let user = AgentBuilder::new("User")
+            .blocking_connection("Echo")
+            .blocking_connection("Billing")
+            .blocking_connection("Coordinator")
+            .build();
+        let coordinator = AgentBuilder::new("Coordinator")
+            .blocking_connection("Echo")
+            .blocking_connection("Billing")
+            .build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        for name in ["Echo", "Billing"] {
+            system
+                .register_agent(AgentBuilder::new(name).build(), Arc::new(EchoHandler))
+                .await
+                .unwrap();
+        }
+        AgentSystem::register_routing_agent(
+            system.clone(),
+            coordinator,
+            Arc::new(FanOutHandler { targets: vec!["Echo", "Billing"], quorum: None }),
+        )
+        .await
+        .unwrap();
+
+        let result = system.send_message("User", "Echo", "my secret").await.unwrap();
+        assert_eq!(result.into_response().unwrap().content, "Echo: my *** [redact] [audit]");
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["redact before User->Echo", "audit before User->Echo", "redact after Echo", "audit after Echo"]
+        );
+
+        // Answered by the first interceptor: the second never sees it
+        log.lock().unwrap().clear();
+        let result = system
+            .send_message_with_trace("User", "Echo", "ping", TraceCollector::new())
+            .await
+            .unwrap();
+        assert_eq!(result.into_response().unwrap().content, "pong");
+        assert_eq!(*log.lock().unwrap(), vec!["redact before User->Echo"]);
+
+        let err = system.send_message("User", "Billing", "invoice").await.unwrap_err();
+        assert!(matches!(err, AgentError::Intercepted { ref interceptor, .. } if interceptor == "audit"));
+
+        // Forwards between agents go through the chain as well
+        let result = system.send_message("User", "Coordinator", "hello").await.unwrap();
+        assert_eq!(
+            result.into_response().unwrap().content,
+            "Echo=Echo: hello [redact] [audit],Billing=blocked [redact] [audit]"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interceptor_answers_pass_the_output_guardrails() {
+        let config: crate::guardrail::GuardrailConfig =
+            serde_json::from_value(serde_json::json!({ "output": { "disclaimer": "Not advice." } })).unwrap();
+        let guardrails = crate::guardrail::Guardrails::compile(&config, |_| None).unwrap();
+        let system = AgentSystem::new(SystemConfig::default().with_guardrails(Arc::new(guardrails)));
+        let log = Arc::new(std::sync::Mutex::new(Vec::new()));
+        system.register_interceptor(Arc::new(Recorder { name: "redact", log })).await;
+        let user = AgentBuilder::new("User").blocking_connection("Echo").build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system.register_agent(AgentBuilder::new("Echo").build(), Arc::new(EchoHandler)).await.unwrap();
+
+        let result = system.send_message("User", "Echo", "ping").await.unwrap();
+        assert_eq!(result.into_response().unwrap().content, "pong\n\nNot advice.");
+
+        let result = system
+            .send_message_with_trace("User", "Echo", "ping", TraceCollector::new())
+            .await
+            .unwrap();
+        assert_eq!(result.into_response().unwrap().content, "pong\n\nNot advice.");
+    }
+
+    /// Interceptor that registers an agent when a message goes to Echo
+    struct Registering {
+        system: std::sync::OnceLock<Arc<AgentSystem>>,
+    }
+
+    #[async_trait]
+    impl Interceptor for Registering {
+        fn name(&self) -> &str {
+            "registering"
+        }
+
+        async fn before_send(&self, message: Message) -> Interception {
+            let system = self.system.get().expect("system set");
+            if message.to == "Echo" && system.get_agent("Auditor").await.is_none() {
+                system
+                    .register_agent(AgentBuilder::new("Auditor").build(), Arc::new(EchoHandler))
+                    .await
+                    .unwrap();
+            }
+            Interception::Continue(message)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interceptors_may_register_agents_on_forwards() {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let interceptor = Arc::new(Registering { system: std::sync::OnceLock::new() });
+        let _ = interceptor.system.set(system.clone());
+        let user = AgentBuilder::new("User").blocking_connection("Coordinator").build();
+        let coordinator = AgentBuilder::new("Coordinator").blocking_connection("Echo").build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system
+            .register_agent(AgentBuilder::new("Echo").build(), Arc::new(EchoHandler))
+            .await
+            .unwrap();
+        AgentSystem::register_routing_agent(
+            system.clone(),
+            coordinator,
+            Arc::new(FanOutHandler { targets: vec!["Echo"], quorum: None }),
+        )
+        .await
+        .unwrap();
+        system.register_interceptor(interceptor).await;
+
+        // The forward from Coordinator to Echo is intercepted while no
+        // registry is locked
+        let result = timeout(Duration::from_secs(2), system.send_message("User", "Coordinator", "hi"))
+            .await
+            .expect("interceptor deadlocked on the registry")
+            .unwrap();
+        assert_eq!(result.into_response().unwrap().content, "Echo=Echo: hi");
+        assert!(system.get_agent("Auditor").await.is_some());
+    }
+
+    /// Message handler that panics on every message
+    struct Panicking;
+
+    #[async_trait]
+    impl MessageHandler for Panicking {
+        async fn handle(&self, _message: &Message, _agent: &Agent) -> Option<String> {
+            panic!("handler bug")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_event_bus_reports_lifecycle_of_agents_and_messages() {
+        let system = AgentSystem::with_default_config();
+        let mut events = system.events().subscribe();
+        let user = AgentBuilder::new("User")
+            .blocking_connection("Echo")
+            .blocking_connection("Broken")
+            .build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system.register_agent(AgentBuilder::new("Echo").build(), Arc::new(EchoHandler)).await.unwrap();
+        system.register_agent(AgentBuilder::new("Broken").build(), Arc::new(Panicking)).await.unwrap();
+
+        system.send_message("User", "Echo", "hi").await.unwrap();
+        let result = system.send_message("User", "Broken", "hi").await.unwrap();
+        assert!(matches!(result, SendResult::Timeout(_)));
+        assert!(system.unregister_agent("Echo").await);
+
+        let mut kinds = Vec::new();
+        while kinds.len() < 9 {
+            let event = timeout(Duration::from_secs(1), events.recv()).await.unwrap().unwrap();
+            kinds.push(match event.kind {
+                SystemEventKind::AgentRegistered { agent } => format!("registered {}", agent),
+                SystemEventKind::AgentUnregistered { agent } => format!("unregistered {}", agent),
+                SystemEventKind::MessageSent { from, to, .. } => format!("sent {}->{}", from, to),
+                SystemEventKind::MessageReceived { from, to, .. } => format!("received {}->{}", from, to),
+                SystemEventKind::Timeout { to, .. } => format!("timeout {}", to),
+                SystemEventKind::Panic { agent, message } => format!("panic {}: {}", agent, message),
+                other => format!("{:?}", other),
+            });
+        }
+        // The panic is reported by a watcher task, so it may come a bit later
+        let panic = kinds.iter().position(|k| k == "panic Broken: handler bug").unwrap();
+        kinds.remove(panic);
+        assert_eq!(
+            kinds,
+            vec![
+                "registered User",
+                "registered Echo",
+                "registered Broken",
+                "sent User->Echo",
+                "received Echo->User",
+                "sent User->Broken",
+                "timeout Broken",
+                "unregistered Echo",
+            ]
+        );
+    }
+
+    /// Message handler that answers with the caller's user id from the message metadata
+    struct WhoAmI;
+
+    #[async_trait]
+    impl MessageHandler for WhoAmI {
+        async fn handle(&self, message: &Message, _agent: &Agent) -> Option<String> {
+            Some(format!("user {}", message.metadata.get("user_id").map_or("?", |u| u.as_str())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connection_guard_blocks_forward() {
+        use crate::connection::Connection;
+        use crate::guard::{Guard, GuardConfig};
+
+        let system = Arc::new(AgentSystem::with_default_config());
+        let guard = Guard::compile(&GuardConfig {
+            metadata: HashMap::from([("authenticated".to_string(), "true".to_string())]),
+            ..Default::default()
+        })
+        .unwrap();
+        let user = AgentBuilder::new("User").blocking_connection("Coordinator").build();
+        let coordinator = AgentBuilder::new("Coordinator")
+            .blocking_connection("Support")
+            .connection("Billing", Connection::blocking(None).with_guard(guard))
+            .build();
+
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system
+            .register_agent(AgentBuilder::new("Support").build(), Arc::new(EchoHandler))
+            .await
+            .unwrap();
+        system
+            .register_agent(AgentBuilder::new("Billing").build(), Arc::new(WhoAmI))
+            .await
+            .unwrap();
+        AgentSystem::register_routing_agent(
+            system.clone(),
+            coordinator,
+            Arc::new(FanOutHandler { targets: vec!["Support", "Billing"], quorum: None }),
+        )
+        .await
+        .unwrap();
+
+        let anonymous = system
+            .send_message_with_trace("User", "Coordinator", "Invoice?", TraceCollector::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            anonymous.into_response().unwrap().content,
+            "Support=Echo: Invoice?,Billing=blocked"
+        );
+
+        let trace = TraceCollector::new()
+            .with_metadata("authenticated", "true")
+            .with_metadata("user_id", "u-42");
+        let authenticated = system
+            .send_message_with_trace("User", "Coordinator", "Invoice?", trace)
+            .await
+            .unwrap();
+        assert_eq!(
+            authenticated.into_response().unwrap().content,
+            "Support=Echo: Invoice?,Billing=user u-42"
+        );
+    }
+
+    /// Message handler that answers with a fixed replica label
+    struct ReplicaLabel(&'static str);
+
+    #[async_trait]
+    impl MessageHandler for ReplicaLabel {
+        async fn handle(&self, _message: &Message, _agent: &Agent) -> Option<String> {
+            Some(self.0.to_string())
+        }
+    }
+
+    async fn replica_system(dispatch: ReplicaDispatch) -> AgentSystem {
+        let system = AgentSystem::with_default_config();
+        let user = AgentBuilder::new("User").blocking_connection("Worker").build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system
+            .register_agent_replicas(
+                AgentBuilder::new("Worker").build(),
+                vec![
+                    Arc::new(ReplicaLabel("a")),
+                    Arc::new(ReplicaLabel("b")),
+                    Arc::new(ReplicaLabel("c")),
+                ],
+                dispatch,
+            )
+            .await
+            .unwrap();
+        system
+    }
+
+    async fn answer(system: &AgentSystem, trace: TraceCollector) -> String {
+        system
+            .send_message_with_trace("User", "Worker", "work", trace)
+            .await
+            .unwrap()
+            .into_response()
+            .unwrap()
+            .content
+    }
+
+    #[tokio::test]
+    async fn test_replicas_round_robin() {
+        let system = replica_system(ReplicaDispatch::RoundRobin).await;
+        let mut answers = Vec::new();
+        for _ in 0..4 {
+            answers.push(answer(&system, TraceCollector::new()).await);
+        }
+        assert_eq!(answers, vec!["a", "b", "c", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_replicas_session_hash_is_sticky() {
+        let system = replica_system(ReplicaDispatch::SessionHash).await;
+        let first = answer(&system, TraceCollector::new().with_session_id("s-1")).await;
+        for _ in 0..3 {
+            let again = answer(&system, TraceCollector::new().with_session_id("s-1")).await;
+            assert_eq!(again, first);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_agent_replicas_requires_a_handler() {
+        let system = AgentSystem::with_default_config();
+        let result = system
+            .register_agent_replicas(AgentBuilder::new("Worker").build(), Vec::new(), ReplicaDispatch::default())
+            .await;
+        assert!(result.is_err());
+        assert!(system.get_agent("Worker").await.is_none());
+    }
+
+    /// Holds calls saying "hold" until released, answers the others at once
+    struct GateHandler {
+        release: Arc<tokio::sync::Notify>,
+    }
+
+    #[async_trait]
+    impl MessageHandler for GateHandler {
+        async fn handle(&self, message: &Message, _agent: &Agent) -> Option<String> {
+            if message.content == "hold" {
+                self.release.notified().await;
+            }
+            Some(format!("done: {}", message.content))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_held_tool_call_does_not_block_other_calls() {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let user = AgentBuilder::new("User").blocking_connection("Search").build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        let release = Arc::new(tokio::sync::Notify::new());
+        let tool = Arc::new(Tool::new(crate::tool::ToolConfig::new(
+            "Search",
+            "Searches the web",
+            crate::tool::ToolEndpoint::get("http://localhost/search"),
+        )));
+        system
+            .register_tool(tool, Arc::new(GateHandler { release: release.clone() }))
+            .await
+            .unwrap();
+
+        let held = tokio::spawn({
+            let system = system.clone();
+            async move { system.send_message("User", "Search", "hold").await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The second call is answered while the first one is still held
+        let quick = timeout(Duration::from_secs(1), system.send_message("User", "Search", "quick"))
+            .await
+            .expect("second call blocked by the held one")
+            .unwrap();
+        assert_eq!(quick.into_response().unwrap().content, "done: quick");
+
+        release.notify_one();
+        let held = held.await.unwrap().unwrap();
+        assert_eq!(held.into_response().unwrap().content, "done: hold");
+    }
+
+    async fn workflow_system(steps: &str) -> Arc<AgentSystem> {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let user = AgentBuilder::new("User").blocking_connection("Flow").build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        for name in ["First", "Second", "Third"] {
+            system
+                .register_agent(AgentBuilder::new(name).build(), Arc::new(EchoHandler))
+                .await
+                .unwrap();
+        }
+
+        let config: crate::workflow::WorkflowConfig =
+            serde_json::from_str(&format!(r#"{{ "name": "Flow", {} }}"#, steps)).unwrap();
+        AgentSystem::register_workflow(system.clone(), Workflow::compile(&config).unwrap())
+            .await
+            .unwrap();
+        system
+    }
+
+    #[tokio::test]
+    async fn test_workflow_runs_branches_and_conditions() {
+        let system = workflow_system(
+            r#""steps": [
+                { "id": "draft", "target": "First" },
+                { "id": "review", "target": "Second", "input": "Review ${steps.draft.output}", "depends_on": ["draft"] },
+                {
+                    "id": "escalate",
+                    "target": "Third",
+                    "depends_on": [{ "step": "draft", "when": { "keywords": ["urgent"] } }]
+                }
+            ],
+            "output": "${steps.review.output} | ${steps.escalate.output}""#,
+        )
+        .await;
+
+        let trace = TraceCollector::new();
+        let result = system
+            .send_message_with_trace("User", "Flow", "report", trace.clone())
+            .await
+            .unwrap();
+        assert_eq!(result.into_response().unwrap().content, "Echo: Review Echo: report | ");
+
+        // Every step is traced, including the one whose condition did not hold
+        let steps: Vec<(String, String)> = trace
+            .events()
+            .await
+            .into_iter()
+            .filter(|e| e.event_type == crate::tracer::TraceEventType::Step)
+            .map(|e| {
+                let status: serde_json::Value = serde_json::from_str(&e.content).unwrap();
+                (status["step"].as_str().unwrap().to_string(), status["status"].as_str().unwrap().to_string())
+            })
+            .collect();
+        assert_eq!(steps.len(), 3);
+        assert!(steps.contains(&("draft".to_string(), "completed".to_string())));
+        assert!(steps.contains(&("review".to_string(), "completed".to_string())));
+        assert!(steps.contains(&("escalate".to_string(), "skipped".to_string())));
+
+        let result = system.send_message("User", "Flow", "urgent report").await.unwrap();
+        assert_eq!(
+            result.into_response().unwrap().content,
+            "Echo: Review Echo: urgent report | Echo: urgent report"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_workflow_steps_without_target_pass_outputs_on_their_branch() {
+        let system = workflow_system(
+            r#""steps": [
+                { "id": "draft", "target": "First", "input": "Draft ${input}" },
+                { "id": "check", "target": "Second" },
+                {
+                    "id": "release",
+                    "input": "${steps.draft.output}",
+                    "depends_on": ["draft", { "step": "check", "when": { "keywords": ["approved"] } }]
+                },
+                {
+                    "id": "correct",
+                    "input": "Corrected: ${steps.check.output}",
+                    "depends_on": [{ "step": "check", "when": { "expr": "!(content matches 'approved')" } }]
+                }
+            ]"#,
+        )
+        .await;
+
+        let result = system.send_message("User", "Flow", "approved").await.unwrap();
+        assert_eq!(result.into_response().unwrap().content, "Echo: Draft approved");
+
+        let result = system.send_message("User", "Flow", "rejected").await.unwrap();
+        assert_eq!(result.into_response().unwrap().content, "Corrected: Echo: rejected");
+    }
+
+    #[tokio::test]
+    async fn test_workflow_step_timeout_applies_to_its_target_only() {
+        let system = Arc::new(AgentSystem::new(SystemConfig::new(Duration::from_millis(100))));
+        let user = AgentBuilder::new("User").blocking_connection("Flow").build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system
+            .register_human_agent(AgentBuilder::new("Clinician").build(), Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        let config: crate::workflow::WorkflowConfig = serde_json::from_str(
+            r#"{
+                "name": "Flow",
+                "timeout_secs": 3,
+                "steps": [{ "id": "approve", "target": "Clinician", "timeout_secs": 2 }]
+            }"#,
+        )
+        .unwrap();
+        let workflow = Workflow::compile(&config).unwrap();
+        AgentSystem::register_workflow(system.clone(), workflow).await.unwrap();
+
+        // The clinician answers long after the global timeout has passed
+        let mut published = system.human_inbox().subscribe();
+        let asking = {
+            let system = system.clone();
+            tokio::spawn(async move { system.send_message("User", "Flow", "Discharge?").await })
+        };
+        let question = published.recv().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        system.human_inbox().answer(question.id, "Yes").unwrap();
+
+        let result = asking.await.unwrap().unwrap();
+        assert_eq!(result.into_response().unwrap().content, "Yes");
+    }
+
+    #[tokio::test]
+    async fn test_workflow_stops_at_failed_step() {
+        let system = workflow_system(
+            r#""steps": [
+                { "id": "lookup", "target": "Missing" },
+                { "id": "answer", "target": "First", "depends_on": ["lookup"] }
+            ]"#,
+        )
+        .await;
+        let result = system.send_message("User", "Flow", "hi").await.unwrap();
+        assert!(result
+            .into_response()
+            .unwrap()
+            .content
+            .starts_with("Workflow stopped at step lookup"));
+
+        // With continue_on_error the failure only closes the edges out of the step
+        let system = workflow_system(
+            r#""steps": [
+                { "id": "lookup", "target": "Missing", "continue_on_error": true },
+                { "id": "answer", "target": "First", "depends_on": ["lookup"] },
+                { "id": "fallback", "target": "Second" }
+            ]"#,
+        )
+        .await;
+        let result = system.send_message("User", "Flow", "hi").await.unwrap();
+        assert_eq!(result.into_response().unwrap().content, "Echo: hi");
+    }
+
+    #[tokio::test]
+    async fn test_human_agent_answers_through_inbox() {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let user = AgentBuilder::new("User").blocking_connection("Clinician").build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system
+            .register_human_agent(AgentBuilder::new("Clinician").build(), Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        let mut published = system.human_inbox().subscribe();
+        let asking = {
+            let system = system.clone();
+            let trace = TraceCollector::new().with_metadata("session_id", "s-1");
+            tokio::spawn(async move {
+                system
+                    .send_message_with_trace("User", "Clinician", "Discharge?", trace)
+                    .await
+            })
+        };
+
+        // A second question is not held up by the first one
+        let first = published.recv().await.unwrap();
+        let second = {
+            let system = system.clone();
+            tokio::spawn(async move { system.send_message("User", "Clinician", "Second?").await })
+        };
+        let other = published.recv().await.unwrap();
+        assert_eq!(other.question, "Second?");
+        system.human_inbox().answer(other.id, "No").unwrap();
+        assert_eq!(second.await.unwrap().unwrap().into_response().unwrap().content, "No");
+
+        assert_eq!(first.session_id.as_deref(), Some("s-1"));
+        system.human_inbox().answer(first.id, "Yes").unwrap();
+        let answer = asking.await.unwrap().unwrap().into_response().unwrap();
+        assert_eq!(answer.content, "Yes");
+        assert_eq!(answer.from, "Clinician");
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_human_agent_times_out() {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let user = AgentBuilder::new("User").blocking_connection("Clinician").build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system
+            .register_human_agent(AgentBuilder::new("Clinician").build(), Duration::from_millis(50))
+            .await
+            .unwrap();
+
+        let result = system.send_message("User", "Clinician", "Discharge?").await.unwrap();
+        match result {
+            SendResult::Timeout(AgentError::Timeout { agent, waited, .. }) => {
+                assert_eq!(agent, "Clinician");
+                assert!(waited < Duration::from_secs(1), "waited {:?}", waited);
+            }
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        assert!(system.human_inbox().pending(None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_human_question_is_withdrawn_when_the_caller_times_out() {
+        let system = Arc::new(AgentSystem::with_default_config());
+        let user = AgentBuilder::new("User")
+            .blocking_connection_with_timeout("Clinician", Duration::from_millis(50))
+            .build();
+        system.register_agent(user, Arc::new(EchoHandler)).await.unwrap();
+        system
+            .register_human_agent(AgentBuilder::new("Clinician").build(), Duration::from_secs(300))
+            .await
+            .unwrap();
+
+        let result = system.send_message("User", "Clinician", "Discharge?").await.unwrap();
+        assert!(matches!(result, SendResult::Timeout(_)));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(system.human_inbox().pending(None).is_empty());
+    }
+
+    #[test]
+    fn test_clamp_to_deadline() {
+        let parent = Instant::now() + Duration::from_secs(2);
+
+        let (clamped, deadline) = clamp_to_deadline(Duration::from_secs(60), Some(parent));
+        assert_eq!(deadline, parent);
+        assert!(clamped <= Duration::from_secs(2));
+
+        let (own, deadline) = clamp_to_deadline(Duration::from_millis(500), Some(parent));
+        assert!(own <= Duration::from_millis(500));
+        assert!(deadline < parent);
+    }
 }
@@ -0,0 +1,310 @@
+//! Approval gates for tool and database calls
+//!
+//! Tools and databases configured with `requires_approval` do not execute a
+//! call on their own. The rendered call (HTTP request or SQL query) is
+//! published as a [`PendingApproval`] in the system's [`ApprovalQueue`], and
+//! the handler waits until a person approves, denies or edits it. Edited
+//! calls run with the reviewer's input instead of the original one.
+//!
+//! Calls nobody decides on in time are not executed. A call whose requester
+//! stops waiting is withdrawn from the queue.
+
+use crate::message::Message;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, oneshot};
+use tokio::time::timeout;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// How long a gated call waits for a decision by default (5 minutes)
+pub const DEFAULT_APPROVAL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// What kind of call is waiting for approval
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalKind {
+    Tool,
+    Database,
+}
+
+/// A tool or database call waiting for a human decision
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingApproval {
+    /// Id used to decide on the call
+    pub id: Uuid,
+    pub kind: ApprovalKind,
+    /// The tool or database that would execute the call
+    pub target: String,
+    /// The agent requesting the call
+    pub from: String,
+    /// The message sent to the target (parameters or SQL); an edit replaces it
+    pub input: String,
+    /// The call as it would be executed (rendered HTTP request or SQL)
+    pub request: String,
+    /// Session the request belongs to, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// When the call is dropped if still undecided
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A reviewer's decision on a pending call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum ApprovalDecision {
+    /// Execute the call as rendered
+    Approve,
+    /// Do not execute the call
+    Deny {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+    /// Execute the call with this input instead (parameters or SQL)
+    Edit { input: String },
+}
+
+impl ApprovalDecision {
+    /// Short name of the decision ("approve", "deny" or "edit")
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApprovalDecision::Approve => "approve",
+            ApprovalDecision::Deny { .. } => "deny",
+            ApprovalDecision::Edit { .. } => "edit",
+        }
+    }
+}
+
+/// Errors from deciding on a pending call
+#[derive(Debug, thiserror::Error)]
+pub enum ApprovalError {
+    #[error("No pending approval with id {0}")]
+    NotFound(Uuid),
+}
+
+struct Waiting {
+    approval: PendingApproval,
+    decision_tx: oneshot::Sender<ApprovalDecision>,
+}
+
+type WaitingMap = Arc<Mutex<HashMap<Uuid, Waiting>>>;
+
+/// Calls waiting for approval, shared by all gated tools and databases of a system
+///
+/// Clones share the same calls and subscribers.
+#[derive(Clone)]
+pub struct ApprovalQueue {
+    waiting: WaitingMap,
+    published_tx: broadcast::Sender<PendingApproval>,
+}
+
+impl Default for ApprovalQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ApprovalQueue {
+    /// Create an empty queue
+    pub fn new() -> Self {
+        let (published_tx, _) = broadcast::channel(64);
+        Self {
+            waiting: Arc::new(Mutex::new(HashMap::new())),
+            published_tx,
+        }
+    }
+
+    /// Undecided calls, oldest first; with a session id only that session's
+    pub fn pending(&self, session_id: Option<&str>) -> Vec<PendingApproval> {
+        let waiting = self.waiting.lock().unwrap();
+        let mut approvals: Vec<PendingApproval> = waiting
+            .values()
+            .map(|w| &w.approval)
+            .filter(|a| session_id.is_none() || a.session_id.as_deref() == session_id)
+            .cloned()
+            .collect();
+        approvals.sort_by_key(|a| a.created_at);
+        approvals
+    }
+
+    /// Look up an undecided call
+    pub fn get(&self, id: Uuid) -> Option<PendingApproval> {
+        self.waiting.lock().unwrap().get(&id).map(|w| w.approval.clone())
+    }
+
+    /// Decide on a call, resuming the tool or database that holds it
+    pub fn decide(&self, id: Uuid, decision: ApprovalDecision) -> Result<PendingApproval, ApprovalError> {
+        let waiting = self
+            .waiting
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or(ApprovalError::NotFound(id))?;
+        // The requester may have given up already; the decision is then dropped
+        let _ = waiting.decision_tx.send(decision);
+        Ok(waiting.approval)
+    }
+
+    /// Subscribe to newly published calls
+    pub fn subscribe(&self) -> broadcast::Receiver<PendingApproval> {
+        self.published_tx.subscribe()
+    }
+
+    /// Publish a call and wait for its decision
+    ///
+    /// Returns `None` if nobody decided within `wait`. The call is withdrawn
+    /// when this returns or the future is dropped.
+    async fn review(&self, approval: PendingApproval, wait: Duration) -> Option<ApprovalDecision> {
+        let (decision_tx, decision_rx) = oneshot::channel();
+        let _withdraw = Withdraw {
+            waiting: self.waiting.clone(),
+            id: approval.id,
+        };
+        self.waiting.lock().unwrap().insert(
+            approval.id,
+            Waiting {
+                approval: approval.clone(),
+                decision_tx,
+            },
+        );
+        let _ = self.published_tx.send(approval);
+
+        timeout(wait, decision_rx).await.ok()?.ok()
+    }
+}
+
+/// Removes a call from the queue when its requester stops waiting
+struct Withdraw {
+    waiting: WaitingMap,
+    id: Uuid,
+}
+
+impl Drop for Withdraw {
+    fn drop(&mut self) {
+        if let Ok(mut waiting) = self.waiting.lock() {
+            waiting.remove(&self.id);
+        }
+    }
+}
+
+/// Approval step used by gated tool and database handlers
+pub(crate) struct ApprovalGate {
+    queue: ApprovalQueue,
+    timeout: Duration,
+}
+
+impl ApprovalGate {
+    pub(crate) fn new(queue: ApprovalQueue, timeout: Duration) -> Self {
+        Self { queue, timeout }
+    }
+
+    /// Hold a call for review; `None` if no decision arrived in time
+    pub(crate) async fn review(
+        &self,
+        kind: ApprovalKind,
+        target: &str,
+        message: &Message,
+        request: String,
+    ) -> Option<ApprovalDecision> {
+        let created_at = Utc::now();
+        let approval = PendingApproval {
+            id: Uuid::new_v4(),
+            kind,
+            target: target.to_string(),
+            from: message.from.clone(),
+            input: message.content.clone(),
+            request,
+            session_id: message.metadata.get("session_id").cloned(),
+            created_at,
+            expires_at: created_at
+                + chrono::Duration::from_std(self.timeout).unwrap_or(chrono::Duration::MAX),
+        };
+        let id = approval.id;
+
+        info!("[{}] Waiting for approval of call {} from {}", target, id, message.from);
+        let decision = self.queue.review(approval, self.timeout).await;
+        match &decision {
+            Some(decision) => info!("[{}] Call {}: {}", target, id, decision.as_str()),
+            None => warn!("[{}] No decision on call {} within {:?}", target, id, self.timeout),
+        }
+        decision
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gate(queue: &ApprovalQueue, wait: Duration) -> ApprovalGate {
+        ApprovalGate::new(queue.clone(), wait)
+    }
+
+    fn call(session_id: &str) -> Message {
+        let mut metadata = HashMap::new();
+        metadata.insert("session_id".to_string(), session_id.to_string());
+        Message::new("Ops", "Tickets", r#"{"id": 7}"#).with_metadata(metadata)
+    }
+
+    #[tokio::test]
+    async fn test_decision_resumes_review() {
+        let queue = ApprovalQueue::new();
+        let mut published = queue.subscribe();
+        let gate = gate(&queue, Duration::from_secs(5));
+
+        let waiting = tokio::spawn(async move {
+            gate.review(ApprovalKind::Tool, "Tickets", &call("s-1"), "DELETE /tickets/7".to_string())
+                .await
+        });
+
+        let approval = published.recv().await.unwrap();
+        assert_eq!(approval.request, "DELETE /tickets/7");
+        assert_eq!(approval.input, r#"{"id": 7}"#);
+        assert_eq!(queue.pending(Some("s-1")).len(), 1);
+        assert!(queue.pending(Some("s-2")).is_empty());
+
+        let edit = ApprovalDecision::Edit { input: r#"{"id": 8}"#.to_string() };
+        queue.decide(approval.id, edit.clone()).unwrap();
+        assert_eq!(waiting.await.unwrap(), Some(edit));
+        assert!(queue.pending(None).is_empty());
+        assert!(matches!(
+            queue.decide(approval.id, ApprovalDecision::Approve),
+            Err(ApprovalError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_undecided_call_is_withdrawn() {
+        let queue = ApprovalQueue::new();
+
+        let timed_out = gate(&queue, Duration::from_millis(20));
+        let decision = timed_out
+            .review(ApprovalKind::Database, "AppDB", &call("s-1"), "DROP TABLE t".to_string())
+            .await;
+        assert_eq!(decision, None);
+        assert!(queue.pending(None).is_empty());
+
+        // A requester that stops waiting takes its call out of the queue
+        let abandoned = gate(&queue, Duration::from_secs(5));
+        let message = call("s-1");
+        let review = abandoned.review(ApprovalKind::Database, "AppDB", &message, "DROP TABLE t".to_string());
+        assert!(tokio::time::timeout(Duration::from_millis(20), review).await.is_err());
+        assert!(queue.pending(None).is_empty());
+    }
+
+    #[test]
+    fn test_parse_decisions() {
+        let approve: ApprovalDecision = serde_json::from_str(r#"{"decision": "approve"}"#).unwrap();
+        assert_eq!(approve, ApprovalDecision::Approve);
+
+        let deny: ApprovalDecision = serde_json::from_str(r#"{"decision": "deny"}"#).unwrap();
+        assert_eq!(deny, ApprovalDecision::Deny { reason: None });
+
+        let edit: ApprovalDecision =
+            serde_json::from_str(r#"{"decision": "edit", "input": "SELECT 1"}"#).unwrap();
+        assert_eq!(edit, ApprovalDecision::Edit { input: "SELECT 1".to_string() });
+    }
+}
@@ -0,0 +1,130 @@
+//! Per-request shared state
+//!
+//! A blackboard is a JSON key-value store created for each request and shared
+//! by every message sent while handling it. A triage agent can write
+//! `patient.severity` once; downstream agents read it from the message they
+//! receive and tools read it through `${blackboard.patient.severity}`
+//! placeholders.
+//!
+//! Keys are dotted paths into nested objects, so `patient.severity` and
+//! `patient.age` both live under the `patient` object.
+
+use serde_json::{Map, Value};
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Default)]
+struct State {
+    values: Map<String, Value>,
+    version: u64,
+}
+
+/// Shared key-value store for a single request
+///
+/// Clones share the same underlying storage.
+#[derive(Debug, Clone, Default)]
+pub struct Blackboard {
+    state: Arc<RwLock<State>>,
+}
+
+impl Blackboard {
+    /// Create an empty blackboard
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the value at a dotted path
+    pub fn get(&self, path: &str) -> Option<Value> {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        let mut segments = path.split('.');
+        let mut current = state.values.get(segments.next()?)?;
+        for segment in segments {
+            current = current.as_object()?.get(segment)?;
+        }
+        Some(current.clone())
+    }
+
+    /// Write a value at a dotted path
+    ///
+    /// Missing intermediate objects are created; a non-object value in the way
+    /// is replaced by one.
+    pub fn set(&self, path: &str, value: Value) {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        let mut segments: Vec<&str> = path.split('.').collect();
+        let last = segments.pop().unwrap_or_default();
+        let mut current = &mut state.values;
+        for segment in segments {
+            let entry = current
+                .entry(segment)
+                .or_insert_with(|| Value::Object(Map::new()));
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+            }
+            current = entry.as_object_mut().expect("entry was just made an object");
+        }
+        current.insert(last.to_string(), value);
+        state.version += 1;
+    }
+
+    /// Read the value at a dotted path as text, for template substitution
+    ///
+    /// Strings are returned as-is, other values as JSON.
+    pub fn get_text(&self, path: &str) -> Option<String> {
+        self.get(path).map(|value| match value {
+            Value::String(s) => s,
+            other => other.to_string(),
+        })
+    }
+
+    /// All values as a JSON object
+    pub fn snapshot(&self) -> Value {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        Value::Object(state.values.clone())
+    }
+
+    /// Whether nothing has been written yet
+    pub fn is_empty(&self) -> bool {
+        self.state.read().unwrap_or_else(|e| e.into_inner()).values.is_empty()
+    }
+
+    /// Number of writes so far; changes whenever a value is written
+    pub fn version(&self) -> u64 {
+        self.state.read().unwrap_or_else(|e| e.into_inner()).version
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_dotted_paths_nest_objects() {
+        let board = Blackboard::new();
+        board.set("patient.severity", json!("high"));
+        board.set("patient.age", json!(54));
+
+        assert_eq!(board.get("patient.severity"), Some(json!("high")));
+        assert_eq!(board.get("patient"), Some(json!({"severity": "high", "age": 54})));
+        assert_eq!(board.get("patient.name"), None);
+        assert_eq!(board.get_text("patient.age").as_deref(), Some("54"));
+        assert_eq!(board.get_text("patient.severity").as_deref(), Some("high"));
+
+        // A scalar in the way is replaced by an object
+        board.set("patient.severity.score", json!(3));
+        assert_eq!(board.get("patient.severity"), Some(json!({"score": 3})));
+    }
+
+    #[test]
+    fn test_clones_share_state_and_version() {
+        let board = Blackboard::new();
+        assert!(board.is_empty());
+        assert_eq!(board.version(), 0);
+
+        let shared = board.clone();
+        shared.set("ticket", json!("T-1"));
+
+        assert!(!board.is_empty());
+        assert_eq!(board.version(), 1);
+        assert_eq!(board.snapshot(), json!({"ticket": "T-1"}));
+    }
+}
@@ -0,0 +1,144 @@
+//! Per-target circuit breaker for forwarded requests
+//!
+//! After a target fails (times out or errors) a number of times in a row, its
+//! circuit opens and forwards to it are skipped until the cooldown elapses.
+//! The next forward after the cooldown is let through as a probe while the
+//! circuit stays open for all others: success closes the circuit, another
+//! failure re-opens it. A probe that settles neither way (it is cancelled or
+//! blocked) gives way to a new probe after another cooldown.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Circuit breaker settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures before the circuit opens
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before a probe is allowed
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct TargetState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Tracks failures per target and decides whether a forward may be attempted
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    targets: Mutex<HashMap<String, TargetState>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            targets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether forwards to `target` should currently be skipped
+    ///
+    /// Once the cooldown has elapsed, the first caller is let through as the
+    /// probe; callers after it are skipped until the probe's outcome is recorded.
+    pub fn is_open(&self, target: &str) -> bool {
+        let mut targets = self.targets.lock().unwrap();
+        let Some(open_until) = targets.get_mut(target).and_then(|state| state.open_until.as_mut()) else {
+            return false;
+        };
+        let now = Instant::now();
+        if now < *open_until {
+            return true;
+        }
+        *open_until = now + self.config.cooldown;
+        false
+    }
+
+    /// Record a successful response, closing the circuit
+    pub fn record_success(&self, target: &str) {
+        let mut targets = self.targets.lock().unwrap();
+        targets.remove(target);
+    }
+
+    /// Record a failure, opening the circuit once the threshold is reached
+    pub fn record_failure(&self, target: &str) {
+        let mut targets = self.targets.lock().unwrap();
+        let state = targets.entry(target.to_string()).or_default();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.config.failure_threshold {
+            state.open_until = Some(Instant::now() + self.config.cooldown);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_threshold_and_closes_on_success() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 2,
+            cooldown: Duration::from_secs(60),
+        });
+
+        breaker.record_failure("Worker");
+        assert!(!breaker.is_open("Worker"));
+        breaker.record_failure("Worker");
+        assert!(breaker.is_open("Worker"));
+        assert!(!breaker.is_open("Other"));
+
+        breaker.record_success("Worker");
+        assert!(!breaker.is_open("Worker"));
+    }
+
+    #[test]
+    fn test_allows_probe_after_cooldown() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            cooldown: Duration::ZERO,
+        });
+
+        breaker.record_failure("Worker");
+        assert!(!breaker.is_open("Worker"));
+    }
+
+    #[test]
+    fn test_lets_one_probe_through_after_cooldown() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            cooldown: Duration::from_millis(50),
+        });
+
+        breaker.record_failure("Worker");
+        assert!(breaker.is_open("Worker"));
+        std::thread::sleep(Duration::from_millis(60));
+
+        // Only the first forward probes; a failed probe re-opens the circuit
+        assert!(!breaker.is_open("Worker"));
+        assert!(breaker.is_open("Worker"));
+        breaker.record_failure("Worker");
+        assert!(breaker.is_open("Worker"));
+        std::thread::sleep(Duration::from_millis(60));
+
+        assert!(!breaker.is_open("Worker"));
+        assert!(breaker.is_open("Worker"));
+        breaker.record_success("Worker");
+        assert!(!breaker.is_open("Worker"));
+        assert!(!breaker.is_open("Worker"));
+    }
+}
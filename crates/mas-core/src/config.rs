@@ -1,27 +1,80 @@
+use crate::circuit_breaker::CircuitBreakerConfig;
+use crate::guardrail::Guardrails;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// System-wide configuration for the multi-agent system
 #[derive(Debug, Clone)]
 pub struct SystemConfig {
+    /// Name of the system, labelling its metrics (empty when unnamed)
+    pub name: String,
     /// Default timeout for blocking connections (when no per-connection override exists)
     pub global_timeout: Duration,
+    /// Time a routing agent keeps free before its own deadline so that
+    /// synthesis can still run over whatever responses arrived in time.
+    /// Capped at a fifth of the remaining time for short deadlines.
+    pub synthesis_reserve: Duration,
+    /// Skip forwards to targets that keep failing (disabled when None)
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// Most sub-agents spawned by routing agents that may exist at once
+    pub spawn_limit: usize,
+    /// Checks on every request sent with `send_message*` and on its response
+    pub guardrails: Option<Arc<Guardrails>>,
 }
 
 impl Default for SystemConfig {
     fn default() -> Self {
         Self {
+            name: String::new(),
             global_timeout: Duration::from_secs(30),
+            synthesis_reserve: Duration::from_secs(5),
+            circuit_breaker: None,
+            spawn_limit: 8,
+            guardrails: None,
         }
     }
 }
 
 impl SystemConfig {
     pub fn new(global_timeout: Duration) -> Self {
-        Self { global_timeout }
+        Self {
+            global_timeout,
+            ..Self::default()
+        }
     }
 
     /// Create config with timeout in seconds (convenience method)
     pub fn with_timeout_secs(secs: u64) -> Self {
         Self::new(Duration::from_secs(secs))
     }
+
+    /// Name the system
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Set the synthesis reserve (see [`SystemConfig::synthesis_reserve`])
+    pub fn with_synthesis_reserve(mut self, reserve: Duration) -> Self {
+        self.synthesis_reserve = reserve;
+        self
+    }
+
+    /// Enable the per-target circuit breaker for forwarded requests
+    pub fn with_circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.circuit_breaker = Some(config);
+        self
+    }
+
+    /// Set the spawn limit (see [`SystemConfig::spawn_limit`])
+    pub fn with_spawn_limit(mut self, limit: usize) -> Self {
+        self.spawn_limit = limit;
+        self
+    }
+
+    /// Check requests and responses against guardrails
+    pub fn with_guardrails(mut self, guardrails: Arc<Guardrails>) -> Self {
+        self.guardrails = Some(guardrails);
+        self
+    }
 }
@@ -36,15 +36,27 @@
 //! ```
 
 use crate::agent::AgentBuilder;
-use crate::agent_system::AgentSystem;
+use crate::agent_system::{AgentSystem, MessageHandler, RoutingHandler};
+use crate::approval::DEFAULT_APPROVAL_TIMEOUT;
+use crate::circuit_breaker::CircuitBreakerConfig;
 use crate::config::SystemConfig;
 use crate::connection::Connection;
 use crate::errors::{AgentError, Result};
 use crate::llm::{CompletionOptions, LlmHandler, LlmProvider, OllamaProvider, RoutingBehavior};
 use crate::database::{Database, DatabaseConfig};
 use crate::database_handler::DatabaseHandler;
+use crate::guard::{Guard, GuardConfig};
+use crate::guardrail::{GuardrailConfig, Guardrails};
+use crate::hook::{Hook, HookConfig};
+use crate::memory::{AgentMemory, MemoryConfig};
+use crate::reflection::ReflectionConfig;
+use crate::replica::ReplicaDispatch;
+use crate::rule_router::{RuleConfig, RuleRouter};
+use crate::workflow::{Workflow, WorkflowConfig};
 use crate::tool::{Tool, ToolConfig};
+use crate::trigger::{Trigger, TriggerConfig};
 use crate::tool_handler::ToolHandler;
+use crate::vote::VoteAggregation;
 
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
@@ -53,6 +65,19 @@ use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, info};
 
+/// Prefix of connections to another registered system, e.g. `"system:legal-review"`
+///
+/// The API server registers an agent under that name which forwards to the
+/// other system's entry point; outside the server such connections fail.
+pub const SYSTEM_CONNECTION_PREFIX: &str = "system:";
+
+/// The system a connection target refers to, if it is a `system:` connection
+pub fn connected_system(target: &str) -> Option<&str> {
+    target
+        .strip_prefix(SYSTEM_CONNECTION_PREFIX)
+        .filter(|name| !name.is_empty())
+}
+
 /// Top-level JSON configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemConfigJson {
@@ -68,27 +93,95 @@ pub struct SystemConfigJson {
     /// Database definitions (optional)
     #[serde(default)]
     pub databases: Vec<DatabaseConfig>,
+    /// Workflow definitions (optional), invoked like agents
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub workflows: Vec<WorkflowConfig>,
+    /// Scheduled prompts (optional), run by the API server
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub triggers: Vec<TriggerConfig>,
+    /// Inbound webhooks (optional), served by the API server
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<HookConfig>,
     /// Opaque metadata for the visual editor (node positions, etc.)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub editor_metadata: Option<serde_json::Value>,
 }
 
+impl SystemConfigJson {
+    /// Systems referenced by `system:` connections, sorted and deduplicated
+    pub fn connected_systems(&self) -> Vec<String> {
+        let mut systems: Vec<String> = self
+            .agents
+            .iter()
+            .flat_map(|a| a.connections.keys())
+            .filter_map(|target| connected_system(target))
+            .map(str::to_string)
+            .collect();
+        systems.sort();
+        systems.dedup();
+        systems
+    }
+}
+
 /// System-wide settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemSettings {
     /// Global timeout for blocking connections (in seconds)
     #[serde(default = "default_timeout")]
     pub global_timeout_secs: u64,
+    /// Seconds a routing agent reserves before its deadline for synthesis
+    #[serde(default = "default_synthesis_reserve")]
+    pub synthesis_reserve_secs: u64,
+    /// Skip forwards to targets that keep failing (disabled when omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker: Option<CircuitBreakerSettings>,
+    /// Most sub-agents spawned by routing agents that may exist at once
+    #[serde(default = "default_spawn_limit")]
+    pub spawn_limit: usize,
+    /// Checks on every request to the system and on its response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guardrails: Option<GuardrailConfig>,
+}
+
+/// Circuit breaker settings for forwarded requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerSettings {
+    /// Consecutive failures (timeouts or errors) before the circuit opens
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Seconds the circuit stays open before a target is tried again
+    #[serde(default = "default_cooldown")]
+    pub cooldown_secs: u64,
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+fn default_cooldown() -> u64 {
+    30
 }
 
 fn default_timeout() -> u64 {
     30
 }
 
+fn default_synthesis_reserve() -> u64 {
+    5
+}
+
+fn default_spawn_limit() -> usize {
+    8
+}
+
 impl Default for SystemSettings {
     fn default() -> Self {
         Self {
             global_timeout_secs: default_timeout(),
+            synthesis_reserve_secs: default_synthesis_reserve(),
+            circuit_breaker: None,
+            spawn_limit: default_spawn_limit(),
+            guardrails: None,
         }
     }
 }
@@ -121,13 +214,58 @@ pub struct AgentConfig {
     /// Mark this agent as the entry point for chat messages
     #[serde(default)]
     pub entry_point: bool,
+    /// Number of workers registered behind this agent's name (default 1)
+    #[serde(default = "default_replicas")]
+    pub replicas: usize,
+    /// How requests are spread over replicas: "round_robin" (default), "least_busy" or "session_hash"
+    #[serde(default)]
+    pub replica_dispatch: ReplicaDispatch,
+    /// Per-replica provider/model overrides, applied by index (replicas without one use `handler`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replica_overrides: Vec<ReplicaOverride>,
+    /// Long-term memory of facts the agent chooses to save (LLM handlers only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryConfig>,
+}
+
+fn default_replicas() -> usize {
+    1
+}
+
+/// Provider or model override for a single replica
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplicaOverride {
+    /// References a key in llm_providers (defaults to the handler's provider)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Model override (defaults to the handler's model)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// Kind of handler an agent runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HandlerKind {
+    /// LLM-backed handler (requires `provider`)
+    #[default]
+    Llm,
+    /// Deterministic rule-based router (see `rules`)
+    Rules,
+    /// A person answering through the API (see `human_timeout_secs`)
+    Human,
 }
 
 /// Handler configuration for an agent
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HandlerConfig {
-    /// References a key in llm_providers
-    pub provider: String,
+    /// Handler type: "llm" (default), "rules" or "human"
+    #[serde(rename = "type", default)]
+    pub handler_type: HandlerKind,
+    /// References a key in llm_providers (required for "llm"; for "rules" only
+    /// needed by `llm_fallback` and example rules)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
     /// Optional model override (uses provider's default if not specified)
     pub model: Option<String>,
     /// Enable routing mode (LLM can decide to forward to connected agents)
@@ -137,6 +275,10 @@ pub struct HandlerConfig {
     /// - "best" (default): Forward to the single most appropriate agent
     /// - "all": MUST forward to ALL connected agents and synthesize responses
     /// - "direct_first": Try to answer directly, only forward if lacking expertise
+    /// - "pipeline": Pass the message through the `pipeline` stages in order (no LLM routing call)
+    /// - "vote": Collect a ballot from every connected agent and aggregate them (see `vote_aggregation`)
+    /// - "debate": Connected agents argue for `debate_rounds` rounds, then this agent judges
+    /// - "plan": Write a plan of steps over connected agents, run them in order, re-plan on failures
     #[serde(default)]
     pub routing_behavior: RoutingBehavior,
     /// Completion options
@@ -145,12 +287,66 @@ pub struct HandlerConfig {
     /// Maximum conversation turns with other agents (0 = unlimited, 1 = single turn default)
     #[serde(default = "default_max_turns")]
     pub max_turns: u16,
+    /// With routing_behavior "all": synthesize as soon as this many agents have answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quorum: Option<usize>,
+    /// With routing_behavior "pipeline": blocking connections to pass the message through, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pipeline: Vec<String>,
+    /// With routing_behavior "vote": "majority" (default), "weighted" or "judge"
+    #[serde(default)]
+    pub vote_aggregation: VoteAggregation,
+    /// With routing_behavior "debate": number of argument rounds (default 2)
+    #[serde(default = "default_debate_rounds")]
+    pub debate_rounds: u16,
+    /// With routing_behavior "plan": most re-plans after failed steps (default 2)
+    #[serde(default = "default_max_replans")]
+    pub max_replans: u16,
+    /// With routing=true: let the LLM spawn short-lived sub-agents
+    #[serde(default)]
+    pub spawn: bool,
+    /// With routing=true: blackboard keys this agent may write (paths below them included)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blackboard_keys: Vec<String>,
+    /// Critique answers against a rubric and revise them before returning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reflection: Option<ReflectionConfig>,
+    /// Checks on the messages going into this agent's LLM calls and on its answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guardrails: Option<GuardrailConfig>,
+    /// With type "rules": routing rules, checked in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RuleConfig>,
+    /// With type "rules": let the LLM route when no rule matches
+    #[serde(default)]
+    pub llm_fallback: bool,
+    /// With type "rules": embedding model for example rules (provider default if omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+    /// With type "rules": response when no rule matches and there is no fallback
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_match_response: Option<String>,
+    /// With type "human": seconds to wait for an answer (default 300)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub human_timeout_secs: Option<u64>,
+}
+
+fn default_human_timeout() -> u64 {
+    300
 }
 
 fn default_max_turns() -> u16 {
     1
 }
 
+fn default_debate_rounds() -> u16 {
+    2
+}
+
+fn default_max_replans() -> u16 {
+    2
+}
+
 /// Completion options for LLM calls
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct CompletionOptionsConfig {
@@ -191,6 +387,9 @@ pub struct ConnectionConfig {
     pub connection_type: String,
     /// Optional timeout override for blocking connections (in seconds)
     pub timeout_secs: Option<u64>,
+    /// Optional guard: the connection is only used for messages that satisfy it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<GuardConfig>,
 }
 
 /// Validation errors that can occur when loading a configuration
@@ -238,11 +437,65 @@ pub enum ConfigError {
     #[error("Database name '{0}' conflicts with agent or tool name")]
     DatabaseNameConflict(String),
 
+    #[error("Duplicate workflow name: {0}")]
+    DuplicateWorkflowName(String),
+
+    #[error("Workflow name '{0}' conflicts with agent, tool or database name")]
+    WorkflowNameConflict(String),
+
+    #[error("Workflow '{0}' is invalid: {1}")]
+    InvalidWorkflow(String, String),
+
     #[error("Database '{0}' has empty connection string")]
     EmptyConnectionString(String),
 
     #[error("Database connection error: {0}")]
     DatabaseConnectionError(String),
+
+    #[error("Agent '{0}' has an invalid quorum: {1}")]
+    InvalidQuorum(String, String),
+
+    #[error("Agent '{0}' has an invalid pipeline: {1}")]
+    InvalidPipeline(String, String),
+
+    #[error("Agent '{0}' has an invalid debate setup: {1}")]
+    InvalidDebate(String, String),
+
+    #[error("Agent '{0}' has invalid replicas: {1}")]
+    InvalidReplicas(String, String),
+
+    #[error("Agent '{from}' has an invalid guard on its connection to '{to}': {reason}")]
+    InvalidGuard { from: String, to: String, reason: String },
+
+    #[error("Agent '{0}' has no provider (required for LLM handlers)")]
+    MissingProvider(String),
+
+    #[error("Agent '{0}' has invalid rules: {1}")]
+    InvalidRules(String, String),
+
+    #[error("Human agent '{0}' is invalid: {1}")]
+    InvalidHumanAgent(String, String),
+
+    #[error("Duplicate trigger name: {0}")]
+    DuplicateTriggerName(String),
+
+    #[error("Trigger '{0}' is invalid: {1}")]
+    InvalidTrigger(String, String),
+
+    #[error("Duplicate hook name: {0}")]
+    DuplicateHookName(String),
+
+    #[error("Hook '{0}' is invalid: {1}")]
+    InvalidHook(String, String),
+
+    #[error("Agent '{0}' has an invalid memory: {1}")]
+    InvalidMemory(String, String),
+
+    #[error("Agent '{0}' has an invalid reflection: {1}")]
+    InvalidReflection(String, String),
+
+    #[error("Invalid guardrails for {0}: {1}")]
+    InvalidGuardrails(String, String),
 }
 
 impl From<ConfigError> for AgentError {
@@ -253,6 +506,10 @@ impl From<ConfigError> for AgentError {
 
 /// Validate a configuration without instantiating anything
 pub fn validate_config(config: &SystemConfigJson) -> std::result::Result<(), ConfigError> {
+    if let Some(guardrails) = &config.system.guardrails {
+        validate_guardrails("the system", guardrails, config, false)?;
+    }
+
     // Collect all agent names for validation
     let mut agent_names: HashSet<String> = HashSet::new();
 
@@ -263,12 +520,72 @@ pub fn validate_config(config: &SystemConfigJson) -> std::result::Result<(), Con
         }
 
         // Check provider reference
-        if !config.llm_providers.contains_key(&agent.handler.provider) {
-            return Err(ConfigError::UnknownProvider(
+        match &agent.handler.provider {
+            Some(provider) if !config.llm_providers.contains_key(provider) => {
+                return Err(ConfigError::UnknownProvider(
+                    agent.name.clone(),
+                    provider.clone(),
+                ));
+            }
+            None if agent.handler.handler_type == HandlerKind::Llm => {
+                return Err(ConfigError::MissingProvider(agent.name.clone()));
+            }
+            _ => {}
+        }
+
+        if agent.handler.handler_type == HandlerKind::Rules {
+            validate_rules(agent)?;
+        }
+
+        if agent.handler.handler_type == HandlerKind::Human {
+            let invalid = |reason: &str| ConfigError::InvalidHumanAgent(agent.name.clone(), reason.to_string());
+            if !agent.connections.is_empty() {
+                return Err(invalid("human agents cannot have connections"));
+            }
+            if agent.replicas != 1 {
+                return Err(invalid("human agents cannot have replicas"));
+            }
+            if agent.handler.human_timeout_secs == Some(0) {
+                return Err(invalid("human_timeout_secs must be at least 1"));
+            }
+        }
+
+        if agent.replicas == 0 {
+            return Err(ConfigError::InvalidReplicas(
+                agent.name.clone(),
+                "must be at least 1".to_string(),
+            ));
+        }
+        if agent.replica_overrides.len() > agent.replicas {
+            return Err(ConfigError::InvalidReplicas(
                 agent.name.clone(),
-                agent.handler.provider.clone(),
+                format!(
+                    "{} overrides for {} replicas",
+                    agent.replica_overrides.len(),
+                    agent.replicas
+                ),
             ));
         }
+        for provider in agent.replica_overrides.iter().filter_map(|r| r.provider.as_ref()) {
+            if !config.llm_providers.contains_key(provider) {
+                return Err(ConfigError::UnknownProvider(agent.name.clone(), provider.clone()));
+            }
+        }
+
+        if let Some(quorum) = agent.handler.quorum {
+            if quorum == 0 {
+                return Err(ConfigError::InvalidQuorum(
+                    agent.name.clone(),
+                    "must be at least 1".to_string(),
+                ));
+            }
+            if agent.handler.routing_behavior != RoutingBehavior::All {
+                return Err(ConfigError::InvalidQuorum(
+                    agent.name.clone(),
+                    "only supported with routing_behavior \"all\"".to_string(),
+                ));
+            }
+        }
     }
 
     // Validate tools
@@ -307,14 +624,88 @@ pub fn validate_config(config: &SystemConfigJson) -> std::result::Result<(), Con
         }
     }
 
-    // Combined set of all valid targets (agents + tools + databases)
-    let all_targets: HashSet<&str> = agent_names
+    // Step targets: agents, tools and databases (workflows do not nest)
+    let step_targets: HashSet<&str> = agent_names
         .iter()
         .map(|s| s.as_str())
         .chain(tool_names.iter().map(|s| s.as_str()))
         .chain(db_names.iter().map(|s| s.as_str()))
         .collect();
 
+    // Validate workflows
+    let mut workflow_names: HashSet<String> = HashSet::new();
+    for workflow in &config.workflows {
+        if !workflow_names.insert(workflow.name.clone()) {
+            return Err(ConfigError::DuplicateWorkflowName(workflow.name.clone()));
+        }
+        if step_targets.contains(workflow.name.as_str()) {
+            return Err(ConfigError::WorkflowNameConflict(workflow.name.clone()));
+        }
+        Workflow::compile(workflow)
+            .map_err(|e| ConfigError::InvalidWorkflow(workflow.name.clone(), e.to_string()))?;
+        for step in &workflow.steps {
+            let Some(target) = &step.target else {
+                continue;
+            };
+            if !step_targets.contains(target.as_str()) {
+                return Err(ConfigError::InvalidWorkflow(
+                    workflow.name.clone(),
+                    format!("step '{}' targets unknown '{}'", step.id, target),
+                ));
+            }
+        }
+    }
+
+    // Validate triggers (they prompt agents and workflows like API clients do)
+    let mut trigger_names: HashSet<&str> = HashSet::new();
+    for trigger in &config.triggers {
+        if !trigger_names.insert(trigger.name.as_str()) {
+            return Err(ConfigError::DuplicateTriggerName(trigger.name.clone()));
+        }
+        Trigger::compile(trigger)
+            .map_err(|e| ConfigError::InvalidTrigger(trigger.name.clone(), e.to_string()))?;
+        if let Some(target) = &trigger.target_agent {
+            if !agent_names.contains(target) && !workflow_names.contains(target) {
+                return Err(ConfigError::InvalidTrigger(
+                    trigger.name.clone(),
+                    format!("unknown target agent '{}'", target),
+                ));
+            }
+        }
+    }
+
+    // Validate hooks (prompted like triggers, from inbound HTTP calls)
+    let mut hook_names: HashSet<&str> = HashSet::new();
+    for hook in &config.hooks {
+        if !hook_names.insert(hook.name.as_str()) {
+            return Err(ConfigError::DuplicateHookName(hook.name.clone()));
+        }
+        Hook::compile(hook).map_err(|e| ConfigError::InvalidHook(hook.name.clone(), e.to_string()))?;
+        if let Some(target) = &hook.target_agent {
+            if !agent_names.contains(target) && !workflow_names.contains(target) {
+                return Err(ConfigError::InvalidHook(
+                    hook.name.clone(),
+                    format!("unknown target agent '{}'", target),
+                ));
+            }
+        }
+        if let Some(url) = &hook.callback_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(ConfigError::InvalidHook(
+                    hook.name.clone(),
+                    format!("callback URL '{}' must start with http:// or https://", url),
+                ));
+            }
+        }
+    }
+
+    // Combined set of all valid targets (agents + tools + databases + workflows)
+    let all_targets: HashSet<&str> = step_targets
+        .iter()
+        .copied()
+        .chain(workflow_names.iter().map(|s| s.as_str()))
+        .collect();
+
     // Validate connections (second pass - need all agent and tool names first)
     for agent in &config.agents {
         for (target, conn_config) in &agent.connections {
@@ -323,8 +714,9 @@ pub fn validate_config(config: &SystemConfigJson) -> std::result::Result<(), Con
                 return Err(ConfigError::SelfConnection(agent.name.clone()));
             }
 
-            // Check target exists (can be agent or tool)
-            if !all_targets.contains(target.as_str()) {
+            // Check target exists (can be agent or tool); other systems are
+            // resolved when the message is sent
+            if !all_targets.contains(target.as_str()) && connected_system(target).is_none() {
                 return Err(ConfigError::UnknownConnectionTarget {
                     from: agent.name.clone(),
                     to: target.clone(),
@@ -341,6 +733,97 @@ pub fn validate_config(config: &SystemConfigJson) -> std::result::Result<(), Con
                     ));
                 }
             }
+
+            // Guards must compile (valid regexes and expression)
+            if let Some(guard) = &conn_config.when {
+                Guard::compile(guard).map_err(|e| ConfigError::InvalidGuard {
+                    from: agent.name.clone(),
+                    to: target.clone(),
+                    reason: e.to_string(),
+                })?;
+            }
+        }
+
+        // Pipeline stages must be blocking connections of this agent
+        if agent.handler.routing_behavior == RoutingBehavior::Pipeline {
+            if agent.handler.pipeline.is_empty() {
+                return Err(ConfigError::InvalidPipeline(
+                    agent.name.clone(),
+                    "routing_behavior \"pipeline\" requires at least one stage".to_string(),
+                ));
+            }
+            for stage in &agent.handler.pipeline {
+                let is_blocking = agent
+                    .connections
+                    .get(stage)
+                    .is_some_and(|c| c.connection_type.eq_ignore_ascii_case("blocking"));
+                if !is_blocking {
+                    return Err(ConfigError::InvalidPipeline(
+                        agent.name.clone(),
+                        format!("stage '{}' is not a blocking connection", stage),
+                    ));
+                }
+            }
+        }
+
+        // Only LLMs decide what to remember, and they need something to recall
+        if let Some(memory) = &agent.memory {
+            if agent.handler.handler_type == HandlerKind::Human {
+                return Err(ConfigError::InvalidMemory(
+                    agent.name.clone(),
+                    "human agents have no long-term memory".to_string(),
+                ));
+            }
+            if memory.top_k == 0 {
+                return Err(ConfigError::InvalidMemory(
+                    agent.name.clone(),
+                    "top_k must be at least 1".to_string(),
+                ));
+            }
+        }
+
+        if let Some(reflection) = &agent.handler.reflection {
+            let invalid = |reason: &str| ConfigError::InvalidReflection(agent.name.clone(), reason.to_string());
+            if agent.handler.handler_type == HandlerKind::Human {
+                return Err(invalid("human agents do not reflect"));
+            }
+            if reflection.rubric.trim().is_empty() {
+                return Err(invalid("rubric must not be empty"));
+            }
+            if let Some(provider) = &reflection.provider {
+                if !config.llm_providers.contains_key(provider) {
+                    return Err(ConfigError::UnknownProvider(agent.name.clone(), provider.clone()));
+                }
+            }
+        }
+
+        if let Some(guardrails) = &agent.handler.guardrails {
+            let owner = format!("agent '{}'", agent.name);
+            if agent.handler.handler_type == HandlerKind::Human {
+                return Err(ConfigError::InvalidGuardrails(owner, "human agents have no LLM calls to guard".to_string()));
+            }
+            validate_guardrails(&owner, guardrails, config, agent.handler.provider.is_some())?;
+        }
+
+        // Debates need at least two participants and one round
+        if agent.handler.routing_behavior == RoutingBehavior::Debate {
+            let participants = agent
+                .connections
+                .values()
+                .filter(|c| c.connection_type.eq_ignore_ascii_case("blocking"))
+                .count();
+            if participants < 2 {
+                return Err(ConfigError::InvalidDebate(
+                    agent.name.clone(),
+                    "at least two blocking connections are required".to_string(),
+                ));
+            }
+            if agent.handler.debate_rounds == 0 {
+                return Err(ConfigError::InvalidDebate(
+                    agent.name.clone(),
+                    "debate_rounds must be at least 1".to_string(),
+                ));
+            }
         }
     }
 
@@ -360,6 +843,76 @@ pub fn validate_config(config: &SystemConfigJson) -> std::result::Result<(), Con
     Ok(())
 }
 
+/// Validate the rules of a "rules" handler (targets are checked with connections)
+fn validate_rules(agent: &AgentConfig) -> std::result::Result<(), ConfigError> {
+    let handler = &agent.handler;
+    let invalid = |reason: String| ConfigError::InvalidRules(agent.name.clone(), reason);
+
+    if handler.rules.is_empty() && !handler.llm_fallback {
+        return Err(invalid("at least one rule or llm_fallback is required".to_string()));
+    }
+    RuleRouter::new(&handler.rules).map_err(|e| invalid(e.to_string()))?;
+
+    let has_examples = handler.rules.iter().any(|r| !r.examples.is_empty());
+    if (handler.llm_fallback || has_examples) && handler.provider.is_none() {
+        return Err(invalid(
+            "llm_fallback and example rules need a provider".to_string(),
+        ));
+    }
+
+    for rule in &handler.rules {
+        let is_blocking = agent
+            .connections
+            .get(&rule.target)
+            .is_some_and(|c| c.connection_type.eq_ignore_ascii_case("blocking"));
+        if !is_blocking {
+            return Err(invalid(format!(
+                "target '{}' is not a blocking connection",
+                rule.target
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that guardrails compile and their classifiers have a provider
+///
+/// `has_fallback` tells whether classifiers without a provider can use the
+/// owner's own provider.
+fn validate_guardrails(
+    owner: &str,
+    guardrails: &GuardrailConfig,
+    config: &SystemConfigJson,
+    has_fallback: bool,
+) -> std::result::Result<(), ConfigError> {
+    let invalid = |reason: String| ConfigError::InvalidGuardrails(owner.to_string(), reason);
+    guardrails.validate().map_err(|e| invalid(e.to_string()))?;
+    for classifier in guardrails.classifiers() {
+        match &classifier.provider {
+            Some(provider) if !config.llm_providers.contains_key(provider) => {
+                return Err(invalid(format!("classifier references unknown provider '{}'", provider)));
+            }
+            None if !has_fallback => return Err(invalid("classifier needs a provider".to_string())),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Compile guardrails; classifiers without a provider use `fallback`
+fn compile_guardrails(
+    guardrails: &GuardrailConfig,
+    providers: &HashMap<String, Arc<dyn LlmProvider>>,
+    fallback: Option<&String>,
+) -> Result<Arc<Guardrails>> {
+    Guardrails::compile(guardrails, |classifier| {
+        classifier.provider.as_ref().or(fallback).and_then(|name| providers.get(name).cloned())
+    })
+    .map(Arc::new)
+    .map_err(|e| AgentError::ConfigError(format!("Invalid guardrails: {}", e)))
+}
+
 /// Create LLM providers from configuration
 async fn create_providers(
     configs: &HashMap<String, LlmProviderConfig>,
@@ -406,8 +959,23 @@ async fn create_providers(
 /// - The JSON is invalid
 /// - Validation fails (duplicate names, invalid references, etc.)
 /// - LLM provider creation fails
+///
+/// The system is named after the file (its stem); see
+/// [`load_named_system_from_json`].
 pub async fn load_system_from_json(json_path: &Path) -> Result<Arc<AgentSystem>> {
-    info!("Loading agent system from: {}", json_path.display());
+    let name = json_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    load_named_system_from_json(json_path, &name).await
+}
+
+/// Load and instantiate the AgentSystem `system_name` from a JSON configuration file
+///
+/// The name keeps state stored per agent, such as long-term memories,
+/// apart from that of same-named agents in other systems.
+pub async fn load_named_system_from_json(json_path: &Path, system_name: &str) -> Result<Arc<AgentSystem>> {
+    info!("Loading agent system '{}' from: {}", system_name, json_path.display());
 
     // Read and parse JSON
     let content = std::fs::read_to_string(json_path).map_err(ConfigError::from)?;
@@ -416,19 +984,32 @@ pub async fn load_system_from_json(json_path: &Path) -> Result<Arc<AgentSystem>>
     // Validate configuration
     validate_config(&config)?;
     info!(
-        "Configuration validated: {} agents, {} tools, {} databases, {} providers",
+        "Configuration validated: {} agents, {} tools, {} databases, {} workflows, {} providers",
         config.agents.len(),
         config.tools.len(),
         config.databases.len(),
+        config.workflows.len(),
         config.llm_providers.len()
     );
 
-    // Create system config
-    let system_config = SystemConfig::with_timeout_secs(config.system.global_timeout_secs);
-
     // Create LLM providers
     let providers = create_providers(&config.llm_providers).await?;
 
+    // Create system config
+    let mut system_config = SystemConfig::with_timeout_secs(config.system.global_timeout_secs)
+        .with_name(system_name)
+        .with_synthesis_reserve(Duration::from_secs(config.system.synthesis_reserve_secs))
+        .with_spawn_limit(config.system.spawn_limit);
+    if let Some(breaker) = &config.system.circuit_breaker {
+        system_config = system_config.with_circuit_breaker(CircuitBreakerConfig {
+            failure_threshold: breaker.failure_threshold,
+            cooldown: Duration::from_secs(breaker.cooldown_secs),
+        });
+    }
+    if let Some(guardrails) = &config.system.guardrails {
+        system_config = system_config.with_guardrails(compile_guardrails(guardrails, &providers, None)?);
+    }
+
     // Create the agent system (wrapped in Arc for routing agents)
     let system = Arc::new(AgentSystem::new(system_config));
 
@@ -462,7 +1043,16 @@ pub async fn load_system_from_json(json_path: &Path) -> Result<Arc<AgentSystem>>
 
     // Register all agents (with tool descriptions for routing)
     for agent_config in &config.agents {
-        register_agent_from_config(system.clone(), agent_config, &providers, &tool_descriptions).await?;
+        register_agent_from_config(system.clone(), system_name, agent_config, &providers, &tool_descriptions)
+            .await?;
+    }
+
+    // Register workflows (they address agents, tools and databases by name)
+    for workflow_config in &config.workflows {
+        let workflow = Workflow::compile(workflow_config).map_err(|e| {
+            ConfigError::InvalidWorkflow(workflow_config.name.clone(), e.to_string())
+        })?;
+        AgentSystem::register_workflow(system.clone(), workflow).await?;
     }
 
     info!("Agent system loaded successfully");
@@ -479,10 +1069,19 @@ pub fn parse_config_file(json_path: &Path) -> std::result::Result<SystemConfigJs
     Ok(config)
 }
 
+/// How long a gated call waits for approval, given its configured seconds
+fn approval_timeout(secs: Option<u64>) -> Duration {
+    secs.map(Duration::from_secs).unwrap_or(DEFAULT_APPROVAL_TIMEOUT)
+}
+
 /// Register a tool from its configuration
 async fn register_tool_from_config(system: &AgentSystem, config: &ToolConfig) -> Result<()> {
     let tool = Arc::new(Tool::new(config.clone()));
-    let handler = Arc::new(ToolHandler::new(tool.clone()));
+    let mut handler = ToolHandler::new(tool.clone());
+    if config.requires_approval {
+        handler = handler.with_approval(system.approval_queue().clone(), approval_timeout(config.approval_timeout_secs));
+    }
+    let handler = Arc::new(handler);
 
     system.register_tool(tool, handler).await?;
     debug!("Registered tool '{}' -> {}", config.name, config.endpoint.url);
@@ -496,11 +1095,13 @@ async fn register_database_from_config(
     config: &DatabaseConfig,
 ) -> Result<()> {
     let database = Arc::new(Database::new(config.clone()));
-    let handler = Arc::new(
-        DatabaseHandler::new(database.clone())
-            .await
-            .map_err(|e| AgentError::ConfigError(e))?,
-    );
+    let mut handler = DatabaseHandler::new(database.clone())
+        .await
+        .map_err(AgentError::ConfigError)?;
+    if config.requires_approval {
+        handler = handler.with_approval(system.approval_queue().clone(), approval_timeout(config.approval_timeout_secs));
+    }
+    let handler = Arc::new(handler);
 
     system.register_database(database, handler).await?;
     debug!(
@@ -516,6 +1117,7 @@ async fn register_database_from_config(
 /// Register a single agent from its configuration
 async fn register_agent_from_config(
     system: Arc<AgentSystem>,
+    system_name: &str,
     config: &AgentConfig,
     providers: &HashMap<String, Arc<dyn LlmProvider>>,
     tool_descriptions: &HashMap<String, String>,
@@ -533,6 +1135,15 @@ async fn register_agent_from_config(
             "notify" => Connection::notify(),
             _ => unreachable!(), // Already validated
         };
+        let connection = match &conn_config.when {
+            Some(guard) => connection.with_guard(Guard::compile(guard).map_err(|e| {
+                AgentError::ConfigError(format!(
+                    "Invalid guard on connection {} -> {}: {}",
+                    config.name, target, e
+                ))
+            })?),
+            None => connection,
+        };
         builder = builder.connection(target, connection);
     }
 
@@ -543,23 +1154,174 @@ async fn register_agent_from_config(
         config.connections.len()
     );
 
-    // Get the provider
-    let provider = providers
-        .get(&config.handler.provider)
-        .ok_or_else(|| {
-            AgentError::ConfigError(format!(
-                "Provider '{}' not found for agent '{}'",
-                config.handler.provider, config.name
-            ))
-        })?
-        .clone();
+    // Human agents answer through the system's human inbox
+    if config.handler.handler_type == HandlerKind::Human {
+        let timeout_secs = config.handler.human_timeout_secs.unwrap_or_else(default_human_timeout);
+        return system
+            .register_human_agent(agent, Duration::from_secs(timeout_secs))
+            .await;
+    }
+
+    // Check if agent has blocking connections (candidates for routing)
+    let has_blocking_connections = config
+        .connections
+        .values()
+        .any(|c| c.connection_type.to_lowercase() == "blocking");
+
+    // Auto-enable routing if agent has blocking connections (unless explicitly disabled)
+    // This makes the UX more intuitive: if you connect agents, routing works automatically.
+    // Rule routers always route.
+    let is_rules = config.handler.handler_type == HandlerKind::Rules;
+    let should_route = is_rules || config.handler.routing || has_blocking_connections;
+
+    // Filter tool descriptions to only include tools this agent is connected to
+    let connected_tool_descriptions: HashMap<String, String> = config
+        .connections
+        .keys()
+        .filter_map(|name| {
+            tool_descriptions.get(name).map(|desc| (name.clone(), desc.clone()))
+        })
+        .collect();
+
+    // Build one handler per replica; overrides swap provider or model by index
+    let replica_count = config.replicas.max(1);
+    let mut routing_handlers: Vec<Arc<dyn RoutingHandler>> = Vec::with_capacity(replica_count);
+    let mut simple_handlers: Vec<Arc<dyn MessageHandler>> = Vec::with_capacity(replica_count);
+    // Replicas share one memory so they see each other's facts
+    let memory = config
+        .memory
+        .clone()
+        .map(|memory| Arc::new(AgentMemory::new(system_name, &config.name, memory)));
+    let guardrails = config
+        .handler
+        .guardrails
+        .as_ref()
+        .map(|guardrails| compile_guardrails(guardrails, providers, config.handler.provider.as_ref()))
+        .transpose()?;
+    for index in 0..replica_count {
+        let replica = config.replica_overrides.get(index);
+        let provider_name = replica
+            .and_then(|r| r.provider.as_ref())
+            .or(config.handler.provider.as_ref());
+        let model = replica
+            .and_then(|r| r.model.as_ref())
+            .or(config.handler.model.as_ref());
+
+        let provider = provider_name
+            .map(|name| {
+                providers.get(name).cloned().ok_or_else(|| {
+                    AgentError::ConfigError(format!(
+                        "Provider '{}' not found for agent '{}'",
+                        name, config.name
+                    ))
+                })
+            })
+            .transpose()?;
+
+        let llm_handler = provider.clone().map(|provider| {
+            let mut handler = build_llm_handler(&system, config, provider, model);
+            if let Some(memory) = &memory {
+                handler = handler.with_memory(memory.clone());
+            }
+            if let Some(reflection) = &config.handler.reflection {
+                let critic = reflection.provider.as_ref().and_then(|name| providers.get(name).cloned());
+                handler = handler.with_reflection(reflection.clone(), critic);
+            }
+            if let Some(guardrails) = &guardrails {
+                handler = handler.with_guardrails(guardrails.clone());
+            }
+            if should_route && config.handler.spawn {
+                handler = handler.with_spawning();
+            }
+            if should_route {
+                handler
+                    .with_routing()
+                    .with_routing_behavior(config.handler.routing_behavior)
+                    .with_tool_descriptions(connected_tool_descriptions.clone())
+                    .with_max_turns(config.handler.max_turns)
+                    .with_quorum(config.handler.quorum)
+                    .with_pipeline(config.handler.pipeline.clone())
+                    .with_vote_aggregation(config.handler.vote_aggregation)
+                    .with_debate_rounds(config.handler.debate_rounds)
+                    .with_max_replans(config.handler.max_replans)
+                    .with_blackboard_keys(config.handler.blackboard_keys.clone())
+            } else {
+                handler
+            }
+        });
+
+        match (config.handler.handler_type, llm_handler) {
+            (HandlerKind::Rules, llm_handler) => {
+                let mut router = RuleRouter::new(&config.handler.rules)
+                    .map_err(|e| ConfigError::InvalidRules(config.name.clone(), e.to_string()))?;
+                if let Some(provider) = provider {
+                    router = router.with_embeddings(provider, config.handler.embedding_model.clone());
+                }
+                if let (true, Some(llm_handler)) = (config.handler.llm_fallback, llm_handler) {
+                    router = router.with_fallback(Arc::new(llm_handler));
+                }
+                if let Some(response) = &config.handler.no_match_response {
+                    router = router.with_no_match_response(response);
+                }
+                router
+                    .validate()
+                    .map_err(|e| ConfigError::InvalidRules(config.name.clone(), e.to_string()))?;
+                routing_handlers.push(Arc::new(router));
+            }
+            (HandlerKind::Llm, Some(llm_handler)) => {
+                let llm_handler = Arc::new(llm_handler);
+                if should_route {
+                    routing_handlers.push(llm_handler);
+                } else {
+                    simple_handlers.push(llm_handler);
+                }
+            }
+            (HandlerKind::Llm, None) => {
+                return Err(ConfigError::MissingProvider(config.name.clone()).into());
+            }
+            (HandlerKind::Human, _) => unreachable!(), // Registered above
+        }
+    }
+
+    // Register based on routing mode
+    if should_route {
+        debug!(
+            "Registering '{}' as routing agent ({:?}) with behavior {:?} (auto={}, explicit={}, replicas={})",
+            config.name,
+            config.handler.handler_type,
+            config.handler.routing_behavior,
+            has_blocking_connections,
+            config.handler.routing,
+            replica_count
+        );
+        AgentSystem::register_routing_agent_replicas(system, agent, routing_handlers, config.replica_dispatch)
+            .await?;
+    } else {
+        debug!(
+            "Registering '{}' as simple agent (replicas={})",
+            config.name, replica_count
+        );
+        system
+            .register_agent_replicas(agent, simple_handlers, config.replica_dispatch)
+            .await?;
+    }
+
+    Ok(())
+}
 
+/// Build the LLM handler for one replica of an agent (without routing settings)
+fn build_llm_handler(
+    system: &AgentSystem,
+    config: &AgentConfig,
+    provider: Arc<dyn LlmProvider>,
+    model: Option<&String>,
+) -> LlmHandler {
     // Build the handler with shared conversation store for history
     let mut handler = LlmHandler::new(provider)
         .with_conversation_store(system.conversation_store());
 
     // Apply model override
-    if let Some(model) = &config.handler.model {
+    if let Some(model) = model {
         handler = handler.with_model(model);
     }
 
@@ -574,43 +1336,7 @@ async fn register_agent_from_config(
         handler = handler.with_options(options);
     }
 
-    // Check if agent has blocking connections (candidates for routing)
-    let has_blocking_connections = config
-        .connections
-        .values()
-        .any(|c| c.connection_type.to_lowercase() == "blocking");
-
-    // Auto-enable routing if agent has blocking connections (unless explicitly disabled)
-    // This makes the UX more intuitive: if you connect agents, routing works automatically
-    let should_route = config.handler.routing || has_blocking_connections;
-
-    // Register based on routing mode
-    if should_route {
-        // Filter tool descriptions to only include tools this agent is connected to
-        let connected_tool_descriptions: HashMap<String, String> = config
-            .connections
-            .keys()
-            .filter_map(|name| {
-                tool_descriptions.get(name).map(|desc| (name.clone(), desc.clone()))
-            })
-            .collect();
-
-        handler = handler
-            .with_routing()
-            .with_routing_behavior(config.handler.routing_behavior)
-            .with_tool_descriptions(connected_tool_descriptions)
-            .with_max_turns(config.handler.max_turns);
-        debug!(
-            "Registering '{}' as routing agent with behavior {:?} (auto={}, explicit={})",
-            config.name, config.handler.routing_behavior, has_blocking_connections, config.handler.routing
-        );
-        AgentSystem::register_routing_agent(system, agent, Arc::new(handler)).await?;
-    } else {
-        debug!("Registering '{}' as simple agent", config.name);
-        system.register_agent(agent, Arc::new(handler)).await?;
-    }
-
-    Ok(())
+    handler
 }
 
 #[cfg(test)]
@@ -736,28 +1462,56 @@ mod tests {
     }
 
     #[test]
-    fn test_validate_self_connection() {
+    fn test_system_connections_are_not_local_targets() {
         let json = r#"{
             "system": {},
             "llm_providers": { "default": { "type": "ollama" } },
             "agents": [
                 {
-                    "name": "Agent1",
-                    "handler": { "provider": "default" },
+                    "name": "Intake",
+                    "handler": { "provider": "default", "routing": true },
                     "connections": {
-                        "Agent1": { "type": "blocking" }
+                        "system:legal-review": { "type": "blocking" },
+                        "system:": { "type": "blocking" }
                     }
                 }
             ]
         }"#;
 
-        let config: SystemConfigJson = serde_json::from_str(json).unwrap();
-        let result = validate_config(&config);
-        assert!(matches!(result, Err(ConfigError::SelfConnection(_))));
+        let mut config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert!(matches!(
+            validate_config(&config),
+            Err(ConfigError::UnknownConnectionTarget { to, .. }) if to == "system:"
+        ));
+
+        config.agents[0].connections.remove("system:");
+        assert!(validate_config(&config).is_ok());
+        assert_eq!(config.connected_systems(), vec!["legal-review".to_string()]);
     }
 
     #[test]
-    fn test_validate_unknown_provider() {
+    fn test_validate_self_connection() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [
+                {
+                    "name": "Agent1",
+                    "handler": { "provider": "default" },
+                    "connections": {
+                        "Agent1": { "type": "blocking" }
+                    }
+                }
+            ]
+        }"#;
+
+        let config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        let result = validate_config(&config);
+        assert!(matches!(result, Err(ConfigError::SelfConnection(_))));
+    }
+
+    #[test]
+    fn test_validate_unknown_provider() {
         let json = r#"{
             "system": {},
             "llm_providers": { "default": { "type": "ollama" } },
@@ -966,4 +1720,524 @@ mod tests {
         let result = validate_config(&config);
         assert!(matches!(result, Err(ConfigError::InvalidToolEndpoint(_, _))));
     }
+
+    #[test]
+    fn test_validate_quorum_requires_all_routing() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [
+                {
+                    "name": "Agent1",
+                    "handler": { "provider": "default", "routing_behavior": "best", "quorum": 1 }
+                }
+            ]
+        }"#;
+
+        let config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        let result = validate_config(&config);
+        assert!(matches!(result, Err(ConfigError::InvalidQuorum(_, _))));
+    }
+
+    #[test]
+    fn test_parse_quorum_and_circuit_breaker() {
+        let json = r#"{
+            "system": { "circuit_breaker": { "failure_threshold": 2 } },
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [
+                {
+                    "name": "Agent1",
+                    "handler": { "provider": "default", "routing_behavior": "all", "quorum": 2 }
+                }
+            ]
+        }"#;
+
+        let config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert!(validate_config(&config).is_ok());
+        assert_eq!(config.agents[0].handler.quorum, Some(2));
+        let breaker = config.system.circuit_breaker.unwrap();
+        assert_eq!(breaker.failure_threshold, 2);
+        assert_eq!(breaker.cooldown_secs, 30);
+    }
+
+    #[test]
+    fn test_validate_debate_requires_two_participants() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [
+                {
+                    "name": "Judge",
+                    "handler": { "provider": "default", "routing_behavior": "debate", "debate_rounds": 3 },
+                    "connections": { "Pro": { "type": "blocking" } }
+                },
+                { "name": "Pro", "handler": { "provider": "default" } },
+                { "name": "Contra", "handler": { "provider": "default" } }
+            ]
+        }"#;
+
+        let mut config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidDebate(_, _))));
+
+        config.agents[0].connections.insert(
+            "Contra".to_string(),
+            ConnectionConfig {
+                connection_type: "blocking".to_string(),
+                timeout_secs: None,
+                when: None,
+            },
+        );
+        assert!(validate_config(&config).is_ok());
+        assert_eq!(config.agents[0].handler.debate_rounds, 3);
+        assert_eq!(config.agents[1].handler.debate_rounds, 2);
+    }
+
+    #[test]
+    fn test_parse_vote_aggregation() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [
+                {
+                    "name": "Panel",
+                    "handler": { "provider": "default", "routing_behavior": "vote", "vote_aggregation": "judge" }
+                },
+                { "name": "Solo", "handler": { "provider": "default" } }
+            ]
+        }"#;
+
+        let config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert_eq!(config.agents[0].handler.routing_behavior, RoutingBehavior::Vote);
+        assert_eq!(config.agents[0].handler.vote_aggregation, VoteAggregation::Judge);
+        assert_eq!(config.agents[1].handler.vote_aggregation, VoteAggregation::Majority);
+    }
+
+    #[test]
+    fn test_parse_plan_behavior() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [
+                {
+                    "name": "Planner",
+                    "handler": { "provider": "default", "routing": true, "routing_behavior": "plan", "max_replans": 1 },
+                    "connections": { "Search": { "type": "blocking" } }
+                },
+                { "name": "Search", "handler": { "provider": "default" } }
+            ]
+        }"#;
+
+        let config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert!(validate_config(&config).is_ok());
+        assert_eq!(config.agents[0].handler.routing_behavior, RoutingBehavior::Plan);
+        assert_eq!(config.agents[0].handler.max_replans, 1);
+        assert_eq!(config.agents[1].handler.max_replans, 2);
+    }
+
+    #[test]
+    fn test_parse_spawn_settings() {
+        let json = r#"{
+            "system": { "spawn_limit": 12 },
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [
+                { "name": "Coordinator", "handler": { "provider": "default", "routing": true, "spawn": true } },
+                { "name": "Writer", "handler": { "provider": "default" } }
+            ]
+        }"#;
+
+        let config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert!(validate_config(&config).is_ok());
+        assert_eq!(config.system.spawn_limit, 12);
+        assert!(config.agents[0].handler.spawn);
+        assert!(!config.agents[1].handler.spawn);
+        assert_eq!(SystemSettings::default().spawn_limit, 8);
+    }
+
+    #[test]
+    fn test_parse_and_validate_guardrails() {
+        let json = r#"{
+            "system": { "guardrails": { "output": { "pii": ["email", "iban"], "disclaimer": "Not advice." } } },
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [
+                {
+                    "name": "Advisor",
+                    "handler": {
+                        "provider": "default",
+                        "guardrails": { "output": { "max_length": 500, "classifier": { "policy": "No diagnoses." } } }
+                    }
+                }
+            ]
+        }"#;
+
+        let mut config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert!(validate_config(&config).is_ok());
+        let output = config.agents[0].handler.guardrails.as_ref().unwrap().output.as_ref().unwrap();
+        assert_eq!(output.max_length, Some(500));
+
+        // System classifiers have no agent provider to fall back to
+        let system = config.system.guardrails.as_mut().unwrap();
+        system.output.as_mut().unwrap().classifier = serde_json::from_str(r#"{ "policy": "Be polite." }"#).unwrap();
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidGuardrails(..))));
+
+        config.system.guardrails = serde_json::from_str(r#"{ "input": { "pii": ["email"], "pii_action": "rewrite" } }"#).unwrap();
+        let err = validate_config(&config).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid guardrails for the system: pii does not support the 'rewrite' action");
+    }
+
+    #[test]
+    fn test_parse_and_validate_replicas() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" }, "gpu": { "type": "ollama" } },
+            "agents": [
+                {
+                    "name": "Worker",
+                    "handler": { "provider": "default" },
+                    "replicas": 3,
+                    "replica_dispatch": "least_busy",
+                    "replica_overrides": [{}, { "provider": "gpu", "model": "llama3.1:70b" }]
+                },
+                { "name": "Solo", "handler": { "provider": "default" } }
+            ]
+        }"#;
+
+        let mut config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert!(validate_config(&config).is_ok());
+        assert_eq!(config.agents[0].replicas, 3);
+        assert_eq!(config.agents[0].replica_dispatch, ReplicaDispatch::LeastBusy);
+        assert_eq!(config.agents[1].replicas, 1);
+        assert_eq!(config.agents[1].replica_dispatch, ReplicaDispatch::RoundRobin);
+
+        config.agents[0].replica_overrides[1].provider = Some("missing".to_string());
+        assert!(matches!(validate_config(&config), Err(ConfigError::UnknownProvider(_, _))));
+
+        config.agents[0].replica_overrides[1].provider = None;
+        config.agents[0].replicas = 1;
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidReplicas(_, _))));
+    }
+
+    #[test]
+    fn test_parse_and_validate_connection_guards() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [
+                {
+                    "name": "Router",
+                    "handler": { "provider": "default" },
+                    "connections": {
+                        "Billing": {
+                            "type": "blocking",
+                            "when": {
+                                "keywords": ["invoice", "refund"],
+                                "metadata": { "authenticated": "true" }
+                            }
+                        },
+                        "Support": { "type": "blocking", "when": { "expr": "!(content matches '(?i)legal')" } }
+                    }
+                },
+                { "name": "Billing", "handler": { "provider": "default" } },
+                { "name": "Support", "handler": { "provider": "default" } }
+            ]
+        }"#;
+
+        let mut config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert!(validate_config(&config).is_ok());
+        let billing = config.agents[0].connections["Billing"].when.as_ref().unwrap();
+        assert_eq!(billing.keywords, vec!["invoice", "refund"]);
+        assert_eq!(billing.metadata["authenticated"], "true");
+
+        config.agents[0].connections.get_mut("Support").unwrap().when = Some(GuardConfig {
+            expr: Some("content contains".to_string()),
+            ..Default::default()
+        });
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidGuard { .. })));
+    }
+
+    #[test]
+    fn test_parse_and_validate_human_agent() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [
+                {
+                    "name": "Triage",
+                    "handler": { "provider": "default" },
+                    "connections": { "Clinician": { "type": "blocking", "timeout_secs": 600 } }
+                },
+                {
+                    "name": "Clinician",
+                    "system_prompt": "Approve the draft or reply with a correction.",
+                    "handler": { "type": "human", "human_timeout_secs": 600 }
+                }
+            ]
+        }"#;
+
+        let mut config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert_eq!(config.agents[1].handler.handler_type, HandlerKind::Human);
+        assert!(validate_config(&config).is_ok());
+
+        config.agents[1].handler.human_timeout_secs = Some(0);
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidHumanAgent(..))));
+
+        config.agents[1].handler.human_timeout_secs = None;
+        config.agents[1].connections.insert(
+            "Triage".to_string(),
+            serde_json::from_str(r#"{ "type": "notify" }"#).unwrap(),
+        );
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidHumanAgent(..))));
+    }
+
+    #[test]
+    fn test_parse_and_validate_rules_handler() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [
+                {
+                    "name": "Router",
+                    "handler": {
+                        "type": "rules",
+                        "rules": [
+                            { "target": "Billing", "keywords": ["invoice", "refund"] },
+                            { "target": "Support", "regex": "(?i)\\b(error|crash)\\b" }
+                        ],
+                        "no_match_response": "Please rephrase your question."
+                    },
+                    "connections": {
+                        "Billing": { "type": "blocking" },
+                        "Support": { "type": "blocking" }
+                    }
+                },
+                { "name": "Billing", "handler": { "provider": "default" } },
+                { "name": "Support", "handler": { "provider": "default" } }
+            ]
+        }"#;
+
+        let mut config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert_eq!(config.agents[0].handler.handler_type, HandlerKind::Rules);
+        assert!(config.agents[0].handler.provider.is_none());
+        assert_eq!(config.agents[1].handler.handler_type, HandlerKind::Llm);
+        assert!(validate_config(&config).is_ok());
+
+        // The LLM fallback needs a provider
+        config.agents[0].handler.llm_fallback = true;
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidRules(..))));
+        config.agents[0].handler.provider = Some("default".to_string());
+        assert!(validate_config(&config).is_ok());
+
+        // Rule targets must be blocking connections
+        config.agents[0].handler.rules[0].target = "Legal".to_string();
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidRules(..))));
+
+        // LLM handlers still need a provider
+        config.agents[0].handler.rules[0].target = "Billing".to_string();
+        config.agents[1].handler.provider = None;
+        assert!(matches!(validate_config(&config), Err(ConfigError::MissingProvider(_))));
+    }
+
+    #[tokio::test]
+    async fn test_example_rules_without_embedder_are_not_registered() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": {},
+            "agents": [
+                {
+                    "name": "Router",
+                    "handler": {
+                        "type": "rules",
+                        "rules": [ { "target": "Billing", "examples": ["I want my money back"] } ]
+                    },
+                    "connections": { "Billing": { "type": "blocking" } }
+                }
+            ]
+        }"#;
+        let config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        let system = Arc::new(AgentSystem::with_default_config());
+
+        let result =
+            register_agent_from_config(system.clone(), "billing", &config.agents[0], &HashMap::new(), &HashMap::new())
+                .await;
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("need an embedding provider"), "{}", error);
+        assert!(system.get_agent("Router").await.is_none());
+    }
+
+    #[test]
+    fn test_parse_and_validate_workflows() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [
+                { "name": "Chat", "handler": { "provider": "default" }, "connections": { "Onboarding": { "type": "blocking" } } },
+                { "name": "Writer", "handler": { "provider": "default" } }
+            ],
+            "tools": [
+                { "name": "crm", "description": "CRM lookup", "endpoint": { "url": "http://localhost/crm" } }
+            ],
+            "workflows": [
+                {
+                    "name": "Onboarding",
+                    "steps": [
+                        { "id": "lookup", "target": "crm", "input": { "email": "${input}" } },
+                        { "id": "welcome", "target": "Writer", "input": "Welcome ${steps.lookup.output}", "depends_on": ["lookup"] }
+                    ]
+                }
+            ]
+        }"#;
+
+        let mut config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert!(validate_config(&config).is_ok());
+        assert_eq!(config.workflows[0].steps[1].depends_on.len(), 1);
+
+        config.workflows[0].steps[1].target = Some("Nobody".to_string());
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidWorkflow(..))));
+
+        config.workflows[0].steps[1].target = Some("Writer".to_string());
+        config.workflows[0].name = "Writer".to_string();
+        assert!(matches!(validate_config(&config), Err(ConfigError::WorkflowNameConflict(_))));
+    }
+
+    #[test]
+    fn test_parse_and_validate_triggers() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [ { "name": "Coordinator", "handler": { "provider": "default" } } ],
+            "triggers": [
+                { "name": "digest", "cron": "0 7 * * 1-5", "target_agent": "Coordinator", "prompt": "Digest for ${date}" }
+            ]
+        }"#;
+
+        let mut config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert!(validate_config(&config).is_ok());
+        assert!(config.triggers[0].session_id.is_none());
+
+        config.triggers[0].cron = "7am".to_string();
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidTrigger(..))));
+
+        config.triggers[0].cron = "0 7 * * *".to_string();
+        config.triggers[0].target_agent = Some("Nobody".to_string());
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidTrigger(..))));
+
+        config.triggers[0].target_agent = None;
+        config.triggers.push(config.triggers[0].clone());
+        assert!(matches!(validate_config(&config), Err(ConfigError::DuplicateTriggerName(_))));
+    }
+
+    #[test]
+    fn test_parse_and_validate_hooks() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [ { "name": "Triage", "handler": { "provider": "default" } } ],
+            "hooks": [
+                { "name": "issues", "secret": "${ISSUE_HOOK_SECRET}", "target_agent": "Triage", "prompt": "New issue: ${payload.issue.title}" }
+            ]
+        }"#;
+
+        let mut config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert!(validate_config(&config).is_ok());
+        assert!(config.hooks[0].callback_url.is_none());
+
+        config.hooks[0].callback_url = Some("ftp://example.com".to_string());
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidHook(..))));
+
+        config.hooks[0].callback_url = None;
+        config.hooks[0].prompt = "New issue: ${issue.title}".to_string();
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidHook(..))));
+
+        config.hooks[0].prompt = "${payload}".to_string();
+        config.hooks.push(config.hooks[0].clone());
+        assert!(matches!(validate_config(&config), Err(ConfigError::DuplicateHookName(_))));
+    }
+
+    #[test]
+    fn test_parse_and_validate_memory() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [ { "name": "Support", "handler": { "provider": "default" }, "memory": { "scope": "user" } } ]
+        }"#;
+
+        let mut config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert!(validate_config(&config).is_ok());
+        let memory = config.agents[0].memory.as_mut().unwrap();
+        assert_eq!(memory.scope, crate::memory::MemoryScope::User);
+        assert_eq!(memory.top_k, 5);
+
+        memory.top_k = 0;
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidMemory(..))));
+
+        config.agents[0].memory = Some(MemoryConfig::default());
+        config.agents[0].handler.handler_type = HandlerKind::Human;
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidMemory(..))));
+    }
+
+    #[test]
+    fn test_parse_and_validate_reflection() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" }, "critic": { "type": "ollama" } },
+            "agents": [ {
+                "name": "Support",
+                "handler": {
+                    "provider": "default",
+                    "reflection": { "rubric": "Cites the policy", "max_revisions": 2, "provider": "critic" }
+                }
+            } ]
+        }"#;
+
+        let mut config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert!(validate_config(&config).is_ok());
+
+        let reflection = config.agents[0].handler.reflection.as_mut().unwrap();
+        assert_eq!(reflection.max_revisions, 2);
+        reflection.provider = Some("missing".to_string());
+        assert!(matches!(validate_config(&config), Err(ConfigError::UnknownProvider(..))));
+
+        config.agents[0].handler.reflection = Some(ReflectionConfig::new(" "));
+        assert!(matches!(validate_config(&config), Err(ConfigError::InvalidReflection(..))));
+        config.agents[0].handler.reflection = Some(ReflectionConfig::new("Cites the policy"));
+        assert_eq!(config.agents[0].handler.reflection.as_ref().unwrap().max_revisions, 1);
+        assert!(validate_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_validate_pipeline_stages() {
+        let json = r#"{
+            "system": {},
+            "llm_providers": { "default": { "type": "ollama" } },
+            "agents": [
+                {
+                    "name": "Lead",
+                    "handler": {
+                        "provider": "default",
+                        "routing_behavior": "pipeline",
+                        "pipeline": ["Security", "Performance"]
+                    },
+                    "connections": {
+                        "Security": { "type": "blocking" },
+                        "Performance": { "type": "notify" }
+                    }
+                },
+                { "name": "Security", "handler": { "provider": "default" } },
+                { "name": "Performance", "handler": { "provider": "default" } }
+            ]
+        }"#;
+
+        let mut config: SystemConfigJson = serde_json::from_str(json).unwrap();
+        assert!(matches!(
+            validate_config(&config),
+            Err(ConfigError::InvalidPipeline(_, _))
+        ));
+
+        config.agents[0]
+            .connections
+            .get_mut("Performance")
+            .unwrap()
+            .connection_type = "blocking".to_string();
+        assert!(validate_config(&config).is_ok());
+        assert_eq!(config.agents[0].handler.pipeline, vec!["Security", "Performance"]);
+    }
 }
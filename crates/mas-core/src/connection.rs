@@ -1,3 +1,6 @@
+use crate::guard::Guard;
+use crate::message::Message;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Defines how a connection behaves when sending messages
@@ -16,6 +19,8 @@ pub struct Connection {
     pub connection_type: ConnectionType,
     /// Per-connection timeout override (takes priority over global timeout)
     pub timeout: Option<Duration>,
+    /// Optional guard restricting which messages may use this connection
+    pub guard: Option<Arc<Guard>>,
 }
 
 impl Connection {
@@ -24,6 +29,7 @@ impl Connection {
         Self {
             connection_type: ConnectionType::Blocking,
             timeout,
+            guard: None,
         }
     }
 
@@ -32,9 +38,21 @@ impl Connection {
         Self {
             connection_type: ConnectionType::Notify,
             timeout: None,
+            guard: None,
         }
     }
 
+    /// Only let messages through that satisfy the guard
+    pub fn with_guard(mut self, guard: Guard) -> Self {
+        self.guard = Some(Arc::new(guard));
+        self
+    }
+
+    /// Whether the connection's guard (if any) lets the message through
+    pub fn allows(&self, message: &Message) -> bool {
+        self.guard.as_ref().is_none_or(|guard| guard.allows(message))
+    }
+
     /// Check if this is a blocking connection
     pub fn is_blocking(&self) -> bool {
         matches!(self.connection_type, ConnectionType::Blocking)
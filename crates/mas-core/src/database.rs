@@ -68,6 +68,14 @@ pub struct DatabaseConfig {
     /// Safety: only allow SELECT/WITH queries when true
     #[serde(default)]
     pub read_only: Option<bool>,
+
+    /// Hold every query until a human approves, denies or rewrites it
+    #[serde(default)]
+    pub requires_approval: bool,
+
+    /// Seconds a held query waits for a decision (default 300)
+    #[serde(default)]
+    pub approval_timeout_secs: Option<u64>,
 }
 
 impl DatabaseConfig {
@@ -80,6 +88,8 @@ impl DatabaseConfig {
             max_connections: None,
             timeout_secs: None,
             read_only: Some(true),
+            requires_approval: false,
+            approval_timeout_secs: None,
         }
     }
 
@@ -92,6 +102,16 @@ impl DatabaseConfig {
         self.read_only = Some(read_only);
         self
     }
+
+    pub fn with_requires_approval(mut self, requires_approval: bool) -> Self {
+        self.requires_approval = requires_approval;
+        self
+    }
+
+    pub fn with_approval_timeout_secs(mut self, secs: u64) -> Self {
+        self.approval_timeout_secs = Some(secs);
+        self
+    }
 }
 
 /// Runtime representation of a database (config + any runtime state)
@@ -127,7 +147,9 @@ mod tests {
             "database_type": "sqlite",
             "read_only": true,
             "max_connections": 5,
-            "timeout_secs": 30
+            "timeout_secs": 30,
+            "requires_approval": true,
+            "approval_timeout_secs": 60
         }"#;
 
         let config: DatabaseConfig = serde_json::from_str(json).unwrap();
@@ -135,6 +157,8 @@ mod tests {
         assert_eq!(config.database_type, DatabaseType::Sqlite);
         assert_eq!(config.read_only, Some(true));
         assert_eq!(config.max_connections, Some(5));
+        assert!(config.requires_approval);
+        assert_eq!(config.approval_timeout_secs, Some(60));
     }
 
     #[test]
@@ -148,6 +172,8 @@ mod tests {
         assert_eq!(config.name, "DB");
         assert_eq!(config.database_type, DatabaseType::Sqlite); // default
         assert!(config.read_only.is_none());
+        assert!(!config.requires_approval);
+        assert!(config.approval_timeout_secs.is_none());
     }
 
     #[test]
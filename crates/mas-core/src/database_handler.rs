@@ -3,22 +3,30 @@
 //! This handler implements `MessageHandler` so it can be registered in the agent system.
 //! When an agent sends a message to a database node, the message content is treated as
 //! a SQL query. The handler executes the query and returns results as CSV.
+//!
+//! Databases with `requires_approval` hold every query until a reviewer
+//! approves, denies or rewrites it (see [`crate::approval`]).
 
 use crate::agent::Agent;
 use crate::agent_system::MessageHandler;
+use crate::approval::{ApprovalDecision, ApprovalGate, ApprovalKind, ApprovalQueue};
 use crate::database::Database;
 use crate::message::Message;
+use crate::metrics::{metrics, CallKind};
 
 use async_trait::async_trait;
 use sqlx::any::AnyRow;
 use sqlx::{Column, Pool, Row, any::Any};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{debug, warn};
 
 /// Handler that executes SQL queries against a database pool
 pub struct DatabaseHandler {
     database: Arc<Database>,
     pool: Pool<Any>,
+    /// Review step for databases with `requires_approval`
+    approval: Option<ApprovalGate>,
 }
 
 impl DatabaseHandler {
@@ -41,7 +49,38 @@ impl DatabaseHandler {
             max_conns
         );
 
-        Ok(Self { database, pool })
+        Ok(Self {
+            database,
+            pool,
+            approval: None,
+        })
+    }
+
+    /// Hold every query for review in `queue`, waiting at most `timeout` for a decision
+    pub fn with_approval(mut self, queue: ApprovalQueue, timeout: Duration) -> Self {
+        self.approval = Some(ApprovalGate::new(queue, timeout));
+        self
+    }
+
+    /// Reject queries this connection may not run
+    fn check_query(&self, sql: &str) -> Result<(), String> {
+        if sql.is_empty() {
+            return Err("Error: Empty SQL query.".to_string());
+        }
+
+        // Read-only enforcement
+        if self.database.config.read_only.unwrap_or(false) {
+            let upper = sql.to_uppercase();
+            let first_keyword = upper.split_whitespace().next().unwrap_or("");
+            if !matches!(first_keyword, "SELECT" | "WITH" | "EXPLAIN" | "SHOW" | "DESCRIBE" | "PRAGMA") {
+                return Err(
+                    "Error: This database connection is read-only. Only SELECT, WITH, EXPLAIN, SHOW, DESCRIBE, and PRAGMA queries are allowed."
+                        .to_string(),
+                );
+            }
+        }
+
+        Ok(())
     }
 
     /// Format query results as CSV
@@ -118,21 +157,26 @@ impl DatabaseHandler {
 #[async_trait]
 impl MessageHandler for DatabaseHandler {
     async fn handle(&self, message: &Message, _agent: &Agent) -> Option<String> {
-        let sql = message.content.trim();
-
-        if sql.is_empty() {
-            return Some("Error: Empty SQL query.".to_string());
+        let mut sql = message.content.trim().to_string();
+        if let Err(e) = self.check_query(&sql) {
+            return Some(e);
         }
 
-        // Read-only enforcement
-        if self.database.config.read_only.unwrap_or(false) {
-            let upper = sql.to_uppercase();
-            let first_keyword = upper.split_whitespace().next().unwrap_or("");
-            if !matches!(first_keyword, "SELECT" | "WITH" | "EXPLAIN" | "SHOW" | "DESCRIBE" | "PRAGMA") {
-                return Some(
-                    "Error: This database connection is read-only. Only SELECT, WITH, EXPLAIN, SHOW, DESCRIBE, and PRAGMA queries are allowed."
-                        .to_string(),
-                );
+        // Hold the query until a reviewer decides on it
+        if let Some(gate) = &self.approval {
+            match gate.review(ApprovalKind::Database, self.database.name(), message, sql.clone()).await {
+                Some(ApprovalDecision::Approve) => {}
+                Some(ApprovalDecision::Edit { input }) => {
+                    sql = input.trim().to_string();
+                    if let Err(e) = self.check_query(&sql) {
+                        return Some(e);
+                    }
+                }
+                Some(ApprovalDecision::Deny { reason }) => {
+                    let reason = reason.map(|r| format!(": {}", r)).unwrap_or_default();
+                    return Some(format!("Error: The query was denied by the reviewer{}", reason));
+                }
+                None => return Some("Error: The query was not approved in time".to_string()),
             }
         }
 
@@ -143,7 +187,10 @@ impl MessageHandler for DatabaseHandler {
         );
 
         // Execute the query
-        match sqlx::query(sql).fetch_all(&self.pool).await {
+        let started = Instant::now();
+        let result = sqlx::query(&sql).fetch_all(&self.pool).await;
+        metrics().observe_call(CallKind::Database, self.database.name(), started.elapsed(), result.is_err());
+        match result {
             Ok(rows) => {
                 if rows.is_empty() {
                     return Some("Query returned 0 rows.".to_string());
@@ -220,4 +267,30 @@ mod tests {
         let csv = DatabaseHandler::format_as_csv(&columns, &rows);
         assert_eq!(csv, "name,age\nAlice,30\nBob,25");
     }
+
+    #[tokio::test]
+    async fn test_edited_query_runs_instead() {
+        let config = crate::database::DatabaseConfig::new("AppDB", "sqlite::memory:").with_read_only(false);
+        let queue = ApprovalQueue::new();
+        let mut published = queue.subscribe();
+        let handler = DatabaseHandler::new(Arc::new(Database::new(config)))
+            .await
+            .unwrap()
+            .with_approval(queue.clone(), Duration::from_secs(5));
+        let agent = crate::agent::AgentBuilder::new("AppDB").build();
+
+        let query = tokio::spawn(async move {
+            let message = Message::new("Analyst", "AppDB", "DELETE FROM orders");
+            handler.handle(&message, &agent).await
+        });
+
+        let approval = published.recv().await.unwrap();
+        assert_eq!(approval.kind, ApprovalKind::Database);
+        assert_eq!(approval.request, "DELETE FROM orders");
+        queue
+            .decide(approval.id, ApprovalDecision::Edit { input: "SELECT 1 AS one".to_string() })
+            .unwrap();
+
+        assert_eq!(query.await.unwrap().as_deref(), Some("one\n1"));
+    }
 }
@@ -0,0 +1,91 @@
+//! Debates between connected agents
+//!
+//! With `RoutingBehavior::Debate`, a routing agent puts the question to two or
+//! more of its blocking connections and lets them exchange arguments for a
+//! fixed number of rounds. In every round after the first, each participant
+//! sees the arguments made so far. The routing agent then judges the debate.
+
+use crate::decision::ConversationTurn;
+
+/// Build the message sent to a participant for the given round
+///
+/// `previous` holds the turns of all earlier rounds (`turn_number` is the round).
+pub fn debate_prompt(
+    question: &str,
+    participant: &str,
+    participants: &[String],
+    round: u16,
+    rounds: u16,
+    previous: &[ConversationTurn],
+) -> String {
+    let opponents: Vec<&str> = participants
+        .iter()
+        .map(|p| p.as_str())
+        .filter(|p| *p != participant)
+        .collect();
+
+    let mut prompt = format!(
+        "Question: {}\n\nYou are taking part in a debate with {} (round {} of {}).",
+        question,
+        opponents.join(", "),
+        round + 1,
+        rounds
+    );
+
+    if previous.is_empty() {
+        prompt.push_str("\nState your position and your strongest arguments.");
+        return prompt;
+    }
+
+    prompt.push_str("\n\nArguments so far:\n");
+    prompt.push_str(&transcript(previous));
+    prompt.push_str(
+        "\nRespond to the other participants: rebut their arguments, concede where they are right, and refine your position.",
+    );
+    prompt
+}
+
+/// Render debate turns as a readable transcript, one argument per block
+pub fn transcript(turns: &[ConversationTurn]) -> String {
+    let mut text = String::new();
+    for turn in turns {
+        text.push_str(&format!(
+            "\n[Round {}] {}: {}\n",
+            turn.turn_number + 1,
+            turn.agent,
+            turn.response
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn participants() -> Vec<String> {
+        vec!["Optimist".to_string(), "Skeptic".to_string()]
+    }
+
+    #[test]
+    fn test_first_round_prompt_names_opponents() {
+        let prompt = debate_prompt("Adopt Rust?", "Optimist", &participants(), 0, 3, &[]);
+        assert!(prompt.contains("Question: Adopt Rust?"));
+        assert!(prompt.contains("debate with Skeptic (round 1 of 3)"));
+        assert!(!prompt.contains("Arguments so far"));
+    }
+
+    #[test]
+    fn test_later_rounds_include_previous_arguments() {
+        let previous = vec![ConversationTurn {
+            agent: "Skeptic".to_string(),
+            message_sent: String::new(),
+            response: "Hiring is hard.".to_string(),
+            turn_number: 0,
+        }];
+
+        let prompt = debate_prompt("Adopt Rust?", "Optimist", &participants(), 1, 3, &previous);
+        assert!(prompt.contains("(round 2 of 3)"));
+        assert!(prompt.contains("[Round 1] Skeptic: Hiring is hard."));
+    }
+}
@@ -4,6 +4,7 @@
 //! to indicate routing decisions - whether to respond directly, forward to
 //! other agents, or both.
 
+use crate::plan::{Plan, PlanStep};
 use serde::{Deserialize, Serialize};
 
 /// A target agent for message forwarding
@@ -25,6 +26,52 @@ impl ForwardTarget {
     }
 }
 
+/// A short-lived sub-agent requested by a routing agent
+///
+/// The sub-agent is registered for a single task, receives `message`, and is
+/// unregistered once it has answered.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SpawnSpec {
+    /// Name of the sub-agent (made unique when it is registered)
+    pub name: String,
+    /// System prompt of the sub-agent
+    #[serde(default)]
+    pub system_prompt: String,
+    /// Model override (the spawning agent's model if omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Connections of the spawning agent the sub-agent may use
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub connections: Vec<String>,
+    /// The task sent to the sub-agent
+    #[serde(default)]
+    pub message: String,
+}
+
+impl SpawnSpec {
+    pub fn new(name: impl Into<String>, system_prompt: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            system_prompt: system_prompt.into(),
+            model: None,
+            connections: Vec::new(),
+            message: message.into(),
+        }
+    }
+
+    /// Set the model of the sub-agent
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Let the sub-agent use these connections of the spawning agent
+    pub fn with_connections(mut self, connections: Vec<String>) -> Self {
+        self.connections = connections;
+        self
+    }
+}
+
 /// The decision made by a handler about how to process a message
 ///
 /// This is the structured return type from routing-aware handlers,
@@ -44,6 +91,26 @@ pub enum HandlerDecision {
         targets: Vec<ForwardTarget>,
     },
 
+    /// Pass the message through the named stages in order, each stage
+    /// receiving the previous stage's output; the last output is the response
+    Pipeline { stages: Vec<String> },
+
+    /// Ask each target for a ballot (answer + confidence) and answer with
+    /// the aggregated vote
+    Vote { targets: Vec<ForwardTarget> },
+
+    /// Let the participants argue for a number of rounds, each seeing the
+    /// earlier arguments, then judge the debate
+    Debate { participants: Vec<String>, rounds: u16 },
+
+    /// Carry out the plan's steps in order, re-planning if a step fails, then
+    /// synthesize the results
+    Plan { plan: Plan },
+
+    /// Register short-lived sub-agents, send each its task in parallel,
+    /// synthesize their answers and unregister them
+    Spawn { agents: Vec<SpawnSpec> },
+
     /// No action - handler chose not to respond or forward
     None,
 }
@@ -68,6 +135,31 @@ impl HandlerDecision {
         Self::Forward { targets }
     }
 
+    /// Create a pipeline decision over the given stages
+    pub fn pipeline(stages: Vec<String>) -> Self {
+        Self::Pipeline { stages }
+    }
+
+    /// Create a vote decision over the given targets
+    pub fn vote(targets: Vec<ForwardTarget>) -> Self {
+        Self::Vote { targets }
+    }
+
+    /// Create a debate decision between the given participants
+    pub fn debate(participants: Vec<String>, rounds: u16) -> Self {
+        Self::Debate { participants, rounds }
+    }
+
+    /// Create a plan decision over the given steps
+    pub fn plan(steps: Vec<PlanStep>) -> Self {
+        Self::Plan { plan: Plan::new(steps) }
+    }
+
+    /// Create a spawn decision for the given sub-agents
+    pub fn spawn(agents: Vec<SpawnSpec>) -> Self {
+        Self::Spawn { agents }
+    }
+
     /// Create a response-and-forward decision
     pub fn respond_and_forward(
         content: impl Into<String>,
@@ -79,6 +171,21 @@ impl HandlerDecision {
         }
     }
 
+    /// Short name of the decision, e.g. "forward" or "debate"
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HandlerDecision::Response { .. } => "response",
+            HandlerDecision::Forward { .. } => "forward",
+            HandlerDecision::ResponseAndForward { .. } => "response_and_forward",
+            HandlerDecision::Pipeline { .. } => "pipeline",
+            HandlerDecision::Vote { .. } => "vote",
+            HandlerDecision::Debate { .. } => "debate",
+            HandlerDecision::Plan { .. } => "plan",
+            HandlerDecision::Spawn { .. } => "spawn",
+            HandlerDecision::None => "none",
+        }
+    }
+
     /// Check if this decision includes a response
     pub fn has_response(&self) -> bool {
         matches!(
@@ -120,6 +227,12 @@ impl HandlerDecision {
 /// - `{ "response": "..." }` - direct response
 /// - `{ "forward_to": [{ "agent": "...", "message": "..." }] }` - forward
 /// - Both fields present - respond and forward
+/// - `{ "spawn": [{ "name": "...", "system_prompt": "...", "message": "..." }] }` -
+///   create sub-agents (takes precedence over the others)
+///
+/// Any of them may carry `"blackboard": { "key.path": value }` to write to
+/// the request's blackboard, and `"remember": ["fact", ...]` to save facts to
+/// the agent's long-term memory.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct LlmDecisionJson {
     /// Direct response content
@@ -129,6 +242,18 @@ pub struct LlmDecisionJson {
     /// Agents to forward to
     #[serde(skip_serializing_if = "Option::is_none")]
     pub forward_to: Option<Vec<ForwardTarget>>,
+
+    /// Sub-agents to create
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn: Option<Vec<SpawnSpec>>,
+
+    /// Values to write to the blackboard, by dotted key path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blackboard: Option<serde_json::Map<String, serde_json::Value>>,
+
+    /// Facts to save to the agent's long-term memory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remember: Option<Vec<String>>,
 }
 
 impl LlmDecisionJson {
@@ -139,6 +264,9 @@ impl LlmDecisionJson {
 
     /// Convert to HandlerDecision
     pub fn into_decision(self) -> HandlerDecision {
+        if let Some(agents) = self.spawn.filter(|agents| !agents.is_empty()) {
+            return HandlerDecision::Spawn { agents };
+        }
         match (self.response, self.forward_to) {
             (Some(content), Some(targets)) if !targets.is_empty() => {
                 HandlerDecision::ResponseAndForward { content, targets }
@@ -171,6 +299,85 @@ pub struct ConversationTurn {
     pub turn_number: u16,
 }
 
+/// How a single forwarded request ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ForwardStatus {
+    /// The target answered
+    Success { response: String },
+    /// The target did not answer within its (deadline-clamped) timeout
+    Timeout { waited_ms: u64 },
+    /// The message could not be delivered
+    Error { message: String },
+    /// The target's circuit breaker is open, so the message was not sent
+    CircuitOpen,
+    /// The connection's guard rejected the message, so it was not sent
+    Blocked,
+    /// The quorum was reached before the target answered
+    Cancelled,
+    /// The target is connected by notify, so it was told but does not answer
+    Notified,
+}
+
+/// Structured outcome of forwarding a message to one target
+///
+/// Passed to `RoutingHandler::synthesize` so handlers can tell which
+/// targets answered and which failed (and why).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForwardOutcome {
+    /// The agent, tool or database the message was forwarded to
+    pub agent: String,
+    /// The message that was forwarded
+    pub message_sent: String,
+    /// How the request ended
+    #[serde(flatten)]
+    pub status: ForwardStatus,
+}
+
+impl ForwardOutcome {
+    pub fn new(agent: impl Into<String>, message_sent: impl Into<String>, status: ForwardStatus) -> Self {
+        Self {
+            agent: agent.into(),
+            message_sent: message_sent.into(),
+            status,
+        }
+    }
+
+    /// Create a successful outcome
+    pub fn success(agent: impl Into<String>, message_sent: impl Into<String>, response: impl Into<String>) -> Self {
+        Self::new(agent, message_sent, ForwardStatus::Success { response: response.into() })
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self.status, ForwardStatus::Success { .. })
+    }
+
+    /// The target's response, if it answered
+    pub fn response(&self) -> Option<&str> {
+        match &self.status {
+            ForwardStatus::Success { response } => Some(response),
+            _ => None,
+        }
+    }
+
+    /// Human-readable reason the target did not answer (None on success)
+    pub fn failure_reason(&self) -> Option<String> {
+        match &self.status {
+            ForwardStatus::Success { .. } => None,
+            ForwardStatus::Timeout { waited_ms } => {
+                Some(format!("timed out after {:.1}s", *waited_ms as f64 / 1000.0))
+            }
+            ForwardStatus::Error { message } => Some(format!("failed: {}", message)),
+            ForwardStatus::CircuitOpen => {
+                Some("skipped: unavailable after repeated failures".to_string())
+            }
+            ForwardStatus::Blocked => Some("skipped: not allowed for this request".to_string()),
+            ForwardStatus::Cancelled => Some("not awaited: quorum already reached".to_string()),
+            ForwardStatus::Notified => Some("notified: no response expected".to_string()),
+        }
+    }
+}
+
 /// Decision from the evaluation step of a multi-turn conversation
 #[derive(Debug, Clone, PartialEq)]
 pub enum EvaluationDecision {
@@ -322,11 +529,36 @@ pub fn extract_json_from_response(response: &str) -> Option<String> {
                 merged.forward_to = Some(forwards);
             }
         }
+        if let Some(writes) = obj.blackboard {
+            merged.blackboard.get_or_insert_with(Default::default).extend(writes);
+        }
+        if let Some(agents) = obj.spawn {
+            merged.spawn.get_or_insert_with(Vec::new).extend(agents);
+        }
+        if let Some(facts) = obj.remember {
+            merged.remember.get_or_insert_with(Vec::new).extend(facts);
+        }
     }
 
     serde_json::to_string(&merged).ok()
 }
 
+/// Extract the blackboard writes from an LLM response, if it has any
+pub fn parse_blackboard_writes(response: &str) -> serde_json::Map<String, serde_json::Value> {
+    extract_json_from_response(response)
+        .and_then(|json_str| LlmDecisionJson::parse(&json_str).ok())
+        .and_then(|decision_json| decision_json.blackboard)
+        .unwrap_or_default()
+}
+
+/// Extract the facts an LLM response asks to remember
+pub fn parse_remembered_facts(response: &str) -> Vec<String> {
+    extract_json_from_response(response)
+        .and_then(|json_str| LlmDecisionJson::parse(&json_str).ok())
+        .and_then(|decision_json| decision_json.remember)
+        .unwrap_or_default()
+}
+
 /// Parse an LLM response into a HandlerDecision
 ///
 /// This handles various edge cases:
@@ -375,6 +607,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_blackboard_writes() {
+        let json = r#"{ "response": "Triaged", "blackboard": { "patient.severity": "high" } }
+            { "blackboard": { "patient.age": 54 } }"#;
+        let writes = parse_blackboard_writes(json);
+        assert_eq!(writes.get("patient.severity"), Some(&serde_json::json!("high")));
+        assert_eq!(writes.get("patient.age"), Some(&serde_json::json!(54)));
+        assert_eq!(
+            parse_llm_response(json),
+            HandlerDecision::Response { content: "Triaged".to_string() }
+        );
+        assert!(parse_blackboard_writes("plain text").is_empty());
+    }
+
+    #[test]
+    fn test_parse_remembered_facts() {
+        let json = r#"{ "response": "Noted", "remember": ["Prefers email"] }"#;
+        assert_eq!(parse_remembered_facts(json), vec!["Prefers email".to_string()]);
+        assert_eq!(parse_llm_response(json), HandlerDecision::Response { content: "Noted".to_string() });
+        assert!(parse_remembered_facts(r#"{ "response": "Hi" }"#).is_empty());
+    }
+
+    #[test]
+    fn test_parse_spawn() {
+        let json = r#"{ "spawn": [
+            { "name": "Summarizer", "system_prompt": "Summarize the document.", "message": "Doc 1" },
+            { "name": "Summarizer", "system_prompt": "Summarize the document.", "model": "small", "connections": ["Search"], "message": "Doc 2" }
+        ], "response": "Summarizing" }"#;
+        assert_eq!(
+            parse_llm_response(json),
+            HandlerDecision::spawn(vec![
+                SpawnSpec::new("Summarizer", "Summarize the document.", "Doc 1"),
+                SpawnSpec::new("Summarizer", "Summarize the document.", "Doc 2")
+                    .with_model("small")
+                    .with_connections(vec!["Search".to_string()]),
+            ])
+        );
+        assert_eq!(
+            parse_llm_response(r#"{ "spawn": [], "response": "Done" }"#),
+            HandlerDecision::Response { content: "Done".to_string() }
+        );
+    }
+
     #[test]
     fn test_parse_forward_only() {
         let json = r#"{ "forward_to": [{ "agent": "Researcher", "message": "Look into this" }] }"#;
@@ -506,4 +781,20 @@ And also forwarding:
             }
         );
     }
+
+    #[test]
+    fn test_forward_outcome_serializes_status_inline() {
+        let outcome = ForwardOutcome::new("Slow", "hi", ForwardStatus::Timeout { waited_ms: 1500 });
+        let json = serde_json::to_value(&outcome).unwrap();
+        assert_eq!(json["agent"], "Slow");
+        assert_eq!(json["status"], "timeout");
+        assert_eq!(json["waited_ms"], 1500);
+        assert_eq!(outcome.failure_reason().unwrap(), "timed out after 1.5s");
+        assert!(outcome.response().is_none());
+
+        let ok = ForwardOutcome::success("Fast", "hi", "hello");
+        assert!(ok.is_success());
+        assert_eq!(ok.response(), Some("hello"));
+        assert!(ok.failure_reason().is_none());
+    }
 }
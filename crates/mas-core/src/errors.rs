@@ -28,6 +28,17 @@ pub enum AgentError {
 
     #[error("Configuration error: {0}")]
     ConfigError(String),
+
+    #[error("Connection from '{from}' to '{to}' does not allow this message")]
+    GuardRejected { from: String, to: String },
+
+    #[error("Interceptor '{interceptor}' rejected the message from '{from}' to '{to}': {reason}")]
+    Intercepted {
+        interceptor: String,
+        from: String,
+        to: String,
+        reason: String,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, AgentError>;
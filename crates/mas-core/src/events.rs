@@ -0,0 +1,226 @@
+//! System-wide lifecycle events
+//!
+//! Unlike a [`TraceCollector`](crate::TraceCollector), which a caller creates
+//! for one request, the event bus belongs to the [`AgentSystem`](crate::AgentSystem)
+//! and reports everything that happens in it: agents coming and going,
+//! messages being delivered and answered, LLM and tool calls, timeouts,
+//! errors and panicking message loops. Any number of subscribers (metrics,
+//! audit logs, the UI, webhooks) can consume the same stream.
+//!
+//! Events are only built when somebody is subscribed, and a subscriber that
+//! falls behind by more than the bus capacity misses the oldest events
+//! (`RecvError::Lagged`) instead of slowing the system down.
+
+use crate::llm::TokenUsage;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Default number of events a subscriber may fall behind by
+pub const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+tokio::task_local! {
+    static CURRENT: (EventBus, Arc<str>);
+}
+
+/// An event on the system's event bus
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemEvent {
+    /// When the event happened
+    pub timestamp: DateTime<Utc>,
+    /// What happened
+    #[serde(flatten)]
+    pub kind: SystemEventKind,
+}
+
+/// The kinds of lifecycle events
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SystemEventKind {
+    /// An agent (or workflow) was registered
+    AgentRegistered { agent: String },
+    /// An agent was unregistered
+    AgentUnregistered { agent: String },
+    /// A message was delivered to an agent, tool or database inbox
+    MessageSent { message_id: Uuid, from: String, to: String },
+    /// The answer to a message came back
+    MessageReceived {
+        /// Id of the message being answered
+        message_id: Uuid,
+        /// The agent that answered
+        from: String,
+        to: String,
+        elapsed_ms: u64,
+    },
+    /// An agent started an LLM call
+    LlmCallStarted {
+        agent: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
+    },
+    /// An LLM call finished
+    LlmCallFinished {
+        agent: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
+        duration_ms: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        usage: Option<TokenUsage>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// A tool or database started handling a call
+    ToolCallStarted { tool: String, caller: String },
+    /// A tool or database call finished (`answered` is false if it gave up)
+    ToolCallFinished {
+        tool: String,
+        caller: String,
+        duration_ms: u64,
+        answered: bool,
+    },
+    /// A message was not answered in time
+    Timeout {
+        message_id: Uuid,
+        from: String,
+        to: String,
+        waited_ms: u64,
+    },
+    /// Sending a message on behalf of an agent failed
+    Error { agent: String, message: String },
+    /// An agent's message loop panicked and stopped
+    Panic { agent: String, message: String },
+}
+
+/// Broadcast bus for [`SystemEvent`]s
+///
+/// Clones share the same channel.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<SystemEvent>,
+    /// Name of the system whose events the bus carries
+    system: Arc<str>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_CAPACITY)
+    }
+}
+
+impl EventBus {
+    /// Create a bus on which subscribers may fall behind by `capacity` events
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity.max(1));
+        Self {
+            tx,
+            system: Arc::from(""),
+        }
+    }
+
+    /// Name the system whose events the bus carries
+    pub fn with_system(mut self, system: impl Into<Arc<str>>) -> Self {
+        self.system = system.into();
+        self
+    }
+
+    /// Name of the system whose events the bus carries (empty when unnamed)
+    pub fn system(&self) -> &str {
+        &self.system
+    }
+
+    /// Subscribe to all events emitted from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<SystemEvent> {
+        self.tx.subscribe()
+    }
+
+    /// Number of current subscribers
+    pub fn subscriber_count(&self) -> usize {
+        self.tx.receiver_count()
+    }
+
+    /// Emit an event (a no-op without subscribers)
+    pub fn emit(&self, kind: SystemEventKind) {
+        self.emit_with(|| kind);
+    }
+
+    /// Emit the event built by `build`, which only runs if somebody listens
+    pub fn emit_with(&self, build: impl FnOnce() -> SystemEventKind) {
+        if self.tx.receiver_count() > 0 {
+            let _ = self.tx.send(SystemEvent {
+                timestamp: Utc::now(),
+                kind: build(),
+            });
+        }
+    }
+
+    /// Run the message loop of `agent` with this bus as the current one
+    ///
+    /// Handlers only see the message and agent; this lets them report their
+    /// own work (e.g. LLM calls) through [`EventBus::emit_current`].
+    pub async fn scope<F: Future>(self, agent: impl Into<Arc<str>>, future: F) -> F::Output {
+        CURRENT.scope((self, agent.into()), future).await
+    }
+
+    /// The bus and agent of the current message loop, if any
+    ///
+    /// Loops that hand messages to tasks of their own re-enter the scope there.
+    pub fn current() -> Option<(EventBus, Arc<str>)> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+
+    /// Name of the system whose message loop is running, if any
+    pub fn current_system() -> Option<Arc<str>> {
+        CURRENT.try_with(|(bus, _)| bus.system.clone()).ok()
+    }
+
+    /// Emit an event on the bus of the current message loop, if any
+    ///
+    /// `build` gets the name of the agent, tool or database whose loop is
+    /// running.
+    pub fn emit_current(build: impl FnOnce(&str) -> SystemEventKind) {
+        let _ = CURRENT.try_with(|(bus, agent)| bus.emit_with(|| build(agent)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_events_reach_every_subscriber_and_serialize_tagged() {
+        let bus = EventBus::default();
+        // Without subscribers nothing is built
+        bus.emit_with(|| unreachable!("no subscribers"));
+
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+        bus.emit(SystemEventKind::AgentRegistered { agent: "Echo".to_string() });
+        bus.clone()
+            .scope("Writer", async {
+                EventBus::emit_current(|agent| SystemEventKind::LlmCallStarted {
+                    agent: agent.to_string(),
+                    model: None,
+                });
+            })
+            .await;
+        // Outside a scope there is no current bus
+        EventBus::emit_current(|_| unreachable!("no current bus"));
+
+        for rx in [&mut first, &mut second] {
+            let registered = serde_json::to_value(rx.recv().await.unwrap()).unwrap();
+            assert_eq!(registered["type"], "agent_registered");
+            assert_eq!(registered["agent"], "Echo");
+            assert!(registered["timestamp"].is_string());
+            match rx.recv().await.unwrap().kind {
+                SystemEventKind::LlmCallStarted { agent, model } => {
+                    assert_eq!(agent, "Writer");
+                    assert!(model.is_none());
+                }
+                other => panic!("Expected LLM call, got {:?}", other),
+            }
+        }
+    }
+}
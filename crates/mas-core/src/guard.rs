@@ -0,0 +1,426 @@
+//! Declarative guards on connections
+//!
+//! A guard decides whether a message may travel over a connection. Guards
+//! are checked twice: when a routing agent lists the connections it may
+//! forward to (against the incoming message), and again when a message is
+//! actually sent (against the outgoing message). All configured conditions
+//! must hold.
+//!
+//! # Expressions
+//!
+//! ```text
+//! metadata.authenticated == "true" && (content contains "invoice" || content matches "(?i)refund")
+//! ```
+//!
+//! Operands are `content` and `metadata.<key>`; operators are `==`, `!=`,
+//! `contains` (case-insensitive) and `matches` (regex), combined with `&&`,
+//! `||`, `!` and parentheses. A bare operand is true when it is present and
+//! neither empty nor `"false"`.
+
+use crate::message::Message;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Guard configuration as written in a connection's `when` block
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GuardConfig {
+    /// Regex the message content must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    /// The content must contain at least one of these keywords (case-insensitive)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// Every listed metadata key must have exactly this value
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+    /// Boolean expression over `content` and `metadata.<key>` (see module docs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expr: Option<String>,
+}
+
+/// Errors raised while compiling a guard
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum GuardError {
+    #[error("invalid regex '{0}': {1}")]
+    InvalidRegex(String, String),
+
+    #[error("invalid expression: {0}")]
+    InvalidExpression(String),
+}
+
+/// A compiled guard, ready to be checked against messages
+#[derive(Debug, Clone)]
+pub struct Guard {
+    regex: Option<Regex>,
+    keywords: Vec<String>,
+    metadata: HashMap<String, String>,
+    expr: Option<Expr>,
+}
+
+impl Guard {
+    /// Compile a guard configuration, validating regexes and the expression
+    pub fn compile(config: &GuardConfig) -> Result<Self, GuardError> {
+        Ok(Self {
+            regex: config.regex.as_deref().map(compile_regex).transpose()?,
+            keywords: config.keywords.iter().map(|k| k.to_lowercase()).collect(),
+            metadata: config.metadata.clone(),
+            expr: config.expr.as_deref().map(parse_expr).transpose()?,
+        })
+    }
+
+    /// Whether the message may pass
+    pub fn allows(&self, message: &Message) -> bool {
+        if let Some(regex) = &self.regex {
+            if !regex.is_match(&message.content) {
+                return false;
+            }
+        }
+
+        if !self.keywords.is_empty() {
+            let content = message.content.to_lowercase();
+            if !self.keywords.iter().any(|k| content.contains(k.as_str())) {
+                return false;
+            }
+        }
+
+        if !self
+            .metadata
+            .iter()
+            .all(|(key, value)| message.metadata.get(key) == Some(value))
+        {
+            return false;
+        }
+
+        self.expr.as_ref().is_none_or(|expr| expr.eval(message))
+    }
+}
+
+fn compile_regex(pattern: &str) -> Result<Regex, GuardError> {
+    Regex::new(pattern).map_err(|e| GuardError::InvalidRegex(pattern.to_string(), e.to_string()))
+}
+
+// ─── Expression language ───
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Content,
+    Metadata(String),
+}
+
+impl Operand {
+    fn value<'a>(&self, message: &'a Message) -> Option<&'a str> {
+        match self {
+            Operand::Content => Some(message.content.as_str()),
+            Operand::Metadata(key) => message.metadata.get(key).map(|v| v.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(bool),
+    Truthy(Operand),
+    Equals(Operand, String),
+    NotEquals(Operand, String),
+    Contains(Operand, String),
+    Matches(Operand, Regex),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, message: &Message) -> bool {
+        match self {
+            Expr::Literal(value) => *value,
+            Expr::Truthy(operand) => operand
+                .value(message)
+                .is_some_and(|v| !v.is_empty() && !v.eq_ignore_ascii_case("false")),
+            Expr::Equals(operand, expected) => operand.value(message) == Some(expected.as_str()),
+            Expr::NotEquals(operand, expected) => operand.value(message) != Some(expected.as_str()),
+            Expr::Contains(operand, needle) => operand
+                .value(message)
+                .is_some_and(|v| v.to_lowercase().contains(needle.as_str())),
+            Expr::Matches(operand, regex) => operand.value(message).is_some_and(|v| regex.is_match(v)),
+            Expr::Not(inner) => !inner.eval(message),
+            Expr::And(left, right) => left.eval(message) && right.eval(message),
+            Expr::Or(left, right) => left.eval(message) || right.eval(message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    LParen,
+    RParen,
+    Not,
+    And,
+    Or,
+    Eq,
+    NotEq,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, GuardError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '!' => {
+                chars.next();
+                if chars.next_if_eq(&'=').is_some() {
+                    tokens.push(Token::NotEq);
+                } else {
+                    tokens.push(Token::Not);
+                }
+            }
+            '=' | '&' | '|' => {
+                chars.next();
+                if chars.next_if_eq(&c).is_none() {
+                    return Err(GuardError::InvalidExpression(format!(
+                        "expected '{}{}'",
+                        c, c
+                    )));
+                }
+                tokens.push(match c {
+                    '=' => Token::Eq,
+                    '&' => Token::And,
+                    _ => Token::Or,
+                });
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => {
+                            if let Some(escaped) = chars.next() {
+                                value.push(escaped);
+                            }
+                        }
+                        Some(ch) if ch == c => break,
+                        Some(ch) => value.push(ch),
+                        None => {
+                            return Err(GuardError::InvalidExpression(
+                                "unterminated string".to_string(),
+                            ))
+                        }
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = String::new();
+                while let Some(ch) = chars.next_if(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '.' | '-')) {
+                    ident.push(ch);
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            other => {
+                return Err(GuardError::InvalidExpression(format!(
+                    "unexpected character '{}'",
+                    other
+                )))
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_expr(input: &str) -> Result<Expr, GuardError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(GuardError::InvalidExpression(format!(
+            "unexpected {:?}",
+            token
+        ))),
+    }
+}
+
+/// Recursive descent parser: `||` binds loosest, then `&&`, then `!`
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, GuardError> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            left = Expr::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, GuardError> {
+        let mut left = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            left = Expr::And(Box::new(left), Box::new(self.parse_unary()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, GuardError> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LParen) => {
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(inner),
+                    _ => Err(GuardError::InvalidExpression("expected ')'".to_string())),
+                }
+            }
+            Some(Token::Ident(ident)) => self.parse_comparison(ident),
+            Some(token) => Err(GuardError::InvalidExpression(format!(
+                "unexpected {:?}",
+                token
+            ))),
+            None => Err(GuardError::InvalidExpression(
+                "unexpected end of expression".to_string(),
+            )),
+        }
+    }
+
+    fn parse_comparison(&mut self, ident: String) -> Result<Expr, GuardError> {
+        let operand = match ident.as_str() {
+            "true" => return Ok(Expr::Literal(true)),
+            "false" => return Ok(Expr::Literal(false)),
+            "content" => Operand::Content,
+            other => match other.strip_prefix("metadata.") {
+                Some(key) if !key.is_empty() => Operand::Metadata(key.to_string()),
+                _ => {
+                    return Err(GuardError::InvalidExpression(format!(
+                        "unknown operand '{}' (expected content or metadata.<key>)",
+                        other
+                    )))
+                }
+            },
+        };
+
+        let operator = match self.peek() {
+            Some(Token::Eq) | Some(Token::NotEq) => self.next(),
+            Some(Token::Ident(word)) if word == "contains" || word == "matches" => self.next(),
+            _ => return Ok(Expr::Truthy(operand)),
+        };
+
+        let value = match self.next() {
+            Some(Token::Str(value)) => value,
+            _ => {
+                return Err(GuardError::InvalidExpression(
+                    "expected a quoted string after operator".to_string(),
+                ))
+            }
+        };
+
+        Ok(match operator {
+            Some(Token::Eq) => Expr::Equals(operand, value),
+            Some(Token::NotEq) => Expr::NotEquals(operand, value),
+            Some(Token::Ident(word)) if word == "contains" => {
+                Expr::Contains(operand, value.to_lowercase())
+            }
+            _ => Expr::Matches(operand, compile_regex(&value)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(content: &str, metadata: &[(&str, &str)]) -> Message {
+        Message::new("Router", "Billing", content).with_metadata(
+            metadata
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    fn guard(config: GuardConfig) -> Guard {
+        Guard::compile(&config).unwrap()
+    }
+
+    #[test]
+    fn test_empty_guard_allows_everything() {
+        assert!(guard(GuardConfig::default()).allows(&message("hi", &[])));
+    }
+
+    #[test]
+    fn test_regex_keywords_and_metadata_must_all_hold() {
+        let guard = guard(GuardConfig {
+            regex: Some(r"\d+".to_string()),
+            keywords: vec!["Invoice".to_string(), "refund".to_string()],
+            metadata: HashMap::from([("authenticated".to_string(), "true".to_string())]),
+            expr: None,
+        });
+
+        assert!(guard.allows(&message("INVOICE 42", &[("authenticated", "true")])));
+        assert!(!guard.allows(&message("invoice", &[("authenticated", "true")])));
+        assert!(!guard.allows(&message("order 42", &[("authenticated", "true")])));
+        assert!(!guard.allows(&message("refund 42", &[])));
+    }
+
+    #[test]
+    fn test_expression_evaluation() {
+        let guard = guard(GuardConfig {
+            expr: Some(
+                r#"metadata.authenticated && !(metadata.plan == 'free') && (content contains "invoice" || content matches "(?i)^refund")"#
+                    .to_string(),
+            ),
+            ..Default::default()
+        });
+
+        assert!(guard.allows(&message("Where is my Invoice?", &[("authenticated", "true")])));
+        assert!(guard.allows(&message("Refund please", &[("authenticated", "yes"), ("plan", "pro")])));
+        assert!(!guard.allows(&message("Refund please", &[("authenticated", "true"), ("plan", "free")])));
+        assert!(!guard.allows(&message("invoice", &[("authenticated", "false")])));
+        assert!(!guard.allows(&message("hello", &[("authenticated", "true")])));
+    }
+
+    #[test]
+    fn test_invalid_guards_are_rejected() {
+        for expr in ["content ==", "metadata.", "user == 'x'", "(true", "a = b", "content contains 'x' extra"] {
+            let result = Guard::compile(&GuardConfig {
+                expr: Some(expr.to_string()),
+                ..Default::default()
+            });
+            assert!(matches!(result, Err(GuardError::InvalidExpression(_))), "{}", expr);
+        }
+
+        let result = Guard::compile(&GuardConfig {
+            regex: Some("(".to_string()),
+            ..Default::default()
+        });
+        assert!(matches!(result, Err(GuardError::InvalidRegex(_, _))));
+    }
+}
@@ -0,0 +1,726 @@
+//! Guardrails on LLM input and output
+//!
+//! Guardrails check the text going into an LLM call (`input`) and the text
+//! coming out of it (`output`). They are configured per agent, around the
+//! agent's LLM calls, and per system, around every user-facing request and
+//! response. Checks run in this order:
+//!
+//! | Check        | Actions                        | Default  |
+//! |--------------|--------------------------------|----------|
+//! | `deny`       | `block`, `redact`              | `block`  |
+//! | `pii`        | `block`, `redact`              | `redact` |
+//! | `classifier` | `block`                        | `block`  |
+//! | `max_length` | `block`, `rewrite` (truncate)  | `rewrite`|
+//! | `disclaimer` | `block`, `rewrite` (append)    | `rewrite`|
+//!
+//! A blocked text is replaced by `blocked_response` and no further checks
+//! run. Every violation is recorded as a `guardrail` trace event.
+//!
+//! The disclaimer counts towards `max_length`: text that needs it appended is
+//! truncated so that both fit. A classifier whose provider fails blocks the
+//! text unless its `on_error` is `allow`.
+//!
+//! # Example
+//!
+//! ```json
+//! "guardrails": {
+//!     "input": { "deny": ["(?i)ignore (all )?previous instructions"] },
+//!     "output": {
+//!         "pii": ["email", "phone", "iban"],
+//!         "max_length": 2000,
+//!         "disclaimer": "This is not legal advice.",
+//!         "classifier": { "policy": "No medical diagnoses.", "provider": "default" }
+//!     }
+//! }
+//! ```
+
+use crate::llm::{LlmMessage, LlmProvider};
+use crate::tracer::{TraceCollector, TraceEvent, TraceEventType};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use tracing::warn;
+
+/// Guardrail configuration of an agent or system
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuardrailConfig {
+    /// Checks on text going into LLM calls (the user's request at system level)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<GuardrailRules>,
+    /// Checks on text coming out of LLM calls (the final response at system level)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<GuardrailRules>,
+    /// Text given instead of blocked content
+    #[serde(default = "default_blocked_response")]
+    pub blocked_response: String,
+}
+
+fn default_blocked_response() -> String {
+    "I can't help with that request.".to_string()
+}
+
+/// The checks applied to one side of an LLM call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuardrailRules {
+    /// Regexes the text must not match
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+    #[serde(default = "default_deny_action")]
+    pub deny_action: GuardrailAction,
+    /// Personal data to look for
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pii: Vec<PiiKind>,
+    #[serde(default = "default_pii_action")]
+    pub pii_action: GuardrailAction,
+    /// Most characters the text may have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+    #[serde(default = "default_rewrite")]
+    pub max_length_action: GuardrailAction,
+    /// Text that must appear in the text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disclaimer: Option<String>,
+    #[serde(default = "default_rewrite")]
+    pub disclaimer_action: GuardrailAction,
+    /// Let an LLM decide whether the text complies with a policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classifier: Option<ClassifierConfig>,
+}
+
+impl Default for GuardrailRules {
+    fn default() -> Self {
+        Self {
+            deny: Vec::new(),
+            deny_action: default_deny_action(),
+            pii: Vec::new(),
+            pii_action: default_pii_action(),
+            max_length: None,
+            max_length_action: default_rewrite(),
+            disclaimer: None,
+            disclaimer_action: default_rewrite(),
+            classifier: None,
+        }
+    }
+}
+
+fn default_deny_action() -> GuardrailAction {
+    GuardrailAction::Block
+}
+
+fn default_pii_action() -> GuardrailAction {
+    GuardrailAction::Redact
+}
+
+fn default_rewrite() -> GuardrailAction {
+    GuardrailAction::Rewrite
+}
+
+/// LLM classifier configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassifierConfig {
+    /// What compliant text looks like
+    pub policy: String,
+    /// References a key in llm_providers (the agent's provider if omitted;
+    /// required for system guardrails)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Model for the classifier (the provider's default if omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// What happens to the text when the classifier cannot be asked
+    #[serde(default)]
+    pub on_error: ClassifierFallback,
+}
+
+/// What a classifier does with text when its LLM call fails
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClassifierFallback {
+    /// Treat the text as violating the policy
+    #[default]
+    Block,
+    /// Let the text through
+    Allow,
+}
+
+/// What happens to text that violates a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardrailAction {
+    /// Replace the whole text with the blocked response
+    Block,
+    /// Mask the offending parts
+    Redact,
+    /// Fix the text (truncate it or append the disclaimer)
+    Rewrite,
+}
+
+impl GuardrailAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GuardrailAction::Block => "block",
+            GuardrailAction::Redact => "redact",
+            GuardrailAction::Rewrite => "rewrite",
+        }
+    }
+}
+
+/// Kinds of personal data guardrails detect
+///
+/// Checked in declaration order, so IBANs are redacted before their digits
+/// could be taken for a phone number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Email,
+    Iban,
+    Phone,
+}
+
+impl PiiKind {
+    /// Placeholder that replaces redacted matches
+    fn placeholder(&self) -> &'static str {
+        match self {
+            PiiKind::Email => "[EMAIL]",
+            PiiKind::Phone => "[PHONE]",
+            PiiKind::Iban => "[IBAN]",
+        }
+    }
+
+    fn regex(&self) -> &'static Regex {
+        static EMAIL: OnceLock<Regex> = OnceLock::new();
+        static PHONE: OnceLock<Regex> = OnceLock::new();
+        static IBAN: OnceLock<Regex> = OnceLock::new();
+        match self {
+            PiiKind::Email => EMAIL.get_or_init(|| {
+                Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").expect("valid email regex")
+            }),
+            // Runs of digits with the usual separators; matches with fewer
+            // than 9 digits (such as dates) are not treated as phone numbers
+            PiiKind::Phone => PHONE.get_or_init(|| Regex::new(r"\+?\(?\d[\d\s().-]{6,}\d").expect("valid phone regex")),
+            PiiKind::Iban => IBAN.get_or_init(|| {
+                Regex::new(r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,3})?\b").expect("valid IBAN regex")
+            }),
+        }
+    }
+
+    /// Whether a regex match really is this kind of data
+    fn accepts(&self, found: &str) -> bool {
+        match self {
+            PiiKind::Phone => found.chars().filter(|c| c.is_ascii_digit()).count() >= 9,
+            _ => true,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            PiiKind::Email => "email",
+            PiiKind::Phone => "phone",
+            PiiKind::Iban => "iban",
+        }
+    }
+}
+
+/// Which side of an LLM call a check ran on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardrailStage {
+    Input,
+    Output,
+}
+
+/// Errors raised while compiling guardrails
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum GuardrailError {
+    #[error("invalid deny regex '{0}': {1}")]
+    InvalidRegex(String, String),
+
+    #[error("{0} does not support the '{1}' action")]
+    UnsupportedAction(&'static str, &'static str),
+
+    #[error("max_length must be at least 1")]
+    InvalidMaxLength,
+
+    #[error("max_length {0} leaves no room for the disclaimer")]
+    DisclaimerTooLong(usize),
+
+    #[error("classifier policy must not be empty")]
+    EmptyPolicy,
+}
+
+/// A check a text failed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GuardrailViolation {
+    pub stage: GuardrailStage,
+    /// The check that failed (`deny`, `pii`, `classifier`, `max_length`, `disclaimer`)
+    pub check: String,
+    pub action: GuardrailAction,
+    /// What was found, without repeating redacted data
+    #[serde(default)]
+    pub detail: String,
+}
+
+impl GuardrailViolation {
+    fn new(stage: GuardrailStage, check: &str, action: GuardrailAction, detail: impl Into<String>) -> Self {
+        Self {
+            stage,
+            check: check.to_string(),
+            action,
+            detail: detail.into(),
+        }
+    }
+
+    /// The violations recorded in a trace
+    pub fn from_events(events: &[TraceEvent]) -> Vec<Self> {
+        events
+            .iter()
+            .filter(|e| e.event_type == TraceEventType::Guardrail)
+            .filter_map(|e| serde_json::from_str(&e.content).ok())
+            .collect()
+    }
+}
+
+/// Result of running guardrails over a text
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuardrailVerdict {
+    /// The text to use: redacted or rewritten, or the blocked response
+    pub text: String,
+    /// Whether the text was blocked
+    pub blocked: bool,
+    pub violations: Vec<GuardrailViolation>,
+}
+
+impl GuardrailVerdict {
+    /// Record every violation in the trace
+    pub async fn record(&self, trace: &TraceCollector, from: &str, to: &str) {
+        for violation in &self.violations {
+            let content = serde_json::to_string(violation).unwrap_or_default();
+            trace.record(TraceEvent::guardrail(from, to, content)).await;
+        }
+    }
+}
+
+/// Compiled guardrails, ready to check texts
+pub struct Guardrails {
+    input: Option<Rules>,
+    output: Option<Rules>,
+    blocked_response: String,
+}
+
+impl fmt::Debug for Guardrails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Guardrails")
+            .field("input", &self.input)
+            .field("output", &self.output)
+            .field("blocked_response", &self.blocked_response)
+            .finish_non_exhaustive()
+    }
+}
+
+struct Rules {
+    deny: Vec<Regex>,
+    deny_action: GuardrailAction,
+    pii: Vec<PiiKind>,
+    pii_action: GuardrailAction,
+    max_length: Option<usize>,
+    max_length_action: GuardrailAction,
+    disclaimer: Option<String>,
+    disclaimer_action: GuardrailAction,
+    classifier: Option<ClassifierConfig>,
+    classifier_provider: Option<Arc<dyn LlmProvider>>,
+}
+
+impl fmt::Debug for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rules")
+            .field("deny", &self.deny.iter().map(Regex::as_str).collect::<Vec<_>>())
+            .field("pii", &self.pii)
+            .field("max_length", &self.max_length)
+            .field("disclaimer", &self.disclaimer)
+            .field("classifier", &self.classifier)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Rules {
+    fn compile(
+        rules: &GuardrailRules,
+        classifier_provider: &impl Fn(&ClassifierConfig) -> Option<Arc<dyn LlmProvider>>,
+    ) -> Result<Self, GuardrailError> {
+        let allow = |check: &'static str, action: GuardrailAction, allowed: &[GuardrailAction]| {
+            if allowed.contains(&action) {
+                Ok(action)
+            } else {
+                Err(GuardrailError::UnsupportedAction(check, action.as_str()))
+            }
+        };
+        use GuardrailAction::{Block, Redact, Rewrite};
+
+        if rules.max_length == Some(0) {
+            return Err(GuardrailError::InvalidMaxLength);
+        }
+        let disclaimer = rules.disclaimer.clone().filter(|d| !d.trim().is_empty());
+        if let (Some(max), Some(disclaimer)) = (rules.max_length, &disclaimer) {
+            if rules.disclaimer_action == Rewrite && disclaimer_length(disclaimer) >= max {
+                return Err(GuardrailError::DisclaimerTooLong(max));
+            }
+        }
+        if rules.classifier.as_ref().is_some_and(|c| c.policy.trim().is_empty()) {
+            return Err(GuardrailError::EmptyPolicy);
+        }
+
+        Ok(Self {
+            deny: rules
+                .deny
+                .iter()
+                .map(|pattern| {
+                    Regex::new(pattern).map_err(|e| GuardrailError::InvalidRegex(pattern.clone(), e.to_string()))
+                })
+                .collect::<Result<_, _>>()?,
+            deny_action: allow("deny", rules.deny_action, &[Block, Redact])?,
+            pii: {
+                let mut pii = rules.pii.clone();
+                pii.sort();
+                pii.dedup();
+                pii
+            },
+            pii_action: allow("pii", rules.pii_action, &[Block, Redact])?,
+            max_length: rules.max_length,
+            max_length_action: allow("max_length", rules.max_length_action, &[Block, Rewrite])?,
+            disclaimer,
+            disclaimer_action: allow("disclaimer", rules.disclaimer_action, &[Block, Rewrite])?,
+            classifier: rules.classifier.clone(),
+            classifier_provider: rules.classifier.as_ref().and_then(classifier_provider),
+        })
+    }
+}
+
+impl GuardrailConfig {
+    /// Check that the configuration compiles
+    pub fn validate(&self) -> Result<(), GuardrailError> {
+        Guardrails::compile(self, |_| None).map(|_| ())
+    }
+
+    /// Classifiers configured on either side
+    pub fn classifiers(&self) -> impl Iterator<Item = &ClassifierConfig> {
+        [&self.input, &self.output]
+            .into_iter()
+            .flatten()
+            .filter_map(|rules| rules.classifier.as_ref())
+    }
+}
+
+impl Guardrails {
+    /// Compile a guardrail configuration
+    ///
+    /// `classifier_provider` picks the provider each classifier calls;
+    /// classifiers without one are skipped with a warning.
+    pub fn compile(
+        config: &GuardrailConfig,
+        classifier_provider: impl Fn(&ClassifierConfig) -> Option<Arc<dyn LlmProvider>>,
+    ) -> Result<Self, GuardrailError> {
+        let compile = |rules: &GuardrailRules| Rules::compile(rules, &classifier_provider);
+        Ok(Self {
+            input: config.input.as_ref().map(compile).transpose()?,
+            output: config.output.as_ref().map(compile).transpose()?,
+            blocked_response: config.blocked_response.clone(),
+        })
+    }
+
+    /// Whether any checks run on this side
+    pub fn checks(&self, stage: GuardrailStage) -> bool {
+        self.rules(stage).is_some()
+    }
+
+    fn rules(&self, stage: GuardrailStage) -> Option<&Rules> {
+        match stage {
+            GuardrailStage::Input => self.input.as_ref(),
+            GuardrailStage::Output => self.output.as_ref(),
+        }
+    }
+
+    /// Run the checks of one side over a text
+    pub async fn check(&self, stage: GuardrailStage, text: &str) -> GuardrailVerdict {
+        let verdict = self.screen(stage, text).await;
+        match self.rules(stage) {
+            Some(rules) if !verdict.blocked => self.shape(stage, rules, verdict),
+            _ => verdict,
+        }
+    }
+
+    /// Run only the checks on what a text says (`deny`, `pii`, `classifier`)
+    ///
+    /// For text that is kept rather than shown, such as remembered facts,
+    /// which must not be truncated or get the disclaimer.
+    pub async fn screen(&self, stage: GuardrailStage, text: &str) -> GuardrailVerdict {
+        let mut verdict = GuardrailVerdict {
+            text: text.to_string(),
+            blocked: false,
+            violations: Vec::new(),
+        };
+        let Some(rules) = self.rules(stage) else {
+            return verdict;
+        };
+
+        for regex in &rules.deny {
+            if !regex.is_match(&verdict.text) {
+                continue;
+            }
+            verdict.violations.push(GuardrailViolation::new(stage, "deny", rules.deny_action, regex.as_str()));
+            if rules.deny_action == GuardrailAction::Block {
+                return self.block(verdict);
+            }
+            verdict.text = regex.replace_all(&verdict.text, "[REDACTED]").into_owned();
+        }
+
+        for kind in &rules.pii {
+            let regex = kind.regex();
+            let found = regex.find_iter(&verdict.text).filter(|m| kind.accepts(m.as_str())).count();
+            if found == 0 {
+                continue;
+            }
+            let detail = format!("{} {}", found, kind.name());
+            verdict.violations.push(GuardrailViolation::new(stage, "pii", rules.pii_action, detail));
+            if rules.pii_action == GuardrailAction::Block {
+                return self.block(verdict);
+            }
+            verdict.text = regex
+                .replace_all(&verdict.text, |caps: &regex::Captures| {
+                    let found = &caps[0];
+                    if kind.accepts(found) { kind.placeholder().to_string() } else { found.to_string() }
+                })
+                .into_owned();
+        }
+
+        if let Some(classifier) = &rules.classifier {
+            if let Some(reason) = classify(classifier, rules.classifier_provider.as_ref(), &verdict.text).await {
+                verdict
+                    .violations
+                    .push(GuardrailViolation::new(stage, "classifier", GuardrailAction::Block, reason));
+                return self.block(verdict);
+            }
+        }
+
+        verdict
+    }
+
+    /// Run the checks on the form of a text (`max_length`, `disclaimer`)
+    fn shape(&self, stage: GuardrailStage, rules: &Rules, mut verdict: GuardrailVerdict) -> GuardrailVerdict {
+        let missing_disclaimer = rules.disclaimer.as_ref().filter(|d| !verdict.text.contains(d.as_str()));
+
+        if let Some(max) = rules.max_length {
+            // Leave room for a disclaimer that gets appended
+            let limit = match missing_disclaimer {
+                Some(disclaimer) if rules.disclaimer_action == GuardrailAction::Rewrite => {
+                    max - disclaimer_length(disclaimer)
+                }
+                _ => max,
+            };
+            let length = verdict.text.chars().count();
+            if length > limit {
+                let detail = format!("{} characters, at most {}", length, limit);
+                verdict.violations.push(GuardrailViolation::new(stage, "max_length", rules.max_length_action, detail));
+                if rules.max_length_action == GuardrailAction::Block {
+                    return self.block(verdict);
+                }
+                verdict.text = verdict.text.chars().take(limit).collect();
+            }
+        }
+
+        if let Some(disclaimer) = missing_disclaimer {
+            verdict.violations.push(GuardrailViolation::new(stage, "disclaimer", rules.disclaimer_action, "missing"));
+            if rules.disclaimer_action == GuardrailAction::Block {
+                return self.block(verdict);
+            }
+            verdict.text = format!("{}\n\n{}", verdict.text.trim_end(), disclaimer);
+        }
+
+        verdict
+    }
+
+    fn block(&self, mut verdict: GuardrailVerdict) -> GuardrailVerdict {
+        verdict.text = self.blocked_response.clone();
+        verdict.blocked = true;
+        verdict
+    }
+}
+
+/// Characters the disclaimer adds when appended, with the blank line before it
+fn disclaimer_length(disclaimer: &str) -> usize {
+    disclaimer.chars().count() + 2
+}
+
+/// Ask the classifier whether the text complies with its policy
+///
+/// Returns why it does not, or `None` if it does. Classifier errors block the
+/// text or let it through as configured by `on_error`.
+async fn classify(
+    classifier: &ClassifierConfig,
+    provider: Option<&Arc<dyn LlmProvider>>,
+    text: &str,
+) -> Option<String> {
+    let Some(provider) = provider else {
+        warn!("Guardrail classifier has no provider, skipping it");
+        return None;
+    };
+    let messages = [LlmMessage::system(classifier_prompt(&classifier.policy)), LlmMessage::user(text)];
+    match provider.complete(&messages, classifier.model.as_deref(), None).await {
+        Ok(response) => parse_classification(&response.content),
+        Err(e) => match classifier.on_error {
+            ClassifierFallback::Block => {
+                warn!("Guardrail classifier failed, blocking the text: {}", e);
+                Some(format!("classifier failed: {}", e))
+            }
+            ClassifierFallback::Allow => {
+                warn!("Guardrail classifier failed, letting the text through: {}", e);
+                None
+            }
+        },
+    }
+}
+
+/// System prompt for the classifier
+fn classifier_prompt(policy: &str) -> String {
+    format!(
+        r#"You check texts against this policy:
+{}
+
+Respond with JSON only:
+{{ "allowed": true }} if the text complies with the policy, or
+{{ "allowed": false, "reason": "which part of the policy it breaks" }} otherwise."#,
+        policy
+    )
+}
+
+#[derive(Deserialize)]
+struct Classification {
+    allowed: bool,
+    #[serde(default)]
+    reason: String,
+}
+
+/// Parse the classifier's reply into why the text is not allowed
+///
+/// Replies that are not JSON count as allowed unless they start with "not allowed".
+fn parse_classification(reply: &str) -> Option<String> {
+    let trimmed = reply.trim();
+    if let (Some(start), Some(end)) = (trimmed.find('{'), trimmed.rfind('}')) {
+        if let Ok(classification) = serde_json::from_str::<Classification>(&trimmed[start..=end]) {
+            return (!classification.allowed).then_some(classification.reason);
+        }
+    }
+    trimmed.to_lowercase().starts_with("not allowed").then(|| trimmed.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guardrails(output: &str) -> Guardrails {
+        let config: GuardrailConfig =
+            serde_json::from_str(&format!(r#"{{ "output": {}, "blocked_response": "Blocked." }}"#, output)).unwrap();
+        Guardrails::compile(&config, |_| None).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pii_is_redacted_and_reported_without_the_data() {
+        let guardrails = guardrails(r#"{ "pii": ["email", "phone", "iban"] }"#);
+        let verdict = guardrails
+            .check(
+                GuardrailStage::Output,
+                "Mail jane.doe@example.com or call +49 30 1234567, pay to DE89 3704 0044 0532 0130 00 by 2024-05-01.",
+            )
+            .await;
+        assert!(!verdict.blocked);
+        assert_eq!(verdict.text, "Mail [EMAIL] or call [PHONE], pay to [IBAN] by 2024-05-01.");
+        let details: Vec<_> = verdict.violations.iter().map(|v| v.detail.as_str()).collect();
+        assert_eq!(details, vec!["1 email", "1 iban", "1 phone"]);
+
+        // Input checks are configured separately
+        assert!(guardrails.check(GuardrailStage::Input, "jane@example.com").await.violations.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_deny_blocks_and_rewrites_apply_in_order() {
+        let guardrails = guardrails(
+            r#"{ "deny": ["(?i)password"], "max_length": 30, "disclaimer": "Not advice." }"#,
+        );
+        let verdict = guardrails.check(GuardrailStage::Output, "The PASSWORD is hunter2").await;
+        assert!(verdict.blocked);
+        assert_eq!(verdict.text, "Blocked.");
+        assert_eq!(verdict.violations[0].check, "deny");
+
+        // The text is cut short enough for the disclaimer to fit
+        let verdict = guardrails.check(GuardrailStage::Output, "Buy low, sell high").await;
+        assert_eq!(verdict.text, "Buy low, sell hig\n\nNot advice.");
+        assert_eq!(verdict.text.chars().count(), 30);
+        let checks: Vec<_> = verdict.violations.iter().map(|v| (v.check.as_str(), v.action)).collect();
+        assert_eq!(checks, vec![("max_length", GuardrailAction::Rewrite), ("disclaimer", GuardrailAction::Rewrite)]);
+
+        let verdict = guardrails.check(GuardrailStage::Output, "Hold. Not advice.").await;
+        assert_eq!(verdict.text, "Hold. Not advice.");
+        assert!(verdict.violations.is_empty());
+
+        let config: GuardrailConfig =
+            serde_json::from_str(r#"{ "output": { "max_length": 12, "disclaimer": "Not advice." } }"#).unwrap();
+        assert_eq!(config.validate(), Err(GuardrailError::DisclaimerTooLong(12)));
+    }
+
+    #[tokio::test]
+    async fn test_classifier_errors_block_unless_allowed() {
+        use crate::llm::{CompletionOptions, CompletionResponse, LlmError};
+
+        struct Failing;
+
+        #[async_trait::async_trait]
+        impl LlmProvider for Failing {
+            fn name(&self) -> &str {
+                "failing"
+            }
+
+            fn default_model(&self) -> &str {
+                "failing-model"
+            }
+
+            async fn complete(
+                &self,
+                _messages: &[LlmMessage],
+                _model: Option<&str>,
+                _options: Option<CompletionOptions>,
+            ) -> Result<CompletionResponse, LlmError> {
+                Err(LlmError::ProviderError("unavailable".to_string()))
+            }
+
+            async fn health_check(&self) -> Result<(), LlmError> {
+                Ok(())
+            }
+        }
+
+        let compile = |on_error: &str| {
+            let config: GuardrailConfig = serde_json::from_str(&format!(
+                r#"{{ "output": {{ "classifier": {{ "policy": "Be nice.", "on_error": "{}" }} }} }}"#,
+                on_error
+            ))
+            .unwrap();
+            Guardrails::compile(&config, |_| Some(Arc::new(Failing) as Arc<dyn LlmProvider>)).unwrap()
+        };
+        let verdict = compile("block").check(GuardrailStage::Output, "Hello").await;
+        assert!(verdict.blocked);
+        assert_eq!(verdict.violations[0].detail, "classifier failed: Provider returned error: unavailable");
+        let verdict = compile("allow").check(GuardrailStage::Output, "Hello").await;
+        assert!(!verdict.blocked);
+        assert_eq!(verdict.text, "Hello");
+    }
+
+    #[test]
+    fn test_validate_rejects_unsupported_actions() {
+        let config: GuardrailConfig =
+            serde_json::from_str(r#"{ "input": { "deny": ["x"], "deny_action": "rewrite" } }"#).unwrap();
+        assert_eq!(config.validate(), Err(GuardrailError::UnsupportedAction("deny", "rewrite")));
+        let config: GuardrailConfig = serde_json::from_str(r#"{ "input": { "deny": ["("] } }"#).unwrap();
+        assert!(matches!(config.validate(), Err(GuardrailError::InvalidRegex(..))));
+        assert_eq!(parse_classification(r#"{ "allowed": false, "reason": "diagnosis" }"#), Some("diagnosis".to_string()));
+        assert_eq!(parse_classification(r#"{ "allowed": true }"#), None);
+    }
+}
@@ -0,0 +1,234 @@
+//! Inbound webhook triggers
+//!
+//! A hook turns an HTTP call from another service (an issue tracker, an
+//! alerting system) into a prompt. The API server exposes each hook at
+//! `POST /hooks/<system>/<hook>`; the request body must be signed with the
+//! hook's secret, and the JSON payload is rendered through the hook's prompt
+//! template. This module holds the configuration, the signature check and
+//! the template logic.
+//!
+//! # Example Configuration
+//!
+//! ```json
+//! {
+//!   "name": "github-issues",
+//!   "secret": "${GITHUB_WEBHOOK_SECRET}",
+//!   "target_agent": "Triage",
+//!   "prompt": "New issue #${payload.issue.number}: ${payload.issue.title}\n\n${payload.issue.body}",
+//!   "callback_url": "https://bots.example.com/triage-result"
+//! }
+//! ```
+//!
+//! Requests carry `sha256=<hex HMAC-SHA256 of the body>` in the signature
+//! header (`X-Hub-Signature-256` unless configured otherwise), the format
+//! GitHub uses. A secret of the form `${ENV_VAR}` is read from the
+//! environment, so it does not have to be stored with the system config.
+//!
+//! Prompt templates may use `${payload}` (the whole payload as JSON),
+//! `${payload.<path>}` (a field; array elements by index, e.g.
+//! `${payload.alerts.0.labels.severity}`) and `${hook}` (the hook's name).
+//! Missing fields render as empty text.
+
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::sync::OnceLock;
+
+/// Default header carrying the request signature
+pub const DEFAULT_SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+
+/// Configuration for an inbound webhook trigger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookConfig {
+    /// Unique name of the hook within its system (the last path segment)
+    pub name: String,
+    /// HMAC secret, or `${ENV_VAR}` to read it from the environment
+    pub secret: String,
+    /// Header carrying the signature (default `X-Hub-Signature-256`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature_header: Option<String>,
+    /// Agent or workflow to send the prompt to (system default if omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_agent: Option<String>,
+    /// Prompt template rendered from the payload
+    pub prompt: String,
+    /// Existing session to post into; a new session per call if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// URL the result is POSTed to; the call is answered right away when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callback_url: Option<String>,
+}
+
+/// Errors from compiling or checking a hook
+#[derive(Debug, thiserror::Error)]
+pub enum HookError {
+    #[error("Unknown placeholder '${{{0}}}' in prompt")]
+    UnknownPlaceholder(String),
+
+    #[error("Secret is empty")]
+    EmptySecret,
+
+    #[error("Secret environment variable {0} is not set")]
+    MissingSecret(String),
+}
+
+/// A checked hook configuration
+#[derive(Debug, Clone)]
+pub struct Hook {
+    config: HookConfig,
+}
+
+impl Hook {
+    /// Check the secret and the prompt template
+    pub fn compile(config: &HookConfig) -> Result<Self, HookError> {
+        if config.secret.trim().is_empty() {
+            return Err(HookError::EmptySecret);
+        }
+        for caps in placeholder_regex().captures_iter(&config.prompt) {
+            let name = &caps[1];
+            if name != "hook" && name != "payload" && !name.starts_with("payload.") {
+                return Err(HookError::UnknownPlaceholder(name.to_string()));
+            }
+        }
+        Ok(Self { config: config.clone() })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    pub fn config(&self) -> &HookConfig {
+        &self.config
+    }
+
+    /// Header the signature is read from
+    pub fn signature_header(&self) -> &str {
+        self.config
+            .signature_header
+            .as_deref()
+            .unwrap_or(DEFAULT_SIGNATURE_HEADER)
+    }
+
+    /// Check a request body against its signature header value
+    ///
+    /// Accepts the hex digest with or without a `sha256=` prefix. Fails if
+    /// the secret comes from an environment variable that is not set.
+    pub fn verify(&self, body: &[u8], signature: &str) -> Result<bool, HookError> {
+        let secret = self.secret()?;
+        let signature = signature.trim();
+        let signature = signature.strip_prefix("sha256=").unwrap_or(signature);
+        let Ok(signature) = hex::decode(signature) else {
+            return Ok(false);
+        };
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(body);
+        Ok(mac.verify_slice(&signature).is_ok())
+    }
+
+    /// The prompt for a payload
+    pub fn render_prompt(&self, payload: &Value) -> String {
+        placeholder_regex()
+            .replace_all(&self.config.prompt, |caps: &regex::Captures| {
+                let name = &caps[1];
+                if name == "hook" {
+                    return self.config.name.clone();
+                }
+                let path = name.strip_prefix("payload").unwrap_or(name);
+                let field = path
+                    .split('.')
+                    .filter(|segment| !segment.is_empty())
+                    .try_fold(payload, |value, segment| match value {
+                        Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+                        _ => value.get(segment),
+                    });
+                match field {
+                    Some(Value::String(s)) => s.clone(),
+                    Some(Value::Null) | None => String::new(),
+                    Some(other) => other.to_string(),
+                }
+            })
+            .into_owned()
+    }
+
+    fn secret(&self) -> Result<String, HookError> {
+        let secret = self.config.secret.trim();
+        match secret.strip_prefix("${").and_then(|s| s.strip_suffix('}')) {
+            Some(var) => std::env::var(var).map_err(|_| HookError::MissingSecret(var.to_string())),
+            None => Ok(secret.to_string()),
+        }
+    }
+}
+
+/// Signature header value for a body: `sha256=<hex HMAC-SHA256>`
+///
+/// The format [`Hook::verify`] accepts; also used to sign outbound webhooks.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\$\{([^}]*)\}").expect("valid placeholder regex"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(secret: &str, prompt: &str) -> HookConfig {
+        HookConfig {
+            name: "alerts".to_string(),
+            secret: secret.to_string(),
+            signature_header: None,
+            target_agent: None,
+            prompt: prompt.to_string(),
+            session_id: None,
+            callback_url: None,
+        }
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let hook = Hook::compile(&config("s3cret", "${payload}")).unwrap();
+        let body = br#"{"alert": "disk full"}"#;
+
+        assert!(hook.verify(body, &sign("s3cret", body)).unwrap());
+        assert!(hook.verify(body, sign("s3cret", body).trim_start_matches("sha256=")).unwrap());
+        assert!(!hook.verify(body, &sign("other", body)).unwrap());
+        assert!(!hook.verify(b"{}", &sign("s3cret", body)).unwrap());
+        assert!(!hook.verify(body, "sha256=not-hex").unwrap());
+
+        let from_env = Hook::compile(&config("${MAS_TEST_UNSET_HOOK_SECRET}", "${payload}")).unwrap();
+        assert!(matches!(from_env.verify(body, "sha256=00"), Err(HookError::MissingSecret(_))));
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        let hook = Hook::compile(&config(
+            "s3cret",
+            "[${hook}] ${payload.alerts.0.labels.severity}: ${payload.alerts.0.summary} (${payload.count}) ${payload.missing}",
+        ))
+        .unwrap();
+        let payload = serde_json::json!({
+            "count": 1,
+            "alerts": [ { "summary": "Disk full", "labels": { "severity": "critical" } } ]
+        });
+        assert_eq!(hook.render_prompt(&payload), "[alerts] critical: Disk full (1) ");
+    }
+
+    #[test]
+    fn test_invalid_hooks() {
+        assert!(matches!(Hook::compile(&config("", "${payload}")), Err(HookError::EmptySecret)));
+        assert!(matches!(
+            Hook::compile(&config("s3cret", "${body.title}")),
+            Err(HookError::UnknownPlaceholder(_))
+        ));
+    }
+}
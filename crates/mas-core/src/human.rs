@@ -0,0 +1,282 @@
+//! Human-in-the-loop agents
+//!
+//! A human agent does not answer by itself. A message forwarded to it is
+//! published as a [`PendingQuestion`] in the system's [`HumanInbox`], where a
+//! person (through the API) reads it and answers. The forwarding agent waits
+//! for that answer like for any other blocking response. The question is
+//! withdrawn when nobody answers within the agent's answer timeout or before
+//! the forwarding agent stops waiting, whichever comes first; the forwarding
+//! agent then sees a timeout.
+
+use crate::agent::Agent;
+use crate::agent_system::MessageHandler;
+use crate::message::Message;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, oneshot};
+use tokio::time::{timeout_at, Instant};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// A question waiting for a human answer
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingQuestion {
+    /// Id used to answer the question
+    pub id: Uuid,
+    /// The human agent the question was sent to
+    pub agent: String,
+    /// The agent asking
+    pub from: String,
+    /// The forwarded message
+    pub question: String,
+    /// Instructions for the human (the agent's system prompt), if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    /// Session the request belongs to, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// When the question is withdrawn if still unanswered
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Errors from answering a pending question
+#[derive(Debug, thiserror::Error)]
+pub enum HumanError {
+    #[error("No pending question with id {0}")]
+    NotFound(Uuid),
+}
+
+struct Waiting {
+    question: PendingQuestion,
+    reply_tx: oneshot::Sender<String>,
+}
+
+type WaitingMap = Arc<Mutex<HashMap<Uuid, Waiting>>>;
+
+/// Questions waiting for human answers, shared by all human agents of a system
+///
+/// Clones share the same questions and subscribers.
+#[derive(Clone)]
+pub struct HumanInbox {
+    waiting: WaitingMap,
+    published_tx: broadcast::Sender<PendingQuestion>,
+}
+
+impl Default for HumanInbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HumanInbox {
+    /// Create an empty inbox
+    pub fn new() -> Self {
+        let (published_tx, _) = broadcast::channel(64);
+        Self {
+            waiting: Arc::new(Mutex::new(HashMap::new())),
+            published_tx,
+        }
+    }
+
+    /// Unanswered questions, oldest first; with a session id only that session's
+    pub fn pending(&self, session_id: Option<&str>) -> Vec<PendingQuestion> {
+        let waiting = self.waiting.lock().unwrap();
+        let mut questions: Vec<PendingQuestion> = waiting
+            .values()
+            .map(|w| &w.question)
+            .filter(|q| session_id.is_none() || q.session_id.as_deref() == session_id)
+            .cloned()
+            .collect();
+        questions.sort_by_key(|q| q.created_at);
+        questions
+    }
+
+    /// Look up an unanswered question
+    pub fn get(&self, id: Uuid) -> Option<PendingQuestion> {
+        self.waiting.lock().unwrap().get(&id).map(|w| w.question.clone())
+    }
+
+    /// Answer a question, resuming the agent that asked it
+    ///
+    /// Fails if the question was withdrawn, including when the asker stopped
+    /// waiting before the answer could be handed over.
+    pub fn answer(&self, id: Uuid, answer: impl Into<String>) -> Result<PendingQuestion, HumanError> {
+        let waiting = self
+            .waiting
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or(HumanError::NotFound(id))?;
+        waiting
+            .reply_tx
+            .send(answer.into())
+            .map_err(|_| HumanError::NotFound(id))?;
+        Ok(waiting.question)
+    }
+
+    /// Subscribe to newly published questions
+    pub fn subscribe(&self) -> broadcast::Receiver<PendingQuestion> {
+        self.published_tx.subscribe()
+    }
+
+    /// Publish a question and wait until `deadline` for its answer
+    ///
+    /// The question is withdrawn when this returns or the future is dropped.
+    async fn ask(&self, question: PendingQuestion, deadline: Instant) -> Option<String> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let _withdraw = Withdraw {
+            waiting: self.waiting.clone(),
+            id: question.id,
+        };
+        self.waiting.lock().unwrap().insert(
+            question.id,
+            Waiting {
+                question: question.clone(),
+                reply_tx,
+            },
+        );
+        let _ = self.published_tx.send(question);
+
+        timeout_at(deadline, reply_rx).await.ok()?.ok()
+    }
+}
+
+/// Removes a question from the inbox when its asker stops waiting
+struct Withdraw {
+    waiting: WaitingMap,
+    id: Uuid,
+}
+
+impl Drop for Withdraw {
+    fn drop(&mut self) {
+        if let Ok(mut waiting) = self.waiting.lock() {
+            waiting.remove(&self.id);
+        }
+    }
+}
+
+/// Message handler that hands each message to a human
+pub struct HumanHandler {
+    inbox: HumanInbox,
+    timeout: Duration,
+}
+
+impl HumanHandler {
+    /// Publish questions to `inbox`, waiting at most `timeout` for each answer
+    pub fn new(inbox: HumanInbox, timeout: Duration) -> Self {
+        Self { inbox, timeout }
+    }
+
+    /// Ask a human, waiting until the answer timeout or `deadline`, whichever
+    /// comes first
+    ///
+    /// Returns `None` if no answer arrived in time.
+    pub async fn ask(&self, message: &Message, agent: &Agent, deadline: Option<Instant>) -> Option<String> {
+        let own = Instant::now() + self.timeout;
+        let deadline = deadline.map_or(own, |d| d.min(own));
+        let wait = deadline.saturating_duration_since(Instant::now());
+        let created_at = Utc::now();
+        let question = PendingQuestion {
+            id: Uuid::new_v4(),
+            agent: agent.name.clone(),
+            from: message.from.clone(),
+            question: message.content.clone(),
+            instructions: Some(agent.system_prompt.clone()).filter(|p| !p.is_empty()),
+            session_id: message.metadata.get("session_id").cloned(),
+            created_at,
+            expires_at: created_at + chrono::Duration::from_std(wait).unwrap_or(chrono::Duration::MAX),
+        };
+        let id = question.id;
+
+        info!("[{}] Waiting for a human answer to {}", agent.name, id);
+        let answer = self.inbox.ask(question, deadline).await;
+        if answer.is_none() {
+            warn!("[{}] No human answer to {} within {:?}", agent.name, id, wait);
+        }
+        answer
+    }
+}
+
+#[async_trait]
+impl MessageHandler for HumanHandler {
+    async fn handle(&self, message: &Message, agent: &Agent) -> Option<String> {
+        self.ask(message, agent, None).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentBuilder;
+    use std::collections::HashMap;
+
+    fn request(session_id: &str) -> Message {
+        let mut metadata = HashMap::new();
+        metadata.insert("session_id".to_string(), session_id.to_string());
+        Message::new("Triage", "Clinician", "Confirm: rest and fluids").with_metadata(metadata)
+    }
+
+    #[tokio::test]
+    async fn test_answer_resumes_handler() {
+        let inbox = HumanInbox::new();
+        let handler = HumanHandler::new(inbox.clone(), Duration::from_secs(5));
+        let mut published = inbox.subscribe();
+        let agent = AgentBuilder::new("Clinician").system_prompt("Approve or correct").build();
+
+        let waiting = tokio::spawn(async move { handler.handle(&request("s-1"), &agent).await });
+
+        let question = published.recv().await.unwrap();
+        assert_eq!(question.from, "Triage");
+        assert_eq!(question.instructions.as_deref(), Some("Approve or correct"));
+        assert_eq!(inbox.pending(Some("s-1")).len(), 1);
+        assert!(inbox.pending(Some("s-2")).is_empty());
+
+        inbox.answer(question.id, "Approved").unwrap();
+        assert_eq!(waiting.await.unwrap().as_deref(), Some("Approved"));
+        assert!(inbox.pending(None).is_empty());
+        assert!(matches!(inbox.answer(question.id, "again"), Err(HumanError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_question_times_out() {
+        let inbox = HumanInbox::new();
+        let handler = HumanHandler::new(inbox.clone(), Duration::from_millis(20));
+        let agent = AgentBuilder::new("Clinician").build();
+
+        assert_eq!(handler.handle(&request("s-1"), &agent).await, None);
+        assert!(inbox.pending(None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_question_is_withdrawn_when_the_asker_stops_waiting() {
+        let inbox = HumanInbox::new();
+        let handler = HumanHandler::new(inbox.clone(), Duration::from_secs(300));
+        let agent = AgentBuilder::new("Clinician").build();
+
+        // The caller's deadline comes before the answer timeout
+        let deadline = Instant::now() + Duration::from_millis(20);
+        assert_eq!(handler.ask(&request("s-1"), &agent, Some(deadline)).await, None);
+        assert!(inbox.pending(None).is_empty());
+
+        // A dropped asker takes its question along
+        let mut published = inbox.subscribe();
+        let asking = {
+            let inbox = inbox.clone();
+            tokio::spawn(async move {
+                let handler = HumanHandler::new(inbox, Duration::from_secs(300));
+                handler.handle(&request("s-1"), &AgentBuilder::new("Clinician").build()).await
+            })
+        };
+        let question = published.recv().await.unwrap();
+        assert!(question.expires_at > question.created_at + chrono::Duration::seconds(299));
+        asking.abort();
+        let _ = asking.await;
+        assert!(inbox.pending(None).is_empty());
+        assert!(matches!(inbox.answer(question.id, "Approved"), Err(HumanError::NotFound(_))));
+    }
+}
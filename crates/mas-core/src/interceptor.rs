@@ -0,0 +1,54 @@
+//! Interceptors on message delivery
+//!
+//! An interceptor sees every hop the [`AgentSystem`](crate::AgentSystem)
+//! delivers: requests from the public send functions as well as forwards
+//! between agents, tools and databases. That makes it the place for
+//! cross-cutting behaviour (logging, redaction, metrics, auth checks) that
+//! should not live in individual handlers.
+//!
+//! Interceptors run in registration order. `before_send` is called before a
+//! message is delivered and `after_receive` when its response comes back;
+//! each hook may pass the message on (possibly modified), answer it itself,
+//! or reject it. Answering or rejecting skips the remaining interceptors.
+//! The system's guardrails wrap the chain: input guardrails run before
+//! `before_send`, output guardrails after `after_receive` and over answers
+//! an interceptor gave itself.
+
+use crate::message::Message;
+use async_trait::async_trait;
+
+/// What an interceptor decided about a message
+#[derive(Debug, Clone)]
+pub enum Interception {
+    /// Hand the message on to the next interceptor (or deliver it)
+    Continue(Message),
+    /// Answer with this content instead; the message is not delivered
+    /// (`before_send`) or the response content is replaced (`after_receive`)
+    Respond(String),
+    /// Reject the message; the send fails with `AgentError::Intercepted`
+    Reject(String),
+}
+
+/// A hook on every message the system delivers
+///
+/// Both hooks default to passing the message on unchanged, so an interceptor
+/// only implements the side it cares about. The sender and recipient of a
+/// message are fixed once it is handed to the chain; changes to `from` and
+/// `to` are ignored.
+#[async_trait]
+pub trait Interceptor: Send + Sync {
+    /// Name used in logs and rejection errors
+    fn name(&self) -> &str;
+
+    /// Called before `message` is delivered to its recipient
+    async fn before_send(&self, message: Message) -> Interception {
+        Interception::Continue(message)
+    }
+
+    /// Called with the response to `request` before it is returned to the
+    /// sender (not called for notify connections, which have no response)
+    async fn after_receive(&self, request: &Message, response: Message) -> Interception {
+        let _ = request;
+        Interception::Continue(response)
+    }
+}
@@ -1,36 +1,83 @@
 pub mod agent;
 pub mod agent_system;
+pub mod approval;
+pub mod blackboard;
+pub mod circuit_breaker;
 pub mod config;
 pub mod config_loader;
 pub mod connection;
 pub mod conversation;
+pub mod debate;
 pub mod database;
 pub mod database_handler;
 pub mod decision;
+pub mod guard;
+pub mod guardrail;
+pub mod hook;
+pub mod human;
+pub mod interceptor;
 pub mod errors;
+pub mod events;
 pub mod llm;
+pub mod memory;
 pub mod message;
+pub mod metrics;
+pub mod plan;
+pub mod reflection;
+pub mod replica;
+pub mod rule_router;
 pub mod session_memory;
+#[cfg(feature = "otlp")]
+pub mod telemetry;
 pub mod tool;
 pub mod tool_handler;
 pub mod tracer;
+pub mod trigger;
+pub mod vote;
+pub mod workflow;
 
 // Re-export commonly used types
 pub use agent::{Agent, AgentBuilder};
 pub use agent_system::{AgentSystem, MessageHandler, RoutingHandler, SendResult, ToolInfo};
+pub use approval::{ApprovalDecision, ApprovalError, ApprovalKind, ApprovalQueue, PendingApproval};
+pub use blackboard::Blackboard;
+pub use circuit_breaker::{CircuitBreaker, CircuitBreakerConfig};
 pub use config::SystemConfig;
-pub use config_loader::{load_system_from_json, parse_config_file, validate_config, SystemConfigJson};
+pub use config_loader::{
+    load_named_system_from_json, load_system_from_json, parse_config_file, validate_config, SystemConfigJson,
+};
 pub use connection::{Connection, ConnectionType};
-pub use decision::{ConversationTurn, EvaluationDecision, ForwardTarget, HandlerDecision};
+pub use decision::{
+    ConversationTurn, EvaluationDecision, ForwardOutcome, ForwardStatus, ForwardTarget, HandlerDecision,
+    SpawnSpec,
+};
 pub use errors::{AgentError, Result};
+pub use events::{EventBus, SystemEvent, SystemEventKind};
 pub use llm::{LlmHandler, LlmProvider, OllamaProvider, RoutingBehavior};
+pub use guard::{Guard, GuardConfig, GuardError};
+pub use guardrail::{
+    GuardrailAction, GuardrailConfig, GuardrailError, GuardrailStage, GuardrailVerdict, GuardrailViolation, Guardrails,
+};
+pub use hook::{Hook, HookConfig, HookError};
+pub use human::{HumanError, HumanHandler, HumanInbox, PendingQuestion};
+pub use interceptor::{Interception, Interceptor};
+pub use memory::{AgentMemory, MemoryConfig, MemoryError, MemoryFact, MemoryScope};
 pub use message::Message;
+pub use plan::{Plan, PlanStep};
+pub use reflection::{Critique, ReflectionConfig};
+pub use replica::ReplicaDispatch;
+pub use rule_router::{RuleConfig, RuleRouter};
 pub use session_memory::{
     delete_session, list_sessions, ContextHit, SessionMemory, SessionMemoryConfig,
     SessionMemoryError, StoredMessage,
 };
 pub use database::{Database, DatabaseConfig, DatabaseType};
 pub use database_handler::DatabaseHandler;
+#[cfg(feature = "otlp")]
+pub use telemetry::{TelemetryConfig, TelemetryError};
 pub use tool::{EndpointType, HttpMethod, ResponseFormat, ResponseMapping, Tool, ToolConfig, ToolEndpoint};
 pub use tool_handler::ToolHandler;
 pub use tracer::{TraceCollector, TraceEvent, TraceEventType};
+pub use trigger::{Trigger, TriggerConfig, TriggerError};
+pub use vote::{Ballot, VoteAggregation, VoteResult};
+pub use workflow::{JoinMode, StepDependency, Workflow, WorkflowConfig, WorkflowError};
@@ -1,16 +1,28 @@
-use super::provider::{CompletionOptions, LlmMessage, LlmProvider, Role};
+use super::provider::{CompletionOptions, CompletionResponse, LlmError, LlmMessage, LlmProvider, Role};
 use crate::agent::Agent;
 use crate::agent_system::{MessageHandler, RoutingHandler};
-use crate::connection::ConnectionType;
 use crate::conversation::ConversationStore;
-use crate::decision::{parse_evaluation_response, parse_llm_response, ConversationTurn, EvaluationDecision, HandlerDecision};
+use crate::debate;
+use crate::events::{EventBus, SystemEventKind};
+use crate::guardrail::{GuardrailStage, Guardrails};
+use crate::decision::{
+    parse_blackboard_writes, parse_evaluation_response, parse_llm_response, parse_remembered_facts, ConversationTurn, EvaluationDecision, ForwardOutcome,
+    ForwardTarget, HandlerDecision, SpawnSpec,
+};
+use crate::memory::AgentMemory;
 use crate::message::Message;
+use crate::metrics::metrics;
+use crate::plan::{self, Plan, PlanStep};
+use crate::reflection::{self, ReflectionConfig};
+use crate::tracer::{TraceCollector, TraceEvent};
+use crate::vote::{self, Ballot, VoteAggregation, VoteResult};
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::RwLock;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, field, info, instrument, warn, Span};
 
 /// Defines how a routing agent should delegate to its connected agents
 ///
@@ -29,6 +41,22 @@ pub enum RoutingBehavior {
     /// Try to answer directly first, only forward if the agent lacks expertise
     /// Use this for agents that should handle most queries themselves
     DirectFirst,
+    /// Pass the message through the declared pipeline stages in order, without
+    /// an LLM routing call. Each stage receives the previous stage's output.
+    /// Use this for fixed processing chains (review, extraction, formatting)
+    Pipeline,
+    /// Ask ALL connected agents for a ballot (answer + confidence) and answer
+    /// with the aggregated vote, without an LLM routing call
+    /// Use this for panels where disagreements should be auditable
+    Vote,
+    /// Let ALL connected agents debate for a number of rounds, each seeing the
+    /// others' arguments, then judge the debate and give the final answer
+    /// Use this when a question benefits from arguments and counter-arguments
+    Debate,
+    /// Write an explicit plan of steps over the connected agents, carry the
+    /// steps out in order and re-plan when a step fails
+    /// Use this for multi-step tasks where each step builds on the previous ones
+    Plan,
 }
 
 /// A message handler that uses an LLM provider to generate responses
@@ -57,6 +85,33 @@ pub struct LlmHandler {
     tool_descriptions: std::collections::HashMap<String, String>,
     /// Maximum conversation turns with other agents (0 = unlimited, 1 = single turn)
     max_turns: u16,
+    /// With `RoutingBehavior::All`: synthesize once this many targets have answered
+    quorum: Option<usize>,
+    /// With `RoutingBehavior::Pipeline`: stage names in execution order
+    pipeline: Vec<String>,
+    /// With `RoutingBehavior::Vote`: how ballots are aggregated
+    vote_aggregation: VoteAggregation,
+    /// With `RoutingBehavior::Debate`: number of argument rounds
+    debate_rounds: u16,
+    /// With `RoutingBehavior::Plan`: most re-plans after failed steps
+    max_replans: u16,
+    /// Blackboard keys the LLM may write in routing mode
+    blackboard_keys: Vec<String>,
+    /// Whether the LLM may spawn sub-agents in routing mode
+    spawning: bool,
+    /// Optional long-term memory, shared by the agent's replicas
+    memory: Option<Arc<AgentMemory>>,
+    /// Optional self-critique of answers before they are returned
+    reflection: Option<Reflection>,
+    /// Optional checks on the incoming message and on answers
+    guardrails: Option<Arc<Guardrails>>,
+}
+
+/// Rubric and critic for self-critique
+struct Reflection {
+    config: ReflectionConfig,
+    /// Provider for the critic; the handler's own provider if `None`
+    critic: Option<Arc<dyn LlmProvider>>,
 }
 
 impl LlmHandler {
@@ -71,6 +126,16 @@ impl LlmHandler {
             routing_behavior: RoutingBehavior::default(),
             tool_descriptions: std::collections::HashMap::new(),
             max_turns: 1,
+            quorum: None,
+            pipeline: Vec::new(),
+            vote_aggregation: VoteAggregation::default(),
+            debate_rounds: 2,
+            max_replans: 2,
+            blackboard_keys: Vec::new(),
+            spawning: false,
+            memory: None,
+            reflection: None,
+            guardrails: None,
         }
     }
 
@@ -131,30 +196,352 @@ impl LlmHandler {
         self
     }
 
-    /// Build the routing instructions to append to the system prompt
-    fn build_routing_instructions(&self, agent: &Agent) -> String {
-        // Collect blocking connections (these are the ones LLM can forward to)
-        let blocking_connections: Vec<(&String, &crate::connection::Connection)> = agent
-            .connections
-            .iter()
-            .filter(|(_, conn)| conn.connection_type == ConnectionType::Blocking)
-            .collect();
+    /// Set the quorum for `RoutingBehavior::All`
+    ///
+    /// Synthesis starts as soon as this many agents have answered; the
+    /// remaining forwards are abandoned. Ignored for other behaviors.
+    pub fn with_quorum(mut self, quorum: Option<usize>) -> Self {
+        self.quorum = quorum;
+        self
+    }
 
-        if blocking_connections.is_empty() {
-            // No connections to forward to - simpler instructions
-            return r#"
+    /// Set the stages for `RoutingBehavior::Pipeline`, in execution order
+    pub fn with_pipeline(mut self, stages: Vec<String>) -> Self {
+        self.pipeline = stages;
+        self
+    }
 
-You must respond with JSON in this exact format:
-{ "response": "your response here" }
+    /// Set how ballots are aggregated for `RoutingBehavior::Vote`
+    pub fn with_vote_aggregation(mut self, aggregation: VoteAggregation) -> Self {
+        self.vote_aggregation = aggregation;
+        self
+    }
 
-Do not include any text outside the JSON object."#
-                .to_string();
+    /// Set the number of argument rounds for `RoutingBehavior::Debate` (default 2)
+    pub fn with_debate_rounds(mut self, rounds: u16) -> Self {
+        self.debate_rounds = rounds;
+        self
+    }
+
+    /// Set the most re-plans after failed steps for `RoutingBehavior::Plan` (default 2)
+    pub fn with_max_replans(mut self, max_replans: u16) -> Self {
+        self.max_replans = max_replans;
+        self
+    }
+
+    /// Let the LLM write these blackboard keys (and paths below them) in routing mode
+    pub fn with_blackboard_keys(mut self, keys: Vec<String>) -> Self {
+        self.blackboard_keys = keys;
+        self
+    }
+
+    /// Let the LLM spawn short-lived sub-agents in routing mode
+    ///
+    /// Sub-agents run on this handler's provider and options, with the model
+    /// the LLM picks or this handler's model.
+    pub fn with_spawning(mut self) -> Self {
+        self.spawning = true;
+        self
+    }
+
+    /// Recall facts from, and save facts to, a long-term memory
+    pub fn with_memory(mut self, memory: Arc<AgentMemory>) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Critique answers against a rubric and revise them before returning
+    ///
+    /// The critic runs on `critic`, or on this handler's provider and model if
+    /// `None` (the config's model still overrides the model).
+    pub fn with_reflection(mut self, config: ReflectionConfig, critic: Option<Arc<dyn LlmProvider>>) -> Self {
+        self.reflection = Some(Reflection { config, critic });
+        self
+    }
+
+    /// Check incoming messages and answers against guardrails
+    pub fn with_guardrails(mut self, guardrails: Arc<Guardrails>) -> Self {
+        self.guardrails = Some(guardrails);
+        self
+    }
+
+    /// Run the input guardrails over an incoming message
+    ///
+    /// Returns the redacted message if anything was redacted, or the blocked
+    /// response if the message must not reach the LLM.
+    async fn guard_input(&self, message: &Message, agent: &Agent) -> Result<Option<Message>, String> {
+        let Some(guardrails) = self.guardrails.as_ref().filter(|g| g.checks(GuardrailStage::Input)) else {
+            return Ok(None);
+        };
+        let verdict = guardrails.check(GuardrailStage::Input, &message.content).await;
+        if let Some(trace) = TraceCollector::current() {
+            verdict.record(&trace, &message.from, &agent.name).await;
+        }
+        if verdict.blocked {
+            warn!("[{}] Input guardrails blocked the message from {}", agent.name, message.from);
+            return Err(verdict.text);
+        }
+        if verdict.violations.is_empty() {
+            return Ok(None);
+        }
+        let mut guarded = message.clone();
+        guarded.content = verdict.text;
+        Ok(Some(guarded))
+    }
+
+    /// Run the output guardrails over an answer
+    async fn guard_output(&self, content: String, message: &Message, agent: &Agent) -> String {
+        let Some(guardrails) = self.guardrails.as_ref().filter(|g| g.checks(GuardrailStage::Output)) else {
+            return content;
+        };
+        let verdict = guardrails.check(GuardrailStage::Output, &content).await;
+        if let Some(trace) = TraceCollector::current() {
+            verdict.record(&trace, &agent.name, &message.from).await;
+        }
+        if verdict.blocked {
+            warn!("[{}] Output guardrails blocked the answer to {}", agent.name, message.from);
+        }
+        verdict.text
+    }
+
+    /// Run the output guardrails' content checks over a fact to remember
+    ///
+    /// Returns the fact as it may be saved, or `None` if it is blocked.
+    async fn screen_fact(&self, fact: String, message: &Message, agent: &Agent) -> Option<String> {
+        let Some(guardrails) = self.guardrails.as_ref().filter(|g| g.checks(GuardrailStage::Output)) else {
+            return Some(fact);
+        };
+        let verdict = guardrails.screen(GuardrailStage::Output, &fact).await;
+        if let Some(trace) = TraceCollector::current() {
+            verdict.record(&trace, &agent.name, &message.from).await;
+        }
+        if verdict.blocked {
+            warn!("[{}] Output guardrails blocked a fact to remember", agent.name);
+            return None;
+        }
+        Some(verdict.text)
+    }
+
+    /// Run the reflection loop on a draft answer
+    ///
+    /// Each draft and critique is recorded in the current trace. Revisions see
+    /// the blackboard and the remembered facts the first answer saw. On LLM
+    /// errors the latest draft is returned.
+    async fn reflect(&self, message: &Message, agent: &Agent, draft: String) -> String {
+        let Some(reflection) = &self.reflection else {
+            return draft;
+        };
+        let trace = TraceCollector::current();
+        let max_revisions = reflection.config.max_revisions;
+
+        let mut answer = draft;
+        let mut system_prompt = None;
+        for revision in 0..=max_revisions {
+            if let Some(t) = &trace {
+                t.record(TraceEvent::draft(&agent.name, &message.from, &answer)).await;
+            }
+
+            let critique = match self.critique(reflection, message, agent, &answer).await {
+                Ok(critique) => critique,
+                Err(e) => {
+                    warn!("[{}] Critique failed, keeping the draft: {}", agent.name, e);
+                    break;
+                }
+            };
+            debug!("[{}] Critique of draft {}: {:?}", agent.name, revision + 1, critique);
+            if let Some(t) = &trace {
+                let content = serde_json::to_string(&critique).unwrap_or_default();
+                t.record(TraceEvent::critique(&agent.name, &agent.name, content)).await;
+            }
+            if critique.approved || revision == max_revisions {
+                break;
+            }
+
+            if system_prompt.is_none() {
+                system_prompt = Some(format!(
+                    "{}{}{}",
+                    agent.system_prompt,
+                    Self::build_blackboard_context(message),
+                    self.build_memory_context(message).await
+                ));
+            }
+            let mut messages = Vec::new();
+            if let Some(prompt) = system_prompt.as_deref().filter(|p| !p.is_empty()) {
+                messages.push(LlmMessage::system(prompt.trim_start()));
+            }
+            messages.push(LlmMessage::user(&message.content));
+            messages.push(LlmMessage::assistant(&answer));
+            messages.push(LlmMessage::user(reflection::revision_request(&critique.feedback)));
+            match self.call_llm(agent, &messages).await {
+                Ok(revised) => answer = unwrap_json_response(&revised),
+                Err(e) => {
+                    warn!("[{}] Revision failed, keeping the draft: {}", agent.name, e);
+                    break;
+                }
+            }
+        }
+        answer
+    }
+
+    /// Ask the critic for its verdict on a draft
+    async fn critique(
+        &self,
+        reflection: &Reflection,
+        message: &Message,
+        agent: &Agent,
+        draft: &str,
+    ) -> Result<reflection::Critique, String> {
+        let messages = [
+            LlmMessage::system(reflection::critique_system_prompt(&reflection.config.rubric)),
+            LlmMessage::user(reflection::critique_request(&message.content, draft)),
+        ];
+        let (provider, model) = match &reflection.critic {
+            Some(critic) => (critic, reflection.config.model.as_deref()),
+            None => (&self.provider, reflection.config.model.as_deref().or(self.model.as_deref())),
+        };
+        complete_observed(&agent.name, provider.as_ref(), &messages, model, self.options.clone())
+            .await
+            .map(|response| reflection::parse_critique(&response.content))
+            .map_err(|e| e.to_string())
+    }
+
+    /// Build the memory section of the system prompt
+    ///
+    /// Lists the remembered facts most relevant to the message and explains
+    /// how to save new ones.
+    async fn build_memory_instructions(&self, message: &Message) -> String {
+        let Some(memory) = &self.memory else {
+            return String::new();
+        };
+        if memory.scope_key(message).is_none() {
+            return String::new();
+        }
+
+        let mut instructions = self.build_memory_context(message).await;
+        if self.routing_enabled {
+            instructions.push_str(
+                r#"
+
+To remember a fact for future conversations (e.g. a customer preference), add a "remember" list to your JSON, e.g.
+{ "response": "...", "remember": ["the fact"] }"#,
+            );
+        } else {
+            instructions.push_str(
+                "\n\nTo remember a fact for future conversations (e.g. a customer preference), \
+                 write it on its own line starting with REMEMBER: (these lines are not shown to anyone).",
+            );
+        }
+        instructions
+    }
+
+    /// Build the part of the memory section listing the remembered facts
+    /// most relevant to the message
+    async fn build_memory_context(&self, message: &Message) -> String {
+        let Some(memory) = self.memory.as_ref().filter(|m| m.scope_key(message).is_some()) else {
+            return String::new();
+        };
+        let mut context = String::new();
+        match memory.recall(message).await {
+            Ok(facts) if !facts.is_empty() => {
+                context.push_str("\n\nWhat you remember from earlier conversations:\n");
+                for fact in facts {
+                    context.push_str(&format!("- {}\n", fact.content));
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Could not recall memories: {}", e),
+        }
+        context
+    }
+
+    /// Save the facts an LLM response asks to remember
+    ///
+    /// In simple mode the facts are `REMEMBER:` lines, which are removed from
+    /// the returned content; in routing mode they are the JSON `remember` list.
+    /// Facts are screened by the output guardrails first: redacted ones are
+    /// saved redacted, blocked ones are not saved.
+    async fn save_memories(&self, content: String, message: &Message, agent: &Agent) -> String {
+        let Some(memory) = &self.memory else {
+            return content;
+        };
+        let (facts, content) = if self.routing_enabled {
+            (parse_remembered_facts(&content), content)
+        } else {
+            let (facts, lines): (Vec<&str>, Vec<&str>) =
+                content.lines().partition(|line| line.trim_start().starts_with("REMEMBER:"));
+            let facts = facts
+                .iter()
+                .map(|line| line.trim_start().trim_start_matches("REMEMBER:").trim().to_string())
+                .collect();
+            (facts, lines.join("\n").trim().to_string())
+        };
+        for fact in facts {
+            let Some(fact) = self.screen_fact(fact, message, agent).await else {
+                continue;
+            };
+            match memory.remember(message, &fact).await {
+                Ok(true) => debug!("[{}] Remembered: {}", agent.name, fact),
+                Ok(false) => {}
+                Err(e) => warn!("[{}] Could not save memory: {}", agent.name, e),
+            }
         }
+        content
+    }
+
+    /// Build the blackboard section of the system prompt
+    ///
+    /// Shows what earlier agents wrote and, in routing mode, which keys this
+    /// agent may write itself.
+    fn build_blackboard_instructions(&self, message: &Message) -> String {
+        let mut instructions = Self::build_blackboard_context(message);
+        if self.routing_enabled && !self.blackboard_keys.is_empty() {
+            instructions.push_str(&format!(
+                r#"
+
+To share facts with the agents and tools after you, add a "blackboard" object to your JSON, e.g.
+{{ "response": "...", "blackboard": {{ "{}": "value" }} }}
+Keys you may write: {}"#,
+                self.blackboard_keys[0],
+                self.blackboard_keys.join(", ")
+            ));
+        }
+        instructions
+    }
 
-        // Build agent/tool list for routing decisions
-        // Include descriptions for tools so LLM knows what they do
+    /// Build the part of the blackboard section showing what earlier agents wrote
+    fn build_blackboard_context(message: &Message) -> String {
+        if message.blackboard.is_empty() {
+            return String::new();
+        }
+        let snapshot = serde_json::to_string_pretty(&message.blackboard.snapshot()).unwrap_or_default();
+        format!("\n\nShared blackboard for this request (written by other agents):\n{}", snapshot)
+    }
+
+    /// Write the blackboard values from an LLM response, skipping keys this agent may not write
+    fn apply_blackboard_writes(&self, content: &str, message: &Message, agent: &Agent) {
+        if self.blackboard_keys.is_empty() {
+            return;
+        }
+        for (key, value) in parse_blackboard_writes(content) {
+            let allowed = self
+                .blackboard_keys
+                .iter()
+                .any(|k| key == *k || key.strip_prefix(k.as_str()).is_some_and(|rest| rest.starts_with('.')));
+            if allowed {
+                debug!("[{}] Blackboard {} = {}", agent.name, key, value);
+                message.blackboard.set(&key, value);
+            } else {
+                warn!("[{}] Ignoring write to blackboard key '{}' it may not write", agent.name, key);
+            }
+        }
+    }
+
+    /// Build the agent/tool list for routing decisions
+    ///
+    /// Tools include their descriptions so the LLM knows what they do.
+    fn connection_list(&self, connections: &[(&String, &crate::connection::Connection)]) -> String {
         let mut agent_list = String::new();
-        for (name, _conn) in &blocking_connections {
+        for (name, _conn) in connections {
             if let Some(description) = self.tool_descriptions.get(*name) {
                 // This is a tool - include its description
                 agent_list.push_str(&format!("- {} (Tool): {}\n", name, description));
@@ -163,6 +550,69 @@ Do not include any text outside the JSON object."#
                 agent_list.push_str(&format!("- {}\n", name));
             }
         }
+        agent_list
+    }
+
+    /// Keep the plan steps assigned to connections the message may be forwarded to
+    fn valid_plan_steps(&self, steps: Vec<PlanStep>, message: &Message, agent: &Agent) -> Vec<PlanStep> {
+        steps
+            .into_iter()
+            .filter(|step| {
+                let valid = agent.blocking_connections_for(message).any(|(name, _)| *name == step.agent);
+                if !valid {
+                    warn!("[{}] Dropping plan step for unknown connection '{}'", agent.name, step.agent);
+                }
+                valid
+            })
+            .collect()
+    }
+
+    /// Build the instructions for spawning sub-agents, if this agent may
+    fn build_spawn_instructions(&self, agent: &Agent, message: &Message) -> String {
+        if !self.spawning {
+            return String::new();
+        }
+        let connections: Vec<_> = agent.blocking_connections_for(message).collect();
+        let connection_note = if connections.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "\nA sub-agent may use these connections if you list them in \"connections\":\n{}",
+                self.connection_list(&connections)
+            )
+        };
+
+        format!(
+            r#"
+
+For work that splits into independent tasks (for example summarising each of several documents),
+you may instead create sub-agents that each handle one task in parallel:
+{{ "spawn": [{{ "name": "Summarizer", "system_prompt": "what the sub-agent does", "message": "its task" }}] }}
+Optionally give a sub-agent a "model" and a "connections" list.{}
+Their answers are combined for you."#,
+            connection_note
+        )
+    }
+
+    /// Build the routing instructions to append to the system prompt
+    fn build_routing_instructions(&self, agent: &Agent, message: &Message) -> String {
+        // Collect blocking connections (these are the ones LLM can forward to),
+        // leaving out those whose guards reject this message
+        let blocking_connections: Vec<(&String, &crate::connection::Connection)> =
+            agent.blocking_connections_for(message).collect();
+
+        if blocking_connections.is_empty() {
+            // No connections to forward to - simpler instructions
+            return r#"
+
+You must respond with JSON in this exact format:
+{ "response": "your response here" }
+
+Do not include any text outside the JSON object."#
+                .to_string();
+        }
+
+        let agent_list = self.connection_list(&blocking_connections);
 
         // Build behavior-specific instructions
         let behavior_instructions = match self.routing_behavior {
@@ -256,6 +706,9 @@ RULES:
                     agent_list
                 )
             }
+            RoutingBehavior::Plan => plan::planning_instructions(&agent_list),
+            // Pipeline, vote and debate agents never ask the LLM for a routing decision
+            RoutingBehavior::Pipeline | RoutingBehavior::Vote | RoutingBehavior::Debate => String::new(),
         };
 
         behavior_instructions
@@ -265,9 +718,15 @@ RULES:
     async fn build_messages(&self, message: &Message, agent: &Agent) -> Vec<LlmMessage> {
         let mut messages = Vec::new();
 
-        // System prompt from agent
-        if !agent.system_prompt.is_empty() {
-            messages.push(LlmMessage::system(&agent.system_prompt));
+        // System prompt from agent, with what is on the blackboard and what it remembers
+        let system_prompt = format!(
+            "{}{}{}",
+            agent.system_prompt,
+            self.build_blackboard_instructions(message),
+            self.build_memory_instructions(message).await
+        );
+        if !system_prompt.is_empty() {
+            messages.push(LlmMessage::system(system_prompt.trim_start()));
         }
 
         // Add conversation history if available
@@ -306,7 +765,10 @@ RULES:
 
         // Enhanced system prompt with routing instructions
         let mut system_prompt = agent.system_prompt.clone();
-        system_prompt.push_str(&self.build_routing_instructions(agent));
+        system_prompt.push_str(&self.build_routing_instructions(agent, message));
+        system_prompt.push_str(&self.build_spawn_instructions(agent, message));
+        system_prompt.push_str(&self.build_blackboard_instructions(message));
+        system_prompt.push_str(&self.build_memory_instructions(message).await);
         messages.push(LlmMessage::system(&system_prompt));
 
         // Add conversation history if available
@@ -339,17 +801,20 @@ RULES:
     }
 
     /// Build LLM messages for synthesis (combining forwarded responses)
+    ///
+    /// Targets that did not answer are listed separately so the LLM can tell
+    /// the sender which perspectives are missing.
     fn build_synthesis_messages(
         &self,
         original_message: &Message,
-        forwarded_responses: &[(String, String)],
+        outcomes: &[ForwardOutcome],
         agent: &Agent,
     ) -> Vec<LlmMessage> {
         let mut messages = Vec::new();
 
         // System prompt for synthesis
         let synthesis_prompt = format!(
-            "{}\n\nYou received responses from other agents. Synthesize them into a coherent response for the original sender. Be concise and helpful.",
+            "{}\n\nYou received responses from other agents. Synthesize them into a coherent response for the original sender. Be concise and helpful. If some agents did not respond, say which ones and do not invent their answers.",
             agent.system_prompt
         );
         messages.push(LlmMessage::system(&synthesis_prompt));
@@ -362,8 +827,22 @@ RULES:
 
         // Forwarded responses
         let mut responses_text = String::from("Responses received:\n");
-        for (agent_name, response) in forwarded_responses {
-            responses_text.push_str(&format!("\n[{}]: {}\n", agent_name, response));
+        for outcome in outcomes {
+            if let Some(response) = outcome.response() {
+                responses_text.push_str(&format!("\n[{}]: {}\n", outcome.agent, response));
+            }
+        }
+
+        // Targets that failed to answer
+        let failures: Vec<String> = outcomes
+            .iter()
+            .filter_map(|o| o.failure_reason().map(|reason| format!("- {}: {}", o.agent, reason)))
+            .collect();
+        if !failures.is_empty() {
+            responses_text.push_str(&format!(
+                "\nAgents that did not respond:\n{}\n",
+                failures.join("\n")
+            ));
         }
         messages.push(LlmMessage::user(&responses_text));
 
@@ -375,6 +854,52 @@ RULES:
         messages
     }
 
+    /// Build LLM messages for judging a vote (the routing agent picks the final answer)
+    fn build_judge_messages(
+        &self,
+        original_message: &Message,
+        ballots: &[Ballot],
+        agent: &Agent,
+    ) -> Vec<LlmMessage> {
+        let judge_prompt = format!(
+            "{}\n\nYou are the judge of a vote. Several agents answered the same question, each with a confidence between 0 and 1. Weigh their answers and reasoning, then give the single final answer. Respond with the final answer only.",
+            agent.system_prompt
+        );
+
+        let mut ballots_text = String::from("Ballots:\n");
+        for ballot in ballots {
+            ballots_text.push_str(&format!(
+                "\n[{}] (confidence {:.2}): {}\n",
+                ballot.agent, ballot.confidence, ballot.answer
+            ));
+        }
+
+        vec![
+            LlmMessage::system(&judge_prompt),
+            LlmMessage::user(format!("Question: {}", original_message.content)),
+            LlmMessage::user(&ballots_text),
+        ]
+    }
+
+    /// Build LLM messages for judging a debate
+    fn build_debate_judge_messages(
+        &self,
+        original_message: &Message,
+        turns: &[ConversationTurn],
+        agent: &Agent,
+    ) -> Vec<LlmMessage> {
+        let judge_prompt = format!(
+            "{}\n\nYou are the judge of a debate. Read all arguments, weigh their strengths and weaknesses, and give the final answer to the question. Briefly explain which arguments convinced you.",
+            agent.system_prompt
+        );
+
+        vec![
+            LlmMessage::system(&judge_prompt),
+            LlmMessage::user(format!("Question: {}", original_message.content)),
+            LlmMessage::user(format!("Debate transcript:\n{}", debate::transcript(turns))),
+        ]
+    }
+
     /// Build LLM messages for evaluation (multi-turn: should the agent follow up?)
     fn build_evaluation_messages(
         &self,
@@ -428,13 +953,9 @@ RULES:
         message: &Message,
         agent: &Agent,
     ) -> HandlerDecision {
-        use crate::decision::ForwardTarget;
-
-        // Get all blocking connection names
+        // Get all blocking connection names the message may be forwarded to
         let blocking_agents: Vec<String> = agent
-            .connections
-            .iter()
-            .filter(|(_, conn)| conn.connection_type == ConnectionType::Blocking)
+            .blocking_connections_for(message)
             .map(|(name, _)| name.clone())
             .collect();
 
@@ -509,6 +1030,12 @@ RULES:
                     }
                 }
             }
+            // Leave pipelines, votes, debates, plans and sub-agents untouched
+            decision @ (HandlerDecision::Pipeline { .. }
+            | HandlerDecision::Vote { .. }
+            | HandlerDecision::Debate { .. }
+            | HandlerDecision::Plan { .. }
+            | HandlerDecision::Spawn { .. }) => decision,
             HandlerDecision::None => {
                 // LLM returned nothing - forward to all agents
                 info!(
@@ -523,11 +1050,11 @@ RULES:
     }
 
     /// Call the LLM provider
-    async fn call_llm(&self, messages: &[LlmMessage]) -> Result<String, String> {
+    async fn call_llm(&self, agent: &Agent, messages: &[LlmMessage]) -> Result<String, String> {
         let model = self.model.as_deref();
         let options = self.options.clone();
 
-        match self.provider.complete(messages, model, options).await {
+        match complete_observed(&agent.name, self.provider.as_ref(), messages, model, options).await {
             Ok(response) => {
                 info!(
                     "LLM response ({} tokens)",
@@ -551,10 +1078,19 @@ impl MessageHandler for LlmHandler {
             agent.name, message.from, message.content
         );
 
+        let guarded = match self.guard_input(message, agent).await {
+            Ok(guarded) => guarded,
+            Err(blocked) => return Some(blocked),
+        };
+        let message = guarded.as_ref().unwrap_or(message);
         let messages = self.build_messages(message, agent).await;
 
-        match self.call_llm(&messages).await {
-            Ok(content) => Some(content),
+        match self.call_llm(agent, &messages).await {
+            Ok(content) => {
+                let content = self.save_memories(content, message, agent).await;
+                let content = self.reflect(message, agent, content).await;
+                Some(self.guard_output(content, message, agent).await)
+            }
             Err(e) => Some(e),
         }
     }
@@ -568,21 +1104,78 @@ impl RoutingHandler for LlmHandler {
             agent.name, message.from, message.content
         );
 
+        let guarded = match self.guard_input(message, agent).await {
+            Ok(guarded) => guarded,
+            Err(blocked) => return HandlerDecision::response(blocked),
+        };
+        let message = guarded.as_ref().unwrap_or(message);
+
+        // Fixed pipeline: no LLM call needed to decide where the message goes
+        if self.routing_enabled && self.routing_behavior == RoutingBehavior::Pipeline {
+            debug!("[{}] Running pipeline {:?}", agent.name, self.pipeline);
+            return HandlerDecision::pipeline(self.pipeline.clone());
+        }
+
+        // Vote: every blocking connection gets the question, no LLM call needed
+        if self.routing_enabled && self.routing_behavior == RoutingBehavior::Vote {
+            let targets: Vec<ForwardTarget> = agent
+                .blocking_connections_for(message)
+                .map(|(name, _)| ForwardTarget::new(name.clone(), message.content.clone()))
+                .collect();
+            if !targets.is_empty() {
+                debug!("[{}] Collecting ballots from {} agents", agent.name, targets.len());
+                return HandlerDecision::vote(targets);
+            }
+        }
+
+        // Debate: blocking connections argue, this agent judges
+        if self.routing_enabled && self.routing_behavior == RoutingBehavior::Debate {
+            let mut participants: Vec<String> = agent
+                .blocking_connections_for(message)
+                .map(|(name, _)| name.clone())
+                .collect();
+            participants.sort();
+            if participants.len() >= 2 {
+                debug!("[{}] Starting {}-round debate between {:?}", agent.name, self.debate_rounds, participants);
+                return HandlerDecision::debate(participants, self.debate_rounds);
+            }
+        }
+
         let messages = if self.routing_enabled {
             self.build_routing_messages(message, agent).await
         } else {
             self.build_messages(message, agent).await
         };
 
-        match self.call_llm(&messages).await {
+        match self.call_llm(agent, &messages).await {
             Ok(content) => {
                 if self.routing_enabled {
                     // Log raw LLM output so we can debug routing issues
                     info!("[{}] Raw LLM response: {}", agent.name, content);
+                    self.apply_blackboard_writes(&content, message, agent);
+                    let content = self.save_memories(content, message, agent).await;
+
+                    // Plan: run the steps assigned to valid connections; without
+                    // any, treat the reply as a regular decision
+                    if self.routing_behavior == RoutingBehavior::Plan {
+                        let steps = plan::parse_plan(&content)
+                            .map(|steps| self.valid_plan_steps(steps, message, agent))
+                            .unwrap_or_default();
+                        if !steps.is_empty() {
+                            info!("[{}] Planned {} steps", agent.name, steps.len());
+                            return HandlerDecision::plan(steps);
+                        }
+                    }
+
                     // Parse JSON decision
                     let mut decision = parse_llm_response(&content);
                     info!("[{}] Routing decision: {:?}", agent.name, decision);
 
+                    if matches!(decision, HandlerDecision::Spawn { .. }) && !self.spawning {
+                        warn!("[{}] Ignoring sub-agents the agent may not spawn", agent.name);
+                        decision = HandlerDecision::None;
+                    }
+
                     // Enforce "all" routing behavior at the system level
                     // If routing_behavior is All, we MUST forward to ALL blocking connections
                     // regardless of what the LLM decided
@@ -591,10 +1184,23 @@ impl RoutingHandler for LlmHandler {
                         debug!("[{}] Routing decision (after 'all' enforcement): {:?}", agent.name, decision);
                     }
 
-                    decision
+                    // Direct answers are reflected on and guarded here, forwarded ones after synthesis
+                    match decision {
+                        HandlerDecision::Response { content } => {
+                            let content = self.reflect(message, agent, content).await;
+                            HandlerDecision::response(self.guard_output(content, message, agent).await)
+                        }
+                        HandlerDecision::ResponseAndForward { content, targets } => HandlerDecision::ResponseAndForward {
+                            content: self.guard_output(content, message, agent).await,
+                            targets,
+                        },
+                        decision => decision,
+                    }
                 } else {
                     // Simple mode - just return as response
-                    HandlerDecision::response(content)
+                    let content = self.save_memories(content, message, agent).await;
+                    let content = self.reflect(message, agent, content).await;
+                    HandlerDecision::response(self.guard_output(content, message, agent).await)
                 }
             }
             Err(e) => {
@@ -607,17 +1213,30 @@ impl RoutingHandler for LlmHandler {
     async fn synthesize(
         &self,
         original_message: &Message,
-        forwarded_responses: &[(String, String)],
+        outcomes: &[ForwardOutcome],
         agent: &Agent,
     ) -> Option<String> {
-        if forwarded_responses.is_empty() {
+        if outcomes.is_empty() {
             return None;
         }
 
+        // Nobody answered: report the failures instead of asking the LLM to guess
+        if !outcomes.iter().any(|o| o.is_success()) {
+            warn!("[{}] No forwarded agent answered, reporting failures", agent.name);
+            let failures = outcomes
+                .iter()
+                .filter_map(|o| o.failure_reason().map(|reason| format!("- {}: {}", o.agent, reason)))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let report = format!("None of the consulted agents could answer:\n{}", failures);
+            return Some(self.guard_output(report, original_message, agent).await);
+        }
+
         // Single response: pass through directly (no synthesis needed)
         // Whether from a tool or agent, a single response is already complete
-        if forwarded_responses.len() == 1 {
-            let (responder_name, response) = &forwarded_responses[0];
+        if outcomes.len() == 1 {
+            let responder_name = &outcomes[0].agent;
+            let response = outcomes[0].response().unwrap_or_default();
 
             // Try to unwrap JSON response format if present
             let unwrapped = unwrap_json_response(response);
@@ -626,33 +1245,37 @@ impl RoutingHandler for LlmHandler {
                 "[{}] Single response pass-through from {} (skipping synthesis)",
                 agent.name, responder_name
             );
-            return Some(unwrapped);
+            return Some(self.guard_output(unwrapped, original_message, agent).await);
         }
 
         // Multiple responses: synthesize to combine them
         debug!(
-            "[{}] Synthesizing {} forwarded responses",
+            "[{}] Synthesizing {} forwarded outcomes",
             agent.name,
-            forwarded_responses.len()
+            outcomes.len()
         );
 
-        let messages = self.build_synthesis_messages(original_message, forwarded_responses, agent);
+        let messages = self.build_synthesis_messages(original_message, outcomes, agent);
 
-        match self.call_llm(&messages).await {
+        match self.call_llm(agent, &messages).await {
             Ok(content) => {
                 // For synthesis, we want plain text, not JSON
                 // So we just return the content directly
-                Some(content)
+                let content = self.reflect(original_message, agent, content).await;
+                Some(self.guard_output(content, original_message, agent).await)
             }
             Err(e) => {
                 error!("[{}] Synthesis failed: {}", agent.name, e);
-                // Fallback: concatenate responses
-                let fallback = forwarded_responses
+                // Fallback: concatenate responses and note failures
+                let fallback = outcomes
                     .iter()
-                    .map(|(name, resp)| format!("[{}]: {}", name, resp))
+                    .map(|o| match o.response() {
+                        Some(resp) => format!("[{}]: {}", o.agent, resp),
+                        None => format!("[{}]: {}", o.agent, o.failure_reason().unwrap_or_default()),
+                    })
                     .collect::<Vec<_>>()
                     .join("\n\n");
-                Some(fallback)
+                Some(self.guard_output(fallback, original_message, agent).await)
             }
         }
     }
@@ -665,7 +1288,7 @@ impl RoutingHandler for LlmHandler {
     ) -> EvaluationDecision {
         let messages = self.build_evaluation_messages(original_message, conversation_turns, agent);
 
-        match self.call_llm(&messages).await {
+        match self.call_llm(agent, &messages).await {
             Ok(content) => {
                 info!("[{}] Evaluation response: {}", agent.name, content);
                 parse_evaluation_response(&content)
@@ -682,6 +1305,123 @@ impl RoutingHandler for LlmHandler {
     fn max_turns(&self) -> u16 {
         self.max_turns
     }
+
+    fn quorum(&self) -> Option<usize> {
+        match self.routing_behavior {
+            RoutingBehavior::All => self.quorum,
+            _ => None,
+        }
+    }
+
+    fn max_replans(&self) -> u16 {
+        self.max_replans
+    }
+
+    fn spawn_handler(&self, spec: &SpawnSpec) -> Option<Arc<dyn RoutingHandler>> {
+        if !self.spawning {
+            return None;
+        }
+        let mut handler = LlmHandler::new(self.provider.clone());
+        handler.model = spec.model.clone().or_else(|| self.model.clone());
+        handler.options = self.options.clone();
+        handler.guardrails = self.guardrails.clone();
+        if !spec.connections.is_empty() {
+            // Sub-agents route to their connections but never spawn themselves
+            let tools = self
+                .tool_descriptions
+                .iter()
+                .filter(|(name, _)| spec.connections.contains(name))
+                .map(|(name, description)| (name.clone(), description.clone()))
+                .collect();
+            handler = handler.with_routing().with_tool_descriptions(tools);
+        }
+        Some(Arc::new(handler))
+    }
+
+    async fn replan(
+        &self,
+        original_message: &Message,
+        plan: &Plan,
+        outcomes: &[ForwardOutcome],
+        agent: &Agent,
+    ) -> Option<Plan> {
+        let connections: Vec<_> = agent.blocking_connections_for(original_message).collect();
+        let messages = vec![
+            LlmMessage::system(format!(
+                "{}{}",
+                agent.system_prompt,
+                plan::planning_instructions(&self.connection_list(&connections))
+            )),
+            LlmMessage::user(plan::replan_request(&original_message.content, plan, outcomes)),
+        ];
+
+        match self.call_llm(agent, &messages).await {
+            Ok(content) => {
+                info!("[{}] Re-plan response: {}", agent.name, content);
+                let steps = plan::parse_plan(&content)?;
+                Some(Plan::new(self.valid_plan_steps(steps, original_message, agent)))
+            }
+            Err(e) => {
+                warn!("[{}] Re-plan LLM call failed: {}, stopping the plan", agent.name, e);
+                None
+            }
+        }
+    }
+
+    async fn judge_debate(
+        &self,
+        original_message: &Message,
+        turns: &[ConversationTurn],
+        agent: &Agent,
+    ) -> Option<String> {
+        if turns.is_empty() {
+            return None;
+        }
+
+        let messages = self.build_debate_judge_messages(original_message, turns, agent);
+        let verdict = match self.call_llm(agent, &messages).await {
+            Ok(content) => unwrap_json_response(&content),
+            Err(e) => {
+                error!("[{}] Debate judging failed: {}", agent.name, e);
+                // Fallback: hand back the transcript so the arguments are not lost
+                format!("Debate transcript:\n{}", debate::transcript(turns))
+            }
+        };
+        Some(self.guard_output(verdict, original_message, agent).await)
+    }
+
+    async fn tally(
+        &self,
+        original_message: &Message,
+        ballots: &[Ballot],
+        agent: &Agent,
+    ) -> Option<VoteResult> {
+        let mut result = if self.vote_aggregation != VoteAggregation::Judge || ballots.is_empty() {
+            vote::aggregate(ballots, self.vote_aggregation)
+        } else {
+            let messages = self.build_judge_messages(original_message, ballots, agent);
+            match self.call_llm(agent, &messages).await {
+                Ok(content) => Some(VoteResult {
+                    answer: unwrap_json_response(&content),
+                    method: VoteAggregation::Judge,
+                    support: None,
+                    tally: vote::aggregate(ballots, VoteAggregation::Weighted)
+                        .map(|r| r.tally)
+                        .unwrap_or_default(),
+                }),
+                Err(e) => {
+                    warn!("[{}] Judge LLM call failed: {}, using weighted vote", agent.name, e);
+                    vote::aggregate(ballots, VoteAggregation::Weighted)
+                }
+            }
+        };
+
+        if let Some(result) = result.as_mut() {
+            let answer = std::mem::take(&mut result.answer);
+            result.answer = self.guard_output(answer, original_message, agent).await;
+        }
+        result
+    }
 }
 
 /// Builder for creating LLM handlers with fluent API
@@ -694,6 +1434,16 @@ pub struct LlmHandlerBuilder {
     routing_behavior: RoutingBehavior,
     tool_descriptions: std::collections::HashMap<String, String>,
     max_turns: u16,
+    quorum: Option<usize>,
+    pipeline: Vec<String>,
+    vote_aggregation: VoteAggregation,
+    debate_rounds: u16,
+    max_replans: u16,
+    blackboard_keys: Vec<String>,
+    spawning: bool,
+    memory: Option<Arc<AgentMemory>>,
+    reflection: Option<Reflection>,
+    guardrails: Option<Arc<Guardrails>>,
 }
 
 impl LlmHandlerBuilder {
@@ -707,6 +1457,16 @@ impl LlmHandlerBuilder {
             routing_behavior: RoutingBehavior::default(),
             tool_descriptions: std::collections::HashMap::new(),
             max_turns: 1,
+            quorum: None,
+            pipeline: Vec::new(),
+            vote_aggregation: VoteAggregation::default(),
+            debate_rounds: 2,
+            max_replans: 2,
+            blackboard_keys: Vec::new(),
+            spawning: false,
+            memory: None,
+            reflection: None,
+            guardrails: None,
         }
     }
 
@@ -756,6 +1516,66 @@ impl LlmHandlerBuilder {
         self
     }
 
+    /// Set the quorum for `RoutingBehavior::All`
+    pub fn quorum(mut self, quorum: usize) -> Self {
+        self.quorum = Some(quorum);
+        self
+    }
+
+    /// Set the stages for `RoutingBehavior::Pipeline`
+    pub fn pipeline(mut self, stages: Vec<String>) -> Self {
+        self.pipeline = stages;
+        self
+    }
+
+    /// Set how ballots are aggregated for `RoutingBehavior::Vote`
+    pub fn vote_aggregation(mut self, aggregation: VoteAggregation) -> Self {
+        self.vote_aggregation = aggregation;
+        self
+    }
+
+    /// Set the number of argument rounds for `RoutingBehavior::Debate`
+    pub fn debate_rounds(mut self, rounds: u16) -> Self {
+        self.debate_rounds = rounds;
+        self
+    }
+
+    /// Set the most re-plans for `RoutingBehavior::Plan`
+    pub fn max_replans(mut self, max_replans: u16) -> Self {
+        self.max_replans = max_replans;
+        self
+    }
+
+    /// Set the blackboard keys the LLM may write
+    pub fn blackboard_keys(mut self, keys: Vec<String>) -> Self {
+        self.blackboard_keys = keys;
+        self
+    }
+
+    /// Let the LLM spawn sub-agents
+    pub fn spawning(mut self) -> Self {
+        self.spawning = true;
+        self
+    }
+
+    /// Set the long-term memory
+    pub fn memory(mut self, memory: Arc<AgentMemory>) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Check incoming messages and answers against guardrails
+    pub fn guardrails(mut self, guardrails: Arc<Guardrails>) -> Self {
+        self.guardrails = Some(guardrails);
+        self
+    }
+
+    /// Critique and revise answers before returning them
+    pub fn reflection(mut self, config: ReflectionConfig, critic: Option<Arc<dyn LlmProvider>>) -> Self {
+        self.reflection = Some(Reflection { config, critic });
+        self
+    }
+
     pub fn build(self) -> LlmHandler {
         LlmHandler {
             provider: self.provider,
@@ -766,8 +1586,74 @@ impl LlmHandlerBuilder {
             routing_behavior: self.routing_behavior,
             tool_descriptions: self.tool_descriptions,
             max_turns: self.max_turns,
+            quorum: self.quorum,
+            pipeline: self.pipeline,
+            vote_aggregation: self.vote_aggregation,
+            debate_rounds: self.debate_rounds,
+            max_replans: self.max_replans,
+            blackboard_keys: self.blackboard_keys,
+            spawning: self.spawning,
+            memory: self.memory,
+            reflection: self.reflection,
+            guardrails: self.guardrails,
+        }
+    }
+}
+
+/// Call an LLM provider for `agent`, reporting the call on the system's
+/// event bus, in the metrics and as a span
+#[instrument(
+    name = "mas.llm.call",
+    skip_all,
+    fields(
+        mas.agent = agent,
+        gen_ai.system = provider.name(),
+        gen_ai.request.model = model,
+        gen_ai.response.model = field::Empty,
+        gen_ai.usage.input_tokens = field::Empty,
+        gen_ai.usage.output_tokens = field::Empty,
+        otel.status_code = field::Empty,
+    )
+)]
+async fn complete_observed(
+    agent: &str,
+    provider: &dyn LlmProvider,
+    messages: &[LlmMessage],
+    model: Option<&str>,
+    options: Option<CompletionOptions>,
+) -> Result<CompletionResponse, LlmError> {
+    EventBus::emit_current(|_| SystemEventKind::LlmCallStarted {
+        agent: agent.to_string(),
+        model: model.map(str::to_string),
+    });
+    let started = Instant::now();
+    let result = provider.complete(messages, model, options).await;
+    let usage = result.as_ref().ok().and_then(|response| response.usage.clone());
+    metrics().observe_llm_call(agent, started.elapsed(), usage.as_ref(), result.is_err());
+    let span = Span::current();
+    match &result {
+        Ok(response) => {
+            span.record("gen_ai.response.model", response.model.as_str());
+            if let Some(usage) = &usage {
+                span.record("gen_ai.usage.input_tokens", i64::from(usage.prompt_tokens));
+                span.record("gen_ai.usage.output_tokens", i64::from(usage.completion_tokens));
+            }
+        }
+        Err(_) => {
+            span.record("otel.status_code", "ERROR");
         }
     }
+    EventBus::emit_current(|_| SystemEventKind::LlmCallFinished {
+        agent: agent.to_string(),
+        model: match &result {
+            Ok(response) => Some(response.model.clone()),
+            Err(_) => model.map(str::to_string),
+        },
+        duration_ms: started.elapsed().as_millis() as u64,
+        usage,
+        error: result.as_ref().err().map(|e| e.to_string()),
+    });
+    result
 }
 
 /// Extract content from JSON response format if present
@@ -936,6 +1822,490 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_synthesis_messages_list_failed_agents() {
+        use crate::decision::ForwardStatus;
+
+        let handler = create_test_handler_with_all_routing();
+        let agent = create_test_agent_with_connections();
+        let message = create_test_message();
+        let outcomes = vec![
+            ForwardOutcome::success("TechExpert", "q", "Use Rust."),
+            ForwardOutcome::new("BusinessExpert", "q", ForwardStatus::Timeout { waited_ms: 90_000 }),
+        ];
+
+        let messages = handler.build_synthesis_messages(&message, &outcomes, &agent);
+        let responses = &messages[2].content;
+        assert!(responses.contains("[TechExpert]: Use Rust."));
+        assert!(responses.contains("Agents that did not respond:"));
+        assert!(responses.contains("- BusinessExpert: timed out after 90.0s"));
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_reports_when_nobody_answered() {
+        use crate::decision::ForwardStatus;
+
+        let handler = create_test_handler_with_all_routing();
+        let agent = create_test_agent_with_connections();
+        let outcomes = vec![ForwardOutcome::new("TechExpert", "q", ForwardStatus::CircuitOpen)];
+
+        let result = RoutingHandler::synthesize(&handler, &create_test_message(), &outcomes, &agent)
+            .await
+            .unwrap();
+        assert!(result.contains("TechExpert"));
+    }
+
+    #[test]
+    fn test_quorum_only_applies_to_all_routing() {
+        let all = create_test_handler_with_all_routing().with_quorum(Some(1));
+        assert_eq!(RoutingHandler::quorum(&all), Some(1));
+
+        let best = LlmHandler::new(Arc::new(MockProvider)).with_quorum(Some(1));
+        assert_eq!(RoutingHandler::quorum(&best), None);
+    }
+
+    #[test]
+    fn test_blackboard_writes_are_limited_to_configured_keys() {
+        let handler = LlmHandler::new(Arc::new(MockProvider))
+            .with_routing()
+            .with_blackboard_keys(vec!["patient".to_string()]);
+        let agent = create_test_agent_with_connections();
+        let message = create_test_message();
+
+        handler.apply_blackboard_writes(
+            r#"{ "response": "Triaged", "blackboard": { "patient.severity": "high", "patients": 2, "billing": "x" } }"#,
+            &message,
+            &agent,
+        );
+        assert_eq!(message.blackboard.snapshot(), serde_json::json!({"patient": {"severity": "high"}}));
+
+        let instructions = handler.build_blackboard_instructions(&message);
+        assert!(instructions.contains(r#""severity": "high""#));
+        assert!(instructions.contains("Keys you may write: patient"));
+
+        // Agents without keys only read the blackboard
+        let reader = LlmHandler::new(Arc::new(MockProvider)).with_routing();
+        reader.apply_blackboard_writes(r#"{ "blackboard": { "patient.age": 54 } }"#, &message, &agent);
+        assert_eq!(message.blackboard.get("patient.age"), None);
+        assert!(!reader.build_blackboard_instructions(&message).contains("Keys you may write"));
+    }
+
+    /// Provider that answers with scripted replies in order (repeating the
+    /// last one) and keeps the prompts it got
+    struct ScriptedProvider {
+        replies: Vec<&'static str>,
+        prompts: std::sync::Mutex<Vec<Vec<LlmMessage>>>,
+    }
+
+    impl ScriptedProvider {
+        fn new(replies: Vec<&'static str>) -> Self {
+            Self { replies, prompts: std::sync::Mutex::new(Vec::new()) }
+        }
+    }
+
+    #[async_trait]
+    impl LlmProvider for ScriptedProvider {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        fn default_model(&self) -> &str {
+            "scripted-model"
+        }
+
+        async fn complete(
+            &self,
+            messages: &[LlmMessage],
+            _model: Option<&str>,
+            _options: Option<CompletionOptions>,
+        ) -> Result<CompletionResponse, LlmError> {
+            let mut prompts = self.prompts.lock().unwrap();
+            let reply = self.replies[prompts.len().min(self.replies.len() - 1)];
+            prompts.push(messages.to_vec());
+            Ok(CompletionResponse {
+                content: reply.to_string(),
+                model: "scripted-model".to_string(),
+                usage: None,
+            })
+        }
+
+        async fn health_check(&self) -> Result<(), LlmError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_llm_calls_are_reported_on_the_event_bus() {
+        let handler = LlmHandler::new(Arc::new(ScriptedProvider::new(vec!["Hello!"])));
+        let agent = AgentBuilder::new("Writer").system_prompt("You write.").build();
+        let bus = EventBus::default();
+        let mut events = bus.subscribe();
+
+        let response = bus
+            .scope("Writer", MessageHandler::handle(&handler, &Message::new("User", "Writer", "Hi"), &agent))
+            .await;
+        assert_eq!(response.as_deref(), Some("Hello!"));
+
+        assert!(matches!(
+            events.recv().await.unwrap().kind,
+            SystemEventKind::LlmCallStarted { ref agent, model: None } if agent == "Writer"
+        ));
+        match events.recv().await.unwrap().kind {
+            SystemEventKind::LlmCallFinished { agent, model, error, .. } => {
+                assert_eq!(agent, "Writer");
+                assert_eq!(model.as_deref(), Some("scripted-model"));
+                assert!(error.is_none());
+            }
+            other => panic!("Expected finished LLM call, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memories_are_saved_and_recalled_across_conversations() {
+        use crate::memory::{MemoryConfig, MemoryScope};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let memory = Arc::new(AgentMemory::new(
+            "helpdesk",
+            "Support",
+            MemoryConfig::new(MemoryScope::User).with_path(dir.path()),
+        ));
+        let provider = Arc::new(ScriptedProvider::new(vec![
+            "Sure, I will email you.\nREMEMBER: Prefers email over phone",
+        ]));
+        let handler = LlmHandler::new(provider.clone()).with_memory(memory);
+        let agent = AgentBuilder::new("Support").system_prompt("You help customers.").build();
+        let from_alice = |content: &str| {
+            Message::new("User", "Support", content).with_metadata(std::collections::HashMap::from([(
+                "user_id".to_string(),
+                "alice".to_string(),
+            )]))
+        };
+
+        let reply = MessageHandler::handle(&handler, &from_alice("Please don't call me"), &agent).await;
+        assert_eq!(reply.as_deref(), Some("Sure, I will email you."));
+
+        // A later conversation gets the fact in its system prompt
+        MessageHandler::handle(&handler, &from_alice("How will you contact me? Email?"), &agent).await;
+        let prompts = provider.prompts.lock().unwrap();
+        assert!(!prompts[0][0].content.contains("Prefers email"));
+        assert!(prompts[1][0].content.starts_with("You help customers."));
+        assert!(prompts[1][0].content.contains("- Prefers email over phone"));
+    }
+
+    #[tokio::test]
+    async fn test_remembered_facts_pass_the_output_guardrails() {
+        use crate::memory::{MemoryConfig, MemoryScope};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let memory = Arc::new(AgentMemory::new(
+            "helpdesk",
+            "Support",
+            MemoryConfig::new(MemoryScope::User).with_path(dir.path()),
+        ));
+        let config: crate::guardrail::GuardrailConfig = serde_json::from_value(serde_json::json!({
+            "output": { "deny": ["(?i)password"], "pii": ["email"], "disclaimer": "Not advice." }
+        }))
+        .unwrap();
+        let guardrails = Guardrails::compile(&config, |_| None).unwrap();
+        let provider = Arc::new(ScriptedProvider::new(vec![
+            "Noted.\nREMEMBER: Reachable at alice@example.com\nREMEMBER: Password is hunter2",
+        ]));
+        let handler = LlmHandler::new(provider.clone())
+            .with_memory(memory.clone())
+            .with_guardrails(Arc::new(guardrails));
+        let agent = AgentBuilder::new("Support").build();
+        let message = Message::new("User", "Support", "Write this down").with_metadata(
+            std::collections::HashMap::from([("user_id".to_string(), "alice".to_string())]),
+        );
+
+        let reply = MessageHandler::handle(&handler, &message, &agent).await;
+        assert_eq!(reply.as_deref(), Some("Noted.\n\nNot advice."));
+        let mut query = message.clone();
+        query.content = "Which password, reachable where?".to_string();
+        let facts: Vec<_> = memory.recall(&query).await.unwrap().into_iter().map(|f| f.content).collect();
+        assert_eq!(facts, vec!["Reachable at [EMAIL]"]);
+    }
+
+    #[tokio::test]
+    async fn test_plan_behavior_keeps_steps_for_known_connections() {
+        let provider = Arc::new(ScriptedProvider::new(vec![
+            r#"{ "plan": [{ "agent": "Search", "task": "Find flights" }, { "agent": "Hotels", "task": "Find a hotel" }] }"#,
+            r#"{ "plan": [{ "agent": "Search", "task": "Find trains" }] }"#,
+        ]));
+        let handler = LlmHandler::new(provider.clone())
+            .with_routing()
+            .with_routing_behavior(RoutingBehavior::Plan);
+        let agent = AgentBuilder::new("Planner").blocking_connection("Search").build();
+        let message = Message::new("User", "Planner", "Plan a trip to Rome");
+
+        // The step for a connection the planner does not have is dropped
+        let decision = RoutingHandler::handle(&handler, &message, &agent).await;
+        let plan = Plan::new(vec![PlanStep::new("Search", "Find flights")]);
+        assert_eq!(decision, HandlerDecision::Plan { plan: plan.clone() });
+        assert!(provider.prompts.lock().unwrap()[0][0].content.contains("\"plan\""));
+
+        let failed = ForwardOutcome::new(
+            "Search",
+            "Find flights",
+            crate::decision::ForwardStatus::Error { message: "no flights".to_string() },
+        );
+        let replanned = handler.replan(&message, &plan, &[failed], &agent).await;
+        assert_eq!(replanned, Some(Plan::new(vec![PlanStep::new("Search", "Find trains")])));
+        assert!(provider.prompts.lock().unwrap()[1][1].content.contains("Search FAILED: failed: no flights"));
+    }
+
+    #[tokio::test]
+    async fn test_spawn_decision_requires_spawning() {
+        let reply = r#"{ "spawn": [{ "name": "Summarizer", "system_prompt": "Summarize.", "model": "small", "message": "Doc 1" }] }"#;
+        let agent = AgentBuilder::new("Coordinator").blocking_connection("Search").build();
+        let message = Message::new("User", "Coordinator", "Summarize these documents");
+
+        let provider = Arc::new(ScriptedProvider::new(vec![reply]));
+        let handler = LlmHandler::new(provider.clone()).with_routing().with_spawning();
+        let decision = RoutingHandler::handle(&handler, &message, &agent).await;
+        let spec = SpawnSpec::new("Summarizer", "Summarize.", "Doc 1").with_model("small");
+        assert_eq!(decision, HandlerDecision::spawn(vec![spec.clone()]));
+        assert!(provider.prompts.lock().unwrap()[0][0].content.contains("\"spawn\""));
+        assert!(handler.spawn_handler(&spec).is_some());
+
+        let provider = Arc::new(ScriptedProvider::new(vec![reply]));
+        let handler = LlmHandler::new(provider.clone()).with_routing();
+        assert_eq!(RoutingHandler::handle(&handler, &message, &agent).await, HandlerDecision::None);
+        assert!(!provider.prompts.lock().unwrap()[0][0].content.contains("\"spawn\""));
+        assert!(handler.spawn_handler(&spec).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_guardrails_redact_input_and_classify_output() {
+        let provider = Arc::new(ScriptedProvider::new(vec![
+            "Take two aspirin.",
+            r#"{ "allowed": false, "reason": "medical advice" }"#,
+        ]));
+        let config: crate::guardrail::GuardrailConfig = serde_json::from_value(serde_json::json!({
+            "input": { "pii": ["phone"] },
+            "output": { "classifier": { "policy": "No medical advice." } },
+            "blocked_response": "Please ask a doctor."
+        }))
+        .unwrap();
+        let classifier: Arc<dyn LlmProvider> = provider.clone();
+        let guardrails = Guardrails::compile(&config, |_| Some(classifier.clone())).unwrap();
+        let handler = LlmHandler::new(provider.clone()).with_guardrails(Arc::new(guardrails));
+        let agent = AgentBuilder::new("Support").build();
+        let message = Message::new("User", "Support", "Call me on 0170 1234567, my head hurts");
+
+        let trace = TraceCollector::new();
+        let reply =
+            TraceCollector::scope(Some(trace.clone()), MessageHandler::handle(&handler, &message, &agent)).await;
+        assert_eq!(reply.as_deref(), Some("Please ask a doctor."));
+
+        let prompts = provider.prompts.lock().unwrap().clone();
+        assert_eq!(prompts[0].last().unwrap().content, "Call me on [PHONE], my head hurts");
+        assert_eq!(prompts[1][1].content, "Take two aspirin.");
+
+        let checks: Vec<_> = crate::guardrail::GuardrailViolation::from_events(&trace.events().await)
+            .into_iter()
+            .map(|v| (v.check, v.detail))
+            .collect();
+        assert_eq!(
+            checks,
+            vec![("pii".to_string(), "1 phone".to_string()), ("classifier".to_string(), "medical advice".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_answers_and_debate_verdicts_get_the_disclaimer() {
+        let config: crate::guardrail::GuardrailConfig =
+            serde_json::from_value(serde_json::json!({ "output": { "disclaimer": "Not advice." } })).unwrap();
+        let guardrails = Arc::new(Guardrails::compile(&config, |_| None).unwrap());
+        let provider = Arc::new(ScriptedProvider::new(vec!["Rest and drink water."]));
+        let handler = LlmHandler::new(provider).with_guardrails(guardrails);
+        let agent = AgentBuilder::new("Triage").build();
+        let message = Message::new("User", "Triage", "I have a cold");
+
+        let forwarded = ForwardOutcome::new(
+            "Nurse",
+            "I have a cold",
+            crate::decision::ForwardStatus::Success { response: "Stay home.".to_string() },
+        );
+        let answer = handler.synthesize(&message, &[forwarded], &agent).await;
+        assert_eq!(answer.as_deref(), Some("Stay home.\n\nNot advice."));
+
+        let turns = vec![ConversationTurn {
+            agent: "Nurse".to_string(),
+            message_sent: "I have a cold".to_string(),
+            response: "Stay home.".to_string(),
+            turn_number: 0,
+        }];
+        let verdict = handler.judge_debate(&message, &turns, &agent).await;
+        assert_eq!(verdict.as_deref(), Some("Rest and drink water.\n\nNot advice."));
+    }
+
+    #[tokio::test]
+    async fn test_reflection_revises_until_approved_and_traces_drafts() {
+        let provider = Arc::new(ScriptedProvider::new(vec![
+            "Refunds are possible.",
+            r#"{ "approved": false, "feedback": "Mention the refund window" }"#,
+            "Refunds are possible within 30 days.",
+            r#"{ "approved": true }"#,
+        ]));
+        let handler = LlmHandler::new(provider.clone())
+            .with_reflection(ReflectionConfig::new("States the refund window").with_max_revisions(3), None);
+        let agent = AgentBuilder::new("Support").build();
+        let message = Message::new("User", "Support", "Can I get a refund?");
+
+        let trace = TraceCollector::new();
+        let reply =
+            TraceCollector::scope(Some(trace.clone()), MessageHandler::handle(&handler, &message, &agent)).await;
+        assert_eq!(reply.as_deref(), Some("Refunds are possible within 30 days."));
+
+        let events: Vec<_> = trace
+            .events()
+            .await
+            .into_iter()
+            .map(|e| (e.event_type.as_str(), e.content))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                ("draft", "Refunds are possible.".to_string()),
+                ("critique", r#"{"approved":false,"feedback":"Mention the refund window"}"#.to_string()),
+                ("draft", "Refunds are possible within 30 days.".to_string()),
+                ("critique", r#"{"approved":true,"feedback":""}"#.to_string()),
+            ]
+        );
+
+        // The critic saw the rubric, the reviser saw the feedback
+        let prompts = provider.prompts.lock().unwrap();
+        assert!(prompts[1][0].content.contains("States the refund window"));
+        assert!(prompts[2].last().unwrap().content.contains("Mention the refund window"));
+    }
+
+    #[tokio::test]
+    async fn test_reflection_sees_saved_memories_and_the_blackboard() {
+        use crate::memory::{MemoryConfig, MemoryScope};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let memory = Arc::new(AgentMemory::new(
+            "helpdesk",
+            "Support",
+            MemoryConfig::new(MemoryScope::User).with_path(dir.path()),
+        ));
+        let provider = Arc::new(ScriptedProvider::new(vec![
+            "We will email you.\nREMEMBER: Prefers email over phone",
+            r#"{ "approved": false, "feedback": "Name the order" }"#,
+            "We will email you about order 42.",
+            r#"{ "approved": true }"#,
+        ]));
+        let handler = LlmHandler::new(provider.clone())
+            .with_memory(memory)
+            .with_reflection(ReflectionConfig::new("Names the order"), None);
+        let agent = AgentBuilder::new("Support").system_prompt("You help customers.").build();
+        let message = Message::new("User", "Support", "How will you contact me? Email?").with_metadata(
+            std::collections::HashMap::from([("user_id".to_string(), "alice".to_string())]),
+        );
+        message.blackboard.set("order_id", serde_json::json!(42));
+
+        let reply = MessageHandler::handle(&handler, &message, &agent).await;
+        assert_eq!(reply.as_deref(), Some("We will email you about order 42."));
+
+        // The critic got the answer without the fact; the reviser got the
+        // blackboard and the fact, already saved
+        let prompts = provider.prompts.lock().unwrap();
+        assert!(!prompts[1][1].content.contains("REMEMBER"));
+        let revision_prompt = &prompts[2][0].content;
+        assert!(revision_prompt.starts_with("You help customers."));
+        assert!(revision_prompt.contains("\"order_id\": 42"));
+        assert!(revision_prompt.contains("- Prefers email over phone"));
+        assert!(!revision_prompt.contains("REMEMBER:"));
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_decision_skips_llm() {
+        // MockProvider always errors, so any LLM call would yield an error response
+        let handler = LlmHandler::new(Arc::new(MockProvider))
+            .with_routing()
+            .with_routing_behavior(RoutingBehavior::Pipeline)
+            .with_pipeline(vec!["TechExpert".to_string(), "BusinessExpert".to_string()]);
+
+        let decision = RoutingHandler::handle(
+            &handler,
+            &create_test_message(),
+            &create_test_agent_with_connections(),
+        )
+        .await;
+
+        assert_eq!(
+            decision,
+            HandlerDecision::pipeline(vec!["TechExpert".to_string(), "BusinessExpert".to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_vote_decision_targets_all_blocking_connections() {
+        let handler = LlmHandler::new(Arc::new(MockProvider))
+            .with_routing()
+            .with_routing_behavior(RoutingBehavior::Vote);
+
+        let decision = RoutingHandler::handle(
+            &handler,
+            &create_test_message(),
+            &create_test_agent_with_connections(),
+        )
+        .await;
+
+        match decision {
+            HandlerDecision::Vote { targets } => {
+                let names: std::collections::HashSet<_> =
+                    targets.iter().map(|t| t.agent.as_str()).collect();
+                assert_eq!(names, ["TechExpert", "BusinessExpert"].into_iter().collect());
+            }
+            other => panic!("Expected Vote decision, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_debate_decision_uses_sorted_blocking_connections() {
+        let handler = LlmHandler::new(Arc::new(MockProvider))
+            .with_routing()
+            .with_routing_behavior(RoutingBehavior::Debate)
+            .with_debate_rounds(3);
+
+        let decision = RoutingHandler::handle(
+            &handler,
+            &create_test_message(),
+            &create_test_agent_with_connections(),
+        )
+        .await;
+
+        assert_eq!(
+            decision,
+            HandlerDecision::debate(vec!["BusinessExpert".to_string(), "TechExpert".to_string()], 3)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_judge_falls_back_to_weighted_on_llm_error() {
+        let handler = LlmHandler::new(Arc::new(MockProvider))
+            .with_vote_aggregation(VoteAggregation::Judge);
+        let ballots = vec![
+            Ballot { agent: "A".into(), answer: "Yes".into(), confidence: 0.2 },
+            Ballot { agent: "B".into(), answer: "No".into(), confidence: 0.9 },
+        ];
+
+        let result = RoutingHandler::tally(
+            &handler,
+            &create_test_message(),
+            &ballots,
+            &create_test_agent_with_connections(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.answer, "No");
+        assert_eq!(result.method, VoteAggregation::Weighted);
+    }
+
     #[test]
     fn test_unwrap_json_response_extracts_content() {
         // JSON with response field should be unwrapped
@@ -144,6 +144,19 @@ struct OllamaModel {
     name: String,
 }
 
+/// Ollama embed API request format
+#[derive(Debug, Serialize)]
+struct OllamaEmbedRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+/// Ollama embed API response format
+#[derive(Debug, Deserialize)]
+struct OllamaEmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
 /// Ollama error response
 #[derive(Debug, Deserialize)]
 struct OllamaErrorResponse {
@@ -257,6 +270,45 @@ impl LlmProvider for OllamaProvider {
 
         Ok(tags.models.into_iter().map(|m| m.name).collect())
     }
+
+    async fn embed(&self, texts: &[String], model: Option<&str>) -> Result<Vec<Vec<f32>>, LlmError> {
+        let model = model.unwrap_or(&self.default_model);
+        let url = format!("{}/api/embed", self.base_url);
+
+        let response = self
+            .client
+            .post(&url)
+            .json(&OllamaEmbedRequest { model, input: texts })
+            .send()
+            .await
+            .map_err(|e| LlmError::RequestFailed(e.to_string()))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| LlmError::RequestFailed(e.to_string()))?;
+
+        if !status.is_success() {
+            if let Ok(err_response) = serde_json::from_str::<OllamaErrorResponse>(&body) {
+                return Err(LlmError::ProviderError(err_response.error));
+            }
+            return Err(LlmError::ProviderError(format!("HTTP {}: {}", status, body)));
+        }
+
+        let embed_response: OllamaEmbedResponse = serde_json::from_str(&body)
+            .map_err(|e| LlmError::ParseError(format!("{}: {}", e, body)))?;
+
+        if embed_response.embeddings.len() != texts.len() {
+            return Err(LlmError::ParseError(format!(
+                "expected {} embeddings, got {}",
+                texts.len(),
+                embed_response.embeddings.len()
+            )));
+        }
+
+        Ok(embed_response.embeddings)
+    }
 }
 
 #[cfg(test)]
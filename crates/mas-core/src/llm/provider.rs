@@ -102,7 +102,7 @@ pub struct CompletionResponse {
 }
 
 /// Token usage statistics
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct TokenUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
@@ -167,4 +167,16 @@ pub trait LlmProvider: Send + Sync {
         // Default implementation returns empty - providers can override
         Ok(vec![])
     }
+
+    /// Embed texts into vectors, one per input (if supported by the provider)
+    ///
+    /// # Arguments
+    /// * `texts` - The texts to embed
+    /// * `model` - Optional embedding model (uses default if None)
+    async fn embed(&self, _texts: &[String], _model: Option<&str>) -> Result<Vec<Vec<f32>>, LlmError> {
+        Err(LlmError::ConfigurationError(format!(
+            "Provider '{}' does not support embeddings",
+            self.name()
+        )))
+    }
 }
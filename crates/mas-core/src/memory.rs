@@ -0,0 +1,362 @@
+//! Long-term agent memory
+//!
+//! Facts an agent chooses to save outlive the session they were learned in.
+//! Each agent keeps its facts per scope:
+//! - `agent`: one memory shared by everybody talking to the agent
+//! - `user`: one memory per caller (the `user_id` request metadata)
+//! - `org`: one memory per organization (the `org_id` request metadata)
+//!
+//! Facts are stored as JSON under `{path}/{system}/{agent}/{scope}.json` and
+//! recalled by keyword relevance to the incoming message. Requests without the
+//! metadata a scope needs neither recall nor save anything.
+//!
+//! All memories of a process share one cache per file, so replicas and
+//! reloaded systems never overwrite each other's facts.
+
+use crate::message::Message;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, Weak};
+use thiserror::Error;
+use tokio::fs;
+use tokio::sync::Mutex;
+
+/// Errors that can occur while reading or saving memories
+#[derive(Debug, Error)]
+pub enum MemoryError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, MemoryError>;
+
+/// Who a memory is shared between
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryScope {
+    /// Everybody talking to the agent
+    #[default]
+    Agent,
+    /// Each user (`user_id` metadata) separately
+    User,
+    /// Each organization (`org_id` metadata) separately
+    Org,
+}
+
+impl MemoryScope {
+    /// Stable lowercase name, matching the serialized form
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemoryScope::Agent => "agent",
+            MemoryScope::User => "user",
+            MemoryScope::Org => "org",
+        }
+    }
+
+    /// The request metadata key that selects the memory, if the scope needs one
+    pub fn metadata_key(&self) -> Option<&'static str> {
+        match self {
+            MemoryScope::Agent => None,
+            MemoryScope::User => Some("user_id"),
+            MemoryScope::Org => Some("org_id"),
+        }
+    }
+}
+
+/// Long-term memory configuration of an agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryConfig {
+    /// Who the memory is shared between: "agent" (default), "user" or "org"
+    #[serde(default)]
+    pub scope: MemoryScope,
+    /// Most facts recalled into a prompt (default 5)
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+    /// Base directory for stored facts (default "data/memory")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+fn default_top_k() -> usize {
+    5
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            scope: MemoryScope::default(),
+            top_k: default_top_k(),
+            path: None,
+        }
+    }
+}
+
+impl MemoryConfig {
+    /// Create a config for the given scope
+    pub fn new(scope: MemoryScope) -> Self {
+        Self {
+            scope,
+            ..Default::default()
+        }
+    }
+
+    /// Store facts under a custom base directory
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Set the most facts recalled into a prompt
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    fn base_path(&self) -> PathBuf {
+        self.path.clone().unwrap_or_else(|| PathBuf::from("data/memory"))
+    }
+}
+
+/// A fact saved by an agent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryFact {
+    /// The fact, as the agent wrote it
+    pub content: String,
+    /// When the fact was saved
+    pub created_at: DateTime<Utc>,
+}
+
+/// Facts of one memory file (None until the file is first read)
+type Facts = Mutex<Option<Vec<MemoryFact>>>;
+type FileFacts = Arc<Facts>;
+
+/// The cache of a memory file, shared by every memory using the file
+///
+/// The registry only holds weak references: a file's cache lives as long as
+/// some memory still uses it.
+fn shared_file(path: &Path) -> FileFacts {
+    static FILES: OnceLock<std::sync::Mutex<HashMap<PathBuf, Weak<Facts>>>> = OnceLock::new();
+    let mut files = FILES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(facts) = files.get(path).and_then(Weak::upgrade) {
+        return facts;
+    }
+    files.retain(|_, facts| facts.strong_count() > 0);
+    let facts = Arc::new(Mutex::new(None));
+    files.insert(path.to_path_buf(), Arc::downgrade(&facts));
+    facts
+}
+
+/// Long-term memory of one agent of a system
+///
+/// Facts are cached after the first read of each scope file.
+pub struct AgentMemory {
+    system: String,
+    agent: String,
+    config: MemoryConfig,
+    files: std::sync::Mutex<HashMap<String, FileFacts>>,
+}
+
+impl AgentMemory {
+    /// Create the memory of an agent of the named system
+    pub fn new(system: impl Into<String>, agent: impl Into<String>, config: MemoryConfig) -> Self {
+        Self {
+            system: system.into(),
+            agent: agent.into(),
+            config,
+            files: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The memory's configuration
+    pub fn config(&self) -> &MemoryConfig {
+        &self.config
+    }
+
+    /// Name of the memory a message reads and writes, if its request has the
+    /// metadata the scope needs
+    pub fn scope_key(&self, message: &Message) -> Option<String> {
+        match self.config.scope.metadata_key() {
+            None => Some(MemoryScope::Agent.as_str().to_string()),
+            Some(key) => {
+                let id = message.metadata.get(key).filter(|id| !id.is_empty())?;
+                Some(format!("{}-{}", self.config.scope.as_str(), sanitize(id)))
+            }
+        }
+    }
+
+    /// The facts most relevant to a message, best first
+    ///
+    /// Facts sharing no word with the message are left out.
+    pub async fn recall(&self, message: &Message) -> Result<Vec<MemoryFact>> {
+        let Some(key) = self.scope_key(message) else {
+            return Ok(Vec::new());
+        };
+        let query = words(&message.content);
+        let path = self.file_path(&key);
+        let file = self.file(&key, &path);
+        let mut facts = file.lock().await;
+        let stored = load(&mut facts, &path).await?;
+
+        let mut scored: Vec<(usize, &MemoryFact)> = stored
+            .iter()
+            .map(|fact| (words(&fact.content).intersection(&query).count(), fact))
+            .filter(|(score, _)| *score > 0)
+            .collect();
+        // Best match first; newer facts win ties
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.created_at.cmp(&a.1.created_at)));
+        Ok(scored
+            .into_iter()
+            .take(self.config.top_k)
+            .map(|(_, fact)| fact.clone())
+            .collect())
+    }
+
+    /// Save a fact to the memory the message belongs to
+    ///
+    /// Returns whether it was saved; facts already known and requests without
+    /// the scope's metadata are skipped.
+    pub async fn remember(&self, message: &Message, content: &str) -> Result<bool> {
+        let content = content.trim();
+        let Some(key) = self.scope_key(message).filter(|_| !content.is_empty()) else {
+            return Ok(false);
+        };
+        let path = self.file_path(&key);
+        let file = self.file(&key, &path);
+        let mut facts = file.lock().await;
+        let stored = load(&mut facts, &path).await?;
+        if stored.iter().any(|fact| fact.content.eq_ignore_ascii_case(content)) {
+            return Ok(false);
+        }
+        stored.push(MemoryFact {
+            content: content.to_string(),
+            created_at: Utc::now(),
+        });
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(stored)?).await?;
+        Ok(true)
+    }
+
+    /// The shared cache of a scope's file
+    fn file(&self, key: &str, path: &Path) -> FileFacts {
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files.entry(key.to_string()).or_insert_with(|| shared_file(path)).clone()
+    }
+
+    fn file_path(&self, key: &str) -> PathBuf {
+        self.config
+            .base_path()
+            .join(sanitize(&self.system))
+            .join(sanitize(&self.agent))
+            .join(format!("{}.json", key))
+    }
+}
+
+/// Read a file's facts into its cache if they are not there yet
+async fn load<'a>(facts: &'a mut Option<Vec<MemoryFact>>, path: &Path) -> Result<&'a mut Vec<MemoryFact>> {
+    if facts.is_none() {
+        let stored = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(path).await?)?
+        } else {
+            Vec::new()
+        };
+        *facts = Some(stored);
+    }
+    Ok(facts.as_mut().expect("file was just loaded"))
+}
+
+/// Keep ids usable as file names
+fn sanitize(id: &str) -> String {
+    id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Lowercased words of at least three characters, for relevance matching
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn message_from(user_id: &str, content: &str) -> Message {
+        Message::new("User", "Support", content)
+            .with_metadata(HashMap::from([("user_id".to_string(), user_id.to_string())]))
+    }
+
+    #[tokio::test]
+    async fn test_facts_are_recalled_by_relevance_and_persisted() {
+        let dir = TempDir::new().unwrap();
+        let config = MemoryConfig::new(MemoryScope::User).with_path(dir.path()).with_top_k(1);
+        let memory = AgentMemory::new("helpdesk", "Support", config.clone());
+
+        let alice = message_from("alice", "hi");
+        assert!(memory.remember(&alice, "Prefers email over phone calls").await.unwrap());
+        assert!(memory.remember(&alice, "Ships orders to Berlin").await.unwrap());
+        assert!(!memory.remember(&alice, "prefers email over phone calls").await.unwrap());
+
+        // A fresh instance reads what the first one saved
+        let memory = AgentMemory::new("helpdesk", "Support", config);
+        let recalled = memory.recall(&message_from("alice", "Where do my orders ship?")).await.unwrap();
+        assert_eq!(recalled.len(), 1);
+        assert_eq!(recalled[0].content, "Ships orders to Berlin");
+
+        // Other users have their own memory
+        assert!(memory.recall(&message_from("bob", "orders")).await.unwrap().is_empty());
+        assert!(dir.path().join("helpdesk").join("Support").join("user-alice.json").exists());
+    }
+
+    #[tokio::test]
+    async fn test_memories_are_per_system_and_shared_within_one() {
+        let dir = TempDir::new().unwrap();
+        let config = MemoryConfig::new(MemoryScope::Agent).with_path(dir.path());
+        let message = message_from("alice", "orders");
+
+        // Two instances of the same agent, both having read the empty file
+        let first = AgentMemory::new("helpdesk", "Support", config.clone());
+        let second = AgentMemory::new("helpdesk", "Support", config.clone());
+        assert!(first.recall(&message).await.unwrap().is_empty());
+        assert!(second.recall(&message).await.unwrap().is_empty());
+        assert!(first.remember(&message, "Orders ship from Hamburg").await.unwrap());
+        assert!(second.remember(&message, "Orders over 100 EUR ship free").await.unwrap());
+
+        let stored: Vec<MemoryFact> = serde_json::from_str(
+            &std::fs::read_to_string(dir.path().join("helpdesk").join("Support").join("agent.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(stored.len(), 2);
+
+        // A same-named agent of another system knows nothing of them
+        let other = AgentMemory::new("billing", "Support", config);
+        assert!(other.recall(&message).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_scope_needs_its_metadata() {
+        let dir = TempDir::new().unwrap();
+        let memory = AgentMemory::new("helpdesk", "Support", MemoryConfig::new(MemoryScope::Org).with_path(dir.path()));
+
+        let message = message_from("alice", "orders");
+        assert_eq!(memory.scope_key(&message), None);
+        assert!(!memory.remember(&message, "Uses net-30 invoices").await.unwrap());
+
+        let message = message.with_metadata(HashMap::from([("org_id".to_string(), "acme/eu".to_string())]));
+        assert_eq!(memory.scope_key(&message).as_deref(), Some("org-acme_eu"));
+    }
+}
@@ -1,4 +1,6 @@
+use crate::blackboard::Blackboard;
 use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 /// A message sent between agents
@@ -16,6 +18,10 @@ pub struct Message {
     pub timestamp: DateTime<Utc>,
     /// Optional ID of the message this is responding to (for implicit response channels)
     pub in_reply_to: Option<Uuid>,
+    /// Request metadata (e.g. `authenticated`, `user_id`), checked by connection guards
+    pub metadata: HashMap<String, String>,
+    /// Shared state of the request this message belongs to
+    pub blackboard: Blackboard,
 }
 
 impl Message {
@@ -28,9 +34,23 @@ impl Message {
             content: content.into(),
             timestamp: Utc::now(),
             in_reply_to: None,
+            metadata: HashMap::new(),
+            blackboard: Blackboard::new(),
         }
     }
 
+    /// Attach request metadata
+    pub fn with_metadata(mut self, metadata: HashMap<String, String>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Share a request's blackboard
+    pub fn with_blackboard(mut self, blackboard: Blackboard) -> Self {
+        self.blackboard = blackboard;
+        self
+    }
+
     /// Create a response to this message
     pub fn reply(&self, content: impl Into<String>) -> Self {
         Self {
@@ -40,6 +60,8 @@ impl Message {
             content: content.into(),
             timestamp: Utc::now(),
             in_reply_to: Some(self.id),
+            metadata: self.metadata.clone(),
+            blackboard: self.blackboard.clone(),
         }
     }
 }
@@ -59,12 +81,17 @@ mod tests {
 
     #[test]
     fn test_message_reply() {
-        let original = Message::new("AgentA", "AgentB", "Hello");
+        let original = Message::new("AgentA", "AgentB", "Hello")
+            .with_metadata(HashMap::from([("user_id".to_string(), "u1".to_string())]));
         let reply = original.reply("Hi back!");
 
         assert_eq!(reply.from, "AgentB");
         assert_eq!(reply.to, "AgentA");
         assert_eq!(reply.content, "Hi back!");
         assert_eq!(reply.in_reply_to, Some(original.id));
+        assert_eq!(reply.metadata, original.metadata);
+
+        original.blackboard.set("ticket", serde_json::json!("T-1"));
+        assert_eq!(reply.blackboard.get("ticket"), Some(serde_json::json!("T-1")));
     }
 }
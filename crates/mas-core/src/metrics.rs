@@ -0,0 +1,255 @@
+//! Prometheus metrics
+//!
+//! All systems in a process share one registry. The agent system, the LLM,
+//! tool and database handlers record into it as they work; whoever serves
+//! the metrics (the API server's `GET /metrics`) fills in the gauges that are
+//! read on demand, such as inbox depths, and renders the text format.
+//!
+//! Series are labelled by system and by agent, tool or database name. LLM,
+//! tool and database calls take the system from the message loop that makes
+//! them (see [`EventBus::current_system`]); calls made outside any loop have
+//! an empty system label.
+//!
+//! Spawned sub-agents (named `{parent}~{spec}~{id}`) are counted toward
+//! their parent, so every spawn does not start new series.
+
+use crate::events::EventBus;
+use crate::llm::TokenUsage;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::error;
+
+/// Content type of [`Metrics::render`]'s output
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Latency buckets in seconds, from fast tool calls to slow LLM answers
+const LATENCY_BUCKETS: &[f64] = &[0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+/// What kind of endpoint a call went to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+    Tool,
+    Database,
+}
+
+impl CallKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CallKind::Tool => "tool",
+            CallKind::Database => "database",
+        }
+    }
+}
+
+/// The process-wide collectors
+pub struct Metrics {
+    registry: Registry,
+    prompts: IntCounterVec,
+    llm_duration: HistogramVec,
+    llm_tokens: IntCounterVec,
+    llm_errors: IntCounterVec,
+    call_duration: HistogramVec,
+    call_errors: IntCounterVec,
+    timeouts: IntCounterVec,
+    inbox_depth: IntGaugeVec,
+    sessions: IntGauge,
+}
+
+/// The process-wide metrics
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let counter = |name: &str, help: &str, labels: &[&str]| {
+            let counter = IntCounterVec::new(Opts::new(name, help), labels).expect("valid counter");
+            registry.register(Box::new(counter.clone())).expect("unique counter");
+            counter
+        };
+        let histogram = |name: &str, help: &str, labels: &[&str]| {
+            let opts = HistogramOpts::new(name, help).buckets(LATENCY_BUCKETS.to_vec());
+            let histogram = HistogramVec::new(opts, labels).expect("valid histogram");
+            registry.register(Box::new(histogram.clone())).expect("unique histogram");
+            histogram
+        };
+
+        let prompts = counter("mas_prompts_total", "Prompts received, by system", &["system"]);
+        let llm_duration = histogram(
+            "mas_llm_request_duration_seconds",
+            "Duration of LLM calls, by system and agent",
+            &["system", "agent"],
+        );
+        let llm_tokens = counter(
+            "mas_llm_tokens_total",
+            "Tokens used by LLM calls, by system, agent and kind (prompt or completion)",
+            &["system", "agent", "kind"],
+        );
+        let llm_errors = counter(
+            "mas_llm_errors_total",
+            "Failed LLM calls, by system and agent",
+            &["system", "agent"],
+        );
+        let call_duration = histogram(
+            "mas_call_duration_seconds",
+            "Duration of tool and database calls, by system, name and kind",
+            &["system", "name", "kind"],
+        );
+        let call_errors = counter(
+            "mas_call_errors_total",
+            "Failed tool and database calls, by system, name and kind",
+            &["system", "name", "kind"],
+        );
+        let timeouts = counter(
+            "mas_timeouts_total",
+            "Messages not answered in time, by system and receiving agent",
+            &["system", "agent"],
+        );
+
+        let inbox_depth = IntGaugeVec::new(
+            Opts::new("mas_inbox_depth", "Messages waiting in an inbox, by system and agent"),
+            &["system", "agent"],
+        )
+        .expect("valid gauge");
+        registry.register(Box::new(inbox_depth.clone())).expect("unique gauge");
+        let sessions = IntGauge::new("mas_sessions", "Stored chat sessions").expect("valid gauge");
+        registry.register(Box::new(sessions.clone())).expect("unique gauge");
+
+        Self {
+            registry,
+            prompts,
+            llm_duration,
+            llm_tokens,
+            llm_errors,
+            call_duration,
+            call_errors,
+            timeouts,
+            inbox_depth,
+            sessions,
+        }
+    }
+
+    /// Count a prompt sent to a system
+    pub fn record_prompt(&self, system: &str) {
+        self.prompts.with_label_values(&[system]).inc();
+    }
+
+    /// Record an LLM call made for `agent` of the current system
+    pub fn observe_llm_call(&self, agent: &str, duration: Duration, usage: Option<&TokenUsage>, failed: bool) {
+        let system = EventBus::current_system();
+        let system = system.as_deref().unwrap_or_default();
+        let agent = agent_label(agent);
+        self.llm_duration
+            .with_label_values(&[system, agent])
+            .observe(duration.as_secs_f64());
+        if let Some(usage) = usage {
+            self.llm_tokens
+                .with_label_values(&[system, agent, "prompt"])
+                .inc_by(u64::from(usage.prompt_tokens));
+            self.llm_tokens
+                .with_label_values(&[system, agent, "completion"])
+                .inc_by(u64::from(usage.completion_tokens));
+        }
+        if failed {
+            self.llm_errors.with_label_values(&[system, agent]).inc();
+        }
+    }
+
+    /// Record a tool or database call of the current system
+    pub fn observe_call(&self, kind: CallKind, name: &str, duration: Duration, failed: bool) {
+        let system = EventBus::current_system();
+        let labels = [system.as_deref().unwrap_or_default(), name, kind.as_str()];
+        self.call_duration.with_label_values(&labels).observe(duration.as_secs_f64());
+        if failed {
+            self.call_errors.with_label_values(&labels).inc();
+        }
+    }
+
+    /// Count a message `agent` of `system` did not answer in time
+    pub fn record_timeout(&self, system: &str, agent: &str) {
+        self.timeouts.with_label_values(&[system, agent_label(agent)]).inc();
+    }
+
+    /// Forget all inbox depths (before setting the current ones)
+    pub fn reset_inbox_depths(&self) {
+        self.inbox_depth.reset();
+    }
+
+    /// Add the number of messages waiting in an agent's inbox
+    pub fn add_inbox_depth(&self, system: &str, agent: &str, depth: usize) {
+        self.inbox_depth.with_label_values(&[system, agent_label(agent)]).add(depth as i64);
+    }
+
+    /// Set the number of stored sessions
+    pub fn set_sessions(&self, sessions: usize) {
+        self.sessions.set(sessions as i64);
+    }
+
+    /// Render all metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!("Could not encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// The agent a series is labelled with: spawned sub-agents count as their parent
+fn agent_label(agent: &str) -> &str {
+    agent.split_once('~').map_or(agent, |(parent, _)| parent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_recorded_metrics_are_rendered() {
+        let metrics = metrics();
+        metrics.record_prompt("metrics-test");
+        metrics.record_timeout("metrics-test", "MetricsSleeper");
+        let usage = TokenUsage { prompt_tokens: 12, completion_tokens: 30, total_tokens: 42 };
+        let bus = EventBus::default().with_system("metrics-test");
+        bus.scope("MetricsWriter", async {
+            metrics.observe_llm_call("MetricsWriter", Duration::from_millis(300), Some(&usage), false);
+            metrics.observe_call(CallKind::Database, "MetricsDb", Duration::from_millis(20), true);
+        })
+        .await;
+
+        let text = metrics.render();
+        assert!(text.contains(r#"mas_prompts_total{system="metrics-test"} 1"#));
+        assert!(text.contains(r#"mas_timeouts_total{agent="MetricsSleeper",system="metrics-test"} 1"#));
+        assert!(
+            text.contains(r#"mas_llm_tokens_total{agent="MetricsWriter",kind="completion",system="metrics-test"} 30"#)
+        );
+        assert!(text.contains(
+            r#"mas_llm_request_duration_seconds_bucket{agent="MetricsWriter",system="metrics-test",le="0.5"} 1"#
+        ));
+        assert!(text.contains(r#"mas_call_errors_total{kind="database",name="MetricsDb",system="metrics-test"} 1"#));
+    }
+
+    #[tokio::test]
+    async fn test_spawned_sub_agents_count_toward_their_parent() {
+        let metrics = metrics();
+        let bus = EventBus::default().with_system("metrics-spawn-test");
+        bus.scope("MetricsParent~Summarizer~1a2b3c4d", async {
+            for id in ["1a2b3c4d", "5e6f7a8b"] {
+                let agent = format!("MetricsParent~Summarizer~{}", id);
+                metrics.observe_llm_call(&agent, Duration::from_millis(100), None, true);
+            }
+        })
+        .await;
+        metrics.record_timeout("metrics-spawn-test", "MetricsParent~Summarizer~1a2b3c4d");
+
+        let text = metrics.render();
+        assert!(!text.contains("MetricsParent~"), "{}", text);
+        assert!(text.contains(r#"mas_llm_errors_total{agent="MetricsParent",system="metrics-spawn-test"} 2"#));
+        assert!(text.contains(r#"mas_timeouts_total{agent="MetricsParent",system="metrics-spawn-test"} 1"#));
+    }
+}
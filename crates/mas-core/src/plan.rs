@@ -0,0 +1,207 @@
+//! Plan-and-execute routing
+//!
+//! With `RoutingBehavior::Plan`, a routing agent first writes an explicit plan:
+//! an ordered list of steps, each assigned to one of its connections. The steps
+//! run one after another through the regular forwarding machinery; each step
+//! sees the results of the steps before it. When a step fails, the agent may
+//! re-plan the remaining work, and the final answer is synthesized from the
+//! completed steps.
+//!
+//! Every plan, including re-plans, is recorded as a `plan` trace event whose
+//! content is the plan as JSON.
+
+use crate::decision::ForwardOutcome;
+use crate::tracer::{TraceEvent, TraceEventType};
+use serde::{Deserialize, Serialize};
+
+/// One step of a plan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanStep {
+    /// Connection that carries out the step
+    pub agent: String,
+    /// What the step should achieve (the original request if empty)
+    #[serde(default)]
+    pub task: String,
+}
+
+impl PlanStep {
+    pub fn new(agent: impl Into<String>, task: impl Into<String>) -> Self {
+        Self {
+            agent: agent.into(),
+            task: task.into(),
+        }
+    }
+}
+
+/// An ordered list of steps
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    /// 0 for the first plan, incremented on every re-plan
+    #[serde(default)]
+    pub revision: u16,
+    /// Steps in execution order
+    pub steps: Vec<PlanStep>,
+}
+
+impl Plan {
+    /// Create a first plan from its steps
+    pub fn new(steps: Vec<PlanStep>) -> Self {
+        Self { revision: 0, steps }
+    }
+
+    /// The most recent plan `agent` recorded in a trace, if any
+    ///
+    /// Plans of the agents it handed work to, or of nested systems, are not
+    /// the plan of the request.
+    pub fn latest(events: &[TraceEvent], agent: &str) -> Option<Plan> {
+        events
+            .iter()
+            .rev()
+            .filter(|e| e.event_type == TraceEventType::Plan && e.from == agent && e.system.is_none())
+            .find_map(|e| serde_json::from_str(&e.content).ok())
+    }
+}
+
+#[derive(Deserialize)]
+struct PlanJson {
+    #[serde(default)]
+    plan: Vec<PlanStep>,
+}
+
+/// Parse the steps of a plan from an LLM reply
+///
+/// Accepts `{ "plan": [{ "agent": "...", "task": "..." }] }`, optionally
+/// wrapped in text or a code fence. Returns `None` if there are no steps.
+pub fn parse_plan(reply: &str) -> Option<Vec<PlanStep>> {
+    let trimmed = reply.trim();
+    let (start, end) = (trimmed.find('{')?, trimmed.rfind('}')?);
+    let json: PlanJson = serde_json::from_str(trimmed.get(start..=end)?).ok()?;
+    let steps: Vec<PlanStep> = json
+        .plan
+        .into_iter()
+        .filter(|s| !s.agent.trim().is_empty())
+        .collect();
+    (!steps.is_empty()).then_some(steps)
+}
+
+/// Routing instructions asking the LLM for a plan over the listed connections
+pub fn planning_instructions(agent_list: &str) -> String {
+    format!(
+        r#"
+
+Before acting, make a plan. Break the request into steps and assign each step to one of these agents/tools:
+{}
+Steps run in order and each step sees the results of the steps before it.
+
+Respond with JSON in one of these formats:
+
+Plan (for anything an agent or tool can help with):
+{{ "plan": [{{ "agent": "NAME", "task": "what this step must achieve" }}] }}
+
+Direct response (only if no step is needed):
+{{ "response": "your answer" }}
+
+Keep plans short. Only include valid JSON in your response."#,
+        agent_list
+    )
+}
+
+/// The message sent to the agent carrying out a step
+///
+/// `completed` holds the outcomes of earlier steps; their results are passed
+/// along as context.
+pub fn step_message(question: &str, step: &PlanStep, completed: &[ForwardOutcome]) -> String {
+    let task = if step.task.trim().is_empty() { question } else { &step.task };
+    let results: Vec<String> = completed
+        .iter()
+        .filter_map(|o| o.response().map(|r| format!("[{}]: {}", o.agent, r)))
+        .collect();
+    if results.is_empty() {
+        return task.to_string();
+    }
+    format!(
+        "{}\n\nOriginal request: {}\n\nResults of earlier steps:\n{}",
+        task,
+        question,
+        results.join("\n")
+    )
+}
+
+/// The request for a new plan after a step failed
+///
+/// `outcomes` holds the completed steps followed by the failed one.
+pub fn replan_request(question: &str, plan: &Plan, outcomes: &[ForwardOutcome]) -> String {
+    let mut text = format!("Original request: {}\n\nCurrent plan:\n", question);
+    for (i, step) in plan.steps.iter().enumerate() {
+        text.push_str(&format!("{}. {}: {}\n", i + 1, step.agent, step.task));
+    }
+    text.push_str("\nProgress:\n");
+    for outcome in outcomes {
+        match outcome.response() {
+            Some(response) => text.push_str(&format!("- {} completed: {}\n", outcome.agent, response)),
+            None => text.push_str(&format!(
+                "- {} FAILED: {}\n",
+                outcome.agent,
+                outcome.failure_reason().unwrap_or_default()
+            )),
+        }
+    }
+    text.push_str(
+        "\nA step failed. Plan the remaining work again, routing around the failure. \
+         Do not repeat completed steps. Respond with JSON only:\n\
+         { \"plan\": [{ \"agent\": \"NAME\", \"task\": \"...\" }] }\n\
+         or { \"plan\": [] } to stop and answer with what is known.",
+    );
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decision::ForwardStatus;
+
+    #[test]
+    fn test_parse_plan() {
+        let reply = "```json\n{ \"plan\": [{ \"agent\": \"Search\", \"task\": \"Find flights\" }, { \"agent\": \"Booker\" }] }\n```";
+        assert_eq!(
+            parse_plan(reply),
+            Some(vec![PlanStep::new("Search", "Find flights"), PlanStep::new("Booker", "")])
+        );
+        assert_eq!(parse_plan(r#"{ "plan": [] }"#), None);
+        assert_eq!(parse_plan(r#"{ "response": "hi" }"#), None);
+        assert_eq!(parse_plan("no json"), None);
+    }
+
+    #[test]
+    fn test_step_message_carries_earlier_results() {
+        let step = PlanStep::new("Booker", "Book the cheapest flight");
+        assert_eq!(step_message("Fly to Rome", &step, &[]), "Book the cheapest flight");
+
+        let completed = vec![
+            ForwardOutcome::success("Search", "Find flights", "AZ 123 at 9:00"),
+            ForwardOutcome::new("Prices", "Compare", ForwardStatus::Timeout { waited_ms: 10 }),
+        ];
+        let message = step_message("Fly to Rome", &step, &completed);
+        assert!(message.starts_with("Book the cheapest flight"));
+        assert!(message.contains("[Search]: AZ 123 at 9:00"));
+        assert!(!message.contains("Prices"));
+
+        // An empty task falls back to the original request
+        assert_eq!(step_message("Fly to Rome", &PlanStep::new("Search", ""), &[]), "Fly to Rome");
+    }
+
+    #[test]
+    fn test_latest_plan_from_trace() {
+        let first = Plan::new(vec![PlanStep::new("A", "x")]);
+        let second = Plan { revision: 1, steps: vec![PlanStep::new("B", "y")] };
+        let events = vec![
+            TraceEvent::plan("Coordinator", "User", serde_json::to_string(&first).unwrap()),
+            TraceEvent::forward("Coordinator", "A", "x"),
+            TraceEvent::plan("Coordinator", "User", serde_json::to_string(&second).unwrap()),
+            TraceEvent::plan("A", "Coordinator", serde_json::to_string(&first).unwrap()),
+        ];
+        assert_eq!(Plan::latest(&events, "Coordinator"), Some(second));
+        assert_eq!(Plan::latest(&events[1..2], "Coordinator"), None);
+        assert_eq!(Plan::latest(&events, "Missing"), None);
+    }
+}
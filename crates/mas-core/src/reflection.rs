@@ -0,0 +1,117 @@
+//! Self-critique before answering
+//!
+//! With a `reflection` block, an LLM agent treats its answer as a draft: a
+//! critic (the same or another model) checks it against a rubric, and the
+//! agent revises it with the critic's feedback until the critic approves or
+//! `max_revisions` is reached. Drafts and critiques are recorded in the trace.
+
+use serde::{Deserialize, Serialize};
+
+/// Reflection configuration of an agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReflectionConfig {
+    /// What a good answer looks like; the critic checks drafts against it
+    pub rubric: String,
+    /// Most revisions after the first draft (default 1; 0 only critiques)
+    #[serde(default = "default_max_revisions")]
+    pub max_revisions: u16,
+    /// References a key in llm_providers for the critic (the agent's provider if omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Model for the critic (the provider's default if omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+fn default_max_revisions() -> u16 {
+    1
+}
+
+impl ReflectionConfig {
+    /// Create a config with the given rubric
+    pub fn new(rubric: impl Into<String>) -> Self {
+        Self {
+            rubric: rubric.into(),
+            max_revisions: default_max_revisions(),
+            provider: None,
+            model: None,
+        }
+    }
+
+    /// Set the most revisions after the first draft
+    pub fn with_max_revisions(mut self, max_revisions: u16) -> Self {
+        self.max_revisions = max_revisions;
+        self
+    }
+}
+
+/// The critic's verdict on a draft
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Critique {
+    /// Whether the draft meets the rubric
+    pub approved: bool,
+    /// What to improve (empty when approved)
+    #[serde(default)]
+    pub feedback: String,
+}
+
+/// System prompt for the critic
+pub fn critique_system_prompt(rubric: &str) -> String {
+    format!(
+        r#"You review draft answers against this rubric:
+{}
+
+Respond with JSON only:
+{{ "approved": true, "feedback": "" }} if the draft meets every point of the rubric, or
+{{ "approved": false, "feedback": "what to change" }} otherwise."#,
+        rubric
+    )
+}
+
+/// The draft to critique, with the question it answers
+pub fn critique_request(question: &str, draft: &str) -> String {
+    format!("Question:\n{}\n\nDraft answer:\n{}", question, draft)
+}
+
+/// Ask the agent to revise its draft
+pub fn revision_request(feedback: &str) -> String {
+    format!(
+        "A reviewer found problems with your answer:\n{}\n\nRewrite the answer to address them. Reply with the revised answer only.",
+        feedback
+    )
+}
+
+/// Parse the critic's reply
+///
+/// Falls back to treating a reply starting with "approved" as approval and
+/// anything else as feedback, for critics that ignore the JSON format.
+pub fn parse_critique(reply: &str) -> Critique {
+    let trimmed = reply.trim();
+    if let (Some(start), Some(end)) = (trimmed.find('{'), trimmed.rfind('}')) {
+        if let Ok(critique) = serde_json::from_str::<Critique>(&trimmed[start..=end]) {
+            return critique;
+        }
+    }
+    if trimmed.to_lowercase().starts_with("approved") {
+        Critique { approved: true, feedback: String::new() }
+    } else {
+        Critique { approved: false, feedback: trimmed.to_string() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_critique() {
+        let json = "```json\n{ \"approved\": false, \"feedback\": \"Cite the policy\" }\n```";
+        assert_eq!(
+            parse_critique(json),
+            Critique { approved: false, feedback: "Cite the policy".to_string() }
+        );
+        assert!(parse_critique(r#"{ "approved": true }"#).approved);
+        assert!(parse_critique("Approved, looks good.").approved);
+        assert_eq!(parse_critique("Too long.").feedback, "Too long.");
+    }
+}
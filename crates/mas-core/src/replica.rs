@@ -0,0 +1,147 @@
+//! Replica pools: several workers behind one agent name
+//!
+//! An agent registered with more than one handler runs one message loop per
+//! handler. Senders still address the agent by name; each request is handed
+//! to one replica, chosen round-robin, by current load, or by a hash of the
+//! session id so that a session keeps talking to the same worker.
+
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// How requests are spread over the replicas of an agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplicaDispatch {
+    /// Take turns, one replica after another
+    #[default]
+    RoundRobin,
+    /// Pick the replica with the fewest queued or running requests
+    LeastBusy,
+    /// Hash the session id so a session always lands on the same replica.
+    /// Requests without a session fall back to round-robin.
+    SessionHash,
+}
+
+/// Counts a request against a replica's load until dropped
+#[derive(Debug)]
+pub(crate) struct InFlight(Arc<AtomicUsize>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct Replica<T> {
+    inbox: T,
+    in_flight: Arc<AtomicUsize>,
+}
+
+/// The replicas registered behind one agent name
+pub(crate) struct ReplicaPool<T> {
+    replicas: Vec<Replica<T>>,
+    dispatch: ReplicaDispatch,
+    next: AtomicUsize,
+}
+
+impl<T: Clone> ReplicaPool<T> {
+    /// Create a pool over the given inboxes (must not be empty)
+    pub fn new(inboxes: Vec<T>, dispatch: ReplicaDispatch) -> Self {
+        assert!(!inboxes.is_empty(), "a replica pool needs at least one replica");
+        Self {
+            replicas: inboxes
+                .into_iter()
+                .map(|inbox| Replica {
+                    inbox,
+                    in_flight: Arc::new(AtomicUsize::new(0)),
+                })
+                .collect(),
+            dispatch,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Choose a replica for a request
+    ///
+    /// Returns the replica's inbox and a guard that keeps the request counted
+    /// as in flight until it is dropped.
+    pub fn pick(&self, session_id: Option<&str>) -> (T, InFlight) {
+        let index = self.pick_index(session_id);
+        let replica = &self.replicas[index];
+        replica.in_flight.fetch_add(1, Ordering::SeqCst);
+        (replica.inbox.clone(), InFlight(replica.in_flight.clone()))
+    }
+
+    /// The inboxes of all replicas
+    pub fn inboxes(&self) -> impl Iterator<Item = &T> {
+        self.replicas.iter().map(|replica| &replica.inbox)
+    }
+
+    fn pick_index(&self, session_id: Option<&str>) -> usize {
+        let count = self.replicas.len();
+        if count == 1 {
+            return 0;
+        }
+
+        match (self.dispatch, session_id) {
+            (ReplicaDispatch::SessionHash, Some(session_id)) => {
+                let mut hasher = DefaultHasher::new();
+                session_id.hash(&mut hasher);
+                (hasher.finish() % count as u64) as usize
+            }
+            (ReplicaDispatch::LeastBusy, _) => {
+                // Start the scan at a rotating offset so ties are spread out
+                let start = self.next.fetch_add(1, Ordering::Relaxed);
+                (0..count)
+                    .map(|offset| (start + offset) % count)
+                    .min_by_key(|&i| self.replicas[i].in_flight.load(Ordering::SeqCst))
+                    .unwrap_or(0)
+            }
+            _ => self.next.fetch_add(1, Ordering::Relaxed) % count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_robin_cycles_replicas() {
+        let pool = ReplicaPool::new(vec![0, 1, 2], ReplicaDispatch::RoundRobin);
+        let picked: Vec<usize> = (0..4).map(|_| pool.pick(None).0).collect();
+        assert_eq!(picked, vec![0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn test_least_busy_avoids_loaded_replicas() {
+        let pool = ReplicaPool::new(vec![0, 1, 2], ReplicaDispatch::LeastBusy);
+
+        let (first, first_guard) = pool.pick(None);
+        let (second, _second_guard) = pool.pick(None);
+        let (third, _third_guard) = pool.pick(None);
+        let mut busy = vec![first, second, third];
+        busy.sort();
+        assert_eq!(busy, vec![0, 1, 2]);
+
+        // Only the replica that finished its request is free
+        drop(first_guard);
+        assert_eq!(pool.pick(None).0, first);
+    }
+
+    #[test]
+    fn test_session_hash_is_sticky() {
+        let pool = ReplicaPool::new(vec![0, 1, 2, 3], ReplicaDispatch::SessionHash);
+        let replica = pool.pick(Some("session-a")).0;
+        for _ in 0..5 {
+            assert_eq!(pool.pick(Some("session-a")).0, replica);
+        }
+
+        // Without a session id requests still rotate
+        let unsessioned: Vec<usize> = (0..4).map(|_| pool.pick(None).0).collect();
+        assert_eq!(unsessioned, vec![0, 1, 2, 3]);
+    }
+}
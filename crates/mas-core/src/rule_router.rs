@@ -0,0 +1,639 @@
+//! Deterministic rule-based routing
+//!
+//! `RuleRouter` is a `RoutingHandler` that picks the connection to forward to
+//! without asking an LLM. Rules are checked in order:
+//!
+//! 1. Regex and keyword rules: the first rule that matches the message wins.
+//! 2. Example rules: the message is embedded and compared to each rule's
+//!    example utterances; the most similar example above its rule's
+//!    threshold wins.
+//! 3. If nothing matched, an optional fallback handler (usually an
+//!    `LlmHandler` in routing mode) decides, otherwise a fixed response is
+//!    returned.
+//!
+//! The fallback also settles what its own decisions lead to: it synthesizes,
+//! evaluates follow-ups, tallies votes, judges debates, re-plans and spawns
+//! sub-agents, with its own turn, quorum and re-plan limits.
+//!
+//! Rules whose target's connection guard rejects the message are skipped.
+
+use crate::agent::Agent;
+use crate::agent_system::RoutingHandler;
+use crate::decision::{ConversationTurn, EvaluationDecision, ForwardOutcome, HandlerDecision, SpawnSpec};
+use crate::llm::LlmProvider;
+use crate::message::Message;
+use crate::plan::Plan;
+use crate::vote::{self, Ballot, VoteAggregation, VoteResult};
+
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tracing::{debug, info, warn};
+
+/// Default minimum cosine similarity for an example match
+const DEFAULT_THRESHOLD: f32 = 0.75;
+
+/// Default response when no rule matched and there is no fallback
+const DEFAULT_NO_MATCH_RESPONSE: &str = "Sorry, I don't know who can help with that.";
+
+/// A routing rule as written in a `"type": "rules"` handler
+///
+/// A rule matches if any of its conditions matches.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuleConfig {
+    /// Connection to forward to when the rule matches
+    pub target: String,
+    /// Regex matched against the message content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    /// Keywords, any of which matches (case-insensitive)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// Example utterances, matched by embedding similarity
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<String>,
+    /// Minimum cosine similarity for an example match (default 0.75)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f32>,
+}
+
+/// Errors raised while building a rule router
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum RuleError {
+    #[error("rule for '{0}' has no regex, keywords or examples")]
+    EmptyRule(String),
+
+    #[error("rule for '{0}' has an invalid regex: {1}")]
+    InvalidRegex(String, String),
+
+    #[error("rules with examples need an embedding provider")]
+    MissingEmbedder,
+}
+
+struct Rule {
+    target: String,
+    regex: Option<Regex>,
+    keywords: Vec<String>,
+    examples: Vec<String>,
+    threshold: f32,
+}
+
+impl Rule {
+    fn matches_lexically(&self, content: &str, lowercase: &str) -> bool {
+        self.regex.as_ref().is_some_and(|r| r.is_match(content))
+            || self.keywords.iter().any(|k| lowercase.contains(k.as_str()))
+    }
+}
+
+/// Embedding of one example utterance, tagged with its rule
+struct ExampleEmbedding {
+    rule: usize,
+    vector: Vec<f32>,
+}
+
+/// Routing handler that forwards by rules instead of an LLM call
+pub struct RuleRouter {
+    rules: Vec<Rule>,
+    embedder: Option<(Arc<dyn LlmProvider>, Option<String>)>,
+    /// Example embeddings, computed on first use
+    examples: OnceCell<Vec<ExampleEmbedding>>,
+    fallback: Option<Arc<dyn RoutingHandler>>,
+    no_match_response: String,
+}
+
+impl RuleRouter {
+    /// Compile routing rules
+    ///
+    /// Rules with examples also need [`RuleRouter::with_embeddings`].
+    pub fn new(rules: &[RuleConfig]) -> Result<Self, RuleError> {
+        let rules = rules
+            .iter()
+            .map(|config| {
+                if config.regex.is_none() && config.keywords.is_empty() && config.examples.is_empty() {
+                    return Err(RuleError::EmptyRule(config.target.clone()));
+                }
+                let regex = config
+                    .regex
+                    .as_deref()
+                    .map(Regex::new)
+                    .transpose()
+                    .map_err(|e| RuleError::InvalidRegex(config.target.clone(), e.to_string()))?;
+                Ok(Rule {
+                    target: config.target.clone(),
+                    regex,
+                    keywords: config.keywords.iter().map(|k| k.to_lowercase()).collect(),
+                    examples: config.examples.clone(),
+                    threshold: config.threshold.unwrap_or(DEFAULT_THRESHOLD),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            rules,
+            embedder: None,
+            examples: OnceCell::new(),
+            fallback: None,
+            no_match_response: DEFAULT_NO_MATCH_RESPONSE.to_string(),
+        })
+    }
+
+    /// Use a provider's embeddings to match example utterances
+    pub fn with_embeddings(mut self, provider: Arc<dyn LlmProvider>, model: Option<String>) -> Self {
+        self.embedder = Some((provider, model));
+        self
+    }
+
+    /// Let another handler decide when no rule matches
+    ///
+    /// The fallback also synthesizes responses for forwards it decided on.
+    pub fn with_fallback(mut self, fallback: Arc<dyn RoutingHandler>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Response sent when no rule matches and there is no fallback
+    pub fn with_no_match_response(mut self, response: impl Into<String>) -> Self {
+        self.no_match_response = response.into();
+        self
+    }
+
+    /// Check that example rules can be matched
+    pub fn validate(&self) -> Result<(), RuleError> {
+        if self.embedder.is_none() && self.rules.iter().any(|r| !r.examples.is_empty()) {
+            return Err(RuleError::MissingEmbedder);
+        }
+        Ok(())
+    }
+
+    /// Embed every example utterance once
+    async fn example_embeddings(&self) -> Option<&[ExampleEmbedding]> {
+        let (provider, model) = self.embedder.as_ref()?;
+        let result = self
+            .examples
+            .get_or_try_init(|| async {
+                let tagged: Vec<(usize, String)> = self
+                    .rules
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, rule)| rule.examples.iter().map(move |e| (i, e.clone())))
+                    .collect();
+                let texts: Vec<String> = tagged.iter().map(|(_, text)| text.clone()).collect();
+                let vectors = provider.embed(&texts, model.as_deref()).await?;
+                Ok::<_, crate::llm::LlmError>(
+                    tagged
+                        .into_iter()
+                        .zip(vectors)
+                        .map(|((rule, _), vector)| ExampleEmbedding { rule, vector })
+                        .collect(),
+                )
+            })
+            .await;
+
+        match result {
+            Ok(examples) => Some(examples.as_slice()),
+            Err(e) => {
+                warn!("Failed to embed rule examples: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Find the rule whose examples are most similar to the message
+    async fn match_examples(&self, content: &str, allowed: &[bool]) -> Option<(usize, f32)> {
+        if !self.rules.iter().any(|r| !r.examples.is_empty()) {
+            return None;
+        }
+        let examples = self.example_embeddings().await?;
+        let (provider, model) = self.embedder.as_ref()?;
+
+        let query = match provider.embed(&[content.to_string()], model.as_deref()).await {
+            Ok(mut vectors) if !vectors.is_empty() => vectors.swap_remove(0),
+            Ok(_) => return None,
+            Err(e) => {
+                warn!("Failed to embed message for rule matching: {}", e);
+                return None;
+            }
+        };
+
+        examples
+            .iter()
+            .filter(|e| allowed[e.rule])
+            .map(|e| (e.rule, cosine_similarity(&query, &e.vector)))
+            .filter(|(rule, score)| *score >= self.rules[*rule].threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+}
+
+/// Cosine similarity of two vectors (0.0 if either is zero or lengths differ)
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[async_trait]
+impl RoutingHandler for RuleRouter {
+    async fn handle(&self, message: &Message, agent: &Agent) -> HandlerDecision {
+        // Only rules whose target the message may be forwarded to
+        let allowed: Vec<bool> = self
+            .rules
+            .iter()
+            .map(|rule| {
+                agent
+                    .blocking_connections_for(message)
+                    .any(|(name, _)| *name == rule.target)
+            })
+            .collect();
+
+        let lowercase = message.content.to_lowercase();
+        if let Some(rule) = self
+            .rules
+            .iter()
+            .zip(&allowed)
+            .find(|(rule, allowed)| **allowed && rule.matches_lexically(&message.content, &lowercase))
+            .map(|(rule, _)| rule)
+        {
+            info!("[{}] Rule matched, forwarding to {}", agent.name, rule.target);
+            return HandlerDecision::forward_to(&rule.target, &message.content);
+        }
+
+        if let Some((rule, score)) = self.match_examples(&message.content, &allowed).await {
+            let target = &self.rules[rule].target;
+            info!(
+                "[{}] Example rule matched (similarity {:.2}), forwarding to {}",
+                agent.name, score, target
+            );
+            return HandlerDecision::forward_to(target, &message.content);
+        }
+
+        match &self.fallback {
+            Some(fallback) => {
+                debug!("[{}] No rule matched, using fallback", agent.name);
+                fallback.handle(message, agent).await
+            }
+            None => {
+                debug!("[{}] No rule matched", agent.name);
+                HandlerDecision::response(&self.no_match_response)
+            }
+        }
+    }
+
+    async fn synthesize(
+        &self,
+        original_message: &Message,
+        outcomes: &[ForwardOutcome],
+        agent: &Agent,
+    ) -> Option<String> {
+        if let Some(fallback) = &self.fallback {
+            return fallback.synthesize(original_message, outcomes, agent).await;
+        }
+
+        let responses: Vec<&str> = outcomes.iter().filter_map(|o| o.response()).collect();
+        if !responses.is_empty() {
+            return Some(responses.join("\n\n"));
+        }
+
+        let failures: Vec<String> = outcomes
+            .iter()
+            .filter_map(|o| o.failure_reason().map(|reason| format!("- {}: {}", o.agent, reason)))
+            .collect();
+        if failures.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "None of the consulted agents could answer:\n{}",
+            failures.join("\n")
+        ))
+    }
+
+    async fn evaluate(
+        &self,
+        original_message: &Message,
+        conversation_turns: &[ConversationTurn],
+        agent: &Agent,
+    ) -> EvaluationDecision {
+        match &self.fallback {
+            Some(fallback) => fallback.evaluate(original_message, conversation_turns, agent).await,
+            None => EvaluationDecision::Satisfied {
+                response: String::new(),
+            },
+        }
+    }
+
+    fn max_turns(&self) -> u16 {
+        self.fallback.as_ref().map_or(1, |fallback| fallback.max_turns())
+    }
+
+    fn quorum(&self) -> Option<usize> {
+        self.fallback.as_ref().and_then(|fallback| fallback.quorum())
+    }
+
+    async fn tally(&self, original_message: &Message, ballots: &[Ballot], agent: &Agent) -> Option<VoteResult> {
+        match &self.fallback {
+            Some(fallback) => fallback.tally(original_message, ballots, agent).await,
+            None => vote::aggregate(ballots, VoteAggregation::Majority),
+        }
+    }
+
+    async fn judge_debate(
+        &self,
+        original_message: &Message,
+        turns: &[ConversationTurn],
+        agent: &Agent,
+    ) -> Option<String> {
+        if let Some(fallback) = &self.fallback {
+            return fallback.judge_debate(original_message, turns, agent).await;
+        }
+
+        // Without a fallback the final round's arguments are the answer
+        let last_round = turns.iter().map(|t| t.turn_number).max()?;
+        let outcomes: Vec<ForwardOutcome> = turns
+            .iter()
+            .filter(|t| t.turn_number == last_round)
+            .map(|t| ForwardOutcome::success(&t.agent, &t.message_sent, &t.response))
+            .collect();
+        self.synthesize(original_message, &outcomes, agent).await
+    }
+
+    fn max_replans(&self) -> u16 {
+        self.fallback.as_ref().map_or(0, |fallback| fallback.max_replans())
+    }
+
+    async fn replan(
+        &self,
+        original_message: &Message,
+        plan: &Plan,
+        outcomes: &[ForwardOutcome],
+        agent: &Agent,
+    ) -> Option<Plan> {
+        self.fallback.as_ref()?.replan(original_message, plan, outcomes, agent).await
+    }
+
+    fn spawn_handler(&self, spec: &SpawnSpec) -> Option<Arc<dyn RoutingHandler>> {
+        self.fallback.as_ref()?.spawn_handler(spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentBuilder;
+    use crate::connection::Connection;
+    use crate::decision::ForwardStatus;
+    use crate::guard::{Guard, GuardConfig};
+    use crate::llm::{CompletionOptions, CompletionResponse, LlmError, LlmMessage};
+
+    /// Embeds text as word counts over a tiny vocabulary
+    struct BagOfWords;
+
+    const VOCABULARY: [&str; 6] = ["price", "buy", "plan", "crash", "error", "bug"];
+
+    #[async_trait]
+    impl LlmProvider for BagOfWords {
+        fn name(&self) -> &str {
+            "bag-of-words"
+        }
+
+        fn default_model(&self) -> &str {
+            "bow"
+        }
+
+        async fn complete(
+            &self,
+            _messages: &[LlmMessage],
+            _model: Option<&str>,
+            _options: Option<CompletionOptions>,
+        ) -> Result<CompletionResponse, LlmError> {
+            Err(LlmError::ProviderError("not a chat model".to_string()))
+        }
+
+        async fn health_check(&self) -> Result<(), LlmError> {
+            Ok(())
+        }
+
+        async fn embed(&self, texts: &[String], _model: Option<&str>) -> Result<Vec<Vec<f32>>, LlmError> {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let text = text.to_lowercase();
+                    VOCABULARY
+                        .iter()
+                        .map(|word| text.matches(word).count() as f32)
+                        .collect()
+                })
+                .collect())
+        }
+    }
+
+    /// Fallback that always forwards to a fixed target
+    struct FixedFallback(&'static str);
+
+    #[async_trait]
+    impl RoutingHandler for FixedFallback {
+        async fn handle(&self, message: &Message, _agent: &Agent) -> HandlerDecision {
+            HandlerDecision::forward_to(self.0, &message.content)
+        }
+
+        async fn synthesize(
+            &self,
+            _original_message: &Message,
+            _outcomes: &[ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<String> {
+            Some("fallback synthesis".to_string())
+        }
+
+        fn max_turns(&self) -> u16 {
+            3
+        }
+
+        fn quorum(&self) -> Option<usize> {
+            Some(2)
+        }
+
+        async fn tally(&self, _original_message: &Message, ballots: &[Ballot], _agent: &Agent) -> Option<VoteResult> {
+            vote::aggregate(ballots, VoteAggregation::Weighted)
+        }
+
+        async fn judge_debate(
+            &self,
+            _original_message: &Message,
+            _turns: &[ConversationTurn],
+            _agent: &Agent,
+        ) -> Option<String> {
+            Some("fallback verdict".to_string())
+        }
+
+        fn max_replans(&self) -> u16 {
+            2
+        }
+
+        fn spawn_handler(&self, _spec: &SpawnSpec) -> Option<Arc<dyn RoutingHandler>> {
+            Some(Arc::new(FixedFallback(self.0)))
+        }
+    }
+
+    fn agent() -> Agent {
+        AgentBuilder::new("Router")
+            .blocking_connection("Billing")
+            .blocking_connection("Sales")
+            .blocking_connection("Engineering")
+            .build()
+    }
+
+    fn rules() -> Vec<RuleConfig> {
+        vec![
+            RuleConfig {
+                target: "Billing".to_string(),
+                regex: Some(r"(?i)\binvoice\s+#?\d+".to_string()),
+                keywords: vec!["Refund".to_string()],
+                ..Default::default()
+            },
+            RuleConfig {
+                target: "Sales".to_string(),
+                examples: vec!["What does the pro plan price?".to_string(), "I want to buy".to_string()],
+                threshold: Some(0.5),
+                ..Default::default()
+            },
+            RuleConfig {
+                target: "Engineering".to_string(),
+                examples: vec!["The app shows an error and crashes".to_string()],
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn target(decision: &HandlerDecision) -> Option<&str> {
+        decision.forward_targets().map(|t| t[0].agent.as_str())
+    }
+
+    async fn route(router: &RuleRouter, content: &str) -> HandlerDecision {
+        router.handle(&Message::new("User", "Router", content), &agent()).await
+    }
+
+    #[tokio::test]
+    async fn test_regex_and_keyword_rules() {
+        let router = RuleRouter::new(&rules()).unwrap().with_embeddings(Arc::new(BagOfWords), None);
+
+        assert_eq!(target(&route(&router, "Where is invoice #123?").await), Some("Billing"));
+        assert_eq!(target(&route(&router, "I need a REFUND").await), Some("Billing"));
+    }
+
+    #[tokio::test]
+    async fn test_example_similarity_rules() {
+        let router = RuleRouter::new(&rules()).unwrap().with_embeddings(Arc::new(BagOfWords), None);
+
+        assert_eq!(target(&route(&router, "Can I buy the team plan?").await), Some("Sales"));
+        assert_eq!(target(&route(&router, "Crash with error code 7").await), Some("Engineering"));
+    }
+
+    #[tokio::test]
+    async fn test_no_match_uses_fallback_or_fixed_response() {
+        let router = RuleRouter::new(&rules())
+            .unwrap()
+            .with_embeddings(Arc::new(BagOfWords), None)
+            .with_no_match_response("No idea.");
+        assert_eq!(route(&router, "Hello there").await, HandlerDecision::response("No idea."));
+
+        let router = router.with_fallback(Arc::new(FixedFallback("Sales")));
+        assert_eq!(target(&route(&router, "Hello there").await), Some("Sales"));
+    }
+
+    #[tokio::test]
+    async fn test_rules_skip_guarded_connections() {
+        let guard = Guard::compile(&GuardConfig {
+            metadata: [("authenticated".to_string(), "true".to_string())].into(),
+            ..Default::default()
+        })
+        .unwrap();
+        let agent = AgentBuilder::new("Router")
+            .connection("Billing", Connection::blocking(None).with_guard(guard))
+            .build();
+        let router = RuleRouter::new(&rules()[..1]).unwrap();
+
+        let decision = router.handle(&Message::new("User", "Router", "refund"), &agent).await;
+        assert!(!decision.has_forward());
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        let empty = RuleConfig {
+            target: "Billing".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(RuleRouter::new(&[empty]), Err(RuleError::EmptyRule(_))));
+
+        let bad_regex = RuleConfig {
+            target: "Billing".to_string(),
+            regex: Some("[".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(RuleRouter::new(&[bad_regex]), Err(RuleError::InvalidRegex(_, _))));
+
+        let router = RuleRouter::new(&rules()).unwrap();
+        assert_eq!(router.validate(), Err(RuleError::MissingEmbedder));
+    }
+
+    #[tokio::test]
+    async fn test_fallback_settles_its_own_decisions() {
+        let message = Message::new("User", "Router", "Should we refund?");
+        let turns = vec![ConversationTurn {
+            agent: "Billing".to_string(),
+            message_sent: "Should we refund?".to_string(),
+            response: "Yes".to_string(),
+            turn_number: 0,
+        }];
+        let spec = SpawnSpec::new("Helper", "Help.", "Should we refund?");
+
+        let router = RuleRouter::new(&rules()[..1]).unwrap();
+        assert_eq!(router.max_turns(), 1);
+        assert_eq!(router.quorum(), None);
+        assert_eq!(router.max_replans(), 0);
+        assert!(router.spawn_handler(&spec).is_none());
+        let verdict = router.judge_debate(&message, &turns, &agent()).await;
+        assert_eq!(verdict.as_deref(), Some("Yes"));
+
+        let router = router.with_fallback(Arc::new(FixedFallback("Sales")));
+        assert_eq!(router.max_turns(), 3);
+        assert_eq!(router.quorum(), Some(2));
+        assert_eq!(router.max_replans(), 2);
+        assert!(router.spawn_handler(&spec).is_some());
+        let verdict = router.judge_debate(&message, &turns, &agent()).await;
+        assert_eq!(verdict.as_deref(), Some("fallback verdict"));
+        let ballots = vec![Ballot {
+            agent: "Billing".to_string(),
+            answer: "Yes".to_string(),
+            confidence: 1.0,
+        }];
+        let result = router.tally(&message, &ballots, &agent()).await.unwrap();
+        assert_eq!(result.method, VoteAggregation::Weighted);
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_without_fallback() {
+        let router = RuleRouter::new(&rules()[..1]).unwrap();
+        let message = Message::new("User", "Router", "refund");
+
+        let outcomes = vec![ForwardOutcome::success("Billing", "refund", "Refund issued")];
+        let answer = router.synthesize(&message, &outcomes, &agent()).await;
+        assert_eq!(answer.as_deref(), Some("Refund issued"));
+
+        let outcomes = vec![ForwardOutcome::new(
+            "Billing",
+            "refund",
+            ForwardStatus::Timeout { waited_ms: 1500 },
+        )];
+        let answer = router.synthesize(&message, &outcomes, &agent()).await.unwrap();
+        assert!(answer.contains("- Billing: timed out after 1.5s"));
+    }
+}
@@ -0,0 +1,237 @@
+//! OpenTelemetry span export
+//!
+//! The agent system describes its work as `tracing` spans:
+//! - `mas.request`: a message sent into the system from outside
+//! - `mas.agent.handle`: an agent or workflow handling a message, a child of
+//!   the sender's span; routing agents record their decision
+//!   (`mas.routing.decision`)
+//! - `mas.llm.call`: one LLM completion, with the requested and answering
+//!   model and the token usage (`gen_ai.*` attributes)
+//! - `mas.tool.call` / `mas.db.query`: a tool or database handling a call
+//! - `mas.synthesis`: a routing agent merging the answers it collected
+//!
+//! [`layer`] turns these spans into OpenTelemetry spans of the given tracer
+//! provider. [`otlp_provider`] builds a provider exporting them over OTLP/HTTP
+//! to a collector; tests can use an in-memory exporter instead.
+
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{ExporterBuildError, WithExportConfig};
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use thiserror::Error;
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Service name reported to the collector unless configured otherwise
+pub const DEFAULT_SERVICE_NAME: &str = "mas";
+
+/// Path the OTLP/HTTP trace endpoint is served at
+const TRACES_PATH: &str = "/v1/traces";
+
+/// Errors that can occur while setting up span export
+#[derive(Debug, Error)]
+pub enum TelemetryError {
+    #[error("Could not build the OTLP exporter: {0}")]
+    Exporter(#[from] ExporterBuildError),
+}
+
+/// Where and as whom spans are exported
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    /// Base URL of the collector's OTLP/HTTP receiver, e.g. `http://localhost:4318`
+    pub endpoint: String,
+    /// Service name the spans are reported under
+    pub service_name: String,
+}
+
+impl TelemetryConfig {
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            service_name: DEFAULT_SERVICE_NAME.to_string(),
+        }
+    }
+
+    pub fn with_service_name(mut self, service_name: impl Into<String>) -> Self {
+        self.service_name = service_name.into();
+        self
+    }
+
+    /// The trace endpoint under the configured base URL
+    fn traces_url(&self) -> String {
+        let base = self.endpoint.trim_end_matches('/');
+        if base.ends_with(TRACES_PATH) {
+            base.to_string()
+        } else {
+            format!("{}{}", base, TRACES_PATH)
+        }
+    }
+}
+
+/// Build a tracer provider exporting spans in batches to the OTLP collector
+///
+/// Call `shutdown` on the provider before exiting so the last batch is sent.
+pub fn otlp_provider(config: &TelemetryConfig) -> Result<SdkTracerProvider, TelemetryError> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(config.traces_url())
+        .build()?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(config.service_name.clone()).build())
+        .build())
+}
+
+/// A `tracing` layer recording spans with a tracer of `provider`
+pub fn layer<S>(provider: &SdkTracerProvider) -> OpenTelemetryLayer<S, SdkTracer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer(DEFAULT_SERVICE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentBuilder};
+    use crate::agent_system::{AgentSystem, MessageHandler, RoutingHandler, SendResult};
+    use crate::config::SystemConfig;
+    use crate::decision::{ForwardOutcome, HandlerDecision};
+    use crate::llm::{
+        CompletionOptions, CompletionResponse, LlmError, LlmHandler, LlmMessage, LlmProvider, TokenUsage,
+    };
+    use crate::message::Message;
+    use async_trait::async_trait;
+    use opentelemetry::Value;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SpanData};
+    use std::sync::Arc;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Answers every prompt with the same text and token usage
+    struct FixedProvider;
+
+    #[async_trait]
+    impl LlmProvider for FixedProvider {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn default_model(&self) -> &str {
+            "fixed-model"
+        }
+
+        async fn complete(
+            &self,
+            _messages: &[LlmMessage],
+            _model: Option<&str>,
+            _options: Option<CompletionOptions>,
+        ) -> Result<CompletionResponse, LlmError> {
+            Ok(CompletionResponse {
+                content: "A haiku".to_string(),
+                model: "fixed-model".to_string(),
+                usage: Some(TokenUsage { prompt_tokens: 21, completion_tokens: 8, total_tokens: 29 }),
+            })
+        }
+
+        async fn health_check(&self) -> Result<(), LlmError> {
+            Ok(())
+        }
+    }
+
+    /// Forwards everything to the Writer and passes its answer on
+    struct Delegate;
+
+    #[async_trait]
+    impl RoutingHandler for Delegate {
+        async fn handle(&self, message: &Message, _agent: &Agent) -> HandlerDecision {
+            HandlerDecision::forward_to("Writer", message.content.clone())
+        }
+
+        async fn synthesize(
+            &self,
+            _original_message: &Message,
+            outcomes: &[ForwardOutcome],
+            _agent: &Agent,
+        ) -> Option<String> {
+            outcomes.first().and_then(|o| o.response()).map(str::to_string)
+        }
+    }
+
+    /// Stands in for the user, who never receives anything
+    struct User;
+
+    #[async_trait]
+    impl MessageHandler for User {
+        async fn handle(&self, _message: &Message, _agent: &Agent) -> Option<String> {
+            None
+        }
+    }
+
+    fn attribute<'a>(span: &'a SpanData, key: &str) -> Option<&'a Value> {
+        span.attributes.iter().find(|kv| kv.key.as_str() == key).map(|kv| &kv.value)
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_requests_are_exported_as_span_trees() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+        let subscriber = tracing_subscriber::registry().with(layer(&provider));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let system = Arc::new(AgentSystem::new(SystemConfig::default()));
+        let user = AgentBuilder::new("User").blocking_connection("Coordinator").build();
+        let coordinator = AgentBuilder::new("Coordinator").blocking_connection("Writer").build();
+        let writer = AgentBuilder::new("Writer").system_prompt("You write haiku.").build();
+        system.register_agent(user, Arc::new(User)).await.unwrap();
+        system
+            .register_agent(writer, Arc::new(LlmHandler::new(Arc::new(FixedProvider))))
+            .await
+            .unwrap();
+        AgentSystem::register_routing_agent(system.clone(), coordinator, Arc::new(Delegate))
+            .await
+            .unwrap();
+
+        let result = system.send_message("User", "Coordinator", "A haiku, please").await.unwrap();
+        assert!(matches!(result, SendResult::Response(ref msg) if msg.content == "A haiku"));
+        provider.force_flush().unwrap();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let find = |name: &str, agent: Option<&str>| {
+            spans
+                .iter()
+                .find(|s| {
+                    s.name == name
+                        && agent.is_none_or(|a| attribute(s, "mas.agent") == Some(&Value::from(a.to_string())))
+                })
+                .unwrap_or_else(|| panic!("No {} span for {:?}", name, agent))
+        };
+        let request = find("mas.request", None);
+        let coordinator = find("mas.agent.handle", Some("Coordinator"));
+        let writer = find("mas.agent.handle", Some("Writer"));
+        let llm = find("mas.llm.call", Some("Writer"));
+        let synthesis = find("mas.synthesis", None);
+
+        // request → Coordinator → { Writer → LLM call, synthesis }
+        assert_eq!(coordinator.parent_span_id, request.span_context.span_id());
+        assert_eq!(writer.parent_span_id, coordinator.span_context.span_id());
+        assert_eq!(llm.parent_span_id, writer.span_context.span_id());
+        assert_eq!(synthesis.parent_span_id, coordinator.span_context.span_id());
+        assert!(spans.iter().all(|s| s.span_context.trace_id() == request.span_context.trace_id()));
+
+        assert_eq!(attribute(request, "mas.to"), Some(&Value::from("Coordinator")));
+        assert_eq!(attribute(coordinator, "mas.routing.decision"), Some(&Value::from("forward")));
+        assert_eq!(attribute(llm, "gen_ai.response.model"), Some(&Value::from("fixed-model")));
+        assert_eq!(attribute(llm, "gen_ai.usage.input_tokens"), Some(&Value::I64(21)));
+        assert_eq!(attribute(llm, "gen_ai.usage.output_tokens"), Some(&Value::I64(8)));
+    }
+
+    #[test]
+    fn test_traces_url_is_appended_once() {
+        assert_eq!(TelemetryConfig::new("http://collector:4318/").traces_url(), "http://collector:4318/v1/traces");
+        assert_eq!(
+            TelemetryConfig::new("http://collector:4318/v1/traces").traces_url(),
+            "http://collector:4318/v1/traces"
+        );
+    }
+}
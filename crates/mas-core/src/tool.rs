@@ -152,6 +152,14 @@ pub struct ToolConfig {
     /// Request timeout in seconds
     #[serde(default)]
     pub timeout_secs: Option<u64>,
+
+    /// Hold every call until a human approves, denies or edits it
+    #[serde(default)]
+    pub requires_approval: bool,
+
+    /// Seconds a held call waits for a decision (default 300)
+    #[serde(default)]
+    pub approval_timeout_secs: Option<u64>,
 }
 
 fn default_parameters() -> Value {
@@ -172,6 +180,8 @@ impl ToolConfig {
             endpoint,
             response_mapping: ResponseMapping::default(),
             timeout_secs: None,
+            requires_approval: false,
+            approval_timeout_secs: None,
         }
     }
 
@@ -193,6 +203,18 @@ impl ToolConfig {
         self
     }
 
+    /// Require human approval before each call
+    pub fn with_requires_approval(mut self, requires_approval: bool) -> Self {
+        self.requires_approval = requires_approval;
+        self
+    }
+
+    /// Set how long a held call waits for a decision
+    pub fn with_approval_timeout_secs(mut self, secs: u64) -> Self {
+        self.approval_timeout_secs = Some(secs);
+        self
+    }
+
     /// Get the effective timeout duration
     pub fn effective_timeout(&self, default: std::time::Duration) -> std::time::Duration {
         self.timeout_secs
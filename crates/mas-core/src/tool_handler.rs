@@ -5,11 +5,15 @@
 //! to a tool, the handler:
 //!
 //! 1. Parses parameters from the message content (JSON or plain text)
-//! 2. Substitutes ${param} placeholders in URL, headers, and body
+//! 2. Substitutes ${param} placeholders in URL, headers, and body, and
+//!    ${blackboard.key.path} placeholders with values from the request's blackboard
 //! 3. Substitutes ${ENV_VAR} placeholders with environment variables
 //! 4. Executes the HTTP request (or MCP protocol for MCP endpoints)
 //! 5. Extracts and formats the response according to response_mapping
 //!
+//! Tools with `requires_approval` hold each call between steps 3 and 4 until a
+//! reviewer approves, denies or edits it (see [`crate::approval`]).
+//!
 //! ## MCP Support
 //!
 //! For MCP (Model Context Protocol) endpoints, the handler uses the `rmcp` SDK
@@ -19,7 +23,10 @@
 
 use crate::agent::Agent;
 use crate::agent_system::MessageHandler;
+use crate::approval::{ApprovalDecision, ApprovalGate, ApprovalKind, ApprovalQueue};
+use crate::blackboard::Blackboard;
 use crate::message::Message;
+use crate::metrics::{metrics, CallKind};
 use crate::tool::{EndpointType, HttpMethod, ResponseFormat, Tool};
 
 use async_trait::async_trait;
@@ -33,7 +40,7 @@ use serde_json::Value;
 use std::collections::HashMap;
 use std::env;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 use tracing::{debug, error, info, warn};
 
@@ -49,6 +56,8 @@ pub struct ToolHandler {
     client: Client,
     /// Cached MCP client — lazily initialized on first MCP call, reused across calls.
     mcp_client: Mutex<Option<McpClient>>,
+    /// Review step for tools with `requires_approval`
+    approval: Option<ApprovalGate>,
 }
 
 impl ToolHandler {
@@ -68,9 +77,16 @@ impl ToolHandler {
             tool,
             client,
             mcp_client: Mutex::new(None),
+            approval: None,
         }
     }
 
+    /// Hold every call for review in `queue`, waiting at most `timeout` for a decision
+    pub fn with_approval(mut self, queue: ApprovalQueue, timeout: Duration) -> Self {
+        self.approval = Some(ApprovalGate::new(queue, timeout));
+        self
+    }
+
     /// Parse parameters from message content, preserving JSON types
     ///
     /// Tries to parse as JSON first. If that fails, treats the entire
@@ -115,15 +131,16 @@ impl ToolHandler {
     }
 
     /// Substitute ${param} placeholders in a string with values from params
-    /// Also substitutes ${ENV_VAR} with environment variables
-    fn substitute_placeholders(&self, template: &str, params: &HashMap<String, String>) -> String {
-        let mut result = template.to_string();
-
-        // First, substitute parameters
-        for (key, value) in params {
-            let placeholder = format!("${{{}}}", key);
-            result = result.replace(&placeholder, value);
-        }
+    /// Also substitutes ${blackboard.path} from the blackboard and ${ENV_VAR}
+    /// with environment variables
+    fn substitute_placeholders(
+        &self,
+        template: &str,
+        params: &HashMap<String, String>,
+        blackboard: &Blackboard,
+    ) -> String {
+        // First, substitute parameters and blackboard values
+        let result = self.substitute_params(template, params, blackboard);
 
         // Then, substitute environment variables for remaining ${...} patterns
         let mut final_result = String::new();
@@ -157,22 +174,77 @@ impl ToolHandler {
         final_result
     }
 
+    /// Substitute ${param} and ${blackboard.path} placeholders, leaving
+    /// ${ENV_VAR} ones untouched
+    fn substitute_params(
+        &self,
+        template: &str,
+        params: &HashMap<String, String>,
+        blackboard: &Blackboard,
+    ) -> String {
+        let mut result = template.to_string();
+        for (key, value) in params {
+            let placeholder = format!("${{{}}}", key);
+            result = result.replace(&placeholder, value);
+        }
+        substitute_blackboard(&result, blackboard)
+    }
+
     /// Substitute placeholders in a JSON value
-    fn substitute_json(&self, value: &Value, params: &HashMap<String, String>) -> Value {
-        match value {
-            Value::String(s) => Value::String(self.substitute_placeholders(s, params)),
-            Value::Object(obj) => {
-                let mut new_obj = serde_json::Map::new();
-                for (k, v) in obj {
-                    new_obj.insert(k.clone(), self.substitute_json(v, params));
-                }
-                Value::Object(new_obj)
-            }
-            Value::Array(arr) => {
-                Value::Array(arr.iter().map(|v| self.substitute_json(v, params)).collect())
-            }
-            other => other.clone(),
+    fn substitute_json(&self, value: &Value, params: &HashMap<String, String>, blackboard: &Blackboard) -> Value {
+        map_json_strings(value, &|s| self.substitute_placeholders(s, params, blackboard))
+    }
+
+    /// Whether GET parameters are appended as a query string
+    ///
+    /// Only when the URL has no ${...} placeholders that would take them instead.
+    fn appends_query(&self, params: &HashMap<String, String>) -> bool {
+        let endpoint = &self.tool.config.endpoint;
+        endpoint.method == HttpMethod::GET && !params.is_empty() && !endpoint.url.contains("${")
+    }
+
+    /// Render the call as it would be sent, for review
+    ///
+    /// Parameters are filled in; ${ENV_VAR} placeholders are left as written so
+    /// secrets are not shown to the reviewer.
+    fn render_request(&self, params: &HashMap<String, Value>, blackboard: &Blackboard) -> String {
+        let string_params = self.params_to_strings(params);
+        let endpoint = &self.tool.config.endpoint;
+        let mut url = self.substitute_params(&endpoint.url, &string_params, blackboard);
+
+        if endpoint.endpoint_type == EndpointType::Mcp {
+            let arguments: serde_json::Map<String, Value> =
+                params.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+            return format!(
+                "MCP {} at {}\n{}",
+                endpoint.mcp_tool_name.as_deref().unwrap_or_default(),
+                url,
+                serde_json::to_string_pretty(&arguments).unwrap_or_default()
+            );
+        }
+
+        if self.appends_query(&string_params) {
+            let mut pairs: Vec<String> = string_params.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            pairs.sort();
+            url = format!("{}?{}", url, pairs.join("&"));
         }
+
+        let mut lines = vec![format!("{} {}", endpoint.method, url)];
+        let mut headers: Vec<String> = endpoint
+            .headers
+            .iter()
+            .map(|(key, value)| format!("{}: {}", key, self.substitute_params(value, &string_params, blackboard)))
+            .collect();
+        headers.sort();
+        lines.extend(headers);
+
+        if let Some(body_template) = &endpoint.body_template {
+            let body = map_json_strings(body_template, &|s| self.substitute_params(s, &string_params, blackboard));
+            lines.push(String::new());
+            lines.push(serde_json::to_string_pretty(&body).unwrap_or_default());
+        }
+
+        lines.join("\n")
     }
 
     /// Extract data from response using a JSONPath-like expression
@@ -254,13 +326,13 @@ impl ToolHandler {
     }
 
     /// Execute the HTTP request
-    async fn execute_request(&self, params: &HashMap<String, Value>) -> Result<String, String> {
+    async fn execute_request(&self, params: &HashMap<String, Value>, blackboard: &Blackboard) -> Result<String, String> {
         // Convert to strings for placeholder substitution in URLs/headers
         let string_params = self.params_to_strings(params);
         let endpoint = &self.tool.config.endpoint;
 
         // Build URL with substitutions
-        let mut url = self.substitute_placeholders(&endpoint.url, &string_params);
+        let mut url = self.substitute_placeholders(&endpoint.url, &string_params, blackboard);
 
         debug!("[{}] Making {} request to: {}", self.tool.name(), endpoint.method, url);
 
@@ -275,23 +347,20 @@ impl ToolHandler {
 
         // For GET requests, auto-append parameters as query string if the URL
         // doesn't already contain ${...} placeholders for them
-        if endpoint.method == HttpMethod::GET && !string_params.is_empty() {
-            let has_placeholders = endpoint.url.contains("${");
-            if !has_placeholders {
-                let query_pairs: Vec<(&String, &String)> = string_params.iter().collect();
-                request = request.query(&query_pairs);
-            }
+        if self.appends_query(&string_params) {
+            let query_pairs: Vec<(&String, &String)> = string_params.iter().collect();
+            request = request.query(&query_pairs);
         }
 
         // Add headers with substitutions
         for (key, value) in &endpoint.headers {
-            let substituted_value = self.substitute_placeholders(value, &string_params);
+            let substituted_value = self.substitute_placeholders(value, &string_params, blackboard);
             request = request.header(key, substituted_value);
         }
 
         // Add body if present and method supports it
         if let Some(body_template) = &endpoint.body_template {
-            let body = self.substitute_json(body_template, &string_params);
+            let body = self.substitute_json(body_template, &string_params, blackboard);
             debug!("[{}] Request body: {}", self.tool.name(), body);
             request = request.json(&body);
         }
@@ -383,10 +452,10 @@ impl ToolHandler {
     ///
     /// The MCP client is created once and reused across calls. If the connection
     /// is lost, it reconnects automatically.
-    async fn execute_mcp_request(&self, params: &HashMap<String, Value>) -> Result<String, String> {
+    async fn execute_mcp_request(&self, params: &HashMap<String, Value>, blackboard: &Blackboard) -> Result<String, String> {
         let endpoint = &self.tool.config.endpoint;
         let string_params = self.params_to_strings(params);
-        let url = self.substitute_placeholders(&endpoint.url, &string_params);
+        let url = self.substitute_placeholders(&endpoint.url, &string_params, blackboard);
 
         let mcp_tool_name = endpoint.mcp_tool_name.as_ref().ok_or_else(|| {
             "MCP endpoint requires mcp_tool_name to be set".to_string()
@@ -472,6 +541,44 @@ impl ToolHandler {
     }
 }
 
+/// Replace ${blackboard.path} placeholders with values from the blackboard
+///
+/// Placeholders for missing values are left as written.
+fn substitute_blackboard(template: &str, blackboard: &Blackboard) -> String {
+    const PREFIX: &str = "${blackboard.";
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(PREFIX) {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let path = &rest[start + PREFIX.len()..start + len];
+        result.push_str(&rest[..start]);
+        match blackboard.get_text(path) {
+            Some(value) => result.push_str(&value),
+            None => {
+                warn!("Blackboard has no value for {}", path);
+                result.push_str(&rest[start..=start + len]);
+            }
+        }
+        rest = &rest[start + len + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Apply `f` to every string in a JSON value
+fn map_json_strings(value: &Value, f: &impl Fn(&str) -> String) -> Value {
+    match value {
+        Value::String(s) => Value::String(f(s)),
+        Value::Object(obj) => Value::Object(
+            obj.iter().map(|(k, v)| (k.clone(), map_json_strings(v, f))).collect(),
+        ),
+        Value::Array(arr) => Value::Array(arr.iter().map(|v| map_json_strings(v, f)).collect()),
+        other => other.clone(),
+    }
+}
+
 #[async_trait]
 impl MessageHandler for ToolHandler {
     async fn handle(&self, message: &Message, _agent: &Agent) -> Option<String> {
@@ -483,14 +590,30 @@ impl MessageHandler for ToolHandler {
         );
 
         // Parse parameters from message
-        let params = self.parse_parameters(&message.content);
+        let mut params = self.parse_parameters(&message.content);
         debug!("[Tool:{}] Parsed parameters: {:?}", self.tool.name(), params);
 
+        // Hold the call until a reviewer decides on it
+        if let Some(gate) = &self.approval {
+            let request = self.render_request(&params, &message.blackboard);
+            match gate.review(ApprovalKind::Tool, self.tool.name(), message, request).await {
+                Some(ApprovalDecision::Approve) => {}
+                Some(ApprovalDecision::Edit { input }) => params = self.parse_parameters(&input),
+                Some(ApprovalDecision::Deny { reason }) => {
+                    let reason = reason.map(|r| format!(": {}", r)).unwrap_or_default();
+                    return Some(format!("Tool error: The call was denied by the reviewer{}", reason));
+                }
+                None => return Some("Tool error: The call was not approved in time".to_string()),
+            }
+        }
+
         // Execute based on endpoint type
+        let started = Instant::now();
         let result = match self.tool.config.endpoint.endpoint_type {
-            EndpointType::Http => self.execute_request(&params).await,
-            EndpointType::Mcp => self.execute_mcp_request(&params).await,
+            EndpointType::Http => self.execute_request(&params, &message.blackboard).await,
+            EndpointType::Mcp => self.execute_mcp_request(&params, &message.blackboard).await,
         };
+        metrics().observe_call(CallKind::Tool, self.tool.name(), started.elapsed(), result.is_err());
 
         match result {
             Ok(result) => {
@@ -588,6 +711,7 @@ mod tests {
         let result = handler.substitute_placeholders(
             "https://api.example.com/search?q=${query}&lang=en",
             &params,
+            &Blackboard::new(),
         );
         assert_eq!(
             result,
@@ -610,11 +734,31 @@ mod tests {
             }
         });
 
-        let result = handler.substitute_json(&template, &params);
+        let result = handler.substitute_json(&template, &params, &Blackboard::new());
         assert_eq!(result["search"], "test");
         assert_eq!(result["nested"]["value"], "test");
     }
 
+    #[test]
+    fn test_substitute_blackboard_values() {
+        let handler = ToolHandler::new(create_test_tool());
+        let blackboard = Blackboard::new();
+        blackboard.set("patient.severity", serde_json::json!("high"));
+        blackboard.set("patient.age", serde_json::json!(54));
+
+        let template = serde_json::json!({
+            "query": "${query}",
+            "triage": "${blackboard.patient.severity} (${blackboard.patient.age})",
+            "missing": "${blackboard.patient.name}"
+        });
+        let params = HashMap::from([("query".to_string(), "rust".to_string())]);
+
+        let result = map_json_strings(&template, &|s| handler.substitute_params(s, &params, &blackboard));
+        assert_eq!(result["query"], "rust");
+        assert_eq!(result["triage"], "high (54)");
+        assert_eq!(result["missing"], "${blackboard.patient.name}");
+    }
+
     #[test]
     fn test_extract_simple_path() {
         let tool = create_test_tool();
@@ -692,4 +836,41 @@ mod tests {
         assert!(formatted.contains("**title**"));
         assert!(formatted.contains("**count**"));
     }
+
+    #[test]
+    fn test_render_request_keeps_env_placeholders() {
+        let tool = create_test_tool();
+        let handler = ToolHandler::new(tool);
+
+        let params = handler.parse_parameters(r#"{"query": "rust"}"#);
+        let rendered = handler.render_request(&params, &Blackboard::new());
+        assert!(rendered.starts_with("POST https://api.example.com/search\n"));
+        assert!(rendered.contains("Authorization: Bearer ${API_KEY}"));
+        assert!(rendered.contains(r#""query": "rust""#));
+    }
+
+    #[tokio::test]
+    async fn test_denied_call_is_not_executed() {
+        let queue = ApprovalQueue::new();
+        let mut published = queue.subscribe();
+        let handler = ToolHandler::new(create_test_tool()).with_approval(queue.clone(), Duration::from_secs(5));
+        let agent = crate::agent::AgentBuilder::new("TestTool").build();
+
+        let call = tokio::spawn(async move {
+            let message = Message::new("Researcher", "TestTool", r#"{"query": "rust"}"#);
+            handler.handle(&message, &agent).await
+        });
+
+        let approval = published.recv().await.unwrap();
+        assert_eq!(approval.kind, ApprovalKind::Tool);
+        assert_eq!(approval.from, "Researcher");
+        queue
+            .decide(approval.id, ApprovalDecision::Deny { reason: Some("not now".to_string()) })
+            .unwrap();
+
+        assert_eq!(
+            call.await.unwrap().as_deref(),
+            Some("Tool error: The call was denied by the reviewer: not now")
+        );
+    }
 }
@@ -3,7 +3,10 @@
 //! Provides a mechanism to capture agent-to-agent communications
 //! for display in verbose/debug mode.
 
+use crate::blackboard::Blackboard;
 use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
 use tokio::sync::{broadcast, RwLock};
 
@@ -18,6 +21,10 @@ pub struct TraceEvent {
     pub content: String,
     /// Type of event
     pub event_type: TraceEventType,
+    /// Nested system the event happened in, as a path like "legal-review/intake"
+    /// (`None` for the system that received the request)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
 }
 
 /// Types of trace events
@@ -32,6 +39,45 @@ pub enum TraceEventType {
     Forward,
     /// Synthesized response combining multiple agent responses
     Synthesis,
+    /// A voter's ballot (content is the ballot as JSON)
+    Ballot,
+    /// Aggregated vote result (content is the result as JSON)
+    Vote,
+    /// A workflow step settled (content is the step status as JSON)
+    Step,
+    /// An agent changed the blackboard (content is the whole blackboard as JSON)
+    Blackboard,
+    /// A draft answer written before reflection (content is the draft)
+    Draft,
+    /// A critique of a draft (content is the critique as JSON)
+    Critique,
+    /// A plan or re-plan (content is the plan as JSON)
+    Plan,
+    /// A sub-agent was spawned (content is its spec as JSON)
+    Spawn,
+    /// A guardrail check failed (content is the violation as JSON)
+    Guardrail,
+}
+
+impl TraceEventType {
+    /// Stable snake_case name, matching the serialized form
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TraceEventType::Request => "request",
+            TraceEventType::Response => "response",
+            TraceEventType::Forward => "forward",
+            TraceEventType::Synthesis => "synthesis",
+            TraceEventType::Ballot => "ballot",
+            TraceEventType::Vote => "vote",
+            TraceEventType::Step => "step",
+            TraceEventType::Blackboard => "blackboard",
+            TraceEventType::Draft => "draft",
+            TraceEventType::Critique => "critique",
+            TraceEventType::Plan => "plan",
+            TraceEventType::Spawn => "spawn",
+            TraceEventType::Guardrail => "guardrail",
+        }
+    }
 }
 
 impl TraceEvent {
@@ -42,9 +88,19 @@ impl TraceEvent {
             to: to.into(),
             content: content.into(),
             event_type,
+            system: None,
         }
     }
 
+    /// The same event as seen by the system that called `system`
+    fn nested_in(mut self, system: &str) -> Self {
+        self.system = Some(match self.system {
+            Some(inner) => format!("{}/{}", system, inner),
+            None => system.to_string(),
+        });
+        self
+    }
+
     pub fn request(from: impl Into<String>, to: impl Into<String>, content: impl Into<String>) -> Self {
         Self::new(from, to, content, TraceEventType::Request)
     }
@@ -60,6 +116,46 @@ impl TraceEvent {
     pub fn synthesis(from: impl Into<String>, to: impl Into<String>, content: impl Into<String>) -> Self {
         Self::new(from, to, content, TraceEventType::Synthesis)
     }
+
+    pub fn ballot(from: impl Into<String>, to: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::new(from, to, content, TraceEventType::Ballot)
+    }
+
+    pub fn vote(from: impl Into<String>, to: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::new(from, to, content, TraceEventType::Vote)
+    }
+
+    pub fn step(from: impl Into<String>, to: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::new(from, to, content, TraceEventType::Step)
+    }
+
+    pub fn blackboard(from: impl Into<String>, to: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::new(from, to, content, TraceEventType::Blackboard)
+    }
+
+    pub fn draft(from: impl Into<String>, to: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::new(from, to, content, TraceEventType::Draft)
+    }
+
+    pub fn critique(from: impl Into<String>, to: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::new(from, to, content, TraceEventType::Critique)
+    }
+
+    pub fn plan(from: impl Into<String>, to: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::new(from, to, content, TraceEventType::Plan)
+    }
+
+    pub fn spawn(from: impl Into<String>, to: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::new(from, to, content, TraceEventType::Spawn)
+    }
+
+    pub fn guardrail(from: impl Into<String>, to: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::new(from, to, content, TraceEventType::Guardrail)
+    }
+}
+
+tokio::task_local! {
+    static CURRENT: TraceCollector;
 }
 
 /// Collector for trace events
@@ -71,6 +167,18 @@ impl TraceEvent {
 pub struct TraceCollector {
     events: Arc<RwLock<Vec<TraceEvent>>>,
     broadcast_tx: broadcast::Sender<TraceEvent>,
+    session_id: Option<Arc<str>>,
+    metadata: Arc<HashMap<String, String>>,
+    blackboard: Blackboard,
+    parent: Option<Arc<Nesting>>,
+    origin: Option<Arc<str>>,
+}
+
+/// Link from the trace of a nested system to the trace of its caller
+#[derive(Debug)]
+struct Nesting {
+    system: String,
+    trace: TraceCollector,
 }
 
 impl Default for TraceCollector {
@@ -86,7 +194,107 @@ impl TraceCollector {
         Self {
             events: Arc::new(RwLock::new(Vec::new())),
             broadcast_tx,
+            session_id: None,
+            metadata: Arc::new(HashMap::new()),
+            blackboard: Blackboard::new(),
+            parent: None,
+            origin: None,
+        }
+    }
+
+    /// Create the trace for a call into another system
+    ///
+    /// The nested trace keeps the session, metadata and origin but starts with
+    /// its own blackboard. Everything it records is also recorded in this trace, tagged
+    /// with the nested system's name.
+    pub fn nested(&self, system: impl Into<String>) -> TraceCollector {
+        let mut trace = TraceCollector::new();
+        trace.session_id = self.session_id.clone();
+        trace.metadata = self.metadata.clone();
+        trace.origin = self.origin.clone();
+        trace.parent = Some(Arc::new(Nesting {
+            system: system.into(),
+            trace: self.clone(),
+        }));
+        trace
+    }
+
+    /// Names of the nested systems this trace belongs to, outermost first
+    pub fn systems(&self) -> Vec<String> {
+        let mut systems = Vec::new();
+        let mut current = self;
+        while let Some(nesting) = &current.parent {
+            systems.push(nesting.system.clone());
+            current = &nesting.trace;
         }
+        systems.reverse();
+        systems
+    }
+
+    /// Tag the traced request with the system it was first sent to
+    pub fn with_origin(mut self, system: impl Into<String>) -> Self {
+        self.origin = Some(Arc::from(system.into()));
+        self
+    }
+
+    /// The system the request was first sent to, if known
+    pub fn origin(&self) -> Option<&str> {
+        self.origin.as_deref()
+    }
+
+    /// Tag the traced request with the session it belongs to
+    ///
+    /// The session id travels with every forwarded message and is used to keep
+    /// a session on the same replica of an agent.
+    pub fn with_session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(Arc::from(session_id.into()));
+        self
+    }
+
+    /// The session this request belongs to, if any
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// Add request metadata
+    ///
+    /// Every message sent while handling the request carries this metadata,
+    /// so connection guards can check it on any hop.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.metadata).insert(key.into(), value.into());
+        self
+    }
+
+    /// Request metadata
+    pub fn metadata(&self) -> &HashMap<String, String> {
+        &self.metadata
+    }
+
+    /// Start the request with an existing blackboard
+    pub fn with_blackboard(mut self, blackboard: Blackboard) -> Self {
+        self.blackboard = blackboard;
+        self
+    }
+
+    /// The request's blackboard, shared by every message sent while handling it
+    pub fn blackboard(&self) -> &Blackboard {
+        &self.blackboard
+    }
+
+    /// Run a handler call with `trace` as the current trace
+    ///
+    /// Handlers only see the message and agent; this lets them record their
+    /// own events (e.g. reflection drafts) through [`TraceCollector::current`].
+    pub async fn scope<F: Future>(trace: Option<TraceCollector>, future: F) -> F::Output {
+        match trace {
+            Some(trace) => CURRENT.scope(trace, future).await,
+            None => future.await,
+        }
+    }
+
+    /// The trace of the request being handled, inside [`TraceCollector::scope`]
+    pub fn current() -> Option<TraceCollector> {
+        CURRENT.try_with(|trace| trace.clone()).ok()
     }
 
     /// Subscribe to real-time trace events via a broadcast receiver.
@@ -98,11 +306,23 @@ impl TraceCollector {
     }
 
     /// Record a trace event
+    ///
+    /// Events of a nested trace are recorded in every enclosing trace too.
     pub async fn record(&self, event: TraceEvent) {
-        // Broadcast to any live subscribers (ignore if none)
-        let _ = self.broadcast_tx.send(event.clone());
-        let mut events = self.events.write().await;
-        events.push(event);
+        let mut event = event;
+        let mut current = self;
+        loop {
+            // Broadcast to any live subscribers (ignore if none)
+            let _ = current.broadcast_tx.send(event.clone());
+            current.events.write().await.push(event.clone());
+            match &current.parent {
+                Some(nesting) => {
+                    event = event.nested_in(&nesting.system);
+                    current = &nesting.trace;
+                }
+                None => break,
+            }
+        }
     }
 
     /// Record a request event
@@ -143,3 +363,28 @@ impl TraceCollector {
         events.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_nested_events_reach_the_caller_tagged_with_the_system() {
+        let root = TraceCollector::new().with_metadata("user_id", "alice");
+        let legal = root.nested("legal-review").with_origin("support");
+        let contracts = legal.nested("contracts");
+        assert_eq!(contracts.metadata().get("user_id").map(String::as_str), Some("alice"));
+        assert_eq!(contracts.origin(), Some("support"));
+        assert_eq!(contracts.systems(), vec!["legal-review", "contracts"]);
+        assert!(root.systems().is_empty());
+
+        contracts.record_forward("Intake", "Clauses", "Check clause 4").await;
+        legal.record_response("Reviewer", "Intake", "Looks fine").await;
+
+        let events = root.events().await;
+        assert_eq!(events[0].system.as_deref(), Some("legal-review/contracts"));
+        assert_eq!(events[1].system.as_deref(), Some("legal-review"));
+        assert_eq!(legal.events().await[0].system.as_deref(), Some("contracts"));
+        assert_eq!(contracts.events().await[0].system, None);
+    }
+}
@@ -0,0 +1,173 @@
+//! Scheduled triggers
+//!
+//! A trigger sends a prompt to an agent or workflow on a cron schedule, for
+//! jobs like daily digests. This module holds the configuration and the pure
+//! schedule and template logic; the scheduler that runs triggers lives in the
+//! API server.
+//!
+//! # Example Configuration
+//!
+//! ```json
+//! {
+//!   "name": "daily-digest",
+//!   "cron": "0 7 * * 1-5",
+//!   "target_agent": "Coordinator",
+//!   "prompt": "Summarize yesterday's tickets for the ${date} stand-up",
+//!   "session_id": "team-digest"
+//! }
+//! ```
+//!
+//! Cron expressions have five fields (minute, hour, day of month, month, day
+//! of week) or six with leading seconds, and are evaluated in UTC. Without a
+//! `session_id` every run starts a new session.
+//!
+//! Prompt templates may use `${date}` (`2024-05-01`), `${time}` (`07:00`),
+//! `${datetime}` (RFC 3339) and `${trigger}` (the trigger's name), all taken
+//! from the scheduled time of the run.
+
+use chrono::{DateTime, Utc};
+use croner::Cron;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Configuration for a scheduled trigger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerConfig {
+    /// Unique name of the trigger within its system
+    pub name: String,
+    /// When to run: a cron expression evaluated in UTC
+    pub cron: String,
+    /// Agent or workflow to send the prompt to (system default if omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_agent: Option<String>,
+    /// Prompt template sent on each run
+    pub prompt: String,
+    /// Existing session to post into; a new session per run if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+/// Errors from compiling a trigger
+#[derive(Debug, thiserror::Error)]
+pub enum TriggerError {
+    #[error("Invalid cron expression '{0}': {1}")]
+    InvalidCron(String, String),
+
+    #[error("Unknown placeholder '${{{0}}}' in prompt")]
+    UnknownPlaceholder(String),
+}
+
+/// A trigger with its parsed schedule
+#[derive(Debug, Clone)]
+pub struct Trigger {
+    config: TriggerConfig,
+    schedule: Cron,
+}
+
+impl Trigger {
+    /// Parse the schedule and check the prompt template
+    pub fn compile(config: &TriggerConfig) -> Result<Self, TriggerError> {
+        let schedule = Cron::new(&config.cron)
+            .with_seconds_optional()
+            .parse()
+            .map_err(|e| TriggerError::InvalidCron(config.cron.clone(), e.to_string()))?;
+
+        for caps in placeholder_regex().captures_iter(&config.prompt) {
+            let name = &caps[1];
+            if !matches!(name, "date" | "time" | "datetime" | "trigger") {
+                return Err(TriggerError::UnknownPlaceholder(name.to_string()));
+            }
+        }
+
+        Ok(Self {
+            config: config.clone(),
+            schedule,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    pub fn config(&self) -> &TriggerConfig {
+        &self.config
+    }
+
+    /// First scheduled time strictly after `after`
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.schedule.find_next_occurrence(&after, false).ok()
+    }
+
+    /// The prompt for a run scheduled at `at`
+    pub fn render_prompt(&self, at: DateTime<Utc>) -> String {
+        placeholder_regex()
+            .replace_all(&self.config.prompt, |caps: &regex::Captures| match &caps[1] {
+                "date" => at.format("%Y-%m-%d").to_string(),
+                "time" => at.format("%H:%M").to_string(),
+                "datetime" => at.to_rfc3339(),
+                "trigger" => self.config.name.clone(),
+                _ => caps[0].to_string(),
+            })
+            .into_owned()
+    }
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\$\{([^}]*)\}").expect("valid placeholder regex"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn config(cron: &str, prompt: &str) -> TriggerConfig {
+        TriggerConfig {
+            name: "digest".to_string(),
+            cron: cron.to_string(),
+            target_agent: None,
+            prompt: prompt.to_string(),
+            session_id: None,
+        }
+    }
+
+    #[test]
+    fn test_next_run_on_weekdays() {
+        let trigger = Trigger::compile(&config("0 7 * * 1-5", "Digest")).unwrap();
+
+        // Friday 2024-05-03 08:00 -> Monday 2024-05-06 07:00
+        let friday = Utc.with_ymd_and_hms(2024, 5, 3, 8, 0, 0).unwrap();
+        assert_eq!(
+            trigger.next_after(friday),
+            Some(Utc.with_ymd_and_hms(2024, 5, 6, 7, 0, 0).unwrap())
+        );
+
+        // A run exactly at the scheduled time is not scheduled again
+        let monday = Utc.with_ymd_and_hms(2024, 5, 6, 7, 0, 0).unwrap();
+        assert_eq!(
+            trigger.next_after(monday),
+            Some(Utc.with_ymd_and_hms(2024, 5, 7, 7, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        let trigger = Trigger::compile(&config("*/15 * * * *", "${trigger} for ${date} at ${time}")).unwrap();
+        let at = Utc.with_ymd_and_hms(2024, 5, 6, 7, 15, 0).unwrap();
+        assert_eq!(trigger.render_prompt(at), "digest for 2024-05-06 at 07:15");
+    }
+
+    #[test]
+    fn test_invalid_triggers() {
+        assert!(matches!(
+            Trigger::compile(&config("every day", "Digest")),
+            Err(TriggerError::InvalidCron(..))
+        ));
+        assert!(matches!(
+            Trigger::compile(&config("0 7 * * *", "Digest for ${user}")),
+            Err(TriggerError::UnknownPlaceholder(_))
+        ));
+    }
+}
@@ -0,0 +1,214 @@
+//! Voting across connected agents
+//!
+//! With `RoutingBehavior::Vote`, a routing agent sends the question to every
+//! blocking connection and asks each for a ballot: a short answer plus a
+//! confidence. Ballots are then aggregated by majority, by confidence-weighted
+//! score, or by an LLM judge.
+
+use serde::{Deserialize, Serialize};
+
+/// Instructions appended to the question sent to each voter
+const BALLOT_INSTRUCTIONS: &str = r#"
+
+Answer as a ballot. Respond ONLY with JSON in this exact format:
+{ "answer": "<your short answer>", "confidence": <number between 0.0 and 1.0> }
+
+Keep the answer short (a word, a number or one sentence) so it can be compared with other answers."#;
+
+/// Confidence assumed when a voter's reply has no usable confidence
+const DEFAULT_CONFIDENCE: f32 = 0.5;
+
+/// How ballots are combined into a single result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteAggregation {
+    /// The most frequent answer wins (ties broken by total confidence)
+    #[default]
+    Majority,
+    /// The answer with the highest summed confidence wins
+    Weighted,
+    /// An LLM judge reads all ballots and decides the final answer
+    Judge,
+}
+
+/// A single voter's answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ballot {
+    /// The agent that cast the ballot
+    pub agent: String,
+    /// The agent's answer
+    pub answer: String,
+    /// Self-reported confidence, clamped to 0.0..=1.0
+    pub confidence: f32,
+}
+
+/// Outcome of aggregating ballots
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VoteResult {
+    /// The winning answer
+    pub answer: String,
+    /// How the ballots were aggregated
+    pub method: VoteAggregation,
+    /// Share of votes (majority) or confidence (weighted) behind the answer.
+    /// None when an LLM judge decided.
+    pub support: Option<f32>,
+    /// Score per distinct answer, highest first
+    pub tally: Vec<(String, f32)>,
+}
+
+#[derive(Deserialize)]
+struct BallotJson {
+    answer: String,
+    #[serde(default)]
+    confidence: Option<f32>,
+}
+
+/// Wrap a question with ballot instructions
+pub fn ballot_request(question: &str) -> String {
+    format!("{}{}", question, BALLOT_INSTRUCTIONS)
+}
+
+/// Parse a voter's reply into a ballot
+///
+/// Accepts the requested JSON (possibly surrounded by text or code fences).
+/// Falls back to the raw reply with a neutral confidence when no JSON is found.
+pub fn parse_ballot(agent: &str, reply: &str) -> Ballot {
+    let parsed = match (reply.find('{'), reply.rfind('}')) {
+        (Some(start), Some(end)) if start < end => {
+            serde_json::from_str::<BallotJson>(&reply[start..=end]).ok()
+        }
+        _ => None,
+    };
+
+    match parsed {
+        Some(json) => Ballot {
+            agent: agent.to_string(),
+            answer: json.answer.trim().to_string(),
+            confidence: json.confidence.unwrap_or(DEFAULT_CONFIDENCE).clamp(0.0, 1.0),
+        },
+        None => Ballot {
+            agent: agent.to_string(),
+            answer: reply.trim().to_string(),
+            confidence: DEFAULT_CONFIDENCE,
+        },
+    }
+}
+
+/// Normalize an answer for comparison (case, surrounding whitespace and punctuation)
+fn normalize(answer: &str) -> String {
+    answer
+        .trim()
+        .trim_end_matches(['.', '!', '?'])
+        .trim()
+        .to_lowercase()
+}
+
+/// Aggregate ballots by majority or weighted score
+///
+/// `VoteAggregation::Judge` needs an LLM and is handled by the routing handler;
+/// here it falls back to the weighted score. Returns None if there are no ballots.
+pub fn aggregate(ballots: &[Ballot], method: VoteAggregation) -> Option<VoteResult> {
+    if ballots.is_empty() {
+        return None;
+    }
+
+    // (display answer, votes, summed confidence), in first-seen order
+    let mut groups: Vec<(String, String, f32, f32)> = Vec::new();
+    for ballot in ballots {
+        let key = normalize(&ballot.answer);
+        match groups.iter_mut().find(|(k, _, _, _)| *k == key) {
+            Some(group) => {
+                group.2 += 1.0;
+                group.3 += ballot.confidence;
+            }
+            None => groups.push((key, ballot.answer.clone(), 1.0, ballot.confidence)),
+        }
+    }
+
+    let method = match method {
+        VoteAggregation::Judge => VoteAggregation::Weighted,
+        other => other,
+    };
+    let score = |votes: f32, confidence: f32| match method {
+        VoteAggregation::Majority => votes,
+        _ => confidence,
+    };
+
+    // Stable sort keeps first-seen order among equal scores
+    groups.sort_by(|a, b| {
+        score(b.2, b.3)
+            .total_cmp(&score(a.2, a.3))
+            .then(b.3.total_cmp(&a.3))
+    });
+
+    let total: f32 = groups.iter().map(|g| score(g.2, g.3)).sum();
+    let winner = &groups[0];
+    let support = if total > 0.0 {
+        score(winner.2, winner.3) / total
+    } else {
+        0.0
+    };
+
+    Some(VoteResult {
+        answer: winner.1.clone(),
+        method,
+        support: Some(support),
+        tally: groups.iter().map(|g| (g.1.clone(), score(g.2, g.3))).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ballot(agent: &str, answer: &str, confidence: f32) -> Ballot {
+        Ballot {
+            agent: agent.to_string(),
+            answer: answer.to_string(),
+            confidence,
+        }
+    }
+
+    #[test]
+    fn test_parse_ballot_json_and_fallback() {
+        let parsed = parse_ballot("A", "Sure!\n```json\n{\"answer\": \"Yes\", \"confidence\": 1.7}\n```");
+        assert_eq!(parsed.answer, "Yes");
+        assert_eq!(parsed.confidence, 1.0);
+
+        let fallback = parse_ballot("B", "  I think no. ");
+        assert_eq!(fallback.answer, "I think no.");
+        assert_eq!(fallback.confidence, DEFAULT_CONFIDENCE);
+    }
+
+    #[test]
+    fn test_majority_groups_equivalent_answers() {
+        let ballots = vec![
+            ballot("A", "Yes", 0.2),
+            ballot("B", "no", 0.9),
+            ballot("C", "yes.", 0.3),
+        ];
+
+        let result = aggregate(&ballots, VoteAggregation::Majority).unwrap();
+        assert_eq!(result.answer, "Yes");
+        assert_eq!(result.support, Some(2.0 / 3.0));
+        assert_eq!(result.tally, vec![("Yes".to_string(), 2.0), ("no".to_string(), 1.0)]);
+    }
+
+    #[test]
+    fn test_weighted_prefers_confident_minority() {
+        let ballots = vec![
+            ballot("A", "Yes", 0.2),
+            ballot("B", "No", 0.9),
+            ballot("C", "Yes", 0.3),
+        ];
+
+        let result = aggregate(&ballots, VoteAggregation::Weighted).unwrap();
+        assert_eq!(result.answer, "No");
+        assert_eq!(result.method, VoteAggregation::Weighted);
+    }
+
+    #[test]
+    fn test_aggregate_empty() {
+        assert!(aggregate(&[], VoteAggregation::Majority).is_none());
+    }
+}
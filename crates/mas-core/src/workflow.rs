@@ -0,0 +1,665 @@
+//! Declarative workflows
+//!
+//! A workflow is a DAG of steps. Each step sends a message to an agent, tool
+//! or database and waits for its response; a step without a target sends
+//! nothing and outputs its rendered input. A step starts as soon as the steps
+//! it depends on have settled, so independent branches run in parallel and a
+//! step with several dependencies joins them.
+//!
+//! Step inputs and the workflow output are templates:
+//!
+//! ```text
+//! ${input}               the message the workflow was invoked with
+//! ${steps.<id>.output}   the response of an upstream step (empty if it did not run)
+//! ${metadata.<key>}      request metadata (empty if missing)
+//! ```
+//!
+//! An input may also be a JSON object (e.g. tool parameters); its string
+//! values are rendered and the object is sent as JSON.
+//!
+//! A dependency can carry a `when` guard (see [`crate::guard`]), checked
+//! against the upstream step's output. Edges whose guard fails are not taken:
+//! with `join: "all"` (default) the step is skipped unless every edge is
+//! taken, with `join: "any"` it runs if at least one is. Steps without a
+//! target behind conditional edges pass an upstream output on only on the
+//! branches that should release it.
+//!
+//! Workflows are registered in the system under their own name and are
+//! invoked like any other agent.
+
+use crate::guard::{Guard, GuardConfig};
+use crate::message::Message;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Workflow configuration as written in the `workflows` section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowConfig {
+    /// Name the workflow is invoked by (shares the namespace with agents)
+    pub name: String,
+    /// Human-readable description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Steps of the workflow; order only matters for the default output
+    pub steps: Vec<WorkflowStepConfig>,
+    /// Template for the final response (default: outputs of the final steps)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// Whether this workflow is the default target for API prompts
+    #[serde(default)]
+    pub entry_point: bool,
+    /// How long senders whose connection sets no timeout wait for the
+    /// workflow (default: the global timeout)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+/// One step of a workflow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowStepConfig {
+    /// Step id, unique within the workflow
+    pub id: String,
+    /// Agent, tool or database the step sends its input to; without one the
+    /// step's output is its rendered input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Input template: a string, or a JSON object whose strings are templates
+    #[serde(default = "default_step_input")]
+    pub input: Value,
+    /// Steps that must settle before this one starts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<StepDependency>,
+    /// How incoming edges are joined
+    #[serde(default)]
+    pub join: JoinMode,
+    /// Keep going when this step fails (dependent edges are not taken)
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// How long the step waits for its target (default: the global timeout)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+fn default_step_input() -> Value {
+    Value::String("${input}".to_string())
+}
+
+/// An edge from an upstream step: a plain step id, or a step id with a guard
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StepDependency {
+    /// Taken whenever the upstream step completes
+    Step(String),
+    /// Taken when the upstream step completes and its output passes the guard
+    Conditional { step: String, when: GuardConfig },
+}
+
+impl StepDependency {
+    /// Id of the upstream step
+    pub fn step(&self) -> &str {
+        match self {
+            StepDependency::Step(step) | StepDependency::Conditional { step, .. } => step,
+        }
+    }
+}
+
+/// How a step with several incoming edges decides whether to run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JoinMode {
+    /// Run only if every edge is taken
+    #[default]
+    All,
+    /// Run if at least one edge is taken
+    Any,
+}
+
+/// Errors from compiling a workflow configuration
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum WorkflowError {
+    #[error("workflow has no steps")]
+    NoSteps,
+
+    #[error("duplicate step id '{0}'")]
+    DuplicateStep(String),
+
+    #[error("step '{step}' depends on unknown step '{reference}'")]
+    UnknownStep { step: String, reference: String },
+
+    #[error("steps form a cycle through '{0}'")]
+    Cycle(String),
+
+    #[error("step '{step}' has an invalid condition: {reason}")]
+    InvalidGuard { step: String, reason: String },
+
+    #[error("invalid template in '{location}': {reason}")]
+    InvalidTemplate { location: String, reason: String },
+}
+
+/// State of a step during one run
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum StepState {
+    Pending,
+    Running,
+    Completed(String),
+    Skipped,
+    Failed(String),
+}
+
+impl StepState {
+    fn is_settled(&self) -> bool {
+        !matches!(self, StepState::Pending | StepState::Running)
+    }
+
+    fn output(&self) -> Option<&str> {
+        match self {
+            StepState::Completed(output) => Some(output),
+            _ => None,
+        }
+    }
+
+    /// Name used in trace events
+    pub fn status(&self) -> &'static str {
+        match self {
+            StepState::Pending => "pending",
+            StepState::Running => "running",
+            StepState::Completed(_) => "completed",
+            StepState::Skipped => "skipped",
+            StepState::Failed(_) => "failed",
+        }
+    }
+}
+
+struct Edge {
+    step: usize,
+    guard: Option<Guard>,
+}
+
+/// A compiled workflow step
+pub(crate) struct WorkflowStep {
+    pub id: String,
+    pub target: Option<String>,
+    pub continue_on_error: bool,
+    pub timeout: Option<Duration>,
+    input: Value,
+    depends_on: Vec<Edge>,
+    join: JoinMode,
+}
+
+impl WorkflowStep {
+    /// The step's target, or its id for steps without one
+    pub fn label(&self) -> &str {
+        self.target.as_deref().unwrap_or(&self.id)
+    }
+}
+
+/// A validated, ready-to-run workflow
+pub struct Workflow {
+    name: String,
+    description: Option<String>,
+    steps: Vec<WorkflowStep>,
+    output: Option<String>,
+    timeout: Option<Duration>,
+}
+
+impl Workflow {
+    /// Compile a workflow configuration, checking step references, cycles,
+    /// conditions and templates
+    pub fn compile(config: &WorkflowConfig) -> Result<Self, WorkflowError> {
+        if config.steps.is_empty() {
+            return Err(WorkflowError::NoSteps);
+        }
+
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        for (i, step) in config.steps.iter().enumerate() {
+            if index.insert(step.id.as_str(), i).is_some() {
+                return Err(WorkflowError::DuplicateStep(step.id.clone()));
+            }
+        }
+
+        let mut steps = Vec::with_capacity(config.steps.len());
+        for step in &config.steps {
+            let mut depends_on = Vec::with_capacity(step.depends_on.len());
+            for dependency in &step.depends_on {
+                let upstream = *index.get(dependency.step()).ok_or_else(|| WorkflowError::UnknownStep {
+                    step: step.id.clone(),
+                    reference: dependency.step().to_string(),
+                })?;
+                let guard = match dependency {
+                    StepDependency::Step(_) => None,
+                    StepDependency::Conditional { when, .. } => {
+                        Some(Guard::compile(when).map_err(|e| WorkflowError::InvalidGuard {
+                            step: step.id.clone(),
+                            reason: e.to_string(),
+                        })?)
+                    }
+                };
+                depends_on.push(Edge { step: upstream, guard });
+            }
+            steps.push(WorkflowStep {
+                id: step.id.clone(),
+                target: step.target.clone(),
+                continue_on_error: step.continue_on_error,
+                timeout: step.timeout_secs.map(Duration::from_secs),
+                input: step.input.clone(),
+                depends_on,
+                join: step.join,
+            });
+        }
+
+        let workflow = Self {
+            name: config.name.clone(),
+            description: config.description.clone(),
+            steps,
+            output: config.output.clone(),
+            timeout: config.timeout_secs.map(Duration::from_secs),
+        };
+        workflow.check_acyclic()?;
+        workflow.check_templates(&index)?;
+        Ok(workflow)
+    }
+
+    /// Name the workflow is registered under
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Description, if configured
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Distinct step targets, in step order
+    pub fn targets(&self) -> Vec<&str> {
+        let mut seen = HashSet::new();
+        self.steps
+            .iter()
+            .filter_map(|s| s.target.as_deref())
+            .filter(|t| seen.insert(*t))
+            .collect()
+    }
+
+    /// Longest timeout configured on the steps sending to `target`
+    pub fn step_timeout(&self, target: &str) -> Option<Duration> {
+        self.steps
+            .iter()
+            .filter(|s| s.target.as_deref() == Some(target))
+            .filter_map(|s| s.timeout)
+            .max()
+    }
+
+    /// How long senders wait for the workflow, if configured
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub(crate) fn steps(&self) -> &[WorkflowStep] {
+        &self.steps
+    }
+
+    /// Fresh state for a run
+    pub(crate) fn initial_states(&self) -> Vec<StepState> {
+        vec![StepState::Pending; self.steps.len()]
+    }
+
+    /// Pending steps whose dependencies have all settled
+    pub(crate) fn ready_steps(&self, states: &[StepState]) -> Vec<usize> {
+        self.steps
+            .iter()
+            .enumerate()
+            .filter(|(i, step)| {
+                states[*i] == StepState::Pending
+                    && step.depends_on.iter().all(|e| states[e.step].is_settled())
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Whether a ready step runs, given which of its edges are taken
+    pub(crate) fn should_run(&self, index: usize, states: &[StepState], request: &Message) -> bool {
+        let step = &self.steps[index];
+        if step.depends_on.is_empty() {
+            return true;
+        }
+
+        let mut taken = step.depends_on.iter().map(|edge| {
+            let Some(output) = states[edge.step].output() else {
+                return false;
+            };
+            edge.guard.as_ref().is_none_or(|guard| {
+                let upstream = &self.steps[edge.step];
+                let message = Message::new(upstream.label(), step.label(), output)
+                    .with_metadata(request.metadata.clone());
+                guard.allows(&message)
+            })
+        });
+
+        match step.join {
+            JoinMode::All => taken.all(|t| t),
+            JoinMode::Any => taken.any(|t| t),
+        }
+    }
+
+    /// Render a step's input
+    pub(crate) fn render_input(&self, index: usize, states: &[StepState], request: &Message) -> String {
+        match &self.steps[index].input {
+            Value::String(template) => self.render(template, states, request),
+            other => self.render_value(other, states, request).to_string(),
+        }
+    }
+
+    /// Render the workflow's response
+    ///
+    /// Without an output template this is the output of every completed step
+    /// that no other step depends on.
+    pub(crate) fn render_output(&self, states: &[StepState], request: &Message) -> String {
+        if let Some(template) = &self.output {
+            return self.render(template, states, request);
+        }
+
+        let upstream: HashSet<usize> = self
+            .steps
+            .iter()
+            .flat_map(|s| s.depends_on.iter().map(|e| e.step))
+            .collect();
+        let outputs: Vec<&str> = (0..self.steps.len())
+            .filter(|i| !upstream.contains(i))
+            .filter_map(|i| states[i].output())
+            .collect();
+
+        if outputs.is_empty() {
+            format!("Workflow '{}' produced no output", self.name)
+        } else {
+            outputs.join("\n\n")
+        }
+    }
+
+    fn render_value(&self, value: &Value, states: &[StepState], request: &Message) -> Value {
+        match value {
+            Value::String(template) => Value::String(self.render(template, states, request)),
+            Value::Array(items) => Value::Array(
+                items.iter().map(|v| self.render_value(v, states, request)).collect(),
+            ),
+            Value::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(k, v)| (k.clone(), self.render_value(v, states, request)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    fn render(&self, template: &str, states: &[StepState], request: &Message) -> String {
+        placeholder_regex()
+            .replace_all(template, |caps: &regex::Captures| {
+                match Placeholder::parse(&caps[1]) {
+                    Some(Placeholder::Input) => request.content.clone(),
+                    Some(Placeholder::Metadata(key)) => {
+                        request.metadata.get(key).cloned().unwrap_or_default()
+                    }
+                    Some(Placeholder::StepOutput(id)) => self
+                        .steps
+                        .iter()
+                        .position(|s| s.id == id)
+                        .and_then(|i| states[i].output())
+                        .unwrap_or_default()
+                        .to_string(),
+                    None => caps[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+
+    fn check_acyclic(&self) -> Result<(), WorkflowError> {
+        // Kahn's algorithm: whatever cannot be ordered lies on a cycle
+        let mut remaining: Vec<usize> = self.steps.iter().map(|s| s.depends_on.len()).collect();
+        let mut ordered = 0;
+        let mut queue: Vec<usize> = (0..self.steps.len()).filter(|&i| remaining[i] == 0).collect();
+        while let Some(done) = queue.pop() {
+            ordered += 1;
+            for (i, step) in self.steps.iter().enumerate() {
+                let edges = step.depends_on.iter().filter(|e| e.step == done).count();
+                if edges > 0 {
+                    remaining[i] -= edges;
+                    if remaining[i] == 0 {
+                        queue.push(i);
+                    }
+                }
+            }
+        }
+
+        if ordered < self.steps.len() {
+            let stuck = remaining.iter().position(|&r| r > 0).unwrap_or(0);
+            return Err(WorkflowError::Cycle(self.steps[stuck].id.clone()));
+        }
+        Ok(())
+    }
+
+    fn check_templates(&self, index: &HashMap<&str, usize>) -> Result<(), WorkflowError> {
+        for (i, step) in self.steps.iter().enumerate() {
+            let ancestors = self.ancestors(i);
+            let location = format!("step '{}'", step.id);
+            for template in template_strings(&step.input) {
+                check_template(template, &location, |id| {
+                    index.get(id).is_some_and(|upstream| ancestors.contains(upstream))
+                })?;
+            }
+        }
+
+        if let Some(output) = &self.output {
+            check_template(output, "output", |id| index.contains_key(id))?;
+        }
+        Ok(())
+    }
+
+    /// Every step a step transitively depends on
+    fn ancestors(&self, index: usize) -> HashSet<usize> {
+        let mut seen = HashSet::new();
+        let mut stack: Vec<usize> = self.steps[index].depends_on.iter().map(|e| e.step).collect();
+        while let Some(i) = stack.pop() {
+            if seen.insert(i) {
+                stack.extend(self.steps[i].depends_on.iter().map(|e| e.step));
+            }
+        }
+        seen
+    }
+}
+
+enum Placeholder<'a> {
+    Input,
+    Metadata(&'a str),
+    StepOutput(&'a str),
+}
+
+impl<'a> Placeholder<'a> {
+    fn parse(name: &'a str) -> Option<Self> {
+        let name = name.trim();
+        if name == "input" {
+            return Some(Placeholder::Input);
+        }
+        if let Some(key) = name.strip_prefix("metadata.") {
+            return Some(Placeholder::Metadata(key));
+        }
+        name.strip_prefix("steps.")
+            .and_then(|rest| rest.strip_suffix(".output"))
+            .map(Placeholder::StepOutput)
+    }
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\$\{([^}]*)\}").expect("valid placeholder regex"))
+}
+
+fn template_strings(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(s) => vec![s.as_str()],
+        Value::Array(items) => items.iter().flat_map(template_strings).collect(),
+        Value::Object(fields) => fields.values().flat_map(template_strings).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn check_template(
+    template: &str,
+    location: &str,
+    step_visible: impl Fn(&str) -> bool,
+) -> Result<(), WorkflowError> {
+    for caps in placeholder_regex().captures_iter(template) {
+        let invalid = |reason: String| WorkflowError::InvalidTemplate {
+            location: location.to_string(),
+            reason,
+        };
+        match Placeholder::parse(&caps[1]) {
+            Some(Placeholder::StepOutput(id)) if !step_visible(id) => {
+                return Err(invalid(format!(
+                    "'{}' does not refer to an upstream step",
+                    &caps[0]
+                )));
+            }
+            Some(_) => {}
+            None => return Err(invalid(format!("unknown placeholder '{}'", &caps[0]))),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> WorkflowConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn review_flow() -> Workflow {
+        Workflow::compile(&parse(
+            r#"{
+                "name": "Review",
+                "steps": [
+                    { "id": "fetch", "target": "Fetcher" },
+                    {
+                        "id": "security",
+                        "target": "Security",
+                        "input": "Audit: ${steps.fetch.output}",
+                        "depends_on": ["fetch"]
+                    },
+                    {
+                        "id": "legal",
+                        "target": "Legal",
+                        "depends_on": [{ "step": "fetch", "when": { "keywords": ["license"] } }]
+                    },
+                    {
+                        "id": "report",
+                        "target": "Writer",
+                        "input": { "security": "${steps.security.output}", "user": "${metadata.user_id}" },
+                        "depends_on": ["security", "legal"],
+                        "join": "any"
+                    }
+                ]
+            }"#,
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_compile_rejects_bad_graphs() {
+        let cycle = parse(
+            r#"{ "name": "W", "steps": [
+                { "id": "a", "target": "A", "depends_on": ["b"] },
+                { "id": "b", "target": "B", "depends_on": ["a"] }
+            ] }"#,
+        );
+        assert!(matches!(Workflow::compile(&cycle), Err(WorkflowError::Cycle(_))));
+
+        let unknown = parse(r#"{ "name": "W", "steps": [{ "id": "a", "target": "A", "depends_on": ["z"] }] }"#);
+        assert!(matches!(Workflow::compile(&unknown), Err(WorkflowError::UnknownStep { .. })));
+
+        let duplicate = parse(
+            r#"{ "name": "W", "steps": [{ "id": "a", "target": "A" }, { "id": "a", "target": "B" }] }"#,
+        );
+        assert_eq!(
+            Workflow::compile(&duplicate).err(),
+            Some(WorkflowError::DuplicateStep("a".to_string()))
+        );
+
+        // Templates may only read steps that are guaranteed to have settled
+        let sibling = parse(
+            r#"{ "name": "W", "steps": [
+                { "id": "a", "target": "A" },
+                { "id": "b", "target": "B", "input": "${steps.a.output}" }
+            ] }"#,
+        );
+        assert!(matches!(Workflow::compile(&sibling), Err(WorkflowError::InvalidTemplate { .. })));
+
+        let placeholder = parse(r#"{ "name": "W", "steps": [{ "id": "a", "target": "A", "input": "${nope}" }] }"#);
+        assert!(matches!(Workflow::compile(&placeholder), Err(WorkflowError::InvalidTemplate { .. })));
+    }
+
+    #[test]
+    fn test_ready_steps_follow_dependencies() {
+        let workflow = review_flow();
+        let mut states = workflow.initial_states();
+        assert_eq!(workflow.ready_steps(&states), vec![0]);
+
+        states[0] = StepState::Completed("code".to_string());
+        // Both branches become ready together and can run in parallel
+        assert_eq!(workflow.ready_steps(&states), vec![1, 2]);
+
+        states[1] = StepState::Running;
+        states[2] = StepState::Skipped;
+        assert!(workflow.ready_steps(&states).is_empty());
+        states[1] = StepState::Completed("ok".to_string());
+        assert_eq!(workflow.ready_steps(&states), vec![3]);
+    }
+
+    #[test]
+    fn test_conditional_edges_and_joins() {
+        let workflow = review_flow();
+        let request = Message::new("User", "Review", "check this");
+        let mut states = workflow.initial_states();
+
+        states[0] = StepState::Completed("plain code".to_string());
+        assert!(workflow.should_run(1, &states, &request));
+        assert!(!workflow.should_run(2, &states, &request));
+
+        states[0] = StepState::Completed("code under a license".to_string());
+        assert!(workflow.should_run(2, &states, &request));
+
+        // "any" join runs with one branch skipped, but not with none taken
+        states[1] = StepState::Completed("ok".to_string());
+        states[2] = StepState::Skipped;
+        assert!(workflow.should_run(3, &states, &request));
+        states[1] = StepState::Failed("timeout".to_string());
+        assert!(!workflow.should_run(3, &states, &request));
+    }
+
+    #[test]
+    fn test_render_templates() {
+        let workflow = review_flow();
+        let mut metadata = HashMap::new();
+        metadata.insert("user_id".to_string(), "u-1".to_string());
+        let request = Message::new("User", "Review", "fn main() {}").with_metadata(metadata);
+        let mut states = workflow.initial_states();
+
+        assert_eq!(workflow.render_input(0, &states, &request), "fn main() {}");
+
+        states[0] = StepState::Completed("fetched".to_string());
+        states[1] = StepState::Completed("no issues".to_string());
+        states[2] = StepState::Skipped;
+        assert_eq!(workflow.render_input(1, &states, &request), "Audit: fetched");
+
+        let input: Value = serde_json::from_str(&workflow.render_input(3, &states, &request)).unwrap();
+        assert_eq!(input["security"], "no issues");
+        assert_eq!(input["user"], "u-1");
+
+        // Default output: the final steps that completed
+        states[3] = StepState::Completed("report".to_string());
+        assert_eq!(workflow.render_output(&states, &request), "report");
+    }
+}